@@ -0,0 +1,120 @@
+//! Tool result cache for a single model interaction
+//!
+//! Local models often repeat the same read in consecutive turns. Identical calls to
+//! read-only tools are answered from this cache instead of running the tool again.
+
+use super::{Agent, ToolCall, ToolResult};
+use anyhow::Result;
+use std::collections::{BTreeMap, HashMap};
+
+/// Tools whose results depend only on their parameters and the filesystem
+const CACHEABLE_TOOLS: &[&str] = &[
+    "read_file",
+    "list_directory",
+    "file_info",
+    "search_files",
+    "calculate",
+];
+
+/// Results of read-only tool calls, keyed by tool name and canonical parameters
+#[derive(Debug, Default)]
+pub struct ToolResultCache {
+    results: HashMap<String, ToolResult>,
+}
+
+impl ToolResultCache {
+    /// Execute a tool call through the agent, reusing an earlier identical read
+    ///
+    /// Any other tool may change the filesystem, so running one clears the cache.
+    pub async fn execute(&mut self, agent: &mut Agent, tool_call: ToolCall) -> Result<ToolResult> {
+        let Some(key) = cache_key(&tool_call) else {
+            self.results.clear();
+            return Ok(agent.execute_tool(tool_call).await?);
+        };
+
+        if let Some(cached) = self.results.get(&key) {
+            tracing::debug!(tool = %tool_call.tool, "Serving tool result from cache");
+            return Ok(mark_cached(cached.clone()));
+        }
+
+        let result = agent.execute_tool(tool_call).await?;
+        if result.success {
+            self.results.insert(key, result.clone());
+        }
+        Ok(result)
+    }
+}
+
+/// Cache key for read-only calls; parameters are sorted so key order does not matter
+fn cache_key(tool_call: &ToolCall) -> Option<String> {
+    if !CACHEABLE_TOOLS.contains(&tool_call.tool.as_str()) {
+        return None;
+    }
+
+    let parameters: BTreeMap<_, _> = tool_call.parameters.iter().collect();
+    let parameters = serde_json::to_string(&parameters).ok()?;
+    Some(format!("{}:{}", tool_call.tool, parameters))
+}
+
+/// Flag a result as a repeat so the model knows the tool did not run again
+fn mark_cached(mut result: ToolResult) -> ToolResult {
+    if let serde_json::Value::Object(ref mut data) = result.data {
+        data.insert("cached".to_string(), serde_json::Value::Bool(true));
+    }
+    let message = result.message.take().unwrap_or_default();
+    result.message = Some(format!("{message} (served from cache)").trim().to_string());
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::AgentConfig;
+
+    fn call(tool: &str, parameters: serde_json::Value) -> ToolCall {
+        ToolCall {
+            tool: tool.to_string(),
+            parameters: serde_json::from_value(parameters).unwrap(),
+            thought: None,
+            reasoning: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn identical_reads_execute_once_until_a_write() {
+        let dir = std::env::temp_dir().join(format!("chatter-cache-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("notes.txt");
+        std::fs::write(&file, "hello").unwrap();
+        let path = file.to_str().unwrap();
+
+        let mut agent = Agent::new(AgentConfig {
+            enabled: true,
+            auto_backup: false,
+            working_directory: dir.clone(),
+            ..AgentConfig::default()
+        })
+        .unwrap();
+        let mut cache = ToolResultCache::default();
+
+        let read = call("read_file", serde_json::json!({ "path": path }));
+        let first = cache.execute(&mut agent, read.clone()).await.unwrap();
+        let second = cache.execute(&mut agent, read.clone()).await.unwrap();
+        assert_eq!(agent.tool_history().len(), 1);
+        assert!(first.data.get("cached").is_none());
+        assert_eq!(second.data["cached"], true);
+        assert!(second.message.unwrap().ends_with("(served from cache)"));
+
+        // A write invalidates earlier reads
+        let write = call(
+            "write_file",
+            serde_json::json!({ "path": path, "content": "changed" }),
+        );
+        cache.execute(&mut agent, write).await.unwrap();
+        let third = cache.execute(&mut agent, read).await.unwrap();
+        assert_eq!(agent.tool_history().len(), 3);
+        assert_eq!(third.data["content"], "changed");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,357 @@
+//! Arithmetic expression evaluator for the `calculate` tool
+//!
+//! A small recursive-descent parser over numbers, `+ - * / % ^`, parentheses,
+//! the constants `pi` and `e`, and a fixed set of functions. Nothing else is
+//! recognised, so an expression can never do more than compute a number.
+
+use anyhow::{anyhow, Result};
+
+/// Longest expression accepted, in bytes
+pub const MAX_EXPRESSION_LEN: usize = 1000;
+
+/// Deepest nesting of parentheses, function calls, and unary operators
+const MAX_DEPTH: usize = 64;
+
+/// Names of the supported functions, for error messages and the tool schema
+pub const FUNCTIONS: &[&str] = &[
+    "sqrt", "abs", "sin", "cos", "tan", "asin", "acos", "atan", "ln", "log", "log2", "exp",
+    "floor", "ceil", "round", "min", "max",
+];
+
+/// Evaluate an arithmetic expression
+pub fn evaluate(expression: &str) -> Result<f64> {
+    if expression.len() > MAX_EXPRESSION_LEN {
+        return Err(anyhow!(
+            "Expression is too long ({} bytes, limit is {})",
+            expression.len(),
+            MAX_EXPRESSION_LEN
+        ));
+    }
+
+    let mut parser = Parser {
+        tokens: tokenize(expression)?,
+        position: 0,
+        depth: 0,
+    };
+    if parser.tokens.is_empty() {
+        return Err(anyhow!("Expression is empty"));
+    }
+
+    let value = parser.expression()?;
+    if let Some(token) = parser.peek() {
+        return Err(anyhow!("Unexpected {} in expression", token.describe()));
+    }
+    if !value.is_finite() {
+        return Err(anyhow!("Result is not a finite number"));
+    }
+    Ok(value)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Op(char),
+    LParen,
+    RParen,
+    Comma,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Number(n) => format!("number {n}"),
+            Token::Ident(name) => format!("'{name}'"),
+            Token::Op(op) => format!("'{op}'"),
+            Token::LParen => "'('".to_string(),
+            Token::RParen => "')'".to_string(),
+            Token::Comma => "','".to_string(),
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+
+    while let Some(&(start, c)) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '0'..='9' | '.' => {
+                let mut end = start;
+                while let Some(&(i, c)) = chars.peek() {
+                    let exponent_sign = matches!(c, '+' | '-')
+                        && matches!(input[..i].chars().last(), Some('e' | 'E'));
+                    if c.is_ascii_digit() || c == '.' || c == 'e' || c == 'E' || exponent_sign {
+                        end = i + c.len_utf8();
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                let literal = &input[start..end];
+                let number = literal
+                    .parse::<f64>()
+                    .map_err(|_| anyhow!("Invalid number '{literal}'"))?;
+                tokens.push(Token::Number(number));
+            }
+            c if c.is_ascii_alphabetic() => {
+                let mut end = start;
+                while let Some(&(i, c)) = chars.peek() {
+                    if c.is_ascii_alphanumeric() || c == '_' {
+                        end = i + 1;
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                tokens.push(Token::Ident(input[start..end].to_lowercase()));
+            }
+            '*' => {
+                chars.next();
+                // Accept `**` as an alias for `^`
+                if chars.peek().map(|&(_, c)| c) == Some('*') {
+                    chars.next();
+                    tokens.push(Token::Op('^'));
+                } else {
+                    tokens.push(Token::Op('*'));
+                }
+            }
+            '+' | '-' | '/' | '%' | '^' => {
+                chars.next();
+                tokens.push(Token::Op(c));
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::LParen);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::RParen);
+            }
+            ',' => {
+                chars.next();
+                tokens.push(Token::Comma);
+            }
+            other => return Err(anyhow!("Unexpected character '{other}' in expression")),
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<()> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => Err(anyhow!(
+                "Expected {} but found {}",
+                expected.describe(),
+                token.describe()
+            )),
+            None => Err(anyhow!(
+                "Expected {} at end of expression",
+                expected.describe()
+            )),
+        }
+    }
+
+    fn enter(&mut self) -> Result<()> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(anyhow!("Expression is nested too deeply"));
+        }
+        Ok(())
+    }
+
+    /// expression := term (('+' | '-') term)*
+    fn expression(&mut self) -> Result<f64> {
+        let mut value = self.term()?;
+        while let Some(Token::Op(op @ ('+' | '-'))) = self.peek().cloned() {
+            self.next();
+            let rhs = self.term()?;
+            value = if op == '+' { value + rhs } else { value - rhs };
+        }
+        Ok(value)
+    }
+
+    /// term := unary (('*' | '/' | '%') unary)*
+    fn term(&mut self) -> Result<f64> {
+        let mut value = self.unary()?;
+        while let Some(Token::Op(op @ ('*' | '/' | '%'))) = self.peek().cloned() {
+            self.next();
+            let rhs = self.unary()?;
+            if rhs == 0.0 && op != '*' {
+                return Err(anyhow!("Division by zero"));
+            }
+            value = match op {
+                '*' => value * rhs,
+                '/' => value / rhs,
+                _ => value % rhs,
+            };
+        }
+        Ok(value)
+    }
+
+    /// unary := ('+' | '-') unary | power
+    fn unary(&mut self) -> Result<f64> {
+        match self.peek() {
+            Some(Token::Op(op @ ('+' | '-'))) => {
+                let negate = *op == '-';
+                self.next();
+                self.enter()?;
+                let value = self.unary()?;
+                self.depth -= 1;
+                Ok(if negate { -value } else { value })
+            }
+            _ => self.power(),
+        }
+    }
+
+    /// power := primary ('^' unary)?, right-associative so 2^3^2 = 2^9
+    fn power(&mut self) -> Result<f64> {
+        let base = self.primary()?;
+        if let Some(Token::Op('^')) = self.peek() {
+            self.next();
+            self.enter()?;
+            let exponent = self.unary()?;
+            self.depth -= 1;
+            return Ok(base.powf(exponent));
+        }
+        Ok(base)
+    }
+
+    /// primary := number | constant | function '(' args ')' | '(' expression ')'
+    fn primary(&mut self) -> Result<f64> {
+        match self.next() {
+            Some(Token::Number(n)) => Ok(n),
+            Some(Token::LParen) => {
+                self.enter()?;
+                let value = self.expression()?;
+                self.expect(Token::RParen)?;
+                self.depth -= 1;
+                Ok(value)
+            }
+            Some(Token::Ident(name)) => match name.as_str() {
+                "pi" => Ok(std::f64::consts::PI),
+                "e" => Ok(std::f64::consts::E),
+                _ if FUNCTIONS.contains(&name.as_str()) => {
+                    self.enter()?;
+                    let args = self.arguments()?;
+                    self.depth -= 1;
+                    call_function(&name, &args)
+                }
+                _ => Err(anyhow!(
+                    "Unknown name '{}' (supported functions: {}; constants: pi, e)",
+                    name,
+                    FUNCTIONS.join(", ")
+                )),
+            },
+            Some(token) => Err(anyhow!("Unexpected {} in expression", token.describe())),
+            None => Err(anyhow!("Expression ended unexpectedly")),
+        }
+    }
+
+    fn arguments(&mut self) -> Result<Vec<f64>> {
+        self.expect(Token::LParen)?;
+        let mut args = vec![self.expression()?];
+        while let Some(Token::Comma) = self.peek() {
+            self.next();
+            args.push(self.expression()?);
+        }
+        self.expect(Token::RParen)?;
+        Ok(args)
+    }
+}
+
+fn call_function(name: &str, args: &[f64]) -> Result<f64> {
+    let expected = if matches!(name, "min" | "max") { 2 } else { 1 };
+    if args.len() != expected {
+        return Err(anyhow!(
+            "{}() takes {} argument{}, got {}",
+            name,
+            expected,
+            if expected == 1 { "" } else { "s" },
+            args.len()
+        ));
+    }
+
+    let x = args[0];
+    Ok(match name {
+        "sqrt" => x.sqrt(),
+        "abs" => x.abs(),
+        "sin" => x.sin(),
+        "cos" => x.cos(),
+        "tan" => x.tan(),
+        "asin" => x.asin(),
+        "acos" => x.acos(),
+        "atan" => x.atan(),
+        "ln" => x.ln(),
+        "log" => x.log10(),
+        "log2" => x.log2(),
+        "exp" => x.exp(),
+        "floor" => x.floor(),
+        "ceil" => x.ceil(),
+        "round" => x.round(),
+        "min" => x.min(args[1]),
+        "max" => x.max(args[1]),
+        _ => unreachable!("function list and dispatch are out of sync"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evaluates_arithmetic_and_functions() {
+        assert_eq!(evaluate("1 + 2 * 3").unwrap(), 7.0);
+        assert_eq!(evaluate("(1 + 2) * 3").unwrap(), 9.0);
+        assert_eq!(evaluate("2 ^ 3 ^ 2").unwrap(), 512.0);
+        assert_eq!(evaluate("2 ** 10").unwrap(), 1024.0);
+        assert_eq!(evaluate("-3^2").unwrap(), -9.0);
+        assert_eq!(evaluate("10 % 4").unwrap(), 2.0);
+        assert_eq!(evaluate("sqrt(16) + max(2, abs(-5))").unwrap(), 9.0);
+        assert_eq!(evaluate("1.5e3 / 3").unwrap(), 500.0);
+        assert!((evaluate("sin(pi / 2)").unwrap() - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn rejects_malformed_and_unsafe_input() {
+        for bad in [
+            "",
+            "1 +",
+            "(1 + 2",
+            "1 2",
+            "foo(1)",
+            "system(\"ls\")",
+            "1; rm -rf /",
+            "sqrt(1, 2)",
+            "1 / 0",
+            "sqrt(-1)",
+        ] {
+            assert!(evaluate(bad).is_err(), "{bad:?} should be rejected");
+        }
+
+        assert!(evaluate(&"1+".repeat(600)).is_err());
+        assert!(evaluate(&format!("{}1{}", "(".repeat(100), ")".repeat(100))).is_err());
+    }
+}
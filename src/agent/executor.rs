@@ -3,12 +3,76 @@
 //! Manages tool registration, execution, and safety checks.
 
 use super::tools::{
-    FileInfoTool, ListDirectoryTool, ReadFileTool, SearchFilesTool, Tool, UpdateFileTool,
-    WriteFileTool,
+    plan_file_change, CalculateTool, ExternalTool, FileInfoTool, ListDirectoryTool, PlannedChange,
+    ReadFileTool, SearchFilesTool, Tool, UpdateFileTool, WriteFileTool,
 };
 use super::{AgentConfig, SafetyManager, ToolCall, ToolResult};
+use crate::chat::theme::{icon, Marker};
+use crate::error::ChatterError;
 use anyhow::{anyhow, Result};
+use colored::*;
 use std::collections::HashMap;
+use std::io::IsTerminal;
+use std::sync::Arc;
+
+/// Longest diff shown when asking to confirm a write
+const CONFIRM_DIFF_LINES: usize = 40;
+
+/// Decides whether a file change may go ahead when `confirm_writes` is on
+#[derive(Clone)]
+pub struct WriteConfirmer(Arc<dyn Fn(&PlannedChange) -> bool + Send + Sync>);
+
+impl WriteConfirmer {
+    pub fn new(decide: impl Fn(&PlannedChange) -> bool + Send + Sync + 'static) -> Self {
+        Self(Arc::new(decide))
+    }
+
+    /// Show the change and ask on the terminal; declines when stdin is not a terminal
+    pub fn interactive() -> Self {
+        Self::new(|change| {
+            if !std::io::stdin().is_terminal() {
+                eprintln!(
+                    "Declined change to {}: confirmation needs an interactive terminal",
+                    change.path.display()
+                );
+                return false;
+            }
+
+            let (added, removed) = change.line_counts();
+            println!(
+                "\n{}Change to {} ({}, {})",
+                icon(Marker::Edit),
+                change.path.display().to_string().bold(),
+                format!("+{added}").bright_green(),
+                format!("-{removed}").bright_red()
+            );
+            let diff = change.diff();
+            let lines: Vec<&str> = diff.lines().collect();
+            for line in lines.iter().take(CONFIRM_DIFF_LINES) {
+                println!("   {line}");
+            }
+            if lines.len() > CONFIRM_DIFF_LINES {
+                println!("   ... {} more lines", lines.len() - CONFIRM_DIFF_LINES);
+            }
+
+            dialoguer::Confirm::new()
+                .with_prompt("Apply this change?")
+                .default(false)
+                .interact()
+                .unwrap_or(false)
+        })
+    }
+
+    fn confirm(&self, change: &PlannedChange) -> bool {
+        (self.0)(change)
+    }
+}
+
+impl std::fmt::Debug for WriteConfirmer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("WriteConfirmer")
+    }
+}
 
 /// Tool execution engine
 #[derive(Debug)]
@@ -16,6 +80,7 @@ pub struct AgentExecutor {
     config: AgentConfig,
     safety_manager: SafetyManager,
     tools: HashMap<String, Tool>,
+    write_confirmer: WriteConfirmer,
 }
 
 impl AgentExecutor {
@@ -25,26 +90,62 @@ impl AgentExecutor {
             config,
             safety_manager,
             tools: HashMap::new(),
+            write_confirmer: WriteConfirmer::interactive(),
         };
 
         // Register built-in tools
         executor.register_builtin_tools()?;
 
+        // Register user-defined tools from configuration
+        executor.register_external_tools()?;
+
         Ok(executor)
     }
 
-    /// Register all built-in tools
+    /// Register all built-in tools except those disabled in the configuration
     fn register_builtin_tools(&mut self) -> Result<()> {
-        self.register_tool(Tool::ReadFile(ReadFileTool))?;
-        self.register_tool(Tool::WriteFile(WriteFileTool))?;
-        self.register_tool(Tool::UpdateFile(UpdateFileTool))?;
-        self.register_tool(Tool::SearchFiles(SearchFilesTool))?;
-        self.register_tool(Tool::ListDirectory(ListDirectoryTool))?;
-        self.register_tool(Tool::FileInfo(FileInfoTool))?;
+        let builtins = [
+            Tool::ReadFile(ReadFileTool::new(self.config.max_file_size)),
+            Tool::WriteFile(WriteFileTool),
+            Tool::UpdateFile(UpdateFileTool),
+            Tool::SearchFiles(SearchFilesTool),
+            Tool::ListDirectory(ListDirectoryTool),
+            Tool::FileInfo(FileInfoTool),
+            Tool::Calculate(CalculateTool),
+        ];
+
+        for tool in builtins {
+            if !self.is_disabled(tool.name()) {
+                self.register_tool(tool)?;
+            }
+        }
 
         Ok(())
     }
 
+    /// Register external command tools declared in the configuration
+    fn register_external_tools(&mut self) -> Result<()> {
+        for tool_config in self.config.external_tools.clone() {
+            if self.is_disabled(&tool_config.name) {
+                continue;
+            }
+            let tool = ExternalTool::new(&tool_config, self.config.working_directory.clone());
+            self.register_tool(Tool::External(tool))?;
+        }
+
+        Ok(())
+    }
+
+    /// Check whether a tool is listed in `disabled_tools`
+    fn is_disabled(&self, name: &str) -> bool {
+        self.config.disabled_tools.iter().any(|tool| tool == name)
+    }
+
+    /// Check whether a tool is registered
+    pub fn has_tool(&self, name: &str) -> bool {
+        self.tools.contains_key(name)
+    }
+
     /// Register a new tool
     pub fn register_tool(&mut self, tool: Tool) -> Result<()> {
         let name = tool.name().to_string();
@@ -72,6 +173,13 @@ impl AgentExecutor {
             .collect()
     }
 
+    /// Replace the prompt used when `confirm_writes` is on
+    #[cfg(test)]
+    pub fn with_write_confirmer(mut self, confirmer: WriteConfirmer) -> Self {
+        self.write_confirmer = confirmer;
+        self
+    }
+
     /// Get tool information
     pub fn get_tool_info(&self, name: &str) -> Option<ToolInfo> {
         self.tools.get(name).map(|tool| ToolInfo {
@@ -87,11 +195,12 @@ impl AgentExecutor {
         let tool = self
             .tools
             .get(&tool_call.tool)
-            .ok_or_else(|| anyhow!("Unknown tool: {}", tool_call.tool))?;
+            .ok_or_else(|| ChatterError::ToolNotFound(tool_call.tool.clone()))?;
 
         // Perform safety checks
         if let Err(e) = self.safety_manager.check_tool_call(&tool_call) {
-            return Ok(ToolResult::error(format!("Safety check failed: {e}")));
+            tracing::debug!(tool = %tool_call.tool, error = %e, "Tool call rejected by safety checks");
+            return Err(ChatterError::SafetyViolation(e.to_string()).into());
         }
 
         // Validate parameters against tool schema before execution
@@ -102,11 +211,39 @@ impl AgentExecutor {
             )));
         }
 
-        // Execute in dry-run mode if configured
-        if self.config.dry_run_mode {
+        // Execute in dry-run mode if configured, or when a file change asks for a preview
+        let preview = self.is_file_modification_tool(&tool_call.tool)
+            && tool_call
+                .parameters
+                .get("preview")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+        if self.config.dry_run_mode || preview {
             return self.execute_dry_run(tool, &tool_call).await;
         }
 
+        // Ask first when writes need confirmation; a change that cannot be shown
+        // for confirmation is declined rather than written unasked
+        if self.config.confirm_writes && self.is_file_modification_tool(&tool_call.tool) {
+            match plan_file_change(&tool_call.tool, &tool_call.parameters) {
+                Ok(Some(change)) => {
+                    if !self.write_confirmer.confirm(&change) {
+                        return Ok(ToolResult::error("User declined operation".to_string()));
+                    }
+                }
+                Ok(None) => {
+                    return Ok(ToolResult::error(
+                        "Declined: this change cannot be shown for confirmation".to_string(),
+                    ));
+                }
+                Err(e) => {
+                    return Ok(ToolResult::error(format!(
+                        "Declined: the change could not be prepared for confirmation: {e}"
+                    )));
+                }
+            }
+        }
+
         // Create backup if this is a file modification operation
         let backup_info = if self.is_file_modification_tool(&tool_call.tool) {
             self.create_backup_if_needed(&tool_call).await?
@@ -115,9 +252,12 @@ impl AgentExecutor {
         };
 
         // Execute the tool
+        tracing::debug!(tool = %tool_call.tool, "Executing tool");
+        tracing::trace!(tool = %tool_call.tool, parameters = ?tool_call.parameters, "Tool parameters");
         let mut result = match tool.execute(tool_call.parameters.clone()).await {
             Ok(result) => result,
             Err(e) => {
+                tracing::debug!(tool = %tool_call.tool, error = %e, "Tool execution failed");
                 // If execution failed and we created a backup, we might want to clean it up
                 if let Some(_backup) = backup_info {
                     // For now, we'll keep the backup even on failure
@@ -126,6 +266,8 @@ impl AgentExecutor {
             }
         };
 
+        tracing::debug!(tool = %tool_call.tool, success = result.success, "Tool finished");
+
         // Add backup information to successful results
         if let Some(backup) = backup_info {
             if result.success {
@@ -142,8 +284,10 @@ impl AgentExecutor {
     }
 
     /// Execute a tool in dry-run mode (preview only)
+    ///
+    /// File changes are computed and returned as a diff without being written.
     async fn execute_dry_run(&self, tool: &Tool, tool_call: &ToolCall) -> Result<ToolResult> {
-        let preview_data = serde_json::json!({
+        let mut preview_data = serde_json::json!({
             "tool": tool_call.tool,
             "parameters": tool_call.parameters,
             "description": tool.description(),
@@ -151,13 +295,42 @@ impl AgentExecutor {
             "note": "This is a preview - no actual changes were made"
         });
 
-        Ok(ToolResult::success(
-            preview_data,
-            Some(format!(
-                "DRY RUN: Would execute {} with given parameters",
-                tool_call.tool
-            )),
-        ))
+        let change = match plan_file_change(&tool_call.tool, &tool_call.parameters) {
+            Ok(change) => change,
+            Err(e) => return Ok(ToolResult::error(format!("Preview failed: {e}"))),
+        };
+        let Some(change) = change else {
+            return Ok(ToolResult::success(
+                preview_data,
+                Some(format!(
+                    "DRY RUN: Would execute {} with given parameters",
+                    tool_call.tool
+                )),
+            ));
+        };
+
+        let diff = change.diff();
+        if let serde_json::Value::Object(ref mut obj) = preview_data {
+            obj.insert("path".to_string(), change.path.display().to_string().into());
+            obj.insert("original_size".to_string(), change.original.len().into());
+            obj.insert("new_size".to_string(), change.new_content.len().into());
+            obj.insert("diff".to_string(), diff.clone().into());
+        }
+        let message = if diff.is_empty() {
+            format!(
+                "DRY RUN: {} would leave {} unchanged",
+                tool_call.tool,
+                change.path.display()
+            )
+        } else {
+            format!(
+                "DRY RUN: {} would change {}:\n{}",
+                tool_call.tool,
+                change.path.display(),
+                diff
+            )
+        };
+        Ok(ToolResult::success(preview_data, Some(message)))
     }
 
     /// Check if a tool modifies files
@@ -242,7 +415,10 @@ impl AgentExecutor {
         Ok(())
     }
 
-    /// Validate parameter type against schema
+    /// Validate a parameter value against its schema
+    ///
+    /// Checks the JSON type (an `integer` must be a whole number), `enum` values,
+    /// and recurses into array `items` and object `properties`.
     fn validate_parameter_type(
         &self,
         param_name: &str,
@@ -252,6 +428,7 @@ impl AgentExecutor {
         if let Some(expected_type) = schema.get("type").and_then(|t| t.as_str()) {
             let actual_type = match value {
                 serde_json::Value::String(_) => "string",
+                serde_json::Value::Number(n) if is_whole_number(n) => "integer",
                 serde_json::Value::Number(_) => "number",
                 serde_json::Value::Bool(_) => "boolean",
                 serde_json::Value::Array(_) => "array",
@@ -260,7 +437,7 @@ impl AgentExecutor {
             };
 
             if expected_type != actual_type
-                && !(expected_type == "integer" && actual_type == "number")
+                && !(expected_type == "number" && actual_type == "integer")
             {
                 return Err(anyhow!(
                     "Parameter '{}' has type '{}' but expected '{}'",
@@ -271,10 +448,70 @@ impl AgentExecutor {
             }
         }
 
+        if let Some(allowed) = schema.get("enum").and_then(|e| e.as_array()) {
+            if !allowed.contains(value) {
+                let options: Vec<String> = allowed.iter().map(|v| v.to_string()).collect();
+                return Err(anyhow!(
+                    "Parameter '{}' must be one of [{}], got {}",
+                    param_name,
+                    options.join(", "),
+                    value
+                ));
+            }
+        }
+
+        match value {
+            serde_json::Value::Array(items) => {
+                if let Some(item_schema) = schema.get("items") {
+                    for (index, item) in items.iter().enumerate() {
+                        self.validate_parameter_type(
+                            &format!("{param_name}[{index}]"),
+                            item,
+                            item_schema,
+                        )?;
+                    }
+                }
+            }
+            serde_json::Value::Object(fields) => {
+                if let Some(required) = schema.get("required").and_then(|r| r.as_array()) {
+                    for field in required.iter().filter_map(|r| r.as_str()) {
+                        if !fields.contains_key(field) {
+                            return Err(anyhow!(
+                                "Missing required parameter: {}.{}",
+                                param_name,
+                                field
+                            ));
+                        }
+                    }
+                }
+                if let Some(properties) = schema.get("properties").and_then(|p| p.as_object()) {
+                    for (field, field_value) in fields {
+                        if let Some(field_schema) = properties.get(field) {
+                            self.validate_parameter_type(
+                                &format!("{param_name}.{field}"),
+                                field_value,
+                                field_schema,
+                            )?;
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+
         Ok(())
     }
 }
 
+/// Whether a JSON number has no fractional part (`3` and `3.0`, but not `3.5`)
+fn is_whole_number(number: &serde_json::Number) -> bool {
+    number.is_i64()
+        || number.is_u64()
+        || number
+            .as_f64()
+            .is_some_and(|n| n.is_finite() && n.fract() == 0.0)
+}
+
 /// Information about a tool
 #[derive(Debug, Clone)]
 pub struct ToolInfo {
@@ -326,3 +563,212 @@ impl ToolInfo {
         desc
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn disabled_tools_are_not_registered() {
+        let config = AgentConfig {
+            disabled_tools: vec!["write_file".to_string()],
+            ..AgentConfig::default()
+        };
+        let safety_manager = SafetyManager::new(&config).unwrap();
+        let executor = AgentExecutor::new(config, safety_manager).unwrap();
+
+        let tools = executor.available_tools();
+        assert!(!tools.contains(&"write_file".to_string()));
+        assert!(tools.contains(&"read_file".to_string()));
+
+        let call = ToolCall {
+            tool: "write_file".to_string(),
+            parameters: HashMap::new(),
+            thought: None,
+            reasoning: None,
+        };
+        let err = executor.execute(call).await.unwrap_err();
+        assert!(err.to_string().contains("Unknown tool"));
+    }
+    fn update_call(parameters: serde_json::Value) -> ToolCall {
+        ToolCall {
+            tool: "update_file".to_string(),
+            parameters: serde_json::from_value(parameters).unwrap(),
+            thought: None,
+            reasoning: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn invalid_enum_values_are_rejected_before_execution() {
+        let dir = std::env::temp_dir();
+        let config = AgentConfig {
+            working_directory: dir.clone(),
+            ..AgentConfig::default()
+        };
+        let safety_manager = SafetyManager::new(&config).unwrap();
+        let executor = AgentExecutor::new(config, safety_manager).unwrap();
+
+        let call = update_call(serde_json::json!({
+            "path": dir.join("chatter-enum-check.txt"),
+            "operation": "overwrite",
+        }));
+        let error = executor.validate_tool_call(&call).unwrap_err().to_string();
+        assert_eq!(
+            error,
+            "Parameter 'operation' must be one of [\"replace\", \"append\", \"prepend\", \"insert_at_line\", \"insert_after\", \"insert_before\"], got \"overwrite\""
+        );
+
+        let result = executor.execute(call).await.unwrap();
+        assert!(!result.success);
+        assert!(result
+            .message
+            .unwrap()
+            .starts_with("Parameter validation failed"));
+    }
+
+    #[tokio::test]
+    async fn preview_returns_a_diff_without_writing() {
+        let dir = std::env::temp_dir().join(format!("chatter-preview-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("notes.txt");
+        std::fs::write(&path, "alpha\nbeta\ngamma\n").unwrap();
+
+        let config = AgentConfig {
+            working_directory: dir.clone(),
+            ..AgentConfig::default()
+        };
+        assert!(!config.dry_run_mode);
+        let safety_manager = SafetyManager::new(&config).unwrap();
+        let executor = AgentExecutor::new(config, safety_manager).unwrap();
+
+        let result = executor
+            .execute(update_call(serde_json::json!({
+                "path": path,
+                "operation": "replace",
+                "search": "beta",
+                "replacement": "BETA",
+                "preview": true,
+            })))
+            .await
+            .unwrap();
+
+        assert!(result.success);
+        assert_eq!(result.data["dry_run"], true);
+        let diff = result.data["diff"].as_str().unwrap();
+        assert!(diff.contains("-beta\n"));
+        assert!(diff.contains("+BETA\n"));
+        assert!(result.modified_files.is_empty());
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "alpha\nbeta\ngamma\n"
+        );
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1, "no backup");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn confirm_writes_asks_before_changing_files() {
+        use std::sync::Mutex;
+
+        let dir = std::env::temp_dir().join(format!("chatter-confirm-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("notes.txt");
+        std::fs::write(&path, "draft\n").unwrap();
+
+        let config = AgentConfig {
+            working_directory: dir.clone(),
+            auto_backup: false,
+            confirm_writes: true,
+            ..AgentConfig::default()
+        };
+        let asked = Arc::new(Mutex::new(Vec::new()));
+        let executor_with = |answer: bool| {
+            let asked = asked.clone();
+            let safety_manager = SafetyManager::new(&config).unwrap();
+            AgentExecutor::new(config.clone(), safety_manager)
+                .unwrap()
+                .with_write_confirmer(WriteConfirmer::new(move |change| {
+                    asked.lock().unwrap().push(change.line_counts());
+                    answer
+                }))
+        };
+        let call = update_call(serde_json::json!({
+            "path": path,
+            "operation": "replace",
+            "search": "draft",
+            "replacement": "final",
+        }));
+
+        let declined = executor_with(false).execute(call.clone()).await.unwrap();
+        assert!(!declined.success);
+        assert_eq!(declined.message.as_deref(), Some("User declined operation"));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "draft\n");
+
+        let accepted = executor_with(true).execute(call).await.unwrap();
+        assert!(accepted.success);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "final\n");
+        assert_eq!(*asked.lock().unwrap(), vec![(1, 1), (1, 1)]);
+
+        // Reads never ask
+        let read = ToolCall {
+            tool: "read_file".to_string(),
+            parameters: serde_json::from_value(serde_json::json!({ "path": path })).unwrap(),
+            thought: None,
+            reasoning: None,
+        };
+        assert!(executor_with(false).execute(read).await.unwrap().success);
+        assert_eq!(asked.lock().unwrap().len(), 2);
+
+        // A change that cannot be shown, such as overwriting a binary file, is declined
+        let binary = dir.join("data.txt");
+        std::fs::write(&binary, [0xff, 0xfe, 0x00]).unwrap();
+        let overwrite = ToolCall {
+            tool: "write_file".to_string(),
+            parameters: serde_json::from_value(
+                serde_json::json!({ "path": binary, "content": "text" }),
+            )
+            .unwrap(),
+            thought: None,
+            reasoning: None,
+        };
+        let declined = executor_with(true).execute(overwrite).await.unwrap();
+        assert!(!declined.success);
+        assert!(declined.message.unwrap().starts_with("Declined"));
+        assert_eq!(std::fs::read(&binary).unwrap(), [0xff, 0xfe, 0x00]);
+        assert_eq!(asked.lock().unwrap().len(), 2);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn integers_must_be_whole_numbers() {
+        let config = AgentConfig::default();
+        let safety_manager = SafetyManager::new(&config).unwrap();
+        let executor = AgentExecutor::new(config, safety_manager).unwrap();
+
+        let call = |line_number: serde_json::Value| {
+            update_call(serde_json::json!({
+                "path": "notes.txt",
+                "operation": "insert_at_line",
+                "line_number": line_number,
+            }))
+        };
+
+        let error = executor
+            .validate_tool_call(&call(serde_json::json!("12")))
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Parameter 'line_number' has type 'string' but expected 'integer'"
+        );
+        assert!(executor
+            .validate_tool_call(&call(serde_json::json!(2.5)))
+            .is_err());
+        assert!(executor
+            .validate_tool_call(&call(serde_json::json!(12)))
+            .is_ok());
+    }
+}
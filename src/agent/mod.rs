@@ -4,20 +4,27 @@
 //! within a safe, sandboxed environment.
 
 use crate::api::ToolDefinition;
+use crate::error::ChatterError;
 use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
+pub mod cache;
+pub mod calculator;
 pub mod completion;
 pub mod executor;
+pub mod results;
 pub mod safety;
+pub mod stats;
 pub mod tools;
 
+pub use cache::ToolResultCache;
 pub use completion::{CompletionDetector, CompletionStatus};
 pub use executor::AgentExecutor;
-pub use safety::SafetyManager;
-pub use tools::{ToolCall, ToolResult};
+pub use safety::{SafetyManager, SafetyRules};
+pub use stats::ToolStats;
+pub use tools::{ExternalToolConfig, ToolCall, ToolResult};
 
 /// Agent configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -34,6 +41,18 @@ pub struct AgentConfig {
     pub auto_backup: bool,
     /// Whether to run in dry-run mode (preview only)
     pub dry_run_mode: bool,
+    /// Ask before `write_file` and `update_file` change anything
+    #[serde(default)]
+    pub confirm_writes: bool,
+    /// User-defined tools backed by external commands
+    #[serde(default)]
+    pub external_tools: Vec<ExternalToolConfig>,
+    /// Tools that are never registered or offered to the model
+    #[serde(default)]
+    pub disabled_tools: Vec<String>,
+    /// Forbidden paths and patterns on top of the built-in protections
+    #[serde(default)]
+    pub safety: SafetyRules,
 }
 
 impl Default for AgentConfig {
@@ -62,6 +81,10 @@ impl Default for AgentConfig {
             working_directory,
             auto_backup: true,
             dry_run_mode: false,
+            confirm_writes: false,
+            external_tools: Vec::new(),
+            disabled_tools: Vec::new(),
+            safety: SafetyRules::default(),
         }
     }
 }
@@ -74,6 +97,7 @@ pub struct Agent {
     completion_detector: CompletionDetector,
     safety_manager: SafetyManager,
     tool_history: Vec<ToolCall>,
+    tool_stats: ToolStats,
 }
 
 impl Agent {
@@ -91,6 +115,7 @@ impl Agent {
             completion_detector,
             safety_manager,
             tool_history: Vec::new(),
+            tool_stats: ToolStats::default(),
         })
     }
 
@@ -118,6 +143,35 @@ impl Agent {
         Ok(())
     }
 
+    /// Stop registering a tool so it can no longer be offered or executed
+    pub fn disable_tool(&mut self, name: &str) -> Result<()> {
+        if self.config.disabled_tools.iter().any(|tool| tool == name) {
+            return Err(anyhow!("Tool '{name}' is already disabled"));
+        }
+        if !self.executor.has_tool(name) {
+            return Err(anyhow!("Unknown tool: {name}"));
+        }
+
+        self.config.disabled_tools.push(name.to_string());
+        self.rebuild_executor()
+    }
+
+    /// Re-register a previously disabled tool
+    pub fn enable_tool(&mut self, name: &str) -> Result<()> {
+        if !self.config.disabled_tools.iter().any(|tool| tool == name) {
+            return Err(anyhow!("Tool '{name}' is not disabled"));
+        }
+
+        self.config.disabled_tools.retain(|tool| tool != name);
+        self.rebuild_executor()
+    }
+
+    /// Rebuild the executor after the tool set changed, keeping runtime safety rules
+    fn rebuild_executor(&mut self) -> Result<()> {
+        self.executor = AgentExecutor::new(self.config.clone(), self.safety_manager.clone())?;
+        Ok(())
+    }
+
     /// Process a message and detect tool calls
     pub fn detect_tool_calls(&self, message: &str) -> Result<Vec<ToolCall>> {
         if !self.is_enabled() {
@@ -129,25 +183,19 @@ impl Agent {
     }
 
     /// Execute a tool call
-    pub async fn execute_tool(&mut self, tool_call: ToolCall) -> Result<ToolResult> {
+    pub async fn execute_tool(&mut self, tool_call: ToolCall) -> Result<ToolResult, ChatterError> {
         if !self.is_enabled() {
-            return Err(anyhow!("Agent mode is not enabled"));
+            return Err(anyhow!("Agent mode is not enabled").into());
         }
 
         // Add to history
         self.tool_history.push(tool_call.clone());
 
         // Execute the tool and record activity
-        match self.executor.execute(tool_call).await {
-            Ok(result) => {
-                self.completion_detector.record_tool_execution();
-                Ok(result)
-            }
-            Err(e) => {
-                self.completion_detector.record_tool_execution();
-                Err(e)
-            }
-        }
+        let outcome = self.executor.execute(tool_call.clone()).await;
+        self.completion_detector.record_tool_execution();
+        self.tool_stats.record(&tool_call, outcome.as_ref().ok());
+        outcome.map_err(ChatterError::from)
     }
 
     /// Check if the current task appears to be complete
@@ -182,9 +230,15 @@ impl Agent {
         &self.tool_history
     }
 
+    /// Usage statistics for the tools executed so far
+    pub fn tool_stats(&self) -> &ToolStats {
+        &self.tool_stats
+    }
+
     /// Clear tool history
     pub fn clear_history(&mut self) {
         self.tool_history.clear();
+        self.tool_stats = ToolStats::default();
     }
 
     /// Get available tools
@@ -434,4 +488,66 @@ mod tests {
         let normalized = normalize_working_directory(absolute.as_path()).unwrap();
         assert_eq!(normalized, absolute);
     }
+
+    #[tokio::test]
+    async fn dry_run_previews_writes_without_performing_them() {
+        let dir = std::env::temp_dir().join(format!("chatter-dry-run-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let target = dir.join("notes.txt");
+
+        let mut agent = Agent::new(AgentConfig {
+            enabled: true,
+            dry_run_mode: true,
+            working_directory: dir.clone(),
+            ..AgentConfig::default()
+        })
+        .unwrap();
+
+        let mut parameters = HashMap::new();
+        parameters.insert(
+            "path".to_string(),
+            serde_json::json!(target.to_str().unwrap()),
+        );
+        parameters.insert("content".to_string(), serde_json::json!("hello"));
+        let result = agent
+            .execute_tool(ToolCall {
+                tool: "write_file".to_string(),
+                parameters,
+                thought: None,
+                reasoning: None,
+            })
+            .await
+            .unwrap();
+
+        assert!(result.success);
+        assert_eq!(result.data["dry_run"], true);
+        assert!(!target.exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn rejected_tool_calls_report_a_typed_error() {
+        let mut agent = Agent::new(AgentConfig {
+            enabled: true,
+            ..AgentConfig::default()
+        })
+        .unwrap();
+        let call = |tool: &str, path: &str| {
+            let mut parameters = HashMap::new();
+            parameters.insert("path".to_string(), serde_json::json!(path));
+            ToolCall {
+                tool: tool.to_string(),
+                parameters,
+                thought: None,
+                reasoning: None,
+            }
+        };
+
+        let missing = agent.execute_tool(call("teleport", "/tmp")).await;
+        assert!(matches!(missing, Err(ChatterError::ToolNotFound(name)) if name == "teleport"));
+
+        let forbidden = agent.execute_tool(call("read_file", "/etc/shadow")).await;
+        assert!(matches!(forbidden, Err(ChatterError::SafetyViolation(_))));
+    }
 }
@@ -0,0 +1,399 @@
+//! Typed results of the built-in file tools
+//!
+//! The tools build these structs and store them in `ToolResult::data` as JSON,
+//! so the payload the model sees keeps its shape. Display code decodes them
+//! with `ToolOutput::parse` instead of picking fields out of untyped JSON.
+//! External tools still return free-form `data`.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Encode a typed result as the JSON stored in `ToolResult::data`
+pub fn to_data(result: &impl Serialize) -> Value {
+    // Structs of strings, numbers, and options always serialize
+    serde_json::to_value(result).unwrap_or_default()
+}
+
+/// `read_file`: the whole file, a line range, or a byte window
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReadFileResult {
+    pub path: String,
+    pub content: String,
+    /// Bytes of `content`, for whole-file and line reads
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_line: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_line: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_lines: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub byte_offset: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bytes_read: Option<usize>,
+    /// Size of the whole file, for byte windows
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eof: Option<bool>,
+}
+
+/// `write_file`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WriteFileResult {
+    pub path: String,
+    pub size: usize,
+}
+
+/// `update_file`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UpdateFileResult {
+    pub path: String,
+    pub operation: String,
+    pub original_size: usize,
+    pub new_size: usize,
+}
+
+/// Where a pattern matched within a line
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MatchSpan {
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+/// A line shown around a match
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContextLine {
+    pub line: usize,
+    pub content: String,
+}
+
+/// One matching line from `search_files`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchMatch {
+    /// Omitted when matches are grouped under their file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    pub line: usize,
+    pub content: String,
+    pub matches: Vec<MatchSpan>,
+    /// The requested capture group for each match
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub captures: Option<Vec<Option<String>>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<Vec<ContextLine>>,
+}
+
+/// Matches from one file, for `group_by_file`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileMatches {
+    pub file: String,
+    pub match_count: usize,
+    pub matches: Vec<SearchMatch>,
+}
+
+/// Number of matches in one file, for `count_only`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileCount {
+    pub file: String,
+    pub match_count: usize,
+}
+
+/// Search results, flat or grouped by file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SearchMatches {
+    Flat(Vec<SearchMatch>),
+    Grouped(Vec<FileMatches>),
+}
+
+/// `search_files`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchResult {
+    pub pattern: String,
+    pub directory: String,
+    pub files_searched: usize,
+    /// Binary files left out of the search
+    pub files_skipped: usize,
+    pub matches_found: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grouped: Option<bool>,
+    /// File the full results were written to; `results` is then left empty
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_file: Option<String>,
+    /// `json` or `text`, when written to a file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_format: Option<String>,
+    /// Matching lines; left out by `count_only` and `files_with_matches`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub results: Option<SearchMatches>,
+    /// Matches in each file, for `count_only`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_counts: Option<Vec<FileCount>>,
+    /// Files with at least one match, for `files_with_matches`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub files: Option<Vec<String>>,
+}
+
+/// One entry of a directory listing
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ListedEntry {
+    pub path: String,
+    pub name: String,
+    /// `file` or `directory`
+    #[serde(rename = "type")]
+    pub entry_type: String,
+    pub size: u64,
+    pub modified: Option<u64>,
+    /// Summed size of the files inside a directory, when requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_size: Option<u64>,
+}
+
+impl ListedEntry {
+    pub fn is_dir(&self) -> bool {
+        self.entry_type == "directory"
+    }
+}
+
+/// `list_directory`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ListDirectoryResult {
+    pub path: String,
+    pub recursive: bool,
+    pub file_pattern: Option<String>,
+    pub sort_by: Option<String>,
+    pub entry_count: usize,
+    pub entries: Vec<ListedEntry>,
+    /// Size of every file under the listed path, when requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_size: Option<u64>,
+}
+
+/// `file_info`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileInfoResult {
+    pub path: String,
+    pub name: String,
+    /// `file`, `directory`, or `other`
+    #[serde(rename = "type")]
+    pub file_type: String,
+    pub size: u64,
+    pub readonly: bool,
+    pub created: Option<u64>,
+    pub modified: Option<u64>,
+    pub accessed: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extension: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_text: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line_count: Option<usize>,
+}
+
+/// A built-in tool's result, decoded from `ToolResult::data`
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToolOutput {
+    ReadFile(ReadFileResult),
+    WriteFile(WriteFileResult),
+    UpdateFile(UpdateFileResult),
+    Search(SearchResult),
+    ListDirectory(ListDirectoryResult),
+    FileInfo(FileInfoResult),
+}
+
+impl ToolOutput {
+    /// Decode the data of a built-in tool
+    ///
+    /// Returns `None` for other tools and for data of another shape, such as a
+    /// preview diff.
+    pub fn parse(tool_name: &str, data: &Value) -> Option<Self> {
+        fn decode<T: for<'de> Deserialize<'de>>(data: &Value) -> Option<T> {
+            T::deserialize(data).ok()
+        }
+
+        match tool_name {
+            "read_file" => decode(data).map(Self::ReadFile),
+            "write_file" => decode(data).map(Self::WriteFile),
+            "update_file" => decode(data).map(Self::UpdateFile),
+            "search_files" => decode(data).map(Self::Search),
+            "list_directory" => decode(data).map(Self::ListDirectory),
+            "file_info" => decode(data).map(Self::FileInfo),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(value: &Value) -> Vec<&str> {
+        let mut keys: Vec<&str> = value
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        keys.sort_unstable();
+        keys
+    }
+
+    #[test]
+    fn typed_results_keep_the_json_keys_of_each_tool() {
+        let whole = to_data(&ReadFileResult {
+            path: "a.txt".to_string(),
+            content: "hi".to_string(),
+            size: Some(2),
+            ..ReadFileResult::default()
+        });
+        assert_eq!(keys(&whole), vec!["content", "path", "size"]);
+
+        let window = to_data(&ReadFileResult {
+            path: "a.txt".to_string(),
+            content: "hi".to_string(),
+            byte_offset: Some(0),
+            bytes_read: Some(2),
+            total_size: Some(2),
+            eof: Some(true),
+            ..ReadFileResult::default()
+        });
+        assert_eq!(
+            keys(&window),
+            vec![
+                "byte_offset",
+                "bytes_read",
+                "content",
+                "eof",
+                "path",
+                "total_size"
+            ]
+        );
+
+        let update = to_data(&UpdateFileResult {
+            path: "a.txt".to_string(),
+            operation: "append".to_string(),
+            original_size: 2,
+            new_size: 5,
+        });
+        assert_eq!(
+            keys(&update),
+            vec!["new_size", "operation", "original_size", "path"]
+        );
+
+        let listing = to_data(&ListDirectoryResult {
+            path: ".".to_string(),
+            recursive: false,
+            file_pattern: None,
+            sort_by: None,
+            entry_count: 1,
+            entries: vec![ListedEntry {
+                path: "./src".to_string(),
+                name: "src".to_string(),
+                entry_type: "directory".to_string(),
+                size: 4096,
+                modified: None,
+                total_size: None,
+            }],
+            total_size: None,
+        });
+        assert_eq!(
+            keys(&listing),
+            vec![
+                "entries",
+                "entry_count",
+                "file_pattern",
+                "path",
+                "recursive",
+                "sort_by"
+            ]
+        );
+        assert_eq!(listing["file_pattern"], Value::Null);
+        assert_eq!(
+            keys(&listing["entries"][0]),
+            vec!["modified", "name", "path", "size", "type"]
+        );
+    }
+
+    #[test]
+    fn search_results_round_trip_flat_and_grouped() {
+        let hit = SearchMatch {
+            file: Some("src/main.rs".to_string()),
+            line: 3,
+            content: "// TODO".to_string(),
+            matches: vec![MatchSpan {
+                start: 3,
+                end: 7,
+                text: "TODO".to_string(),
+            }],
+            captures: None,
+            context: None,
+        };
+        let flat = SearchResult {
+            pattern: "TODO".to_string(),
+            directory: ".".to_string(),
+            files_searched: 1,
+            files_skipped: 0,
+            matches_found: 1,
+            grouped: None,
+            output_file: None,
+            output_format: None,
+            results: Some(SearchMatches::Flat(vec![hit.clone()])),
+            file_counts: None,
+            files: None,
+        };
+        let data = to_data(&flat);
+        assert_eq!(
+            keys(&data),
+            vec![
+                "directory",
+                "files_searched",
+                "files_skipped",
+                "matches_found",
+                "pattern",
+                "results"
+            ]
+        );
+        assert_eq!(
+            keys(&data["results"][0]),
+            vec!["content", "file", "line", "matches"]
+        );
+        assert_eq!(
+            ToolOutput::parse("search_files", &data),
+            Some(ToolOutput::Search(flat))
+        );
+
+        let grouped = SearchResult {
+            grouped: Some(true),
+            results: Some(SearchMatches::Grouped(vec![FileMatches {
+                file: "src/main.rs".to_string(),
+                match_count: 1,
+                matches: vec![SearchMatch { file: None, ..hit }],
+            }])),
+            ..match ToolOutput::parse("search_files", &data) {
+                Some(ToolOutput::Search(result)) => result,
+                _ => unreachable!(),
+            }
+        };
+        let data = to_data(&grouped);
+        assert_eq!(
+            keys(&data["results"][0]),
+            vec!["file", "match_count", "matches"]
+        );
+        assert_eq!(
+            ToolOutput::parse("search_files", &data),
+            Some(ToolOutput::Search(grouped))
+        );
+
+        // Other tools and other shapes are left to the caller
+        assert_eq!(ToolOutput::parse("calculate", &data), None);
+        assert_eq!(
+            ToolOutput::parse("write_file", &serde_json::json!({"diff": "+x"})),
+            None
+        );
+    }
+}
@@ -4,8 +4,177 @@
 
 use super::{AgentConfig, ToolCall};
 use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 
+/// System paths that are always forbidden, whatever the configuration says
+const CRITICAL_PATHS: &[&str] = &[
+    "/etc",
+    "/usr",
+    "/bin",
+    "/sbin",
+    "/boot",
+    "/dev",
+    "/proc",
+    "/sys",
+    "/var/log",
+    "/var/lib",
+    "/root",
+    "/home/*/.ssh",
+    "/home/*/.gnupg",
+    "C:\\Windows",
+    "C:\\Program Files",
+    "C:\\Program Files (x86)",
+    "C:\\System32",
+];
+
+/// File name fragments that mark a path as sensitive unless configured otherwise
+const DEFAULT_SENSITIVE_PATTERNS: &[&str] = &[
+    "passwd",
+    "shadow",
+    "hosts",
+    "sudoers",
+    "ssh_config",
+    "authorized_keys",
+    "id_rsa",
+    "id_dsa",
+    "id_ecdsa",
+    "id_ed25519",
+    ".env",
+    "config.json",
+    "database.yml",
+    "secrets.yml",
+    "private.key",
+    "certificate.pem",
+];
+
+/// Content fragments refused in writes unless configured otherwise
+const DEFAULT_DANGEROUS_PATTERNS: &[&str] = &[
+    "rm -rf",
+    "del /s",
+    "format c:",
+    "dd if=",
+    ":(){ :|:& };:",
+    "sudo rm",
+    "chmod 777",
+    "wget http",
+    "curl http",
+    "eval(",
+    "exec(",
+    "system(",
+    "shell_exec(",
+    "<script",
+    "javascript:",
+    "data:text/html",
+];
+
+/// Dangerous patterns that belong in some kinds of file, as (extensions, patterns)
+const DEFAULT_CONTENT_EXEMPTIONS: &[(&[&str], &[&str])] = &[
+    (&["sh", "bash", "zsh"], &["curl http", "wget http"]),
+    (&["html", "htm"], &["<script", "javascript:"]),
+];
+
+fn to_strings(patterns: &[&str]) -> Vec<String> {
+    patterns.iter().map(|pattern| pattern.to_string()).collect()
+}
+
+fn default_builtin_patterns() -> bool {
+    true
+}
+
+fn default_content_exemptions() -> Vec<ContentExemption> {
+    DEFAULT_CONTENT_EXEMPTIONS
+        .iter()
+        .map(|(extensions, patterns)| ContentExemption {
+            extensions: to_strings(extensions),
+            paths: Vec::new(),
+            patterns: to_strings(patterns),
+        })
+        .collect()
+}
+
+/// Dangerous patterns permitted in files with certain extensions or locations
+///
+/// A written file is exempt when its extension is listed or it lies under one
+/// of the paths (relative paths start at the working directory).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContentExemption {
+    /// Extensions without the dot, compared case-insensitively
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    /// Directories whose files are exempt
+    #[serde(default)]
+    pub paths: Vec<PathBuf>,
+    /// Entries of `dangerous_patterns` allowed in those files
+    pub patterns: Vec<String>,
+}
+
+/// User-adjustable safety rules
+///
+/// Forbidden and allowed paths and both pattern lists add to the built-in rules.
+/// Setting `builtin_patterns` to false drops the built-in patterns, leaving only
+/// the configured ones; the critical system paths stay forbidden regardless.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SafetyRules {
+    /// Extra paths the agent may never touch (`*` matches any run of characters)
+    #[serde(default)]
+    pub forbidden_paths: Vec<PathBuf>,
+    /// Extra directories the agent may use besides the working directory
+    #[serde(default)]
+    pub allowed_paths: Vec<PathBuf>,
+    /// Extra case-insensitive path fragments that mark a file as sensitive
+    #[serde(default)]
+    pub sensitive_patterns: Vec<String>,
+    /// Extra case-insensitive fragments refused in written content
+    #[serde(default)]
+    pub dangerous_patterns: Vec<String>,
+    /// Whether the built-in sensitive and dangerous patterns apply
+    #[serde(default = "default_builtin_patterns")]
+    pub builtin_patterns: bool,
+    /// Dangerous patterns allowed in particular files, such as `<script` in HTML
+    #[serde(default = "default_content_exemptions")]
+    pub content_exemptions: Vec<ContentExemption>,
+}
+
+impl Default for SafetyRules {
+    fn default() -> Self {
+        Self {
+            forbidden_paths: Vec::new(),
+            allowed_paths: Vec::new(),
+            sensitive_patterns: Vec::new(),
+            dangerous_patterns: Vec::new(),
+            builtin_patterns: true,
+            content_exemptions: default_content_exemptions(),
+        }
+    }
+}
+
+impl SafetyRules {
+    /// Path fragments in force: the built-in ones, unless turned off, and the configured ones
+    fn sensitive_patterns(&self) -> Vec<String> {
+        self.with_builtins(DEFAULT_SENSITIVE_PATTERNS, &self.sensitive_patterns)
+    }
+
+    /// Content fragments in force: the built-in ones, unless turned off, and the configured ones
+    fn dangerous_patterns(&self) -> Vec<String> {
+        self.with_builtins(DEFAULT_DANGEROUS_PATTERNS, &self.dangerous_patterns)
+    }
+
+    fn with_builtins(&self, builtins: &[&str], configured: &[String]) -> Vec<String> {
+        let mut patterns = if self.builtin_patterns {
+            to_strings(builtins)
+        } else {
+            Vec::new()
+        };
+        for pattern in configured {
+            if !patterns.contains(pattern) {
+                patterns.push(pattern.clone());
+            }
+        }
+        patterns
+    }
+}
+
 /// Safety manager for agent operations
 #[derive(Debug, Clone)]
 pub struct SafetyManager {
@@ -34,31 +203,15 @@ impl SafetyManager {
         // Allow operations in the working directory and subdirectories
         self.allowed_paths
             .push(self.config.working_directory.clone());
+        self.allowed_paths
+            .extend(self.config.safety.allowed_paths.iter().cloned());
 
-        // Forbidden system paths
-        let forbidden = [
-            "/etc",
-            "/usr",
-            "/bin",
-            "/sbin",
-            "/boot",
-            "/dev",
-            "/proc",
-            "/sys",
-            "/var/log",
-            "/var/lib",
-            "/root",
-            "/home/*/.ssh",
-            "/home/*/.gnupg",
-            "C:\\Windows",
-            "C:\\Program Files",
-            "C:\\Program Files (x86)",
-            "C:\\System32",
-        ];
-
-        for path in &forbidden {
+        // Forbidden system paths, followed by any configured by the user
+        for path in CRITICAL_PATHS {
             self.forbidden_paths.push(PathBuf::from(path));
         }
+        self.forbidden_paths
+            .extend(self.config.safety.forbidden_paths.iter().cloned());
 
         Ok(())
     }
@@ -88,12 +241,32 @@ impl SafetyManager {
             "search_files" => {
                 let directory = self.resolve_path_argument(tool_call, "directory", Some("."))?;
                 self.check_file_path_safety(&directory)?;
+                // Results written to a file get the same checks as write_file
+                if let Some(output) = tool_call.parameters.get("output_file") {
+                    let output = output
+                        .as_str()
+                        .ok_or_else(|| anyhow!("Parameter 'output_file' must be a string"))?;
+                    self.check_file_path_safety(output)?;
+                    self.check_extension(output)?;
+                }
             }
             "list_directory" => {
                 let path = self.resolve_path_argument(tool_call, "path", Some("."))?;
                 self.check_file_path_safety(&path)?;
             }
-            _ => {}
+            "calculate" => {
+                // Pure computation; the evaluator enforces its own input limits
+            }
+            _ => {
+                // External tools: apply path checks to any path-like parameters
+                for (name, value) in &tool_call.parameters {
+                    if let Some(path) = value.as_str() {
+                        if is_path_parameter(name) {
+                            self.check_file_path_safety(path)?;
+                        }
+                    }
+                }
+            }
         }
 
         // Check file size restrictions
@@ -223,27 +396,8 @@ impl SafetyManager {
         let path_str = path.to_string_lossy().to_lowercase();
 
         // Check for common sensitive files
-        let sensitive_patterns = [
-            "passwd",
-            "shadow",
-            "hosts",
-            "sudoers",
-            "ssh_config",
-            "authorized_keys",
-            "id_rsa",
-            "id_dsa",
-            "id_ecdsa",
-            "id_ed25519",
-            ".env",
-            "config.json",
-            "database.yml",
-            "secrets.yml",
-            "private.key",
-            "certificate.pem",
-        ];
-
-        for pattern in &sensitive_patterns {
-            if path_str.contains(pattern) {
+        for pattern in &self.config.safety.sensitive_patterns() {
+            if path_str.contains(&pattern.to_lowercase()) {
                 return Err(anyhow!(
                     "Access to potentially sensitive file '{}' is not allowed",
                     path.display()
@@ -276,7 +430,11 @@ impl SafetyManager {
             .get("path")
             .and_then(|v| v.as_str())
             .ok_or_else(|| anyhow!("Missing path parameter"))?;
+        self.check_extension(path)
+    }
 
+    /// Check that a file's extension, if it has one, is allowed
+    fn check_extension(&self, path: &str) -> Result<()> {
         let path = Path::new(path);
 
         if let Some(extension) = path.extension().and_then(|ext| ext.to_str()) {
@@ -297,29 +455,18 @@ impl SafetyManager {
     /// Check content for potentially dangerous patterns
     fn check_content_safety(&self, tool_call: &ToolCall) -> Result<()> {
         if let Some(content) = tool_call.parameters.get("content").and_then(|v| v.as_str()) {
-            // Check for potentially dangerous content patterns
-            let dangerous_patterns = [
-                "rm -rf",
-                "del /s",
-                "format c:",
-                "dd if=",
-                ":(){ :|:& };:",
-                "sudo rm",
-                "chmod 777",
-                "wget http",
-                "curl http",
-                "eval(",
-                "exec(",
-                "system(",
-                "shell_exec(",
-                "<script",
-                "javascript:",
-                "data:text/html",
-            ];
-
+            // Check for dangerous patterns not expected in this kind of file
+            let exempt = match tool_call.parameters.get("path").and_then(|v| v.as_str()) {
+                Some(path) => self.exempt_patterns(Path::new(path))?,
+                None => Vec::new(),
+            };
             let content_lower = content.to_lowercase();
-            for pattern in &dangerous_patterns {
-                if content_lower.contains(pattern) {
+            for pattern in &self.config.safety.dangerous_patterns() {
+                let pattern_lower = pattern.to_lowercase();
+                if exempt.contains(&pattern_lower) {
+                    continue;
+                }
+                if content_lower.contains(&pattern_lower) {
                     return Err(anyhow!(
                         "Content contains potentially dangerous pattern: '{}'",
                         pattern
@@ -345,6 +492,40 @@ impl SafetyManager {
         Ok(())
     }
 
+    /// Lowercased dangerous patterns the exemptions allow in the file at `path`
+    fn exempt_patterns(&self, path: &Path) -> Result<Vec<String>> {
+        let absolute = |path: &Path| {
+            if path.is_absolute() {
+                path.to_path_buf()
+            } else {
+                self.config.working_directory.join(path)
+            }
+        };
+        let target = self.normalize_path(&absolute(path))?;
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_lowercase);
+
+        let mut exempt = Vec::new();
+        for exemption in &self.config.safety.content_exemptions {
+            let extension_matches = extension.as_ref().is_some_and(|ext| {
+                exemption
+                    .extensions
+                    .iter()
+                    .any(|allowed| allowed.trim_start_matches('.').eq_ignore_ascii_case(ext))
+            });
+            let path_matches = exemption.paths.iter().any(|dir| {
+                self.normalize_path(&absolute(dir))
+                    .is_ok_and(|dir| target.starts_with(dir))
+            });
+            if extension_matches || path_matches {
+                exempt.extend(exemption.patterns.iter().map(|p| p.to_lowercase()));
+            }
+        }
+        Ok(exempt)
+    }
+
     /// Normalize a path by resolving . and .. components
     fn normalize_path(&self, path: &Path) -> Result<PathBuf> {
         let mut components = Vec::new();
@@ -433,6 +614,14 @@ impl SafetyManager {
     }
 }
 
+/// Check whether a parameter name conventionally holds a filesystem path
+fn is_path_parameter(name: &str) -> bool {
+    matches!(name, "path" | "file" | "directory" | "dir")
+        || name.ends_with("_path")
+        || name.ends_with("_file")
+        || name.ends_with("_dir")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -446,9 +635,76 @@ mod tests {
             working_directory: PathBuf::from("/tmp/test"),
             auto_backup: true,
             dry_run_mode: false,
+            confirm_writes: false,
+            external_tools: Vec::new(),
+            disabled_tools: Vec::new(),
+            safety: SafetyRules::default(),
+        }
+    }
+
+    fn read_call(path: &str) -> ToolCall {
+        let mut params = HashMap::new();
+        params.insert("path".to_string(), serde_json::json!(path));
+        ToolCall {
+            tool: "read_file".to_string(),
+            parameters: params,
+            thought: None,
+            reasoning: None,
         }
     }
 
+    #[test]
+    fn configured_forbidden_paths_are_honored() {
+        let mut config = create_test_config();
+        config.safety.forbidden_paths = vec![PathBuf::from("/tmp/test/secrets")];
+        let safety = SafetyManager::new(&config).unwrap();
+
+        assert!(safety.check_tool_call(&read_call("notes.txt")).is_ok());
+        let err = safety
+            .check_tool_call(&read_call("secrets/api.txt"))
+            .unwrap_err();
+        assert!(err.to_string().contains("forbidden"));
+    }
+
+    #[test]
+    fn configured_patterns_add_to_the_builtin_ones() {
+        let mut config = create_test_config();
+        config.safety.sensitive_patterns = vec!["payroll".to_string()];
+        config.safety.dangerous_patterns = vec!["DROP TABLE".to_string()];
+        let safety = SafetyManager::new(&config).unwrap();
+
+        assert!(safety.check_tool_call(&read_call("payroll.txt")).is_err());
+        assert!(safety.check_tool_call(&read_call(".env.txt")).is_err());
+        assert!(safety
+            .check_tool_call(&write_call("notes.txt", "drop table users;"))
+            .is_err());
+        assert!(safety
+            .check_tool_call(&write_call("notes.txt", "rm -rf /"))
+            .is_err());
+
+        // Only the configured patterns remain once the built-in ones are off
+        config.safety.builtin_patterns = false;
+        let safety = SafetyManager::new(&config).unwrap();
+        assert!(safety.check_tool_call(&read_call("payroll.txt")).is_err());
+        assert!(safety
+            .check_tool_call(&write_call("notes.txt", "rm -rf /"))
+            .is_ok());
+    }
+
+    #[test]
+    fn disabling_builtin_patterns_keeps_system_protections() {
+        let mut config = create_test_config();
+        config.safety.builtin_patterns = false;
+        config.safety.allowed_paths = vec![PathBuf::from("/")];
+        let safety = SafetyManager::new(&config).unwrap();
+
+        // Sensitive file names are no longer refused in the working directory...
+        assert!(safety.check_tool_call(&read_call(".env.txt")).is_ok());
+        // ...but critical system paths stay forbidden even when allowed
+        assert!(safety.check_tool_call(&read_call("/etc/motd.txt")).is_err());
+        assert!(!safety.would_allow_path(Path::new("/root/notes.txt")));
+    }
+
     #[test]
     fn test_path_traversal_detection() {
         let config = create_test_config();
@@ -543,6 +799,63 @@ mod tests {
         assert!(safety.check_tool_call(&tool_call).is_err());
     }
 
+    fn write_call(path: &str, content: &str) -> ToolCall {
+        let mut params = HashMap::new();
+        params.insert("path".to_string(), serde_json::json!(path));
+        params.insert("content".to_string(), serde_json::json!(content));
+        ToolCall {
+            tool: "write_file".to_string(),
+            parameters: params,
+            thought: None,
+            reasoning: None,
+        }
+    }
+
+    #[test]
+    fn dangerous_patterns_depend_on_the_file_being_written() {
+        let mut config = create_test_config();
+        config
+            .allowed_extensions
+            .extend(["html".into(), "sh".into()]);
+        let safety = SafetyManager::new(&config).unwrap();
+
+        let page = "<html><SCRIPT src=\"app.js\"></script></html>";
+        assert!(safety
+            .check_tool_call(&write_call("index.html", page))
+            .is_ok());
+        assert!(safety
+            .check_tool_call(&write_call("notes.txt", page))
+            .is_err());
+
+        let script = "curl https://example.com/install | tee install.log";
+        assert!(safety
+            .check_tool_call(&write_call("fetch.sh", script))
+            .is_ok());
+        assert!(safety
+            .check_tool_call(&write_call("notes.txt", script))
+            .is_err());
+
+        // Exemptions are per pattern: HTML still may not contain shell commands
+        let mixed = "<script></script><!-- rm -rf / -->";
+        assert!(safety
+            .check_tool_call(&write_call("index.html", mixed))
+            .is_err());
+
+        // A path exemption covers every file under the directory
+        config.safety.content_exemptions.push(ContentExemption {
+            extensions: Vec::new(),
+            paths: vec![PathBuf::from("scripts")],
+            patterns: vec!["curl http".to_string()],
+        });
+        let safety = SafetyManager::new(&config).unwrap();
+        assert!(safety
+            .check_tool_call(&write_call("scripts/setup.txt", script))
+            .is_ok());
+        assert!(safety
+            .check_tool_call(&write_call("setup.txt", script))
+            .is_err());
+    }
+
     #[test]
     fn test_search_files_directory_restriction() {
         let config = create_test_config();
@@ -568,6 +881,48 @@ mod tests {
         assert!(safety.check_tool_call(&tool_call).is_err());
     }
 
+    #[test]
+    fn test_external_tool_path_parameters_are_checked() {
+        let config = create_test_config();
+        let safety = SafetyManager::new(&config).unwrap();
+
+        let mut params = HashMap::new();
+        params.insert(
+            "input_path".to_string(),
+            serde_json::Value::String("/etc/shadow".to_string()),
+        );
+
+        let tool_call = ToolCall {
+            tool: "my_script".to_string(),
+            parameters: params,
+            thought: None,
+            reasoning: None,
+        };
+
+        assert!(safety.check_tool_call(&tool_call).is_err());
+    }
+
+    #[test]
+    fn search_output_file_must_pass_path_checks() {
+        let safety = SafetyManager::new(&create_test_config()).unwrap();
+        let search = |output: &str| {
+            let mut params = HashMap::new();
+            params.insert("pattern".to_string(), serde_json::json!("TODO"));
+            params.insert("output_file".to_string(), serde_json::json!(output));
+            ToolCall {
+                tool: "search_files".to_string(),
+                parameters: params,
+                thought: None,
+                reasoning: None,
+            }
+        };
+
+        assert!(safety.check_tool_call(&search("todo.txt")).is_ok());
+        assert!(safety.check_tool_call(&search("/etc/todo.txt")).is_err());
+        assert!(safety.check_tool_call(&search("../todo.txt")).is_err());
+        assert!(safety.check_tool_call(&search("todo.exe")).is_err());
+    }
+
     #[test]
     fn test_list_directory_restriction() {
         let config = create_test_config();
@@ -0,0 +1,154 @@
+//! Tool usage statistics
+//!
+//! Aggregates what the agent did over a session: calls per tool, files modified,
+//! and bytes moved by the read and write tools.
+
+use super::{ToolCall, ToolResult};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+/// Per-tool call counts
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ToolCounts {
+    pub calls: usize,
+    pub failures: usize,
+}
+
+/// Cumulative tool usage for an agent session
+#[derive(Debug, Clone, Default)]
+pub struct ToolStats {
+    /// Counts keyed by tool name, sorted for display
+    pub tools: BTreeMap<String, ToolCounts>,
+    /// Distinct files changed by write tools
+    pub files_modified: BTreeSet<PathBuf>,
+    pub bytes_read: u64,
+    pub bytes_written: u64,
+}
+
+impl ToolStats {
+    /// Record one tool call; `result` is `None` when execution returned an error
+    pub fn record(&mut self, tool_call: &ToolCall, result: Option<&ToolResult>) {
+        let counts = self.tools.entry(tool_call.tool.clone()).or_default();
+        counts.calls += 1;
+
+        let Some(result) = result.filter(|result| result.success) else {
+            counts.failures += 1;
+            return;
+        };
+
+        self.files_modified
+            .extend(result.modified_files.iter().cloned());
+
+        let size = |field: &str| result.data.get(field).and_then(|v| v.as_u64());
+        match tool_call.tool.as_str() {
+            // Byte-window reads report `bytes_read` instead of the file size
+            "read_file" => {
+                self.bytes_read += size("size").or(size("bytes_read")).unwrap_or(0);
+            }
+            "write_file" => self.bytes_written += size("size").unwrap_or(0),
+            "update_file" => self.bytes_written += size("new_size").unwrap_or(0),
+            _ => {}
+        }
+    }
+
+    /// Total number of tool calls
+    pub fn total_calls(&self) -> usize {
+        self.tools.values().map(|counts| counts.calls).sum()
+    }
+
+    /// Render the statistics as a small plain-text table
+    pub fn table(&self) -> String {
+        let width = self
+            .tools
+            .keys()
+            .map(|name| name.len())
+            .max()
+            .unwrap_or(0)
+            .max("Tool".len());
+
+        let mut lines = vec![format!(
+            "{:<width$}  {:>5}  {:>6}",
+            "Tool", "Calls", "Failed"
+        )];
+        for (name, counts) in &self.tools {
+            lines.push(format!(
+                "{:<width$}  {:>5}  {:>6}",
+                name, counts.calls, counts.failures
+            ));
+        }
+        lines.push(String::new());
+        lines.push(format!("Total calls:    {}", self.total_calls()));
+        lines.push(format!("Files modified: {}", self.files_modified.len()));
+        lines.push(format!("Bytes read:     {}", self.bytes_read));
+        lines.push(format!("Bytes written:  {}", self.bytes_written));
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(tool: &str) -> ToolCall {
+        ToolCall {
+            tool: tool.to_string(),
+            parameters: Default::default(),
+            thought: None,
+            reasoning: None,
+        }
+    }
+
+    #[test]
+    fn aggregates_counts_files_and_bytes() {
+        let mut stats = ToolStats::default();
+        let read = ToolResult::success(serde_json::json!({ "size": 100 }), None);
+        let write = ToolResult::success_with_files(
+            serde_json::json!({ "size": 40 }),
+            None,
+            vec![PathBuf::from("a.txt")],
+        );
+        let update = ToolResult::success_with_files(
+            serde_json::json!({ "original_size": 40, "new_size": 55 }),
+            None,
+            vec![PathBuf::from("a.txt")],
+        );
+
+        stats.record(&call("read_file"), Some(&read));
+        stats.record(&call("read_file"), Some(&read));
+        stats.record(
+            &call("read_file"),
+            Some(&ToolResult::error("missing".into())),
+        );
+        stats.record(&call("write_file"), Some(&write));
+        stats.record(&call("update_file"), Some(&update));
+        stats.record(&call("search_files"), None);
+
+        assert_eq!(stats.total_calls(), 6);
+        assert_eq!(
+            stats.tools["read_file"],
+            ToolCounts {
+                calls: 3,
+                failures: 1
+            }
+        );
+        assert_eq!(stats.tools["search_files"].failures, 1);
+        assert_eq!(stats.files_modified.len(), 1);
+        assert_eq!(stats.bytes_read, 200);
+        assert_eq!(stats.bytes_written, 95);
+
+        let table = stats.table();
+        assert!(table.contains("read_file"));
+        assert!(table.contains("Bytes written:  95"));
+    }
+
+    #[test]
+    fn windowed_reads_count_the_bytes_read() {
+        let mut stats = ToolStats::default();
+        let window = ToolResult::success(
+            serde_json::json!({ "bytes_read": 512, "total_size": 4096, "eof": false }),
+            None,
+        );
+        stats.record(&call("read_file"), Some(&window));
+        assert_eq!(stats.bytes_read, 512);
+    }
+}
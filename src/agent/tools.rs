@@ -3,12 +3,23 @@
 //! Provides safe file operations, search capabilities, and other utilities
 //! for autonomous task execution.
 
+use super::calculator;
+use super::results::{
+    to_data, ContextLine, FileCount, FileInfoResult, FileMatches, ListDirectoryResult, ListedEntry,
+    MatchSpan, ReadFileResult, SearchMatch, SearchMatches, SearchResult, UpdateFileResult,
+    WriteFileResult,
+};
 use anyhow::{anyhow, Result};
+use rayon::prelude::*;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
 use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
 use walkdir::WalkDir;
 
 /// A tool call request
@@ -82,6 +93,8 @@ pub enum Tool {
     SearchFiles(SearchFilesTool),
     ListDirectory(ListDirectoryTool),
     FileInfo(FileInfoTool),
+    Calculate(CalculateTool),
+    External(ExternalTool),
 }
 
 impl Tool {
@@ -94,6 +107,8 @@ impl Tool {
             Tool::SearchFiles(tool) => tool.name(),
             Tool::ListDirectory(tool) => tool.name(),
             Tool::FileInfo(tool) => tool.name(),
+            Tool::Calculate(tool) => tool.name(),
+            Tool::External(tool) => tool.name(),
         }
     }
 
@@ -106,6 +121,8 @@ impl Tool {
             Tool::SearchFiles(tool) => tool.description(),
             Tool::ListDirectory(tool) => tool.description(),
             Tool::FileInfo(tool) => tool.description(),
+            Tool::Calculate(tool) => tool.description(),
+            Tool::External(tool) => tool.description(),
         }
     }
 
@@ -118,6 +135,8 @@ impl Tool {
             Tool::SearchFiles(tool) => tool.parameters(),
             Tool::ListDirectory(tool) => tool.parameters(),
             Tool::FileInfo(tool) => tool.parameters(),
+            Tool::Calculate(tool) => tool.parameters(),
+            Tool::External(tool) => tool.parameters(),
         }
     }
 
@@ -133,6 +152,8 @@ impl Tool {
             Tool::SearchFiles(tool) => tool.execute(parameters).await,
             Tool::ListDirectory(tool) => tool.execute(parameters).await,
             Tool::FileInfo(tool) => tool.execute(parameters).await,
+            Tool::Calculate(tool) => tool.execute(parameters).await,
+            Tool::External(tool) => tool.execute(parameters).await,
         }
     }
 }
@@ -154,7 +175,16 @@ pub trait ToolImpl: Send + Sync {
 
 /// Tool for reading file contents
 #[derive(Debug)]
-pub struct ReadFileTool;
+pub struct ReadFileTool {
+    /// Largest byte window returned by a `byte_offset` / `byte_length` read
+    max_window: usize,
+}
+
+impl ReadFileTool {
+    pub fn new(max_window: usize) -> Self {
+        Self { max_window }
+    }
+}
 
 impl ToolImpl for ReadFileTool {
     fn name(&self) -> &str {
@@ -172,6 +202,22 @@ impl ToolImpl for ReadFileTool {
                 "path": {
                     "type": "string",
                     "description": "Path to the file to read"
+                },
+                "start_line": {
+                    "type": "integer",
+                    "description": "First line to return (1-based, inclusive). Defaults to the first line"
+                },
+                "end_line": {
+                    "type": "integer",
+                    "description": "Last line to return (1-based, inclusive). Defaults to the last line"
+                },
+                "byte_offset": {
+                    "type": "integer",
+                    "description": "Read a window of the file starting at this byte, for files too large to read whole (default: 0)"
+                },
+                "byte_length": {
+                    "type": "integer",
+                    "description": "Number of bytes to read from byte_offset (default and maximum: the agent's max file size)"
                 }
             },
             "required": ["path"]
@@ -186,6 +232,15 @@ impl ToolImpl for ReadFileTool {
 
         let path = Path::new(path);
 
+        let start_line = match line_parameter(&parameters, "start_line") {
+            Ok(line) => line,
+            Err(message) => return Ok(ToolResult::error(message)),
+        };
+        let end_line = match line_parameter(&parameters, "end_line") {
+            Ok(line) => line,
+            Err(message) => return Ok(ToolResult::error(message)),
+        };
+
         if !path.exists() {
             return Ok(ToolResult::error(format!(
                 "File does not exist: {}",
@@ -200,12 +255,90 @@ impl ToolImpl for ReadFileTool {
             )));
         }
 
+        let byte_offset = parameters.get("byte_offset").filter(|v| !v.is_null());
+        let byte_length = parameters.get("byte_length").filter(|v| !v.is_null());
+        if byte_offset.is_some() || byte_length.is_some() {
+            if start_line.is_some() || end_line.is_some() {
+                return Ok(ToolResult::error(
+                    "Use either a line range or a byte range, not both".to_string(),
+                ));
+            }
+            let Some(offset) = byte_offset.map_or(Some(0), |v| v.as_u64()) else {
+                return Ok(ToolResult::error(
+                    "'byte_offset' must be 0 or more".to_string(),
+                ));
+            };
+            let length = match byte_length.map(|v| v.as_u64()) {
+                None => self.max_window,
+                Some(Some(length)) if length >= 1 => length as usize,
+                Some(_) => {
+                    return Ok(ToolResult::error(
+                        "'byte_length' must be 1 or more".to_string(),
+                    ))
+                }
+            };
+            if length > self.max_window {
+                return Ok(ToolResult::error(format!(
+                    "byte_length ({length}) exceeds the maximum window of {} bytes",
+                    self.max_window
+                )));
+            }
+            return Ok(read_byte_window(path, offset, length));
+        }
+
+        // Whole-file and line-range reads load everything into memory
+        let size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        if size > self.max_window as u64 {
+            return Ok(ToolResult::error(format!(
+                "{} is {} bytes, more than the {} byte limit; read it in windows with byte_offset and byte_length",
+                path.display(),
+                size,
+                self.max_window
+            )));
+        }
+
         match fs::read_to_string(path) {
+            Ok(content) if start_line.is_some() || end_line.is_some() => {
+                let lines: Vec<&str> = content.split_inclusive('\n').collect();
+                let (start, end) = match resolve_line_range(lines.len(), start_line, end_line) {
+                    Ok(range) => range,
+                    Err(message) => return Ok(ToolResult::error(message)),
+                };
+                let slice = lines[start - 1..end].concat();
+
+                let result = to_data(&ReadFileResult {
+                    path: path.display().to_string(),
+                    size: Some(slice.len()),
+                    content: slice,
+                    start_line: Some(start),
+                    end_line: Some(end),
+                    total_lines: Some(lines.len()),
+                    ..ReadFileResult::default()
+                });
+                let message = if start > end {
+                    format!(
+                        "{} has only {} lines; nothing to read from line {}",
+                        path.display(),
+                        lines.len(),
+                        start_line.unwrap_or(start)
+                    )
+                } else {
+                    format!(
+                        "Read lines {}-{} of {} from {}",
+                        start,
+                        end,
+                        lines.len(),
+                        path.display()
+                    )
+                };
+                Ok(ToolResult::success(result, Some(message)))
+            }
             Ok(content) => {
-                let result = serde_json::json!({
-                    "path": path.display().to_string(),
-                    "content": content,
-                    "size": content.len()
+                let result = to_data(&ReadFileResult {
+                    path: path.display().to_string(),
+                    content: content.clone(),
+                    size: Some(content.len()),
+                    ..ReadFileResult::default()
                 });
                 Ok(ToolResult::success(
                     result,
@@ -221,6 +354,92 @@ impl ToolImpl for ReadFileTool {
     }
 }
 
+/// Read up to `length` bytes from `offset`, clamped to the end of the file
+///
+/// Bytes are decoded as lossy UTF-8, so a window that splits a character shows a
+/// replacement character at the edge.
+fn read_byte_window(path: &Path, offset: u64, length: usize) -> ToolResult {
+    let read = || -> std::io::Result<(u64, Vec<u8>)> {
+        let mut file = fs::File::open(path)?;
+        let total = file.metadata()?.len();
+        let mut buffer = Vec::new();
+        if offset < total {
+            file.seek(SeekFrom::Start(offset))?;
+            file.take(length as u64).read_to_end(&mut buffer)?;
+        }
+        Ok((total, buffer))
+    };
+    let (total, bytes) = match read() {
+        Ok(window) => window,
+        Err(e) => return ToolResult::error(format!("Failed to read file: {e}")),
+    };
+
+    let end = offset + bytes.len() as u64;
+    let result = to_data(&ReadFileResult {
+        path: path.display().to_string(),
+        content: String::from_utf8_lossy(&bytes).into_owned(),
+        byte_offset: Some(offset),
+        bytes_read: Some(bytes.len()),
+        total_size: Some(total),
+        eof: Some(end >= total),
+        ..ReadFileResult::default()
+    });
+    let message = if bytes.is_empty() {
+        format!(
+            "{} is only {} bytes; nothing to read from byte {}",
+            path.display(),
+            total,
+            offset
+        )
+    } else {
+        format!(
+            "Read bytes {}-{} of {} from {}",
+            offset,
+            end,
+            total,
+            path.display()
+        )
+    };
+    ToolResult::success(result, Some(message))
+}
+
+/// Read an optional 1-based line number parameter
+fn line_parameter(
+    parameters: &HashMap<String, serde_json::Value>,
+    name: &str,
+) -> std::result::Result<Option<usize>, String> {
+    match parameters.get(name) {
+        None | Some(serde_json::Value::Null) => Ok(None),
+        Some(value) => match value.as_u64() {
+            Some(line) if line >= 1 => Ok(Some(line as usize)),
+            _ => Err(format!("'{name}' must be a line number of 1 or more")),
+        },
+    }
+}
+
+/// Resolve a 1-based inclusive line range against a file with `total` lines
+///
+/// Missing bounds default to the first and last line, and bounds past the end of
+/// the file are clamped, so a range that starts after the last line is empty
+/// (`start == end + 1`).
+fn resolve_line_range(
+    total: usize,
+    start: Option<usize>,
+    end: Option<usize>,
+) -> std::result::Result<(usize, usize), String> {
+    let requested_start = start.unwrap_or(1);
+    let requested_end = end.unwrap_or(total);
+    if end.is_some() && requested_start > requested_end {
+        return Err(format!(
+            "start_line ({requested_start}) is after end_line ({requested_end})"
+        ));
+    }
+
+    let end = requested_end.min(total);
+    let start = requested_start.min(end + 1);
+    Ok((start, end))
+}
+
 /// Tool for writing file contents
 #[derive(Debug)]
 pub struct WriteFileTool;
@@ -245,6 +464,10 @@ impl ToolImpl for WriteFileTool {
                 "content": {
                     "type": "string",
                     "description": "Content to write to the file"
+                },
+                "preview": {
+                    "type": "boolean",
+                    "description": "Return a diff of the change without writing the file"
                 }
             },
             "required": ["path", "content"]
@@ -277,9 +500,9 @@ impl ToolImpl for WriteFileTool {
 
         match fs::write(path, content) {
             Ok(()) => {
-                let result = serde_json::json!({
-                    "path": path.display().to_string(),
-                    "size": content.len()
+                let result = to_data(&WriteFileResult {
+                    path: path.display().to_string(),
+                    size: content.len(),
                 });
                 Ok(ToolResult::success_with_files(
                     result,
@@ -319,12 +542,12 @@ impl ToolImpl for UpdateFileTool {
                 },
                 "operation": {
                     "type": "string",
-                    "enum": ["replace", "append", "prepend", "insert_at_line"],
+                    "enum": ["replace", "append", "prepend", "insert_at_line", "insert_after", "insert_before"],
                     "description": "Type of update operation"
                 },
                 "search": {
                     "type": "string",
-                    "description": "Text to search for (required for replace operation)"
+                    "description": "Text to search for (required for replace; for insert_after and insert_before, text on the line to insert next to)"
                 },
                 "replacement": {
                     "type": "string",
@@ -333,6 +556,10 @@ impl ToolImpl for UpdateFileTool {
                 "line_number": {
                     "type": "integer",
                     "description": "Line number for insert_at_line operation (1-based)"
+                },
+                "preview": {
+                    "type": "boolean",
+                    "description": "Return a diff of the change without writing the file"
                 }
             },
             "required": ["path", "operation"]
@@ -364,75 +591,18 @@ impl ToolImpl for UpdateFileTool {
             Err(e) => return Ok(ToolResult::error(format!("Failed to read file: {e}"))),
         };
 
-        let new_content = match operation {
-            "replace" => {
-                let search = parameters
-                    .get("search")
-                    .and_then(|v| v.as_str())
-                    .ok_or_else(|| anyhow!("Missing 'search' parameter for replace operation"))?;
-
-                let replacement = parameters
-                    .get("replacement")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("");
-
-                original_content.replace(search, replacement)
-            }
-            "append" => {
-                let content_to_add = parameters
-                    .get("replacement")
-                    .and_then(|v| v.as_str())
-                    .ok_or_else(|| {
-                        anyhow!("Missing 'replacement' parameter for append operation")
-                    })?;
-
-                format!("{original_content}\n{content_to_add}")
-            }
-            "prepend" => {
-                let content_to_add = parameters
-                    .get("replacement")
-                    .and_then(|v| v.as_str())
-                    .ok_or_else(|| {
-                        anyhow!("Missing 'replacement' parameter for prepend operation")
-                    })?;
-
-                format!("{content_to_add}\n{original_content}")
-            }
-            "insert_at_line" => {
-                let line_number = parameters
-                    .get("line_number")
-                    .and_then(|v| v.as_u64())
-                    .ok_or_else(|| anyhow!("Missing or invalid 'line_number' parameter"))?;
-
-                let content_to_add = parameters
-                    .get("replacement")
-                    .and_then(|v| v.as_str())
-                    .ok_or_else(|| {
-                        anyhow!("Missing 'replacement' parameter for insert_at_line operation")
-                    })?;
-
-                let mut lines: Vec<&str> = original_content.lines().collect();
-                let insert_index = (line_number as usize).saturating_sub(1);
-
-                if insert_index <= lines.len() {
-                    lines.insert(insert_index, content_to_add);
-                    lines.join("\n")
-                } else {
-                    return Ok(ToolResult::error(format!(
-                        "Line number {line_number} is out of range"
-                    )));
-                }
-            }
-            _ => return Ok(ToolResult::error(format!("Unknown operation: {operation}"))),
+        let new_content = match updated_content(&original_content, operation, &parameters) {
+            Ok(content) => content,
+            Err(e) => return Ok(ToolResult::error(e.to_string())),
         };
 
         match fs::write(path, &new_content) {
             Ok(()) => {
-                let result = serde_json::json!({
-                    "path": path.display().to_string(),
-                    "operation": operation,
-                    "original_size": original_content.len(),
-                    "new_size": new_content.len()
+                let result = to_data(&UpdateFileResult {
+                    path: path.display().to_string(),
+                    operation: operation.to_string(),
+                    original_size: original_content.len(),
+                    new_size: new_content.len(),
                 });
                 Ok(ToolResult::success_with_files(
                     result,
@@ -449,6 +619,181 @@ impl ToolImpl for UpdateFileTool {
     }
 }
 
+/// Apply an `update_file` operation to the original content
+fn updated_content(
+    original_content: &str,
+    operation: &str,
+    parameters: &HashMap<String, serde_json::Value>,
+) -> Result<String> {
+    let replacement = |operation: &str| {
+        parameters
+            .get("replacement")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Missing 'replacement' parameter for {operation} operation"))
+    };
+
+    match operation {
+        "replace" => {
+            let search = parameters
+                .get("search")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| anyhow!("Missing 'search' parameter for replace operation"))?;
+
+            let replacement = parameters
+                .get("replacement")
+                .and_then(|v| v.as_str())
+                .unwrap_or("");
+
+            Ok(original_content.replace(search, replacement))
+        }
+        "append" => Ok(format!("{original_content}\n{}", replacement("append")?)),
+        "prepend" => Ok(format!("{}\n{original_content}", replacement("prepend")?)),
+        "insert_at_line" => {
+            let line_number = parameters
+                .get("line_number")
+                .and_then(|v| v.as_u64())
+                .ok_or_else(|| anyhow!("Missing or invalid 'line_number' parameter"))?;
+            let content_to_add = replacement("insert_at_line")?;
+
+            let mut lines: Vec<&str> = original_content.lines().collect();
+            let insert_index = (line_number as usize).saturating_sub(1);
+
+            if insert_index <= lines.len() {
+                lines.insert(insert_index, content_to_add);
+                Ok(lines.join("\n"))
+            } else {
+                Err(anyhow!("Line number {line_number} is out of range"))
+            }
+        }
+        "insert_after" | "insert_before" => {
+            let search = parameters
+                .get("search")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| anyhow!("Missing 'search' parameter for {operation} operation"))?;
+            let content_to_add = replacement(operation)?;
+            insert_next_to_match(
+                original_content,
+                search,
+                content_to_add,
+                operation == "insert_after",
+            )
+        }
+        _ => Err(anyhow!("Unknown operation: {operation}")),
+    }
+}
+
+/// Insert `content` as its own line before or after the first line containing `search`
+fn insert_next_to_match(
+    original: &str,
+    search: &str,
+    content: &str,
+    after: bool,
+) -> Result<String> {
+    let mut offset = 0;
+    for line in original.split_inclusive('\n') {
+        if line.contains(search) {
+            let mut updated = original.to_string();
+            if !after {
+                updated.insert_str(offset, &format!("{content}\n"));
+            } else if line.ends_with('\n') {
+                updated.insert_str(offset + line.len(), &format!("{content}\n"));
+            } else {
+                updated.insert_str(offset + line.len(), &format!("\n{content}"));
+            }
+            return Ok(updated);
+        }
+        offset += line.len();
+    }
+    Err(anyhow!("Search text not found: {search}"))
+}
+
+/// A change `write_file` or `update_file` would make, computed without writing
+#[derive(Debug, Clone)]
+pub struct PlannedChange {
+    pub path: PathBuf,
+    /// Current file content, empty when the file does not exist yet
+    pub original: String,
+    pub new_content: String,
+}
+
+impl PlannedChange {
+    /// Unified diff from the current content to the new content
+    pub fn diff(&self) -> String {
+        let path = self.path.display().to_string();
+        similar::TextDiff::from_lines(&self.original, &self.new_content)
+            .unified_diff()
+            .header(&path, &path)
+            .to_string()
+    }
+
+    /// Number of lines added and removed
+    pub fn line_counts(&self) -> (usize, usize) {
+        let diff = similar::TextDiff::from_lines(&self.original, &self.new_content);
+        diff.iter_all_changes()
+            .fold((0, 0), |(added, removed), change| match change.tag() {
+                similar::ChangeTag::Insert => (added + 1, removed),
+                similar::ChangeTag::Delete => (added, removed + 1),
+                similar::ChangeTag::Equal => (added, removed),
+            })
+    }
+}
+
+/// Compute what a file-modifying tool call would write
+///
+/// Returns `None` for tools that do not modify files.
+pub fn plan_file_change(
+    tool: &str,
+    parameters: &HashMap<String, serde_json::Value>,
+) -> Result<Option<PlannedChange>> {
+    let path = || {
+        parameters
+            .get("path")
+            .and_then(|v| v.as_str())
+            .map(PathBuf::from)
+            .ok_or_else(|| anyhow!("Missing or invalid 'path' parameter"))
+    };
+
+    match tool {
+        "write_file" => {
+            let path = path()?;
+            let new_content = parameters
+                .get("content")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| anyhow!("Missing or invalid 'content' parameter"))?
+                .to_string();
+            let original = if path.is_file() {
+                fs::read_to_string(&path).map_err(|e| anyhow!("Failed to read file: {e}"))?
+            } else {
+                String::new()
+            };
+            Ok(Some(PlannedChange {
+                path,
+                original,
+                new_content,
+            }))
+        }
+        "update_file" => {
+            let path = path()?;
+            let operation = parameters
+                .get("operation")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| anyhow!("Missing or invalid 'operation' parameter"))?;
+            if !path.exists() {
+                return Err(anyhow!("File does not exist: {}", path.display()));
+            }
+            let original =
+                fs::read_to_string(&path).map_err(|e| anyhow!("Failed to read file: {e}"))?;
+            let new_content = updated_content(&original, operation, parameters)?;
+            Ok(Some(PlannedChange {
+                path,
+                original,
+                new_content,
+            }))
+        }
+        _ => Ok(None),
+    }
+}
+
 /// Tool for searching files
 #[derive(Debug)]
 pub struct SearchFilesTool;
@@ -478,13 +823,55 @@ impl ToolImpl for SearchFilesTool {
                     "type": "string",
                     "description": "File name pattern to filter (e.g., '*.rs', '*.txt')"
                 },
+                "exclude_pattern": {
+                    "type": "string",
+                    "description": "Skip files whose name matches this glob (e.g., '*.min.js')"
+                },
+                "exclude_dirs": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": "Directory names whose contents are not searched (e.g., ['target', 'dist'])"
+                },
                 "case_sensitive": {
                     "type": "boolean",
                     "description": "Whether the search should be case sensitive (default: false)"
                 },
+                "whole_word": {
+                    "type": "boolean",
+                    "description": "Only match the pattern as a whole word, so 'use' does not match 'house' (default: false)"
+                },
                 "max_results": {
                     "type": "integer",
                     "description": "Maximum number of results to return (default: 100)"
+                },
+                "capture_group": {
+                    "type": "integer",
+                    "description": "Regex capture group to extract from each match (0 is the whole match)"
+                },
+                "context_lines": {
+                    "type": "integer",
+                    "description": "Number of lines to include before and after each match (default: 0)"
+                },
+                "group_by_file": {
+                    "type": "boolean",
+                    "description": "Nest matches under each file with a per-file match count (default: false)"
+                },
+                "count_only": {
+                    "type": "boolean",
+                    "description": "Return only the number of matches in each file and the total, without the matching lines (default: false)"
+                },
+                "files_with_matches": {
+                    "type": "boolean",
+                    "description": "Return only the paths of files that contain a match, like grep -l (default: false)"
+                },
+                "output_file": {
+                    "type": "string",
+                    "description": "Write the full results to this file and return only a summary; all matches are written unless max_results is given"
+                },
+                "output_format": {
+                    "type": "string",
+                    "enum": ["json", "text"],
+                    "description": "Format of output_file: 'json' or grep-like 'text' lines of file:line:content (default: json)"
                 }
             },
             "required": ["pattern"]
@@ -504,120 +891,550 @@ impl ToolImpl for SearchFilesTool {
 
         let file_pattern = parameters.get("file_pattern").and_then(|v| v.as_str());
 
+        let exclude_pattern = parameters.get("exclude_pattern").and_then(|v| v.as_str());
+
+        let exclude_dirs: Vec<&str> = parameters
+            .get("exclude_dirs")
+            .and_then(|v| v.as_array())
+            .map(|dirs| dirs.iter().filter_map(|d| d.as_str()).collect())
+            .unwrap_or_default();
+
         let case_sensitive = parameters
             .get("case_sensitive")
             .and_then(|v| v.as_bool())
             .unwrap_or(false);
 
+        let output_file = parameters.get("output_file").and_then(|v| v.as_str());
+
+        let output_format = parameters
+            .get("output_format")
+            .and_then(|v| v.as_str())
+            .unwrap_or("json");
+        if !matches!(output_format, "json" | "text") {
+            return Ok(ToolResult::error(format!(
+                "Unknown output_format '{output_format}'; use 'json' or 'text'"
+            )));
+        }
+
+        let count_only = parameters
+            .get("count_only")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+        let files_with_matches = parameters
+            .get("files_with_matches")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        if count_only && files_with_matches {
+            return Ok(ToolResult::error(
+                "Use either count_only or files_with_matches, not both".to_string(),
+            ));
+        }
+        let lines_omitted = count_only || files_with_matches;
+
+        // Counts, and results kept out of the conversation, are not capped unless asked
         let max_results = parameters
             .get("max_results")
             .and_then(|v| v.as_u64())
-            .unwrap_or(100) as usize;
-
-        let regex_pattern = if case_sensitive {
-            match Regex::new(pattern) {
-                Ok(r) => r,
-                Err(_) => {
-                    // If regex fails, treat as literal string
-                    match Regex::new(&regex::escape(pattern)) {
-                        Ok(r) => r,
-                        Err(e) => return Ok(ToolResult::error(format!("Invalid pattern: {e}"))),
-                    }
-                }
-            }
-        } else {
-            match Regex::new(&format!("(?i){pattern}")) {
-                Ok(r) => r,
-                Err(_) => {
-                    // If regex fails, treat as literal string
-                    match Regex::new(&format!("(?i){}", regex::escape(pattern))) {
-                        Ok(r) => r,
-                        Err(e) => return Ok(ToolResult::error(format!("Invalid pattern: {e}"))),
-                    }
-                }
+            .map(|v| v as usize)
+            .unwrap_or(if output_file.is_some() || lines_omitted {
+                usize::MAX
+            } else {
+                100
+            });
+
+        let capture_group = parameters
+            .get("capture_group")
+            .and_then(|v| v.as_u64())
+            .map(|v| v as usize);
+
+        let context_lines = parameters
+            .get("context_lines")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0) as usize;
+
+        let whole_word = parameters
+            .get("whole_word")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+        let group_by_file = parameters
+            .get("group_by_file")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+        let regex_pattern = match build_search_regex(pattern, case_sensitive, whole_word) {
+            Ok(r) => r,
+            Err(e) => return Ok(ToolResult::error(format!("Invalid pattern: {e}"))),
+        };
+
+        if let Some(group) = capture_group {
+            if group >= regex_pattern.captures_len() {
+                return Ok(ToolResult::error(format!(
+                    "Capture group {group} does not exist; the pattern has {} group(s)",
+                    regex_pattern.captures_len() - 1
+                )));
             }
+        }
+
+        // Collect candidates first so files can be scanned in parallel
+        let mut candidates: Vec<PathBuf> = WalkDir::new(directory)
+            .into_iter()
+            // Excluded directories are pruned, so nothing below them is visited
+            .filter_entry(|entry| {
+                entry.depth() == 0
+                    || !entry.file_type().is_dir()
+                    || !entry
+                        .file_name()
+                        .to_str()
+                        .is_some_and(|name| exclude_dirs.contains(&name))
+            })
+            .filter_map(|e| e.ok())
+            .filter(|entry| entry.file_type().is_file())
+            .map(|entry| entry.into_path())
+            .filter(|path| {
+                let name = path.file_name().and_then(|n| n.to_str());
+                let included = match file_pattern {
+                    Some(file_pat) => name.map(|n| glob_match(file_pat, n)).unwrap_or(false),
+                    None => true,
+                };
+                let excluded = match exclude_pattern {
+                    Some(exclude) => name.map(|n| glob_match(exclude, n)).unwrap_or(false),
+                    None => false,
+                };
+                included && !excluded
+            })
+            .collect();
+        let before_sniffing = candidates.len();
+        candidates.retain(|path| is_text_file(path));
+        candidates.sort();
+
+        let files_searched = candidates.len();
+        let files_skipped = before_sniffing - files_searched;
+        let options = SearchOptions {
+            capture_group,
+            // Context is never shown when the lines are left out
+            context_lines: if lines_omitted { 0 } else { context_lines },
+            max_results,
         };
+        let results = search_paths(&candidates, &regex_pattern, &options);
+        let matches_found = results.len();
+
+        let mut result = SearchResult {
+            pattern: pattern.to_string(),
+            directory: directory.to_string(),
+            files_searched,
+            files_skipped,
+            matches_found,
+            grouped: (group_by_file && !lines_omitted).then_some(true),
+            output_file: None,
+            output_format: None,
+            results: None,
+            file_counts: None,
+            files: None,
+        };
+        if count_only {
+            let counts = group_results_by_file(results)
+                .into_iter()
+                .map(|group| FileCount {
+                    file: group.file,
+                    match_count: group.match_count,
+                });
+            result.file_counts = Some(counts.collect());
+        } else if files_with_matches {
+            let files = group_results_by_file(results)
+                .into_iter()
+                .map(|group| group.file);
+            result.files = Some(files.collect());
+        } else if group_by_file {
+            result.results = Some(SearchMatches::Grouped(group_results_by_file(results)));
+        } else {
+            result.results = Some(SearchMatches::Flat(results));
+        }
 
-        let mut results = Vec::new();
-        let mut files_searched = 0;
+        let mut message = format!("Found {matches_found} matches in {files_searched} files");
+        if files_skipped > 0 {
+            message.push_str(&format!(" ({files_skipped} binary files skipped)"));
+        }
 
-        for entry in WalkDir::new(directory).into_iter().filter_map(|e| e.ok()) {
-            if !entry.file_type().is_file() {
-                continue;
+        let Some(output_file) = output_file else {
+            return Ok(ToolResult::success(to_data(&result), Some(message)));
+        };
+
+        let report = match output_format {
+            "text" => search_report_text(&result),
+            _ => serde_json::to_string_pretty(&result)?,
+        };
+        let path = Path::new(output_file);
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            if let Err(e) = fs::create_dir_all(parent) {
+                return Ok(ToolResult::error(format!(
+                    "Failed to create directories: {e}"
+                )));
+            }
+        }
+        // A report is always a new file; overwriting goes through write_file,
+        // which asks for confirmation and takes a backup
+        let written = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .and_then(|mut file| std::io::Write::write_all(&mut file, report.as_bytes()));
+        match written {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                return Ok(ToolResult::error(format!(
+                    "{output_file} already exists; search results are only written to a new file"
+                )));
+            }
+            Err(e) => {
+                return Ok(ToolResult::error(format!("Failed to write results: {e}")));
             }
+        }
 
-            let path = entry.path();
+        // Only the counts go back to the model
+        result.output_file = Some(output_file.to_string());
+        result.output_format = Some(output_format.to_string());
+        result.results = result.results.map(|results| match results {
+            SearchMatches::Grouped(_) => SearchMatches::Grouped(Vec::new()),
+            SearchMatches::Flat(_) => SearchMatches::Flat(Vec::new()),
+        });
+        message.push_str(&format!("; full results written to {output_file}"));
+        Ok(ToolResult::success_with_files(
+            to_data(&result),
+            Some(message),
+            vec![path.to_path_buf()],
+        ))
+    }
+}
 
-            // Apply file pattern filter if specified
-            if let Some(file_pat) = file_pattern {
-                if !path
-                    .file_name()
-                    .and_then(|n| n.to_str())
-                    .map(|n| glob_match(file_pat, n))
-                    .unwrap_or(false)
-                {
-                    continue;
-                }
-            }
+/// Search results as grep-like lines
+///
+/// Matches read `file:line:content` and context lines `file-line-content`, with
+/// `--` between separate runs of context, as `grep -n -C` prints them. Counts
+/// read `file:count` as with `grep -c`, and matching files are listed one per
+/// line as with `grep -l`.
+fn search_report_text(result: &SearchResult) -> String {
+    if let Some(counts) = &result.file_counts {
+        return counts
+            .iter()
+            .map(|count| format!("{}:{}\n", count.file, count.match_count))
+            .collect();
+    }
+    if let Some(files) = &result.files {
+        return files.iter().map(|file| format!("{file}\n")).collect();
+    }
 
-            // Only search text files
-            if !is_text_file(path) {
+    let flat: Vec<(&str, &SearchMatch)> = match &result.results {
+        None => Vec::new(),
+        Some(SearchMatches::Flat(matches)) => matches
+            .iter()
+            .map(|found| (found.file.as_deref().unwrap_or_default(), found))
+            .collect(),
+        Some(SearchMatches::Grouped(groups)) => groups
+            .iter()
+            .flat_map(|group| {
+                group
+                    .matches
+                    .iter()
+                    .map(|found| (group.file.as_str(), found))
+            })
+            .collect(),
+    };
+
+    let matched: HashSet<(&str, usize)> = flat
+        .iter()
+        .map(|(file, found)| (*file, found.line))
+        .collect();
+
+    let mut report = String::new();
+    let mut last: Option<(&str, usize)> = None;
+    for (file, found) in flat {
+        let Some(context) = &found.context else {
+            report.push_str(&format!("{file}:{}:{}\n", found.line, found.content));
+            continue;
+        };
+
+        let mut window: Vec<(usize, &str)> = context
+            .iter()
+            .map(|line| (line.line, line.content.as_str()))
+            .chain(std::iter::once((found.line, found.content.as_str())))
+            .collect();
+        window.sort_by_key(|(line, _)| *line);
+        for (line, content) in window {
+            // Overlapping windows share lines that were already printed
+            let printed =
+                last.is_some_and(|(last_file, last_line)| last_file == file && line <= last_line);
+            if printed {
                 continue;
             }
-
-            files_searched += 1;
-
-            if let Ok(content) = fs::read_to_string(path) {
-                for (line_num, line) in content.lines().enumerate() {
-                    if regex_pattern.is_match(line) {
-                        results.push(serde_json::json!({
-                            "file": path.display().to_string(),
-                            "line": line_num + 1,
-                            "content": line,
-                            "matches": regex_pattern.find_iter(line)
-                                .map(|m| serde_json::json!({
-                                    "start": m.start(),
-                                    "end": m.end(),
-                                    "text": m.as_str()
-                                }))
-                                .collect::<Vec<_>>()
-                        }));
-
-                        if results.len() >= max_results {
-                            break;
-                        }
-                    }
-                }
+            let adjacent = last
+                .is_some_and(|(last_file, last_line)| last_file == file && line == last_line + 1);
+            if last.is_some() && !adjacent {
+                report.push_str("--\n");
             }
+            let separator = if matched.contains(&(file, line)) {
+                ':'
+            } else {
+                '-'
+            };
+            report.push_str(&format!("{file}{separator}{line}{separator}{content}\n"));
+            last = Some((file, line));
+        }
+    }
+    report
+}
+
+/// Nest flat search matches under their file
+///
+/// Files keep the order of their first match, and each match loses its now
+/// redundant `file` field.
+fn group_results_by_file(results: Vec<SearchMatch>) -> Vec<FileMatches> {
+    let mut groups: Vec<FileMatches> = Vec::new();
+    for mut entry in results {
+        let file = entry.file.take().unwrap_or_default();
+        match groups.last_mut() {
+            Some(group) if group.file == file => group.matches.push(entry),
+            _ => groups.push(FileMatches {
+                file,
+                match_count: 0,
+                matches: vec![entry],
+            }),
+        }
+    }
+    for group in &mut groups {
+        group.match_count = group.matches.len();
+    }
+    groups
+}
+
+/// Per-file options for `search_files`
+struct SearchOptions {
+    capture_group: Option<usize>,
+    context_lines: usize,
+    max_results: usize,
+}
+
+/// Search files in parallel, returning at most `max_results` matches
+///
+/// Results keep the order of `paths` and line order within each file, so the
+/// truncation to `max_results` is the same as a sequential scan.
+fn search_paths(paths: &[PathBuf], regex: &Regex, options: &SearchOptions) -> Vec<SearchMatch> {
+    let mut results: Vec<SearchMatch> = paths
+        .par_iter()
+        .map(|path| search_file(path, regex, options))
+        .flatten_iter()
+        .collect();
+    results.truncate(options.max_results);
+    results
+}
 
-            if results.len() >= max_results {
+/// Search a single file, stopping after `max_results` matches
+///
+/// Files are read line by line unless context lines are requested. Bytes that are
+/// not valid UTF-8 (e.g. Latin-1 text) are decoded lossily so the rest of the line
+/// can still match; unreadable files produce no matches.
+fn search_file(path: &Path, regex: &Regex, options: &SearchOptions) -> Vec<SearchMatch> {
+    let file_name = path.display().to_string();
+    let mut matches = Vec::new();
+    let mut push_match = |mut entry: SearchMatch| {
+        entry.file = Some(file_name.clone());
+        matches.push(entry);
+        matches.len() >= options.max_results
+    };
+
+    if options.context_lines == 0 {
+        let Ok(file) = fs::File::open(path) else {
+            return Vec::new();
+        };
+        for (index, line) in BufReader::new(file).split(b'\n').enumerate() {
+            let Ok(line) = line else {
+                return Vec::new();
+            };
+            let line = String::from_utf8_lossy(line.strip_suffix(b"\r").unwrap_or(&line));
+            if regex.is_match(&line)
+                && push_match(line_match_entry(
+                    regex,
+                    &line,
+                    index + 1,
+                    options.capture_group,
+                ))
+            {
                 break;
             }
         }
+    } else {
+        let Ok(bytes) = fs::read(path) else {
+            return Vec::new();
+        };
+        let content = String::from_utf8_lossy(&bytes);
+        let lines: Vec<&str> = content.lines().collect();
+        for (index, line) in lines.iter().enumerate() {
+            if regex.is_match(line) {
+                let mut entry = line_match_entry(regex, line, index + 1, options.capture_group);
+                entry.context = Some(context_window(&lines, index, options.context_lines));
+                if push_match(entry) {
+                    break;
+                }
+            }
+        }
+    }
 
-        let result = serde_json::json!({
-            "pattern": pattern,
-            "directory": directory,
-            "files_searched": files_searched,
-            "matches_found": results.len(),
-            "results": results
-        });
+    matches
+}
 
-        Ok(ToolResult::success(
-            result,
-            Some(format!(
-                "Found {} matches in {} files",
-                results.len(),
-                files_searched
-            )),
-        ))
+/// Describe a matching line
+///
+/// `captures` is included when a capture group is requested.
+fn line_match_entry(
+    regex: &Regex,
+    line: &str,
+    line_number: usize,
+    capture_group: Option<usize>,
+) -> SearchMatch {
+    let captures = capture_group.map(|group| {
+        regex
+            .captures_iter(line)
+            .map(|caps| caps.get(group).map(|m| m.as_str().to_string()))
+            .collect()
+    });
+
+    SearchMatch {
+        file: None,
+        line: line_number,
+        content: line.to_string(),
+        matches: regex
+            .find_iter(line)
+            .map(|m| MatchSpan {
+                start: m.start(),
+                end: m.end(),
+                text: m.as_str().to_string(),
+            })
+            .collect(),
+        captures,
+        context: None,
     }
 }
 
+/// Lines surrounding `index`, clamped at file boundaries
+fn context_window(lines: &[&str], index: usize, context_lines: usize) -> Vec<ContextLine> {
+    let start = index.saturating_sub(context_lines);
+    let end = (index + context_lines + 1).min(lines.len());
+    (start..end)
+        .filter(|&i| i != index)
+        .map(|i| ContextLine {
+            line: i + 1,
+            content: lines[i].to_string(),
+        })
+        .collect()
+}
+
 /// Tool for listing directory contents
 #[derive(Debug)]
 pub struct ListDirectoryTool;
 
+/// Levels below a directory summed by `compute_dir_sizes` when `max_depth` is not given
+const DEFAULT_DIR_SIZE_DEPTH: usize = 16;
+
+/// Total size of the files under `dir`, descending at most `max_depth` levels
+fn dir_size(dir: &Path, max_depth: usize) -> u64 {
+    WalkDir::new(dir)
+        .max_depth(max_depth)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// A single entry collected by `list_directory`
+#[derive(Debug)]
+struct DirectoryEntry {
+    path: String,
+    name: String,
+    is_dir: bool,
+    size: u64,
+    modified: Option<u64>,
+    /// Summed size of the files inside a directory, when requested
+    total_size: Option<u64>,
+}
+
+impl DirectoryEntry {
+    fn new(path: &Path, name: &str, is_dir: bool, metadata: Option<&fs::Metadata>) -> Self {
+        Self {
+            path: path.display().to_string(),
+            name: name.to_string(),
+            is_dir,
+            size: metadata.map(|m| m.len()).unwrap_or(0),
+            modified: metadata
+                .and_then(|m| m.modified().ok())
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| d.as_secs()),
+            total_size: None,
+        }
+    }
+
+    /// Size used for sorting: the summed size for directories when known
+    fn sort_size(&self) -> u64 {
+        self.total_size.unwrap_or(self.size)
+    }
+
+    fn to_result(&self) -> ListedEntry {
+        ListedEntry {
+            path: self.path.clone(),
+            name: self.name.clone(),
+            entry_type: if self.is_dir { "directory" } else { "file" }.to_string(),
+            size: self.size,
+            modified: self.modified,
+            total_size: self.total_size,
+        }
+    }
+}
+
+/// Ordering applied to `list_directory` results
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EntrySort {
+    Name,
+    Size,
+    Modified,
+}
+
+impl EntrySort {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "name" => Some(Self::Name),
+            "size" => Some(Self::Size),
+            "modified" => Some(Self::Modified),
+            _ => None,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Name => "name",
+            Self::Size => "size",
+            Self::Modified => "modified",
+        }
+    }
+
+    /// Sort entries; size and modified put the largest / newest first
+    fn apply(self, entries: &mut [DirectoryEntry]) {
+        match self {
+            Self::Name => entries.sort_by(|a, b| a.name.cmp(&b.name).then(a.path.cmp(&b.path))),
+            Self::Size => {
+                entries.sort_by(|a, b| b.sort_size().cmp(&a.sort_size()).then(a.path.cmp(&b.path)))
+            }
+            Self::Modified => {
+                entries.sort_by(|a, b| b.modified.cmp(&a.modified).then(a.path.cmp(&b.path)))
+            }
+        }
+    }
+}
+
 impl ToolImpl for ListDirectoryTool {
     fn name(&self) -> &str {
         "list_directory"
@@ -642,6 +1459,23 @@ impl ToolImpl for ListDirectoryTool {
                 "show_hidden": {
                     "type": "boolean",
                     "description": "Whether to show hidden files (default: false)"
+                },
+                "file_pattern": {
+                    "type": "string",
+                    "description": "Only include entries whose name matches this glob (e.g., '*.rs')"
+                },
+                "sort_by": {
+                    "type": "string",
+                    "enum": ["name", "size", "modified"],
+                    "description": "Order entries by name, size (largest first), or modified time (newest first); default is filesystem order"
+                },
+                "compute_dir_sizes": {
+                    "type": "boolean",
+                    "description": "Sum the sizes of the files inside each listed directory and the whole path; slower on large trees (default: false)"
+                },
+                "max_depth": {
+                    "type": "integer",
+                    "description": "Deepest level below a directory to descend when listing recursively or summing sizes (default: unlimited for listing, 16 for sizes)"
                 }
             }
         })
@@ -663,6 +1497,30 @@ impl ToolImpl for ListDirectoryTool {
             .and_then(|v| v.as_bool())
             .unwrap_or(false);
 
+        let file_pattern = parameters.get("file_pattern").and_then(|v| v.as_str());
+
+        let compute_dir_sizes = parameters
+            .get("compute_dir_sizes")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+        let max_depth = parameters
+            .get("max_depth")
+            .and_then(|v| v.as_u64())
+            .map(|depth| depth as usize);
+
+        let sort_by = match parameters.get("sort_by").and_then(|v| v.as_str()) {
+            None => None,
+            Some(value) => match EntrySort::parse(value) {
+                Some(sort) => Some(sort),
+                None => {
+                    return Ok(ToolResult::error(format!(
+                        "Invalid sort_by '{value}': expected name, size, or modified"
+                    )))
+                }
+            },
+        };
+
         let path = Path::new(path);
 
         if !path.exists() {
@@ -679,10 +1537,11 @@ impl ToolImpl for ListDirectoryTool {
             )));
         }
 
-        let mut entries = Vec::new();
+        let mut listed = Vec::new();
 
         if recursive {
-            for entry in WalkDir::new(path).into_iter().filter_map(|e| e.ok()) {
+            let walker = WalkDir::new(path).max_depth(max_depth.unwrap_or(usize::MAX));
+            for entry in walker.into_iter().filter_map(|e| e.ok()) {
                 let entry_path = entry.path();
                 let file_name = entry_path
                     .file_name()
@@ -693,17 +1552,12 @@ impl ToolImpl for ListDirectoryTool {
                     continue;
                 }
 
-                let metadata = entry.metadata().ok();
-                entries.push(serde_json::json!({
-                    "path": entry_path.display().to_string(),
-                    "name": file_name,
-                    "type": if entry.file_type().is_dir() { "directory" } else { "file" },
-                    "size": metadata.as_ref().map(|m| m.len()).unwrap_or(0),
-                    "modified": metadata.as_ref()
-                        .and_then(|m| m.modified().ok())
-                        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-                        .map(|d| d.as_secs())
-                }));
+                listed.push(DirectoryEntry::new(
+                    entry_path,
+                    file_name,
+                    entry.file_type().is_dir(),
+                    entry.metadata().ok().as_ref(),
+                ));
             }
         } else {
             match fs::read_dir(path) {
@@ -719,38 +1573,47 @@ impl ToolImpl for ListDirectoryTool {
                             continue;
                         }
 
-                        let metadata = entry.metadata().ok();
-                        entries.push(serde_json::json!({
-                            "path": entry_path.display().to_string(),
-                            "name": file_name,
-                            "type": if entry_path.is_dir() { "directory" } else { "file" },
-                            "size": metadata.as_ref().map(|m| m.len()).unwrap_or(0),
-                            "modified": metadata.as_ref()
-                                .and_then(|m| m.modified().ok())
-                                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-                                .map(|d| d.as_secs())
-                        }));
+                        listed.push(DirectoryEntry::new(
+                            &entry_path,
+                            file_name,
+                            entry_path.is_dir(),
+                            entry.metadata().ok().as_ref(),
+                        ));
                     }
                 }
                 Err(e) => return Ok(ToolResult::error(format!("Failed to read directory: {e}"))),
             }
         }
 
-        let result = serde_json::json!({
-            "path": path.display().to_string(),
-            "recursive": recursive,
-            "entry_count": entries.len(),
-            "entries": entries
+        if let Some(pattern) = file_pattern {
+            listed.retain(|entry| glob_match(pattern, &entry.name));
+        }
+
+        let size_depth = max_depth.unwrap_or(DEFAULT_DIR_SIZE_DEPTH);
+        if compute_dir_sizes {
+            for entry in listed.iter_mut().filter(|entry| entry.is_dir) {
+                entry.total_size = Some(dir_size(Path::new(&entry.path), size_depth));
+            }
+        }
+
+        if let Some(sort) = sort_by {
+            sort.apply(&mut listed);
+        }
+
+        let entries: Vec<ListedEntry> = listed.iter().map(DirectoryEntry::to_result).collect();
+        let message = format!("Listed {} entries in {}", entries.len(), path.display());
+
+        let result = to_data(&ListDirectoryResult {
+            path: path.display().to_string(),
+            recursive,
+            file_pattern: file_pattern.map(str::to_string),
+            sort_by: sort_by.map(|sort| sort.as_str().to_string()),
+            entry_count: entries.len(),
+            entries,
+            total_size: compute_dir_sizes.then(|| dir_size(path, size_depth)),
         });
 
-        Ok(ToolResult::success(
-            result,
-            Some(format!(
-                "Listed {} entries in {}",
-                entries.len(),
-                path.display()
-            )),
-        ))
+        Ok(ToolResult::success(result, Some(message)))
     }
 }
 
@@ -808,50 +1671,347 @@ impl ToolImpl for FileInfoTool {
             "other"
         };
 
-        let mut result = serde_json::json!({
-            "path": path.display().to_string(),
-            "name": path.file_name().and_then(|n| n.to_str()).unwrap_or(""),
-            "type": file_type,
-            "size": metadata.len(),
-            "readonly": metadata.permissions().readonly(),
-            "created": metadata.created().ok()
-                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-                .map(|d| d.as_secs()),
-            "modified": metadata.modified().ok()
-                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-                .map(|d| d.as_secs()),
-            "accessed": metadata.accessed().ok()
+        let seconds = |time: std::io::Result<std::time::SystemTime>| {
+            time.ok()
                 .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
                 .map(|d| d.as_secs())
-        });
+        };
+
+        let mut info = FileInfoResult {
+            path: path.display().to_string(),
+            name: path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("")
+                .to_string(),
+            file_type: file_type.to_string(),
+            size: metadata.len(),
+            readonly: metadata.permissions().readonly(),
+            created: seconds(metadata.created()),
+            modified: seconds(metadata.modified()),
+            accessed: seconds(metadata.accessed()),
+            extension: None,
+            is_text: None,
+            encoding: None,
+            line_count: None,
+        };
 
         // Add file-specific information
         if metadata.is_file() {
-            if let Some(extension) = path.extension().and_then(|e| e.to_str()) {
-                result["extension"] = serde_json::Value::String(extension.to_string());
-            }
+            info.extension = path
+                .extension()
+                .and_then(|e| e.to_str())
+                .map(str::to_string);
 
-            result["is_text"] = serde_json::Value::Bool(is_text_file(path));
+            let detection = detect_text(path);
+            info.is_text = Some(detection.is_text);
+            info.encoding = detection.encoding.map(|encoding| encoding.to_string());
 
             // For text files, add line count
-            if is_text_file(path) {
+            if detection.is_text {
                 if let Ok(content) = fs::read_to_string(path) {
-                    result["line_count"] = serde_json::Value::Number(serde_json::Number::from(
-                        content.lines().count(),
-                    ));
+                    info.line_count = Some(content.lines().count());
                 }
             }
         }
 
         Ok(ToolResult::success(
-            result,
+            to_data(&info),
             Some(format!("Retrieved information for {}", path.display())),
         ))
     }
 }
 
-/// Check if a file is likely a text file based on extension
+/// Tool for evaluating arithmetic expressions
+#[derive(Debug)]
+pub struct CalculateTool;
+
+impl ToolImpl for CalculateTool {
+    fn name(&self) -> &str {
+        "calculate"
+    }
+
+    fn description(&self) -> &str {
+        "Evaluate an arithmetic expression exactly instead of computing it by hand"
+    }
+
+    fn parameters(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "expression": {
+                    "type": "string",
+                    "description": format!(
+                        "Expression using numbers, + - * / % ^, parentheses, pi, e, and the functions {}",
+                        calculator::FUNCTIONS.join(", ")
+                    )
+                }
+            },
+            "required": ["expression"]
+        })
+    }
+
+    async fn execute(&self, parameters: HashMap<String, serde_json::Value>) -> Result<ToolResult> {
+        let expression = parameters
+            .get("expression")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Missing or invalid 'expression' parameter"))?;
+
+        match calculator::evaluate(expression) {
+            Ok(value) => Ok(ToolResult::success(
+                serde_json::json!({
+                    "expression": expression,
+                    "result": value
+                }),
+                Some(format!("{} = {}", expression.trim(), value)),
+            )),
+            Err(e) => Ok(ToolResult::error(format!(
+                "Could not evaluate expression: {e}"
+            ))),
+        }
+    }
+}
+
+fn default_external_tool_timeout() -> u64 {
+    30
+}
+
+/// User-defined tool backed by an external command, as declared in the configuration file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExternalToolConfig {
+    /// Tool name exposed to the model
+    pub name: String,
+    /// Description of what the tool does
+    pub description: String,
+    /// Shell command to run; parameters are passed as JSON on stdin
+    pub command: String,
+    /// JSON schema describing the tool parameters
+    #[serde(default)]
+    pub parameters_schema: serde_json::Value,
+    /// Maximum time the command may run, in seconds
+    #[serde(default = "default_external_tool_timeout")]
+    pub timeout_secs: u64,
+}
+
+/// Tool that runs an external command
+#[derive(Debug)]
+pub struct ExternalTool {
+    name: String,
+    description: String,
+    command: String,
+    parameters: serde_json::Value,
+    timeout: Duration,
+    working_directory: PathBuf,
+}
+
+impl ExternalTool {
+    /// Create an external tool from its configuration
+    pub fn new(config: &ExternalToolConfig, working_directory: PathBuf) -> Self {
+        let parameters = if config.parameters_schema.is_null() {
+            serde_json::json!({ "type": "object", "properties": {} })
+        } else {
+            config.parameters_schema.clone()
+        };
+
+        Self {
+            name: config.name.clone(),
+            description: config.description.clone(),
+            command: config.command.clone(),
+            parameters,
+            timeout: Duration::from_secs(config.timeout_secs),
+            working_directory,
+        }
+    }
+
+    fn shell_command(&self) -> tokio::process::Command {
+        let mut command = if cfg!(windows) {
+            let mut cmd = tokio::process::Command::new("cmd");
+            cmd.arg("/C").arg(&self.command);
+            cmd
+        } else {
+            let mut cmd = tokio::process::Command::new("sh");
+            cmd.arg("-c").arg(&self.command);
+            cmd
+        };
+        command
+            .current_dir(&self.working_directory)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        command
+    }
+}
+
+impl ToolImpl for ExternalTool {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn parameters(&self) -> serde_json::Value {
+        self.parameters.clone()
+    }
+
+    async fn execute(&self, parameters: HashMap<String, serde_json::Value>) -> Result<ToolResult> {
+        let input = serde_json::to_vec(&parameters)?;
+
+        let mut child = match self.shell_command().spawn() {
+            Ok(child) => child,
+            Err(e) => {
+                return Ok(ToolResult::error(format!(
+                    "Failed to start command '{}': {e}",
+                    self.command
+                )))
+            }
+        };
+
+        // Input is written while the output is read, both within the timeout, so a
+        // command that never reads its input cannot hang the agent
+        let stdin = child.stdin.take();
+        let write_input = async move {
+            if let Some(mut stdin) = stdin {
+                match stdin.write_all(&input).await {
+                    // A command may exit without reading all of its input
+                    Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => return Err(e),
+                    _ => {}
+                }
+                // Dropping stdin closes the pipe so the command sees EOF
+            }
+            Ok(())
+        };
+        let run = async {
+            let (written, output) = tokio::join!(write_input, child.wait_with_output());
+            written?;
+            output
+        };
+
+        let output = match tokio::time::timeout(self.timeout, run).await {
+            Ok(output) => output?,
+            Err(_) => {
+                return Ok(ToolResult::error(format!(
+                    "Command '{}' timed out after {} seconds",
+                    self.command,
+                    self.timeout.as_secs_f64()
+                )))
+            }
+        };
+
+        let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+            return Ok(ToolResult::error(format!(
+                "Command '{}' failed ({}): {}",
+                self.command,
+                output.status,
+                if stderr.is_empty() { &stdout } else { &stderr }
+            )));
+        }
+
+        let data = serde_json::from_str(&stdout)
+            .unwrap_or_else(|_| serde_json::Value::String(stdout.clone()));
+
+        Ok(ToolResult::success(
+            data,
+            Some(format!("Executed external tool {}", self.name)),
+        ))
+    }
+}
+
+/// Number of leading bytes inspected when sniffing file contents
+const SNIFF_BYTES: usize = 8 * 1024;
+
+/// Share of control bytes above which a sample is treated as binary
+const MAX_CONTROL_RATIO: f64 = 0.1;
+
+/// Result of inspecting a file to decide whether it holds text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TextDetection {
+    is_text: bool,
+    /// Detected character encoding, when it can be determined
+    encoding: Option<&'static str>,
+}
+
+/// Check if a file is likely a text file
 fn is_text_file(path: &Path) -> bool {
+    detect_text(path).is_text
+}
+
+/// Decide whether a file is text by sniffing its first bytes
+///
+/// Falls back to the extension list when the file cannot be read or is empty.
+fn detect_text(path: &Path) -> TextDetection {
+    let mut sample = Vec::with_capacity(SNIFF_BYTES);
+    let read = fs::File::open(path)
+        .and_then(|file| file.take(SNIFF_BYTES as u64).read_to_end(&mut sample));
+
+    match read {
+        Ok(len) if len > 0 => sniff_text(&sample, len == SNIFF_BYTES),
+        _ => TextDetection {
+            is_text: has_text_extension(path),
+            encoding: None,
+        },
+    }
+}
+
+/// Classify a sample of bytes as text or binary
+///
+/// `truncated` marks samples cut from a longer file, which may end mid-character.
+fn sniff_text(sample: &[u8], truncated: bool) -> TextDetection {
+    let text = |encoding| TextDetection {
+        is_text: true,
+        encoding,
+    };
+
+    // Byte order marks settle the question outright
+    if sample.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        return text(Some("utf-8"));
+    }
+    if sample.starts_with(&[0xFF, 0xFE]) {
+        return text(Some("utf-16le"));
+    }
+    if sample.starts_with(&[0xFE, 0xFF]) {
+        return text(Some("utf-16be"));
+    }
+
+    if sample.contains(&0) {
+        return TextDetection {
+            is_text: false,
+            encoding: None,
+        };
+    }
+
+    let control_bytes = sample
+        .iter()
+        .filter(|&&byte| {
+            (byte < 0x20 && !matches!(byte, b'\t' | b'\n' | b'\r' | 0x0C | 0x1B)) || byte == 0x7F
+        })
+        .count();
+    if control_bytes as f64 / sample.len() as f64 > MAX_CONTROL_RATIO {
+        return TextDetection {
+            is_text: false,
+            encoding: None,
+        };
+    }
+
+    // A sample cut off mid-character is still valid UTF-8 up to the cut
+    let encoding = match std::str::from_utf8(sample) {
+        Ok(_) if sample.is_ascii() => Some("ascii"),
+        Ok(_) => Some("utf-8"),
+        Err(e) if truncated && e.error_len().is_none() => Some("utf-8"),
+        // Legacy 8-bit text: readable, but the charset cannot be determined
+        Err(_) => None,
+    };
+
+    text(encoding)
+}
+
+/// Check if a file is likely a text file based on extension
+fn has_text_extension(path: &Path) -> bool {
     let text_extensions = [
         "txt",
         "md",
@@ -900,6 +2060,28 @@ fn is_text_file(path: &Path) -> bool {
         .unwrap_or(false)
 }
 
+/// Compile the `search_files` pattern
+///
+/// Patterns that are not valid regexes are searched for literally. With `whole_word`
+/// the pattern is wrapped in word boundaries inside a non-capturing group, so capture
+/// group numbers are unchanged.
+fn build_search_regex(
+    pattern: &str,
+    case_sensitive: bool,
+    whole_word: bool,
+) -> std::result::Result<Regex, regex::Error> {
+    let flags = if case_sensitive { "" } else { "(?i)" };
+    let compile = |body: &str| {
+        if whole_word {
+            Regex::new(&format!(r"{flags}\b(?:{body})\b"))
+        } else {
+            Regex::new(&format!("{flags}{body}"))
+        }
+    };
+
+    compile(pattern).or_else(|_| compile(&regex::escape(pattern)))
+}
+
 /// Simple glob pattern matching
 fn glob_match(pattern: &str, text: &str) -> bool {
     // Convert glob pattern to regex
@@ -914,3 +2096,725 @@ fn glob_match(pattern: &str, text: &str) -> bool {
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn external_tool(command: &str, timeout: Duration) -> ExternalTool {
+        let config = ExternalToolConfig {
+            name: "echo_tool".to_string(),
+            description: "Echo parameters back".to_string(),
+            command: command.to_string(),
+            parameters_schema: serde_json::Value::Null,
+            timeout_secs: 30,
+        };
+        let mut tool = ExternalTool::new(&config, std::env::temp_dir());
+        tool.timeout = timeout;
+        tool
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn external_tool_passes_parameters_on_stdin() {
+        let tool = external_tool("cat", Duration::from_secs(5));
+        let mut params = HashMap::new();
+        params.insert("message".to_string(), serde_json::json!("hello"));
+
+        let result = tool.execute(params).await.unwrap();
+        assert!(result.success);
+        assert_eq!(result.data, serde_json::json!({"message": "hello"}));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn external_tool_timeout_covers_writing_the_input() {
+        // More input than a pipe buffers, for a command that never reads it
+        let tool = external_tool("sleep 5", Duration::from_millis(200));
+        let mut params = HashMap::new();
+        params.insert("blob".to_string(), serde_json::json!("x".repeat(1 << 20)));
+
+        let started = std::time::Instant::now();
+        let result = tool.execute(params).await.unwrap();
+        assert!(!result.success);
+        assert!(result.message.unwrap().contains("timed out"));
+        assert!(started.elapsed() < Duration::from_secs(3));
+    }
+
+    async fn read_lines(path: &Path, start_line: Option<u64>, end_line: Option<u64>) -> ToolResult {
+        let mut params = HashMap::new();
+        params.insert("path".to_string(), serde_json::json!(path));
+        if let Some(line) = start_line {
+            params.insert("start_line".to_string(), serde_json::json!(line));
+        }
+        if let Some(line) = end_line {
+            params.insert("end_line".to_string(), serde_json::json!(line));
+        }
+        ReadFileTool::new(1024).execute(params).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn read_file_returns_the_requested_line_range() {
+        let path = std::env::temp_dir().join(format!("chatter-lines-{}.txt", uuid::Uuid::new_v4()));
+        let body: String = (1..=10).map(|n| format!("line {n}\n")).collect();
+        fs::write(&path, &body).unwrap();
+
+        let result = read_lines(&path, Some(3), Some(5)).await;
+        assert!(result.success);
+        assert_eq!(result.data["content"], "line 3\nline 4\nline 5\n");
+        assert_eq!(result.data["start_line"], 3);
+        assert_eq!(result.data["end_line"], 5);
+        assert_eq!(result.data["total_lines"], 10);
+
+        // Out-of-bounds ranges are clamped to the file
+        let clamped = read_lines(&path, Some(8), Some(50)).await;
+        assert_eq!(clamped.data["content"], "line 8\nline 9\nline 10\n");
+        assert_eq!(clamped.data["end_line"], 10);
+        let past_end = read_lines(&path, Some(20), None).await;
+        assert!(past_end.success);
+        assert_eq!(past_end.data["content"], "");
+
+        let whole = read_lines(&path, None, None).await;
+        assert_eq!(whole.data["content"], body.as_str());
+        assert!(whole.data.get("total_lines").is_none());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn read_file_rejects_inverted_ranges() {
+        let path = std::env::temp_dir().join(format!("chatter-lines-{}.txt", uuid::Uuid::new_v4()));
+        fs::write(&path, "a\nb\nc\n").unwrap();
+
+        let inverted = read_lines(&path, Some(3), Some(2)).await;
+        assert!(!inverted.success);
+        assert!(inverted.message.unwrap().contains("after end_line"));
+        assert!(!read_lines(&path, Some(0), None).await.success);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn read_file_pages_through_byte_windows() {
+        let path = std::env::temp_dir().join(format!("chatter-bytes-{}.log", uuid::Uuid::new_v4()));
+        fs::write(&path, "0123456789abcdefghij").unwrap();
+        let tool = ReadFileTool::new(8);
+        let read = |offset: Option<u64>, length: Option<u64>| {
+            let mut params = HashMap::new();
+            params.insert("path".to_string(), serde_json::json!(path));
+            if let Some(offset) = offset {
+                params.insert("byte_offset".to_string(), serde_json::json!(offset));
+            }
+            if let Some(length) = length {
+                params.insert("byte_length".to_string(), serde_json::json!(length));
+            }
+            tool.execute(params)
+        };
+
+        let middle = read(Some(5), Some(4)).await.unwrap();
+        assert!(middle.success);
+        assert_eq!(middle.data["content"], "5678");
+        assert_eq!(middle.data["bytes_read"], 4);
+        assert_eq!(middle.data["total_size"], 20);
+        assert_eq!(middle.data["eof"], false);
+
+        // A window running past the end is clamped, and the default length is the maximum
+        let tail = read(Some(16), None).await.unwrap();
+        assert_eq!(tail.data["content"], "ghij");
+        assert_eq!(tail.data["eof"], true);
+        let past_end = read(Some(50), Some(4)).await.unwrap();
+        assert!(past_end.success);
+        assert_eq!(past_end.data["bytes_read"], 0);
+
+        let too_large = read(Some(0), Some(9)).await.unwrap();
+        assert!(!too_large.success);
+        assert!(too_large
+            .message
+            .unwrap()
+            .contains("maximum window of 8 bytes"));
+        let whole = read(None, None).await.unwrap();
+        assert!(!whole.success);
+        assert!(whole.message.unwrap().contains("byte_offset"));
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn line_match_entry_extracts_capture_group() {
+        let regex = Regex::new(r#"version = "(\d+)\.(\d+)""#).unwrap();
+        let line = r#"version = "1.2" # and version = "3.4""#;
+
+        let entry = line_match_entry(&regex, line, 1, Some(2));
+
+        assert_eq!(
+            entry.captures,
+            Some(vec![Some("2".to_string()), Some("4".to_string())])
+        );
+        assert!(entry.context.is_none());
+    }
+
+    #[test]
+    fn context_window_clamps_at_file_boundaries() {
+        let lines = vec!["needle", "two", "three", "needle"];
+
+        let first = context_window(&lines, 0, 2);
+        let context_lines: Vec<usize> = first.iter().map(|c| c.line).collect();
+        assert_eq!(context_lines, vec![2, 3]);
+
+        let last = context_window(&lines, 3, 5);
+        assert_eq!(last.len(), 3);
+        assert_eq!(last[0].content, "needle");
+    }
+
+    #[test]
+    fn parallel_search_matches_sequential_scan() {
+        let root = std::env::temp_dir().join(format!("chatter-search-{}", uuid::Uuid::new_v4()));
+        let mut paths = Vec::new();
+        for dir in 0..8 {
+            let dir_path = root.join(format!("dir{dir}"));
+            fs::create_dir_all(&dir_path).unwrap();
+            for file in 0..25 {
+                let path = dir_path.join(format!("file{file}.txt"));
+                let body: String = (0..40)
+                    .map(|line| {
+                        if (dir + file + line) % 7 == 0 {
+                            format!("TODO item {dir}-{file}-{line}\n")
+                        } else {
+                            format!("plain line {line}\n")
+                        }
+                    })
+                    .collect();
+                fs::write(&path, body).unwrap();
+                paths.push(path);
+            }
+        }
+        paths.sort();
+
+        let regex = Regex::new(r"TODO item (\d+)").unwrap();
+        for (context_lines, max_results) in [(0, 1_000_000), (0, 50), (1, 75)] {
+            let options = SearchOptions {
+                capture_group: Some(1),
+                context_lines,
+                max_results,
+            };
+
+            let sequential: Vec<SearchMatch> = paths
+                .iter()
+                .flat_map(|path| search_file(path, &regex, &options))
+                .take(max_results)
+                .collect();
+            let parallel = search_paths(&paths, &regex, &options);
+
+            assert!(!sequential.is_empty());
+            assert_eq!(parallel, sequential);
+        }
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn external_tool_times_out() {
+        let tool = external_tool("sleep 5", Duration::from_millis(100));
+        let result = tool.execute(HashMap::new()).await.unwrap();
+        assert!(!result.success);
+        assert!(result.message.unwrap().contains("timed out"));
+    }
+
+    fn temp_listing_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("chatter-list-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        fs::write(dir.join("small.rs"), "a").unwrap();
+        fs::write(dir.join("large.rs"), "a".repeat(100)).unwrap();
+        fs::write(dir.join("medium.txt"), "a".repeat(10)).unwrap();
+        fs::write(dir.join("nested").join("deep.rs"), "a".repeat(50)).unwrap();
+        dir
+    }
+
+    fn listed_names(result: &ToolResult) -> Vec<String> {
+        result.data["entries"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| entry["name"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn list_directory_filters_by_file_pattern() {
+        let dir = temp_listing_dir();
+        let mut params = HashMap::new();
+        params.insert("path".to_string(), serde_json::json!(dir.to_str().unwrap()));
+        params.insert("file_pattern".to_string(), serde_json::json!("*.rs"));
+        params.insert("sort_by".to_string(), serde_json::json!("name"));
+
+        let flat = ListDirectoryTool.execute(params.clone()).await.unwrap();
+        assert_eq!(listed_names(&flat), vec!["large.rs", "small.rs"]);
+
+        params.insert("recursive".to_string(), serde_json::json!(true));
+        let recursive = ListDirectoryTool.execute(params).await.unwrap();
+        assert_eq!(
+            listed_names(&recursive),
+            vec!["deep.rs", "large.rs", "small.rs"]
+        );
+        assert_eq!(recursive.data["file_pattern"], "*.rs");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn list_directory_sums_directory_sizes_when_asked() {
+        let dir = temp_listing_dir();
+        fs::create_dir_all(dir.join("nested").join("inner")).unwrap();
+        fs::write(
+            dir.join("nested").join("inner").join("huge.bin"),
+            [0u8; 500],
+        )
+        .unwrap();
+
+        let mut params = HashMap::new();
+        params.insert("path".to_string(), serde_json::json!(dir.to_str().unwrap()));
+        params.insert("sort_by".to_string(), serde_json::json!("size"));
+
+        let plain = ListDirectoryTool.execute(params.clone()).await.unwrap();
+        assert!(plain.data.get("total_size").is_none());
+
+        params.insert("compute_dir_sizes".to_string(), serde_json::json!(true));
+        let sized = ListDirectoryTool.execute(params.clone()).await.unwrap();
+        // 1 + 100 + 10 bytes of files, plus 50 + 500 inside nested/
+        assert_eq!(sized.data["total_size"], 661);
+        let nested = &sized.data["entries"][0];
+        assert_eq!(nested["name"], "nested");
+        assert_eq!(nested["total_size"], 550);
+        assert!(sized.data["entries"][1].get("total_size").is_none());
+
+        // Depth bounds the sum: nested/inner/huge.bin is two levels down
+        params.insert("max_depth".to_string(), serde_json::json!(1));
+        let shallow = ListDirectoryTool.execute(params).await.unwrap();
+        assert_eq!(shallow.data["total_size"], 111);
+        // nested/ now sums to 50 bytes and sorts after large.rs
+        assert_eq!(shallow.data["entries"][1]["name"], "nested");
+        assert_eq!(shallow.data["entries"][1]["total_size"], 50);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn list_directory_sorts_by_size() {
+        let dir = temp_listing_dir();
+        let mut params = HashMap::new();
+        params.insert("path".to_string(), serde_json::json!(dir.to_str().unwrap()));
+        params.insert("file_pattern".to_string(), serde_json::json!("*.*"));
+        params.insert("sort_by".to_string(), serde_json::json!("size"));
+
+        let result = ListDirectoryTool.execute(params.clone()).await.unwrap();
+        assert_eq!(
+            listed_names(&result),
+            vec!["large.rs", "medium.txt", "small.rs"]
+        );
+
+        params.insert("sort_by".to_string(), serde_json::json!("color"));
+        let invalid = ListDirectoryTool.execute(params).await.unwrap();
+        assert!(!invalid.success);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn file_info_sniffs_content_instead_of_extension() {
+        let dir = std::env::temp_dir().join(format!("chatter-sniff-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let makefile = dir.join("Makefile");
+        fs::write(&makefile, "build:\n\tcargo build\n").unwrap();
+        let blob = dir.join("blob.txt");
+        fs::write(
+            &blob,
+            [0x89, b'P', b'N', b'G', 0x00, 0x01, 0x02, 0xFF, 0xFE],
+        )
+        .unwrap();
+
+        let info = |path: &Path| {
+            let mut params = HashMap::new();
+            params.insert(
+                "path".to_string(),
+                serde_json::json!(path.to_str().unwrap()),
+            );
+            FileInfoTool.execute(params)
+        };
+
+        let text = info(&makefile).await.unwrap();
+        assert_eq!(text.data["is_text"], true);
+        assert_eq!(text.data["encoding"], "ascii");
+        assert_eq!(text.data["line_count"], 2);
+
+        let binary = info(&blob).await.unwrap();
+        assert_eq!(binary.data["is_text"], false);
+        assert!(binary.data.get("line_count").is_none());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn sniff_text_detects_encodings() {
+        assert_eq!(
+            sniff_text("héllo".as_bytes(), false).encoding,
+            Some("utf-8")
+        );
+        // A sample cut mid-character is still recognised as UTF-8
+        assert_eq!(
+            sniff_text(&"héllo".as_bytes()[..2], true).encoding,
+            Some("utf-8")
+        );
+        assert_eq!(
+            sniff_text(&[0xFF, 0xFE, b'h', 0], false).encoding,
+            Some("utf-16le")
+        );
+        // Latin-1 text is readable but its charset is unknown
+        let latin1 = sniff_text(&[b'c', b'a', b'f', 0xE9], false);
+        assert!(latin1.is_text);
+        assert_eq!(latin1.encoding, None);
+        assert!(!sniff_text(&[0x01, 0x02, 0x03, b'a'], false).is_text);
+    }
+
+    #[tokio::test]
+    async fn grouped_search_nests_matches_under_each_file() {
+        let dir = std::env::temp_dir().join(format!("chatter-group-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.rs"), "// TODO one\nfn main() {}\n// TODO two\n").unwrap();
+        fs::write(dir.join("b.rs"), "// TODO three\n").unwrap();
+        fs::write(dir.join("c.rs"), "nothing here\n").unwrap();
+
+        let mut params = HashMap::new();
+        params.insert("pattern".to_string(), serde_json::json!("TODO"));
+        params.insert(
+            "directory".to_string(),
+            serde_json::json!(dir.display().to_string()),
+        );
+        params.insert("group_by_file".to_string(), serde_json::json!(true));
+        let grouped = SearchFilesTool.execute(params.clone()).await.unwrap();
+
+        assert_eq!(grouped.data["grouped"], true);
+        assert_eq!(grouped.data["matches_found"], 3);
+        let groups = grouped.data["results"].as_array().unwrap();
+        assert_eq!(groups.len(), 2);
+        assert!(groups[0]["file"].as_str().unwrap().ends_with("a.rs"));
+        assert_eq!(groups[0]["match_count"], 2);
+        assert_eq!(groups[0]["matches"][0]["line"], 1);
+        assert_eq!(groups[0]["matches"][1]["line"], 3);
+        assert!(groups[0]["matches"][0].get("file").is_none());
+        assert_eq!(groups[1]["match_count"], 1);
+
+        // The flat shape stays the default
+        params.remove("group_by_file");
+        let flat = SearchFilesTool.execute(params).await.unwrap();
+        assert!(flat.data.get("grouped").is_none());
+        assert_eq!(flat.data["results"].as_array().unwrap().len(), 3);
+        assert!(flat.data["results"][0]["file"].is_string());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn search_results_can_be_written_to_a_file() {
+        let dir = std::env::temp_dir().join(format!("chatter-report-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(dir.join("src")).unwrap();
+        let body: String = (1..=150).map(|n| format!("line {n} TODO\n")).collect();
+        fs::write(dir.join("src").join("big.rs"), body).unwrap();
+        fs::write(dir.join("src").join("small.rs"), "one\nTODO two\nthree\n").unwrap();
+
+        let search = |extra: serde_json::Value| {
+            let mut params = HashMap::from([
+                ("pattern".to_string(), serde_json::json!("TODO")),
+                (
+                    "directory".to_string(),
+                    serde_json::json!(dir.join("src").display().to_string()),
+                ),
+            ]);
+            for (key, value) in extra.as_object().unwrap() {
+                params.insert(key.clone(), value.clone());
+            }
+            SearchFilesTool.execute(params)
+        };
+
+        // Every match goes to the file; only the counts come back
+        let json_path = dir.join("reports").join("todo.json");
+        let result = search(serde_json::json!({
+            "output_file": json_path.display().to_string()
+        }))
+        .await
+        .unwrap();
+        assert!(result.success);
+        assert_eq!(result.modified_files, vec![json_path.clone()]);
+        assert_eq!(result.data["matches_found"], 151);
+        assert_eq!(result.data["results"], serde_json::json!([]));
+        assert_eq!(result.data["output_format"], "json");
+        let written: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&json_path).unwrap()).unwrap();
+        assert_eq!(written["results"].as_array().unwrap().len(), 151);
+        assert!(written.get("output_file").is_none());
+
+        let text_path = dir.join("todo.txt");
+        search(serde_json::json!({
+            "output_file": text_path.display().to_string(),
+            "output_format": "text",
+            "file_pattern": "small.rs",
+            "context_lines": 1
+        }))
+        .await
+        .unwrap();
+        let small = dir.join("src").join("small.rs").display().to_string();
+        assert_eq!(
+            fs::read_to_string(&text_path).unwrap(),
+            format!("{small}-1-one\n{small}:2:TODO two\n{small}-3-three\n")
+        );
+
+        // Existing files, source or earlier reports, are never overwritten
+        for existing in [dir.join("src").join("small.rs"), text_path.clone()] {
+            let before = fs::read_to_string(&existing).unwrap();
+            let result = search(serde_json::json!({
+                "output_file": existing.display().to_string()
+            }))
+            .await
+            .unwrap();
+            assert!(!result.success);
+            assert!(result.message.unwrap().contains("already exists"));
+            assert_eq!(fs::read_to_string(&existing).unwrap(), before);
+        }
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn search_can_return_only_counts_or_file_names() {
+        let dir = std::env::temp_dir().join(format!("chatter-counts-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let body: String = (1..=150).map(|n| format!("line {n} TODO\n")).collect();
+        fs::write(dir.join("big.rs"), body).unwrap();
+        fs::write(dir.join("small.rs"), "one\nTODO two\nthree\n").unwrap();
+        fs::write(dir.join("clean.rs"), "nothing here\n").unwrap();
+
+        let search = |extra: serde_json::Value| {
+            let mut params = HashMap::from([
+                ("pattern".to_string(), serde_json::json!("TODO")),
+                (
+                    "directory".to_string(),
+                    serde_json::json!(dir.display().to_string()),
+                ),
+                ("context_lines".to_string(), serde_json::json!(2)),
+            ]);
+            for (key, value) in extra.as_object().unwrap() {
+                params.insert(key.clone(), value.clone());
+            }
+            SearchFilesTool.execute(params)
+        };
+        let big = dir.join("big.rs").display().to_string();
+        let small = dir.join("small.rs").display().to_string();
+
+        // Counts are not capped at the default 100 matches
+        let counts = search(serde_json::json!({"count_only": true}))
+            .await
+            .unwrap();
+        assert!(counts.success);
+        assert_eq!(counts.data["matches_found"], 151);
+        assert!(counts.data.get("results").is_none());
+        assert!(counts.data.get("grouped").is_none());
+        assert_eq!(
+            counts.data["file_counts"],
+            serde_json::json!([
+                {"file": big, "match_count": 150},
+                {"file": small, "match_count": 1}
+            ])
+        );
+
+        let files = search(serde_json::json!({"files_with_matches": true}))
+            .await
+            .unwrap();
+        assert!(files.data.get("results").is_none());
+        assert_eq!(files.data["files"], serde_json::json!([big, small]));
+
+        let text_path = dir.join("counts.txt");
+        search(serde_json::json!({
+            "count_only": true,
+            "output_file": text_path.display().to_string(),
+            "output_format": "text"
+        }))
+        .await
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(&text_path).unwrap(),
+            format!("{big}:150\n{small}:1\n")
+        );
+
+        let both = search(serde_json::json!({"count_only": true, "files_with_matches": true}))
+            .await
+            .unwrap();
+        assert!(!both.success);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn search_skips_excluded_directories_and_file_names() {
+        let dir = std::env::temp_dir().join(format!("chatter-exclude-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::create_dir_all(dir.join("target").join("debug")).unwrap();
+        fs::create_dir_all(dir.join("dist")).unwrap();
+        fs::write(dir.join("src").join("main.js"), "// TODO real\n").unwrap();
+        fs::write(dir.join("src").join("bundle.min.js"), "// TODO minified\n").unwrap();
+        fs::write(
+            dir.join("target").join("debug").join("gen.js"),
+            "// TODO gen\n",
+        )
+        .unwrap();
+        fs::write(dir.join("dist").join("out.js"), "// TODO dist\n").unwrap();
+
+        let search = |extra: serde_json::Value| {
+            let mut params: HashMap<String, serde_json::Value> =
+                serde_json::from_value(extra).unwrap();
+            params.insert("pattern".to_string(), serde_json::json!("TODO"));
+            params.insert(
+                "directory".to_string(),
+                serde_json::json!(dir.display().to_string()),
+            );
+            SearchFilesTool.execute(params)
+        };
+        let files = |result: &ToolResult| {
+            let mut files: Vec<String> = result.data["results"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|m| {
+                    Path::new(m["file"].as_str().unwrap())
+                        .file_name()
+                        .unwrap()
+                        .to_string_lossy()
+                        .into_owned()
+                })
+                .collect();
+            files.sort();
+            files
+        };
+
+        let all = search(serde_json::json!({})).await.unwrap();
+        assert_eq!(all.data["files_searched"], 4);
+
+        let no_build_dirs = search(serde_json::json!({ "exclude_dirs": ["target", "dist"] }))
+            .await
+            .unwrap();
+        assert_eq!(no_build_dirs.data["files_searched"], 2);
+        assert_eq!(files(&no_build_dirs), vec!["bundle.min.js", "main.js"]);
+
+        let combined = search(serde_json::json!({
+            "file_pattern": "*.js",
+            "exclude_pattern": "*.min.js",
+            "exclude_dirs": ["target", "dist"],
+        }))
+        .await
+        .unwrap();
+        assert_eq!(files(&combined), vec!["main.js"]);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn search_reads_legacy_encodings_and_counts_binary_files() {
+        let dir = std::env::temp_dir().join(format!("chatter-latin1-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        // "café TODO" in Latin-1, which is not valid UTF-8
+        fs::write(dir.join("notes.txt"), b"caf\xe9 TODO\r\nplain line\n").unwrap();
+        fs::write(
+            dir.join("image.bin"),
+            [0x89, b'P', b'N', b'G', 0, 0, b'T', b'O'],
+        )
+        .unwrap();
+
+        let mut params = HashMap::new();
+        params.insert("pattern".to_string(), serde_json::json!("TODO"));
+        params.insert(
+            "directory".to_string(),
+            serde_json::json!(dir.display().to_string()),
+        );
+        let result = SearchFilesTool.execute(params.clone()).await.unwrap();
+        assert_eq!(result.data["files_searched"], 1);
+        assert_eq!(result.data["files_skipped"], 1);
+        assert_eq!(result.data["matches_found"], 1);
+        assert_eq!(result.data["results"][0]["content"], "caf\u{FFFD} TODO");
+        assert!(result.message.unwrap().contains("1 binary files skipped"));
+
+        params.insert("context_lines".to_string(), serde_json::json!(1));
+        let with_context = SearchFilesTool.execute(params).await.unwrap();
+        assert_eq!(with_context.data["matches_found"], 1);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn whole_word_search_skips_partial_matches() {
+        let regex = build_search_regex("use", false, true).unwrap();
+        assert!(regex.is_match("use std::fs;"));
+        assert!(regex.is_match("USE it"));
+        assert!(!regex.is_match("my house"));
+        assert!(!regex.is_match("used before"));
+
+        let case_sensitive = build_search_regex("use", true, true).unwrap();
+        assert!(!case_sensitive.is_match("USE it"));
+
+        // Invalid regexes fall back to a literal search, still bounded by words
+        let literal = build_search_regex("use(", false, true).unwrap();
+        assert!(literal.is_match("use(x)"));
+        assert!(!literal.is_match("reuse(x)"));
+    }
+
+    fn insert_params(operation: &str, search: &str) -> HashMap<String, serde_json::Value> {
+        serde_json::from_value(serde_json::json!({
+            "path": "main.rs",
+            "operation": operation,
+            "search": search,
+            "replacement": "    println!(\"inserted\");",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn insert_after_and_before_anchor_on_the_first_matching_line() {
+        let original = "fn main() {\n    run();\n    run();\n}\n";
+
+        let after = updated_content(
+            original,
+            "insert_after",
+            &insert_params("insert_after", "run()"),
+        )
+        .unwrap();
+        assert_eq!(
+            after,
+            "fn main() {\n    run();\n    println!(\"inserted\");\n    run();\n}\n"
+        );
+
+        let before = updated_content(
+            original,
+            "insert_before",
+            &insert_params("insert_before", "}"),
+        )
+        .unwrap();
+        assert_eq!(
+            before,
+            "fn main() {\n    run();\n    run();\n    println!(\"inserted\");\n}\n"
+        );
+
+        // A last line without a newline still gets the insertion on a line of its own
+        let after_last =
+            updated_content("a\nb", "insert_after", &insert_params("insert_after", "b")).unwrap();
+        assert_eq!(after_last, "a\nb\n    println!(\"inserted\");");
+    }
+
+    #[test]
+    fn insert_after_reports_missing_search_text() {
+        let error = updated_content(
+            "fn main() {}\n",
+            "insert_after",
+            &insert_params("insert_after", "missing()"),
+        )
+        .unwrap_err();
+        assert_eq!(error.to_string(), "Search text not found: missing()");
+    }
+}
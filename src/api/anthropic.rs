@@ -0,0 +1,582 @@
+//! Anthropic Messages API client implementation
+//!
+//! Maps the shared conversation format onto Anthropic's `messages` array, where the
+//! system prompt is a separate field and tool calls are content blocks.
+
+use super::http::{client_builder, HttpSettings};
+use super::models::ModelListCache;
+use super::rate_limit::RateLimiter;
+use super::{Content, ModelToolCall, Part};
+use crate::api::llm::{ChatResponse, ToolDefinition};
+use crate::error::ChatterError;
+use anyhow::{anyhow, Context, Result};
+use futures_util::{Stream, StreamExt};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{self, Value};
+use std::collections::VecDeque;
+use std::pin::Pin;
+
+/// Default base URL for the Anthropic API
+pub const ANTHROPIC_API_BASE: &str = "https://api.anthropic.com";
+
+/// API version sent with every request
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// Anthropic requires an explicit output limit on every request
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+/// HTTP client for the Anthropic Messages API
+pub struct AnthropicClient {
+    client: Client,
+    api_key: String,
+    base_url: String,
+    models_cache: ModelListCache,
+    rate_limiter: Option<RateLimiter>,
+}
+
+impl AnthropicClient {
+    pub fn new(api_key: String, endpoint: String, http: &HttpSettings) -> Result<Self> {
+        if api_key.trim().is_empty() {
+            return Err(anyhow!("Anthropic API key cannot be empty"));
+        }
+
+        let trimmed = endpoint.trim();
+        let base_url = if trimmed.is_empty() {
+            ANTHROPIC_API_BASE
+        } else {
+            trimmed
+        };
+
+        let client = client_builder(http)?.build()?;
+
+        Ok(Self {
+            client,
+            api_key: api_key.trim().to_string(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            models_cache: ModelListCache::default(),
+            rate_limiter: None,
+        })
+    }
+
+    /// Limit message requests to the given number per minute
+    pub fn with_rate_limit(mut self, requests_per_minute: u32) -> Self {
+        self.rate_limiter = Some(RateLimiter::per_minute(requests_per_minute));
+        self
+    }
+
+    /// List the models available to this API key
+    pub async fn list_models(&self) -> Result<Vec<String>> {
+        self.models_cache
+            .get_or_fetch(|| async {
+                let url = format!("{}/v1/models", self.base_url);
+                tracing::debug!(url = %url, "Listing Anthropic models");
+
+                let response = self
+                    .client
+                    .get(url)
+                    .query(&[("limit", "1000")])
+                    .header("x-api-key", &self.api_key)
+                    .header("anthropic-version", ANTHROPIC_VERSION)
+                    .send()
+                    .await?;
+                let status = response.status();
+                tracing::debug!(status = %status, "Anthropic model list received");
+
+                if !status.is_success() {
+                    let error_text = response.text().await?;
+                    return Err(anyhow!("Listing Anthropic models failed: {}", error_text));
+                }
+
+                let list: AnthropicModelList = response.json().await?;
+                Ok(list.data.into_iter().map(|model| model.id).collect())
+            })
+            .await
+    }
+
+    /// Send a conversation and return the complete response, including tool calls
+    pub async fn chat(
+        &self,
+        model: &str,
+        conversation: &[Content],
+        system_instruction: Option<&str>,
+        tools: &[ToolDefinition],
+    ) -> Result<ChatResponse> {
+        let request = build_request(model, conversation, system_instruction, tools, false);
+        let response = self.send(&request).await?;
+
+        let status = response.status();
+        let bytes = response.bytes().await?;
+
+        if !status.is_success() {
+            let error_text = String::from_utf8_lossy(&bytes);
+            let message = format!("Anthropic request failed: {error_text}");
+            return Err(ChatterError::api(status, message).into());
+        }
+
+        let response: AnthropicResponse = serde_json::from_slice(&bytes).with_context(|| {
+            format!(
+                "Failed to decode Anthropic response body: {}",
+                String::from_utf8_lossy(&bytes)
+            )
+        })?;
+
+        Ok(ChatResponse {
+            message: convert_response(response),
+            thinking: None,
+        })
+    }
+
+    /// Send a conversation and stream text deltas as they arrive
+    pub async fn chat_stream(
+        &self,
+        model: &str,
+        conversation: &[Content],
+        system_instruction: Option<&str>,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<String>> + Send>>> {
+        let request = build_request(model, conversation, system_instruction, &[], true);
+        let response = self.send(&request).await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await?;
+            let message = format!("Anthropic request failed: {error_text}");
+            return Err(ChatterError::api(status, message).into());
+        }
+
+        let bytes_stream = response.bytes_stream();
+        let stream = futures_util::stream::unfold(
+            (bytes_stream, AnthropicSseParser::default()),
+            |(mut bs, mut parser)| async move {
+                loop {
+                    if let Some(next) = parser.pop() {
+                        return Some((next, (bs, parser)));
+                    }
+                    if parser.done {
+                        return None;
+                    }
+
+                    match bs.next().await {
+                        Some(Ok(bytes)) => parser.feed(&String::from_utf8_lossy(&bytes)),
+                        Some(Err(e)) => {
+                            return Some((Err(anyhow!("Stream error: {}", e)), (bs, parser)));
+                        }
+                        None => {
+                            parser.feed("\n\n");
+                            parser.done = true;
+                        }
+                    }
+                }
+            },
+        );
+
+        Ok(Box::pin(stream))
+    }
+
+    async fn send(&self, request: &AnthropicRequest<'_>) -> Result<reqwest::Response> {
+        let url = format!("{}/v1/messages", self.base_url);
+
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.acquire().await;
+        }
+        tracing::debug!(url = %url, stream = request.stream, "Sending Anthropic request");
+
+        let response = self
+            .client
+            .post(url)
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .header("Content-Type", "application/json")
+            .json(request)
+            .send()
+            .await?;
+
+        tracing::debug!(status = %response.status(), "Anthropic response received");
+        Ok(response)
+    }
+}
+
+fn build_request<'a>(
+    model: &'a str,
+    conversation: &[Content],
+    system_instruction: Option<&str>,
+    tools: &[ToolDefinition],
+    stream: bool,
+) -> AnthropicRequest<'a> {
+    let (system, messages) = convert_conversation(conversation, system_instruction);
+
+    AnthropicRequest {
+        model,
+        max_tokens: DEFAULT_MAX_TOKENS,
+        system,
+        messages,
+        tools: tools
+            .iter()
+            .map(|tool| AnthropicTool {
+                name: tool.name.clone(),
+                description: tool.description.clone(),
+                input_schema: tool.parameters.clone(),
+            })
+            .collect(),
+        stream,
+    }
+}
+
+/// Split the conversation into Anthropic's system prompt and message list
+///
+/// System turns are appended to the system prompt, tool results become
+/// `tool_result` blocks in a user message, and adjacent messages with the same
+/// role are merged because the API requires alternating roles.
+fn convert_conversation(
+    conversation: &[Content],
+    system_instruction: Option<&str>,
+) -> (Option<String>, Vec<AnthropicMessage>) {
+    let mut system_parts: Vec<String> = system_instruction
+        .filter(|s| !s.trim().is_empty())
+        .map(|s| vec![s.to_string()])
+        .unwrap_or_default();
+    let mut messages: Vec<AnthropicMessage> = Vec::new();
+    // Ids of tool calls still waiting for a result, for histories without call ids
+    let mut pending_ids: VecDeque<String> = VecDeque::new();
+
+    for (index, content) in conversation.iter().enumerate() {
+        let (role, blocks) = match content.role.as_str() {
+            "system" => {
+                system_parts.extend(content.parts.iter().map(|p| p.text.clone()));
+                continue;
+            }
+            "model" | "assistant" => {
+                let mut blocks = text_blocks(&content.parts);
+                for (call_index, call) in content.tool_calls.iter().enumerate() {
+                    let id = call
+                        .id
+                        .clone()
+                        .unwrap_or_else(|| format!("toolu_{index}_{call_index}"));
+                    pending_ids.push_back(id.clone());
+                    blocks.push(ContentBlock::ToolUse {
+                        id,
+                        name: call.name.clone(),
+                        input: tool_input(&call.arguments),
+                    });
+                }
+                ("assistant", blocks)
+            }
+            "tool" => {
+                let tool_use_id = match content.tool_call_id {
+                    Some(ref id) => {
+                        pending_ids.retain(|pending| pending != id);
+                        id.clone()
+                    }
+                    None => pending_ids
+                        .pop_front()
+                        .unwrap_or_else(|| format!("toolu_{index}")),
+                };
+                let text: Vec<&str> = content.parts.iter().map(|p| p.text.as_str()).collect();
+                (
+                    "user",
+                    vec![ContentBlock::ToolResult {
+                        tool_use_id,
+                        content: text.join("\n"),
+                    }],
+                )
+            }
+            _ => ("user", text_blocks(&content.parts)),
+        };
+
+        if blocks.is_empty() {
+            continue;
+        }
+
+        match messages.last_mut() {
+            Some(previous) if previous.role == role => previous.content.extend(blocks),
+            _ => messages.push(AnthropicMessage {
+                role: role.to_string(),
+                content: blocks,
+            }),
+        }
+    }
+
+    let system = if system_parts.is_empty() {
+        None
+    } else {
+        Some(system_parts.join("\n\n"))
+    };
+
+    (system, messages)
+}
+
+fn text_blocks(parts: &[Part]) -> Vec<ContentBlock> {
+    parts
+        .iter()
+        .filter(|part| !part.text.is_empty())
+        .map(|part| ContentBlock::Text {
+            text: part.text.clone(),
+        })
+        .collect()
+}
+
+/// Anthropic requires tool input to be a JSON object
+fn tool_input(arguments: &Value) -> Value {
+    match arguments {
+        Value::Object(_) => arguments.clone(),
+        Value::String(raw) => match serde_json::from_str::<Value>(raw) {
+            Ok(parsed @ Value::Object(_)) => parsed,
+            _ => Value::Object(serde_json::Map::new()),
+        },
+        _ => Value::Object(serde_json::Map::new()),
+    }
+}
+
+fn convert_response(response: AnthropicResponse) -> Content {
+    let mut text = String::new();
+    let mut tool_calls = Vec::new();
+
+    for block in response.content {
+        match block {
+            ContentBlock::Text { text: chunk } => text.push_str(&chunk),
+            ContentBlock::ToolUse { id, name, input } => tool_calls.push(ModelToolCall {
+                id: Some(id),
+                name,
+                arguments: input,
+            }),
+            ContentBlock::ToolResult { .. } | ContentBlock::Other => {}
+        }
+    }
+
+    let mut content = Content::model(text);
+    content.tool_calls = tool_calls;
+    content
+}
+
+/// Response from `GET /v1/models`
+#[derive(Debug, Deserialize)]
+struct AnthropicModelList {
+    #[serde(default)]
+    data: Vec<AnthropicModelInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicModelInfo {
+    id: String,
+}
+
+/// Incremental parser for the Messages API server-sent events
+///
+/// Only `content_block_delta` text deltas are emitted; errors reported by the
+/// server are surfaced as stream errors.
+#[derive(Default)]
+struct AnthropicSseParser {
+    buffer: String,
+    data: String,
+    queue: VecDeque<Result<String>>,
+    done: bool,
+}
+
+impl AnthropicSseParser {
+    fn feed(&mut self, chunk: &str) {
+        self.buffer.push_str(chunk);
+        while let Some(pos) = self.buffer.find('\n') {
+            let line: String = self.buffer.drain(..pos + 1).collect();
+            let line = line.trim_end_matches(['\n', '\r']);
+
+            if line.is_empty() {
+                self.finalize_event();
+            } else if let Some(data) = line.strip_prefix("data:") {
+                self.data.push_str(data.trim_start());
+            }
+            // `event:` lines repeat the type carried in the payload and are ignored
+        }
+    }
+
+    fn finalize_event(&mut self) {
+        let data = std::mem::take(&mut self.data);
+        if data.trim().is_empty() {
+            return;
+        }
+
+        let Ok(event) = serde_json::from_str::<StreamEvent>(&data) else {
+            return;
+        };
+
+        match event {
+            StreamEvent::ContentBlockDelta {
+                delta: StreamDelta::TextDelta { text },
+            } => self.queue.push_back(Ok(text)),
+            StreamEvent::MessageStop => self.done = true,
+            StreamEvent::Error { error } => {
+                self.queue
+                    .push_back(Err(anyhow!("Anthropic stream error: {}", error.message)));
+                self.done = true;
+            }
+            _ => {}
+        }
+    }
+
+    fn pop(&mut self) -> Option<Result<String>> {
+        self.queue.pop_front()
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct AnthropicRequest<'a> {
+    model: &'a str,
+    max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
+    messages: Vec<AnthropicMessage>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<AnthropicTool>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct AnthropicMessage {
+    role: String,
+    content: Vec<ContentBlock>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ContentBlock {
+    Text {
+        text: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        input: Value,
+    },
+    ToolResult {
+        tool_use_id: String,
+        content: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Serialize)]
+struct AnthropicTool {
+    name: String,
+    description: String,
+    input_schema: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicResponse {
+    content: Vec<ContentBlock>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StreamEvent {
+    ContentBlockDelta {
+        delta: StreamDelta,
+    },
+    MessageStop,
+    Error {
+        error: StreamError,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StreamDelta {
+    TextDelta {
+        text: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamError {
+    message: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_tool_turns_into_content_blocks() {
+        let mut call = Content::model(String::new());
+        call.tool_calls.push(ModelToolCall {
+            id: None,
+            name: "read_file".to_string(),
+            arguments: serde_json::json!({ "path": "Cargo.toml" }),
+        });
+        let mut result = Content::user("{\"success\":true}".to_string());
+        result.role = "tool".to_string();
+        let mut system = Content::user("Be brief".to_string());
+        system.role = "system".to_string();
+
+        let (system_prompt, messages) = convert_conversation(
+            &[
+                Content::user("Read Cargo.toml".to_string()),
+                call,
+                result,
+                system,
+                Content::user("Thanks".to_string()),
+            ],
+            Some("You are helpful"),
+        );
+
+        assert_eq!(
+            system_prompt.as_deref(),
+            Some("You are helpful\n\nBe brief")
+        );
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[1].role, "assistant");
+        // The tool result and the following user text share one user message
+        assert_eq!(messages[2].role, "user");
+        assert_eq!(messages[2].content.len(), 2);
+
+        let json = serde_json::to_value(&messages).unwrap();
+        let tool_use_id = &json[1]["content"][0]["id"];
+        assert_eq!(json[1]["content"][0]["type"], "tool_use");
+        assert_eq!(json[2]["content"][0]["type"], "tool_result");
+        assert_eq!(&json[2]["content"][0]["tool_use_id"], tool_use_id);
+    }
+
+    #[test]
+    fn parses_text_deltas_from_sse_stream() {
+        let mut parser = AnthropicSseParser::default();
+        parser.feed("event: message_start\ndata: {\"type\":\"message_start\",\"message\":{}}\n\n");
+        parser.feed(
+            "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,",
+        );
+        parser.feed("\"delta\":{\"type\":\"text_delta\",\"text\":\"Hel\"}}\n\n");
+        parser.feed("data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"lo\"}}\n\n");
+        parser.feed("event: message_stop\ndata: {\"type\":\"message_stop\"}\n\n");
+
+        assert_eq!(parser.pop().unwrap().unwrap(), "Hel");
+        assert_eq!(parser.pop().unwrap().unwrap(), "lo");
+        assert!(parser.pop().is_none());
+        assert!(parser.done);
+    }
+
+    #[test]
+    fn response_tool_use_becomes_model_tool_call() {
+        let payload = r#"{
+            "id": "msg_1",
+            "type": "message",
+            "role": "assistant",
+            "content": [
+                {"type": "text", "text": "Let me check."},
+                {"type": "tool_use", "id": "toolu_01", "name": "list_directory", "input": {"path": "."}}
+            ],
+            "stop_reason": "tool_use"
+        }"#;
+
+        let response: AnthropicResponse = serde_json::from_str(payload).unwrap();
+        let content = convert_response(response);
+
+        assert_eq!(content.parts[0].text, "Let me check.");
+        assert_eq!(content.tool_calls.len(), 1);
+        assert_eq!(content.tool_calls[0].id.as_deref(), Some("toolu_01"));
+        assert_eq!(content.tool_calls[0].arguments["path"], ".");
+    }
+}
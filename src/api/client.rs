@@ -2,7 +2,12 @@
 //!
 //! Provides the main client for communicating with Google's Gemini API.
 
+use super::http::{client_builder, HttpSettings};
+use super::llm::ChatResponse;
+use super::models::ModelListCache;
+use super::rate_limit::RateLimiter;
 use super::*;
+use crate::error::ChatterError;
 use anyhow::{anyhow, Result};
 use futures_util::StreamExt;
 use reqwest::Client;
@@ -15,21 +20,18 @@ pub struct GeminiClient {
     client: Client,
     api_key: String,
     base_url: String,
+    models_cache: ModelListCache,
+    rate_limiter: Option<RateLimiter>,
 }
 
 impl GeminiClient {
     /// Create a new Gemini client with the given API key
-    pub fn new(api_key: String) -> Result<Self> {
+    pub fn new(api_key: String, http: &HttpSettings) -> Result<Self> {
         if api_key.trim().is_empty() {
             return Err(anyhow!("API key cannot be empty"));
         }
 
-        let client = Client::builder()
-            .timeout(REQUEST_TIMEOUT)
-            .connect_timeout(CONNECT_TIMEOUT)
-            .pool_idle_timeout(Duration::from_secs(90))
-            .pool_max_idle_per_host(10)
-            .tcp_keepalive(Duration::from_secs(60))
+        let client = client_builder(http)?
             .http2_keep_alive_interval(Duration::from_secs(30))
             .http2_keep_alive_timeout(Duration::from_secs(10))
             .http2_keep_alive_while_idle(true)
@@ -39,9 +41,23 @@ impl GeminiClient {
             client,
             api_key,
             base_url: GEMINI_API_BASE.to_string(),
+            models_cache: ModelListCache::default(),
+            rate_limiter: None,
         })
     }
 
+    /// Limit generation requests to the given number per minute
+    pub fn with_rate_limit(mut self, requests_per_minute: u32) -> Self {
+        self.rate_limiter = Some(RateLimiter::per_minute(requests_per_minute));
+        self
+    }
+
+    async fn wait_for_rate_limit(&self) {
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.acquire().await;
+        }
+    }
+
     /// Generate content using the specified model
     pub async fn generate_content(
         &self,
@@ -49,6 +65,7 @@ impl GeminiClient {
         request: GenerateContentRequest,
     ) -> Result<GenerateContentResponse> {
         let url = format!("{}/models/{}:generateContent", self.base_url, model);
+        self.wait_for_rate_limit().await;
 
         let response = self
             .client
@@ -59,9 +76,17 @@ impl GeminiClient {
             .send()
             .await?;
 
-        if !response.status().is_success() {
+        tracing::debug!(
+            url = %super::redact_url(response.url()),
+            status = %response.status(),
+            "Gemini response received"
+        );
+
+        let status = response.status();
+        if !status.is_success() {
             let error_text = response.text().await?;
-            return Err(anyhow!("API request failed: {}", error_text));
+            let message = format!("API request failed: {error_text}");
+            return Err(ChatterError::api(status, message).into());
         }
 
         let response_data: GenerateContentResponse = response.json().await?;
@@ -75,6 +100,7 @@ impl GeminiClient {
         request: GenerateContentRequest,
     ) -> Result<std::pin::Pin<Box<dyn tokio_stream::Stream<Item = Result<String>> + Send>>> {
         let url = format!("{}/models/{}:streamGenerateContent", self.base_url, model);
+        self.wait_for_rate_limit().await;
 
         let response = self
             .client
@@ -87,16 +113,24 @@ impl GeminiClient {
             .send()
             .await?;
 
-        if !response.status().is_success() {
+        tracing::debug!(
+            url = %super::redact_url(response.url()),
+            status = %response.status(),
+            "Gemini response received"
+        );
+
+        let status = response.status();
+        if !status.is_success() {
             let error_text = response.text().await?;
-            return Err(anyhow!("API request failed: {}", error_text));
+            let message = format!("API request failed: {error_text}");
+            return Err(ChatterError::api(status, message).into());
         }
 
         // Streaming parser that accumulates across chunks and emits text events
         struct SseParser {
             buffer: String,
             current_event: String,
-            queue: VecDeque<String>,
+            queue: VecDeque<Result<String>>,
             done: bool,
         }
 
@@ -152,14 +186,17 @@ impl GeminiClient {
                 if !data.is_empty() {
                     if let Ok(response) = serde_json::from_str::<GenerateContentResponse>(data) {
                         if let Some(text) = response.text() {
-                            self.queue.push_back(text);
+                            self.queue.push_back(Ok(text));
+                        } else if let Some(reason) = response.block_reason() {
+                            self.queue
+                                .push_back(Err(anyhow!("Response blocked: {}", reason)));
                         }
                     }
                 }
                 self.current_event.clear();
             }
 
-            fn pop(&mut self) -> Option<String> {
+            fn pop(&mut self) -> Option<Result<String>> {
                 self.queue.pop_front()
             }
 
@@ -177,7 +214,7 @@ impl GeminiClient {
             |(mut bs, mut parser)| async move {
                 loop {
                     if let Some(next) = parser.pop() {
-                        return Some((Ok(next), (bs, parser)));
+                        return Some((next, (bs, parser)));
                     }
 
                     match bs.next().await {
@@ -214,7 +251,7 @@ impl GeminiClient {
                         None => {
                             parser.finish();
                             if let Some(next) = parser.pop() {
-                                return Some((Ok(next), (bs, parser)));
+                                return Some((next, (bs, parser)));
                             }
                             return None;
                         }
@@ -226,20 +263,22 @@ impl GeminiClient {
         Ok(Box::pin(stream))
     }
 
-    /// Send a simple text message and get response
-    pub async fn send_message(
+    /// Send a conversation and return the reply, including any function calls
+    pub async fn chat(
         &self,
         model: &str,
         conversation: &[Content],
         system_instruction: Option<&str>,
-    ) -> Result<String> {
-        let request = build_gemini_request(conversation, system_instruction);
+        tools: &[ToolDefinition],
+    ) -> Result<ChatResponse> {
+        let request = build_gemini_request(conversation, system_instruction, tools);
 
         let response = self.generate_content(model, request).await?;
 
-        response
-            .text()
-            .ok_or_else(|| anyhow!("No response text received"))
+        Ok(ChatResponse {
+            message: response.to_model_message()?,
+            thinking: None,
+        })
     }
 
     /// Send a message with streaming response
@@ -249,17 +288,110 @@ impl GeminiClient {
         conversation: &[Content],
         system_instruction: Option<&str>,
     ) -> Result<impl tokio_stream::Stream<Item = Result<String>>> {
-        let request = build_gemini_request(conversation, system_instruction);
+        let request = build_gemini_request(conversation, system_instruction, &[]);
 
         self.generate_content_stream(model, request).await
     }
+
+    /// List the models that support `generateContent`
+    pub async fn list_models(&self) -> Result<Vec<String>> {
+        self.models_cache.get_or_fetch(|| self.fetch_models()).await
+    }
+
+    async fn fetch_models(&self) -> Result<Vec<String>> {
+        let url = format!("{}/models", self.base_url);
+        let mut models = Vec::new();
+        let mut page_token: Option<String> = None;
+
+        loop {
+            let mut request = self
+                .client
+                .get(&url)
+                .query(&[("key", self.api_key.as_str()), ("pageSize", "1000")]);
+            if let Some(token) = &page_token {
+                request = request.query(&[("pageToken", token)]);
+            }
+
+            let response = request.send().await?;
+            tracing::debug!(
+                url = %super::redact_url(response.url()),
+                status = %response.status(),
+                "Gemini model list received"
+            );
+
+            let status = response.status();
+            if matches!(status.as_u16(), 400 | 401 | 403) {
+                return Err(anyhow!(
+                    "Gemini rejected the API key while listing models. Run 'chatter config set-api-key'."
+                ));
+            }
+            if !status.is_success() {
+                let error_text = response.text().await?;
+                return Err(anyhow!("Listing Gemini models failed: {}", error_text));
+            }
+
+            let page: GeminiModelList = response.json().await?;
+            models.extend(generate_content_models(&page));
+
+            match page.next_page_token {
+                Some(token) if !token.is_empty() => page_token = Some(token),
+                _ => break,
+            }
+        }
+
+        Ok(models)
+    }
+}
+
+/// Page of results from the Gemini `models.list` endpoint
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiModelList {
+    #[serde(default)]
+    models: Vec<GeminiModelInfo>,
+    #[serde(default)]
+    next_page_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiModelInfo {
+    name: String,
+    #[serde(default)]
+    supported_generation_methods: Vec<String>,
+}
+
+/// Names of models usable for chat, without the `models/` resource prefix
+fn generate_content_models(page: &GeminiModelList) -> Vec<String> {
+    page.models
+        .iter()
+        .filter(|model| {
+            model
+                .supported_generation_methods
+                .iter()
+                .any(|method| method == "generateContent")
+        })
+        .map(|model| {
+            model
+                .name
+                .strip_prefix("models/")
+                .unwrap_or(&model.name)
+                .to_string()
+        })
+        .collect()
 }
 
 fn build_gemini_request(
     conversation: &[Content],
     system_instruction: Option<&str>,
+    tools: &[ToolDefinition],
 ) -> GenerateContentRequest {
-    let mut request = GenerateContentRequest::new(normalize_conversation_for_gemini(conversation));
+    let native_tools = !tools.is_empty();
+    let mut request = GenerateContentRequest::new(normalize_conversation_for_gemini(
+        conversation,
+        native_tools,
+    ))
+    .with_tools(tools);
 
     if let Some(instruction) = system_instruction {
         request = request.with_system_instruction(instruction.to_string());
@@ -268,26 +400,86 @@ fn build_gemini_request(
     request
 }
 
-fn normalize_conversation_for_gemini(conversation: &[Content]) -> Vec<Content> {
-    conversation
-        .iter()
-        .filter_map(|content| match content.role.as_str() {
-            "user" => Some(Content {
-                role: "user".to_string(),
-                parts: content.parts.clone(),
-                name: None,
-                tool_call_id: None,
-                tool_calls: Vec::new(),
-            }),
-            "model" | "assistant" => Some(Content {
-                role: "model".to_string(),
-                parts: content.parts.clone(),
+/// Map our conversation onto the two roles Gemini accepts
+///
+/// With `native_tools`, tool calls and results become `functionCall` and
+/// `functionResponse` parts. Otherwise (and for system messages) they are folded
+/// into `user` turns with a label so their content stays in context. Adjacent turns
+/// with the same role are merged.
+fn normalize_conversation_for_gemini(conversation: &[Content], native_tools: bool) -> Vec<Content> {
+    let mut normalized: Vec<Content> = Vec::new();
+
+    for content in conversation {
+        let (role, parts) = match content.role.as_str() {
+            "user" => ("user", content.parts.clone()),
+            "model" | "assistant" => {
+                let mut parts: Vec<Part> = content
+                    .parts
+                    .iter()
+                    .filter(|part| !part.text.is_empty() || part.inline_data.is_some())
+                    .cloned()
+                    .collect();
+                for call in &content.tool_calls {
+                    parts.push(if native_tools {
+                        Part::function_call(call)
+                    } else {
+                        Part::text(format!(
+                            "[Called tool {} with arguments {}]",
+                            call.name, call.arguments
+                        ))
+                    });
+                }
+                ("model", parts)
+            }
+            "tool" if native_tools && content.name.is_some() => {
+                let name = content.name.clone().unwrap_or_default();
+                let output = content.joined_text();
+                (
+                    "user",
+                    vec![Part::function_response(
+                        content.tool_call_id.clone(),
+                        name,
+                        &output,
+                    )],
+                )
+            }
+            "tool" => {
+                let label = match content.name {
+                    Some(ref name) => format!("[Tool result from {name}]"),
+                    None => "[Tool result]".to_string(),
+                };
+                ("user", labelled_parts(&label, &content.parts))
+            }
+            other => (
+                "user",
+                labelled_parts(&format!("[{other} message]"), &content.parts),
+            ),
+        };
+
+        if parts.is_empty() {
+            continue;
+        }
+
+        match normalized.last_mut() {
+            Some(previous) if previous.role == role => previous.parts.extend(parts),
+            _ => normalized.push(Content {
+                role: role.to_string(),
+                parts,
                 name: None,
                 tool_call_id: None,
                 tool_calls: Vec::new(),
+                provider: None,
             }),
-            _ => None,
-        })
+        }
+    }
+
+    normalized
+}
+
+fn labelled_parts(label: &str, parts: &[Part]) -> Vec<Part> {
+    parts
+        .iter()
+        .map(|part| Part::text(format!("{label}\n{}", part.text)))
         .collect()
 }
 
@@ -298,17 +490,16 @@ mod tests {
     fn content_with_role(role: &str, text: &str) -> Content {
         Content {
             role: role.to_string(),
-            parts: vec![Part {
-                text: text.to_string(),
-            }],
+            parts: vec![Part::text(text.to_string())],
             name: None,
             tool_call_id: None,
             tool_calls: Vec::new(),
+            provider: None,
         }
     }
 
     #[test]
-    fn normalize_conversation_filters_and_maps_roles() {
+    fn normalize_conversation_folds_non_chat_roles_into_user_turns() {
         let conversation = vec![
             content_with_role("user", "Hello"),
             content_with_role("assistant", "Hi there"),
@@ -317,18 +508,99 @@ mod tests {
             content_with_role("model", "Response"),
         ];
 
-        let normalized = normalize_conversation_for_gemini(&conversation);
+        let normalized = normalize_conversation_for_gemini(&conversation, false);
 
-        assert_eq!(
-            normalized.len(),
-            3,
-            "system/tool messages should be dropped"
-        );
+        assert_eq!(normalized.len(), 4);
         assert_eq!(normalized[0].role, "user");
         assert_eq!(normalized[0].parts[0].text, "Hello");
         assert_eq!(normalized[1].role, "model");
         assert_eq!(normalized[1].parts[0].text, "Hi there");
-        assert_eq!(normalized[2].role, "model");
-        assert_eq!(normalized[2].parts[0].text, "Response");
+        assert_eq!(normalized[2].role, "user");
+        assert_eq!(normalized[2].parts.len(), 2);
+        assert!(normalized[2].parts[0].text.contains("Guidance"));
+        assert!(normalized[2].parts[1].text.starts_with("[Tool result]"));
+        assert!(normalized[2].parts[1].text.contains("Tool output"));
+        assert_eq!(normalized[3].role, "model");
+        assert_eq!(normalized[3].parts[0].text, "Response");
+    }
+
+    #[test]
+    fn normalize_conversation_describes_tool_calls() {
+        let mut call_turn = content_with_role("assistant", "");
+        call_turn.tool_calls.push(ModelToolCall {
+            id: Some("call_1".to_string()),
+            name: "read_file".to_string(),
+            arguments: serde_json::json!({ "path": "notes.txt" }),
+        });
+        let mut result_turn = content_with_role("tool", "{\"success\":true}");
+        result_turn.name = Some("read_file".to_string());
+
+        let conversation = [
+            content_with_role("user", "Read my notes"),
+            call_turn,
+            result_turn,
+        ];
+        let normalized = normalize_conversation_for_gemini(&conversation, false);
+
+        assert_eq!(normalized.len(), 3);
+        assert_eq!(normalized[1].parts.len(), 1);
+        assert!(normalized[1].parts[0].text.contains("read_file"));
+        assert!(normalized[2].parts[0]
+            .text
+            .starts_with("[Tool result from read_file]"));
+
+        // With tools declared, the same turns use Gemini's function parts
+        let native = normalize_conversation_for_gemini(&conversation, true);
+        let call = native[1].parts[0].function_call.as_ref().unwrap();
+        assert_eq!(call.name, "read_file");
+        assert_eq!(call.args, serde_json::json!({ "path": "notes.txt" }));
+        let response = native[2].parts[0].function_response.as_ref().unwrap();
+        assert_eq!(response.name, "read_file");
+        assert_eq!(response.id.as_deref(), None);
+        assert_eq!(response.response, serde_json::json!({ "success": true }));
+    }
+
+    #[test]
+    fn model_list_keeps_generate_content_models() {
+        let page: GeminiModelList = serde_json::from_value(serde_json::json!({
+            "models": [
+                {
+                    "name": "models/gemini-2.5-flash",
+                    "supportedGenerationMethods": ["generateContent", "countTokens"]
+                },
+                {
+                    "name": "models/text-embedding-004",
+                    "supportedGenerationMethods": ["embedContent"]
+                }
+            ]
+        }))
+        .unwrap();
+
+        assert_eq!(generate_content_models(&page), vec!["gemini-2.5-flash"]);
+        assert!(page.next_page_token.is_none());
+    }
+
+    #[test]
+    fn attached_image_serializes_as_inline_data() {
+        let mut message = Content::user("What is in this screenshot?".to_string());
+        message
+            .parts
+            .push(Part::inline_data("image/png", "aGVsbG8="));
+
+        let request = build_gemini_request(&[message], None, &[]);
+        let json = serde_json::to_value(&request).unwrap();
+
+        assert_eq!(
+            json["contents"][0]["parts"],
+            serde_json::json!([
+                { "text": "What is in this screenshot?" },
+                { "inlineData": { "mimeType": "image/png", "data": "aGVsbG8=" } }
+            ])
+        );
+
+        // Text-only sessions saved before images were supported still load
+        let legacy: Content =
+            serde_json::from_str(r#"{"role":"user","parts":[{"text":"hi"}]}"#).unwrap();
+        assert!(legacy.parts[0].inline_data.is_none());
     }
 }
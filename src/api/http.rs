@@ -0,0 +1,213 @@
+//! HTTP client construction shared by the provider clients
+//!
+//! Applies the configurable request timeout, the common timeouts, the proxy
+//! (from the config or the usual `HTTP_PROXY` / `HTTPS_PROXY` / `NO_PROXY`
+//! variables), and any extra headers configured for gateways in front of a
+//! provider.
+
+use super::{CONNECT_TIMEOUT, MIN_REQUEST_TIMEOUT_SECS, REQUEST_TIMEOUT};
+use anyhow::{anyhow, bail, Context, Result};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{ClientBuilder, NoProxy, Proxy};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Network settings applied to every provider client
+#[derive(Debug, Clone, Default)]
+pub struct HttpSettings {
+    /// Proxy URL used for all requests, overriding the proxy environment variables
+    pub proxy: Option<String>,
+    /// Headers added to every request
+    pub headers: HashMap<String, String>,
+    /// Time allowed for a whole request, instead of the five-minute default
+    pub request_timeout: Option<Duration>,
+}
+
+/// Start a client builder with the shared timeouts, proxy, and headers
+pub fn client_builder(settings: &HttpSettings) -> Result<ClientBuilder> {
+    client_builder_with_env(settings, |name| std::env::var(name).ok())
+}
+
+/// `client_builder` reading proxy variables through `env`
+fn client_builder_with_env(
+    settings: &HttpSettings,
+    env: impl Fn(&str) -> Option<String>,
+) -> Result<ClientBuilder> {
+    let mut builder = reqwest::Client::builder()
+        .timeout(request_timeout(settings)?)
+        .connect_timeout(CONNECT_TIMEOUT)
+        .pool_idle_timeout(Duration::from_secs(90))
+        .pool_max_idle_per_host(10)
+        .tcp_keepalive(Duration::from_secs(60))
+        // Proxies are resolved below so the config can take precedence
+        .no_proxy();
+
+    for proxy in proxies(settings.proxy.as_deref(), &env)? {
+        builder = builder.proxy(proxy);
+    }
+
+    if !settings.headers.is_empty() {
+        builder = builder.default_headers(header_map(&settings.headers)?);
+    }
+
+    Ok(builder)
+}
+
+/// The configured request timeout, or the default when none is set
+fn request_timeout(settings: &HttpSettings) -> Result<Duration> {
+    match settings.request_timeout {
+        Some(timeout) => {
+            check_request_timeout(timeout.as_secs())?;
+            Ok(timeout)
+        }
+        None => Ok(REQUEST_TIMEOUT),
+    }
+}
+
+/// Reject request timeouts too short for a model to answer in
+pub fn check_request_timeout(secs: u64) -> Result<()> {
+    if secs < MIN_REQUEST_TIMEOUT_SECS {
+        bail!("Request timeout must be at least {MIN_REQUEST_TIMEOUT_SECS} seconds, got {secs}");
+    }
+    Ok(())
+}
+
+/// Proxies from the config override, or else from the environment
+fn proxies(configured: Option<&str>, env: &impl Fn(&str) -> Option<String>) -> Result<Vec<Proxy>> {
+    // Variables are conventionally accepted in either case
+    let var = |name: &str| {
+        env(name)
+            .or_else(|| env(&name.to_lowercase()))
+            .filter(|value| !value.trim().is_empty())
+    };
+    let no_proxy = || var("NO_PROXY").and_then(|list| NoProxy::from_string(&list));
+
+    if let Some(url) = configured.filter(|url| !url.trim().is_empty()) {
+        let proxy = Proxy::all(url.trim()).with_context(|| format!("Invalid proxy URL: {url}"))?;
+        return Ok(vec![proxy.no_proxy(no_proxy())]);
+    }
+
+    let mut proxies = Vec::new();
+    if let Some(url) = var("HTTPS_PROXY") {
+        let proxy =
+            Proxy::https(url.trim()).with_context(|| format!("Invalid HTTPS_PROXY: {url}"))?;
+        proxies.push(proxy.no_proxy(no_proxy()));
+    }
+    if let Some(url) = var("HTTP_PROXY") {
+        let proxy =
+            Proxy::http(url.trim()).with_context(|| format!("Invalid HTTP_PROXY: {url}"))?;
+        proxies.push(proxy.no_proxy(no_proxy()));
+    }
+    Ok(proxies)
+}
+
+fn header_map(headers: &HashMap<String, String>) -> Result<HeaderMap> {
+    let mut map = HeaderMap::new();
+    for (name, value) in headers {
+        let header_name = HeaderName::from_bytes(name.trim().as_bytes())
+            .map_err(|_| anyhow!("Invalid header name: {name}"))?;
+        let mut header_value =
+            HeaderValue::from_str(value).map_err(|_| anyhow!("Invalid value for header {name}"))?;
+        header_value.set_sensitive(true);
+        map.insert(header_name, header_value);
+    }
+    Ok(map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Answer one request with an empty 200 and return what was received
+    async fn accept_one(listener: TcpListener) -> String {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut received = Vec::new();
+        let mut buffer = [0u8; 1024];
+        while !received.windows(4).any(|w| w == b"\r\n\r\n") {
+            let read = socket.read(&mut buffer).await.unwrap();
+            if read == 0 {
+                break;
+            }
+            received.extend_from_slice(&buffer[..read]);
+        }
+        socket
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        String::from_utf8_lossy(&received).to_lowercase()
+    }
+
+    #[tokio::test]
+    async fn requests_go_through_the_proxy_from_the_environment() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy_url = format!("http://{}", listener.local_addr().unwrap());
+        let proxy = tokio::spawn(accept_one(listener));
+
+        let settings = HttpSettings {
+            headers: HashMap::from([("X-Gateway-Key".to_string(), "secret".to_string())]),
+            ..HttpSettings::default()
+        };
+        let client = client_builder_with_env(&settings, |name| {
+            (name == "http_proxy").then(|| proxy_url.clone())
+        })
+        .unwrap()
+        .build()
+        .unwrap();
+
+        let response = client
+            .get("http://chatter.invalid/v1/models")
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+
+        // A proxied request names the full target URL
+        let request = proxy.await.unwrap();
+        assert!(request.starts_with("get http://chatter.invalid/v1/models http/1.1"));
+        assert!(request.contains("x-gateway-key: secret"));
+    }
+
+    #[test]
+    fn configured_proxy_overrides_the_environment_and_bad_values_fail() {
+        let env = |name: &str| (name == "HTTPS_PROXY").then(|| "http://env-proxy:8080".to_string());
+        assert_eq!(proxies(None, &env).unwrap().len(), 1);
+        let configured = proxies(Some("http://config-proxy:3128"), &env).unwrap();
+        assert_eq!(configured.len(), 1);
+        assert!(format!("{:?}", configured[0]).contains("config-proxy"));
+        assert!(proxies(Some("not a url"), &env).is_err());
+
+        let bad_header = HttpSettings {
+            headers: HashMap::from([("bad header".to_string(), "x".to_string())]),
+            ..HttpSettings::default()
+        };
+        assert!(client_builder_with_env(&bad_header, |_| None).is_err());
+    }
+
+    #[test]
+    fn builder_uses_the_configured_request_timeout() {
+        assert_eq!(
+            request_timeout(&HttpSettings::default()).unwrap(),
+            REQUEST_TIMEOUT
+        );
+
+        let mut config = crate::config::Config::default();
+        config.request_timeout = Some(42);
+        let settings = config.http_settings();
+        assert_eq!(request_timeout(&settings).unwrap(), Duration::from_secs(42));
+        assert!(client_builder_with_env(&settings, |_| None).is_ok());
+
+        let too_short = HttpSettings {
+            request_timeout: Some(Duration::from_secs(1)),
+            ..HttpSettings::default()
+        };
+        let error = client_builder_with_env(&too_short, |_| None)
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(error.contains("at least 5 seconds"));
+        assert!(config.set_request_timeout(0).is_err());
+        assert_eq!(config.request_timeout, Some(42));
+    }
+}
@@ -1,6 +1,10 @@
+use super::anthropic::AnthropicClient;
 use super::client::GeminiClient;
+use super::http::HttpSettings;
 use super::ollama::OllamaClient;
 use super::Content;
+use crate::config::{Config, ModelProvider};
+use crate::error::ChatterError;
 use anyhow::{anyhow, Result};
 use futures_util::Stream;
 use std::pin::Pin;
@@ -31,21 +35,86 @@ impl ToolDefinition {
 #[derive(Debug, Clone)]
 pub struct ChatResponse {
     pub message: Content,
+    /// Reasoning the model produced before its answer, kept out of `message`
+    pub thinking: Option<String>,
 }
 
 /// Unified language model client wrapper
 pub enum LlmClient {
     Gemini(GeminiClient),
     Ollama(OllamaClient),
+    Anthropic(AnthropicClient),
+    #[cfg(test)]
+    Mock(super::mock::MockClient),
 }
 
 impl LlmClient {
-    pub fn new_gemini(api_key: String) -> Result<Self> {
-        Ok(Self::Gemini(GeminiClient::new(api_key)?))
+    pub fn new_gemini(api_key: String, http: &HttpSettings) -> Result<Self> {
+        Ok(Self::Gemini(GeminiClient::new(api_key, http)?))
     }
 
-    pub fn new_ollama(endpoint: String) -> Result<Self> {
-        Ok(Self::Ollama(OllamaClient::new(endpoint)?))
+    pub fn new_ollama(endpoint: String, http: &HttpSettings) -> Result<Self> {
+        Ok(Self::Ollama(OllamaClient::new(endpoint, http)?))
+    }
+
+    pub fn new_anthropic(api_key: String, endpoint: String, http: &HttpSettings) -> Result<Self> {
+        Ok(Self::Anthropic(AnthropicClient::new(
+            api_key, endpoint, http,
+        )?))
+    }
+
+    /// Build the client for a provider from the configuration, applying any rate limit
+    pub fn from_config(config: &Config, provider: &ModelProvider) -> Result<Self, ChatterError> {
+        let http = config.http_settings();
+        let client = match provider {
+            ModelProvider::Gemini => {
+                if config.api_key.trim().is_empty() {
+                    return Err(ChatterError::MissingApiKey {
+                        provider: "Gemini",
+                        hint: "Run 'chatter config set-api-key'.".to_string(),
+                    });
+                }
+                Self::new_gemini(config.api_key.clone(), &http)
+            }
+            ModelProvider::Ollama => Self::new_ollama(config.ollama.endpoint.clone(), &http),
+            ModelProvider::Anthropic => {
+                let api_key = if config.anthropic.api_key.trim().is_empty() {
+                    std::env::var("ANTHROPIC_API_KEY").unwrap_or_default()
+                } else {
+                    config.anthropic.api_key.clone()
+                };
+                if api_key.trim().is_empty() {
+                    return Err(ChatterError::MissingApiKey {
+                        provider: "Anthropic",
+                        hint: "Set ANTHROPIC_API_KEY or add anthropic.api_key to the config file."
+                            .to_string(),
+                    });
+                }
+                Self::new_anthropic(api_key, config.anthropic.endpoint.clone(), &http)
+            }
+        }?;
+
+        Ok(match config.rate_limit_per_minute {
+            Some(limit) if limit > 0 => client.with_rate_limit(limit),
+            _ => client,
+        })
+    }
+
+    /// Limit requests to the provider to the given number per minute
+    pub fn with_rate_limit(self, requests_per_minute: u32) -> Self {
+        match self {
+            LlmClient::Gemini(client) => {
+                LlmClient::Gemini(client.with_rate_limit(requests_per_minute))
+            }
+            LlmClient::Ollama(client) => {
+                LlmClient::Ollama(client.with_rate_limit(requests_per_minute))
+            }
+            LlmClient::Anthropic(client) => {
+                LlmClient::Anthropic(client.with_rate_limit(requests_per_minute))
+            }
+            #[cfg(test)]
+            LlmClient::Mock(client) => LlmClient::Mock(client),
+        }
     }
 
     /// Generate a response for the given conversation (non-streaming)
@@ -55,23 +124,33 @@ impl LlmClient {
         conversation: &[Content],
         system_instruction: Option<&str>,
         tools: &[ToolDefinition],
-    ) -> Result<ChatResponse> {
-        match self {
+    ) -> Result<ChatResponse, ChatterError> {
+        let response = match self {
             LlmClient::Gemini(client) => {
-                // Gemini client currently has no tool invocation support
-                let response = client
-                    .send_message(model, conversation, system_instruction)
-                    .await?;
-                Ok(ChatResponse {
-                    message: Content::model(response),
-                })
+                client
+                    .chat(model, conversation, system_instruction, tools)
+                    .await
             }
             LlmClient::Ollama(client) => {
                 client
                     .chat(model, conversation, system_instruction, tools)
                     .await
             }
-        }
+            LlmClient::Anthropic(client) => {
+                client
+                    .chat(model, conversation, system_instruction, tools)
+                    .await
+            }
+            #[cfg(test)]
+            LlmClient::Mock(client) => {
+                // Even a zero-length sleep yields once, which would trip zero time budgets
+                if !client.delay().is_zero() {
+                    tokio::time::sleep(client.delay()).await;
+                }
+                client.chat(conversation)
+            }
+        }?;
+        Ok(response)
     }
 
     /// Generate a streaming response for the given conversation
@@ -80,7 +159,7 @@ impl LlmClient {
         model: &str,
         conversation: &[Content],
         system_instruction: Option<&str>,
-    ) -> Result<Pin<Box<dyn Stream<Item = Result<String>> + Send>>> {
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<String>> + Send>>, ChatterError> {
         match self {
             LlmClient::Gemini(client) => {
                 let stream = client
@@ -88,9 +167,79 @@ impl LlmClient {
                     .await?;
                 Ok(Box::pin(stream) as Pin<Box<dyn Stream<Item = Result<String>> + Send>>)
             }
-            LlmClient::Ollama(_) => Err(anyhow!(
-                "Streaming responses are not yet supported for Ollama"
-            )),
+            LlmClient::Ollama(_) => {
+                Err(anyhow!("Streaming responses are not yet supported for Ollama").into())
+            }
+            LlmClient::Anthropic(client) => Ok(client
+                .chat_stream(model, conversation, system_instruction)
+                .await?),
+            #[cfg(test)]
+            LlmClient::Mock(client) => {
+                // Each part of the scripted reply arrives as its own chunk
+                let chunks: Vec<Result<String>> = client
+                    .chat(conversation)?
+                    .message
+                    .parts
+                    .into_iter()
+                    .map(|part| Ok(part.text))
+                    .collect();
+                Ok(Box::pin(futures_util::stream::iter(chunks)))
+            }
+        }
+    }
+
+    /// Warning for a model name the provider is unlikely to accept
+    ///
+    /// Unknown names are allowed (new models appear before our lists catch up), so
+    /// this only explains the likely problem. A failed model listing is ignored.
+    pub async fn model_warning(&self, provider: &ModelProvider, model: &str) -> Option<String> {
+        if let Some(hint) = super::models::provider_mismatch_hint(model, provider) {
+            return Some(hint);
         }
+
+        match self.list_models().await {
+            Ok(models) if !models.is_empty() && !super::models::is_listed(model, &models) => {
+                Some(format!(
+                    "Model '{}' is not in the list reported by {}; using it anyway. Run `chatter models` to see what is available.",
+                    model,
+                    provider.display_name()
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    /// List the models the provider offers, cached briefly per client
+    pub async fn list_models(&self) -> Result<Vec<String>, ChatterError> {
+        let models = match self {
+            LlmClient::Gemini(client) => client.list_models().await,
+            LlmClient::Ollama(client) => client.list_models().await,
+            LlmClient::Anthropic(client) => client.list_models().await,
+            #[cfg(test)]
+            LlmClient::Mock(_) => Ok(vec!["mock".to_string()]),
+        }?;
+        Ok(models)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_api_keys_are_reported_as_such() {
+        let mut config = Config::default();
+        config.api_key.clear();
+        let Err(error) = LlmClient::from_config(&config, &ModelProvider::Gemini) else {
+            panic!("a Gemini client needs an API key");
+        };
+        assert!(matches!(
+            error,
+            ChatterError::MissingApiKey {
+                provider: "Gemini",
+                ..
+            }
+        ));
+        assert!(LlmClient::from_config(&config, &ModelProvider::Ollama).is_ok());
     }
 }
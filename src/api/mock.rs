@@ -0,0 +1,84 @@
+//! Scripted client for tests
+//!
+//! Replies with canned messages in order and records every conversation it was sent.
+
+use super::llm::ChatResponse;
+use super::Content;
+use anyhow::{anyhow, Result};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Client that returns queued replies instead of calling a provider
+#[derive(Debug, Default)]
+pub struct MockClient {
+    replies: Mutex<VecDeque<Result<Content>>>,
+    requests: Mutex<Vec<Vec<Content>>>,
+    delay: Duration,
+}
+
+impl MockClient {
+    /// Queue plain text replies, returned one per request
+    pub fn with_replies<I, S>(replies: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let client = Self::default();
+        for reply in replies {
+            client.push_reply(Content::model(reply.into()));
+        }
+        client
+    }
+
+    /// Wait this long before answering each request
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// How long each request takes
+    pub fn delay(&self) -> Duration {
+        self.delay
+    }
+
+    /// Queue a reply message, which may carry tool calls
+    pub fn push_reply(&self, message: Content) {
+        self.replies.lock().unwrap().push_back(Ok(message));
+    }
+
+    /// Queue a reply that streams as the given chunks
+    pub fn push_stream<I, S>(&self, chunks: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut message = Content::model(String::new());
+        message.parts = chunks
+            .into_iter()
+            .map(|chunk| super::Part::text(chunk.into()))
+            .collect();
+        self.push_reply(message);
+    }
+
+    /// Queue a failed request
+    pub fn push_error(&self, error: anyhow::Error) {
+        self.replies.lock().unwrap().push_back(Err(error));
+    }
+
+    /// Conversations sent so far, oldest first
+    pub fn requests(&self) -> Vec<Vec<Content>> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    pub fn chat(&self, conversation: &[Content]) -> Result<ChatResponse> {
+        self.requests.lock().unwrap().push(conversation.to_vec());
+        match self.replies.lock().unwrap().pop_front() {
+            Some(reply) => reply.map(|message| ChatResponse {
+                message,
+                thinking: None,
+            }),
+            None => Err(anyhow!("MockClient has no more scripted replies")),
+        }
+    }
+}
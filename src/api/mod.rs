@@ -3,14 +3,22 @@
 //! Handles communication with Google's Gemini API, including request/response
 //! serialization, streaming, and error handling.
 
+use crate::config::ModelProvider;
+use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::time::Duration;
 
+pub mod anthropic;
 pub mod client;
+pub mod http;
 pub mod llm;
+#[cfg(test)]
+pub mod mock;
 pub mod models;
 pub mod ollama;
+pub mod race;
+pub mod rate_limit;
 pub mod streaming;
 
 pub use llm::{LlmClient, ToolDefinition};
@@ -21,17 +29,132 @@ const GEMINI_API_BASE: &str = "https://generativelanguage.googleapis.com/v1beta"
 /// HTTP client configuration
 const REQUEST_TIMEOUT: Duration = Duration::from_secs(300); // 5 minutes for streaming responses
 const CONNECT_TIMEOUT: Duration = Duration::from_secs(30); // 30 seconds to establish connection
+/// Shortest request timeout accepted from the config or `--timeout`
+pub const MIN_REQUEST_TIMEOUT_SECS: u64 = 5;
 
 /// Content part in a message
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Part {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub text: String,
+    /// Base64-encoded binary payload such as an image
+    #[serde(
+        rename = "inlineData",
+        alias = "inline_data",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub inline_data: Option<InlineData>,
+    /// Tool invocation requested by a Gemini model
+    #[serde(
+        rename = "functionCall",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub function_call: Option<FunctionCall>,
+    /// Tool result sent back to a Gemini model
+    #[serde(
+        rename = "functionResponse",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub function_response: Option<FunctionResponse>,
+}
+
+/// Gemini's request to call one of the declared functions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionCall {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub args: Value,
+}
+
+/// Output of a function call, answering the `FunctionCall` with the same name
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionResponse {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub name: String,
+    /// Must be a JSON object
+    pub response: Value,
+}
+
+/// Inline binary data attached to a part
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InlineData {
+    #[serde(alias = "mime_type")]
+    pub mime_type: String,
+    /// Base64-encoded bytes
+    pub data: String,
+}
+
+impl Part {
+    /// Create a text part
+    pub fn text(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            inline_data: None,
+            function_call: None,
+            function_response: None,
+        }
+    }
+
+    /// Create a part carrying base64-encoded inline data
+    pub fn inline_data(mime_type: impl Into<String>, data: impl Into<String>) -> Self {
+        Self {
+            text: String::new(),
+            inline_data: Some(InlineData {
+                mime_type: mime_type.into(),
+                data: data.into(),
+            }),
+            function_call: None,
+            function_response: None,
+        }
+    }
+
+    /// Create a part asking Gemini to replay a tool call from the history
+    pub fn function_call(call: &ModelToolCall) -> Self {
+        let args = if call.arguments.is_null() {
+            Value::Object(Default::default())
+        } else {
+            call.arguments.clone()
+        };
+        Self {
+            function_call: Some(FunctionCall {
+                id: call.id.clone(),
+                name: call.name.clone(),
+                args,
+            }),
+            ..Self::text(String::new())
+        }
+    }
+
+    /// Create a part carrying a tool result, wrapping non-object output in `{"content": ...}`
+    pub fn function_response(id: Option<String>, name: impl Into<String>, output: &str) -> Self {
+        let response = match serde_json::from_str::<Value>(output) {
+            Ok(value @ Value::Object(_)) => value,
+            _ => serde_json::json!({ "content": output }),
+        };
+        Self {
+            function_response: Some(FunctionResponse {
+                id,
+                name: name.into(),
+                response,
+            }),
+            ..Self::text(String::new())
+        }
+    }
 }
 
 /// Message content with role and parts
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Content {
+    #[serde(default)]
     pub role: String,
+    #[serde(default)]
     pub parts: Vec<Part>,
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
@@ -42,6 +165,10 @@ pub struct Content {
     #[serde(skip_serializing_if = "Vec::is_empty")]
     #[serde(default)]
     pub tool_calls: Vec<ModelToolCall>,
+    /// Provider that produced a model message, kept so mixed sessions display correctly
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub provider: Option<ModelProvider>,
 }
 
 /// Model tool call representation used across providers
@@ -74,10 +201,75 @@ pub struct GenerationConfig {
     pub max_output_tokens: Option<i32>,
 }
 
+/// Group of functions the model may call
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiTool {
+    pub function_declarations: Vec<FunctionDeclaration>,
+}
+
+/// A function the model may call, described by an OpenAPI-style schema
+#[derive(Debug, Clone, Serialize)]
+pub struct FunctionDeclaration {
+    pub name: String,
+    pub description: String,
+    /// Omitted for functions without parameters, which Gemini requires
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parameters: Option<Value>,
+}
+
+impl FunctionDeclaration {
+    fn from_definition(tool: &ToolDefinition) -> Self {
+        let has_properties = tool
+            .parameters
+            .get("properties")
+            .and_then(Value::as_object)
+            .is_some_and(|properties| !properties.is_empty());
+        Self {
+            name: tool.name.clone(),
+            description: tool.description.clone(),
+            parameters: has_properties.then(|| gemini_schema(&tool.parameters)),
+        }
+    }
+}
+
+/// Drop JSON Schema keywords that Gemini's schema subset rejects
+///
+/// Gemini fails the whole request on unknown fields such as `additionalProperties`
+/// or `$schema`, which user-defined external tools commonly include.
+fn gemini_schema(schema: &Value) -> Value {
+    let Value::Object(map) = schema else {
+        return schema.clone();
+    };
+
+    let mut cleaned = serde_json::Map::new();
+    for (key, value) in map {
+        let value = match (key.as_str(), value) {
+            ("additionalProperties", _) => continue,
+            (key, _) if key.starts_with('$') => continue,
+            ("properties", Value::Object(properties)) => Value::Object(
+                properties
+                    .iter()
+                    .map(|(name, schema)| (name.clone(), gemini_schema(schema)))
+                    .collect(),
+            ),
+            ("items", schema) => gemini_schema(schema),
+            ("anyOf", Value::Array(schemas)) => {
+                Value::Array(schemas.iter().map(gemini_schema).collect())
+            }
+            _ => value.clone(),
+        };
+        cleaned.insert(key.clone(), value);
+    }
+    Value::Object(cleaned)
+}
+
 /// Request to generate content
 #[derive(Debug, Clone, Serialize)]
 pub struct GenerateContentRequest {
     pub contents: Vec<Content>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<GeminiTool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub system_instruction: Option<SystemInstruction>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -87,16 +279,65 @@ pub struct GenerateContentRequest {
 /// Candidate response from the model
 #[derive(Debug, Clone, Deserialize)]
 pub struct Candidate {
-    pub content: Content,
+    /// Missing when the candidate was blocked before any content was produced
+    #[serde(default)]
+    pub content: Option<Content>,
     #[serde(rename = "finishReason")]
-    #[allow(dead_code)]
     pub finish_reason: Option<String>,
+    #[serde(rename = "safetyRatings", default)]
+    pub safety_ratings: Vec<SafetyRating>,
+}
+
+/// Safety classification of a prompt or candidate
+#[derive(Debug, Clone, Deserialize)]
+pub struct SafetyRating {
+    pub category: String,
+    #[serde(default)]
+    pub probability: Option<String>,
+    #[serde(default)]
+    pub blocked: bool,
+}
+
+/// Feedback on the prompt, present when the prompt itself was blocked
+#[derive(Debug, Clone, Deserialize)]
+pub struct PromptFeedback {
+    #[serde(rename = "blockReason")]
+    pub block_reason: Option<String>,
+    #[serde(rename = "safetyRatings", default)]
+    pub safety_ratings: Vec<SafetyRating>,
 }
 
 /// Response from the generate content API
 #[derive(Debug, Clone, Deserialize)]
 pub struct GenerateContentResponse {
+    /// Empty when the prompt was blocked by safety filters
+    #[serde(default)]
     pub candidates: Vec<Candidate>,
+    #[serde(rename = "promptFeedback")]
+    pub prompt_feedback: Option<PromptFeedback>,
+}
+
+/// Render a request URL for logging with credentials in the query string masked
+pub fn redact_url(url: &reqwest::Url) -> String {
+    if !url.query_pairs().any(|(name, _)| name == "key") {
+        return url.to_string();
+    }
+
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(name, value)| {
+            let value = if name == "key" {
+                "REDACTED".to_string()
+            } else {
+                value.into_owned()
+            };
+            (name.into_owned(), value)
+        })
+        .collect();
+
+    let mut redacted = url.clone();
+    redacted.query_pairs_mut().clear().extend_pairs(pairs);
+    redacted.to_string()
 }
 
 impl GenerateContentRequest {
@@ -104,6 +345,7 @@ impl GenerateContentRequest {
     pub fn new(contents: Vec<Content>) -> Self {
         Self {
             contents,
+            tools: Vec::new(),
             system_instruction: None,
             generation_config: None,
         }
@@ -112,11 +354,24 @@ impl GenerateContentRequest {
     /// Add system instruction to the request
     pub fn with_system_instruction(mut self, instruction: String) -> Self {
         self.system_instruction = Some(SystemInstruction {
-            parts: vec![Part { text: instruction }],
+            parts: vec![Part::text(instruction)],
         });
         self
     }
 
+    /// Declare the functions the model may call
+    pub fn with_tools(mut self, tools: &[ToolDefinition]) -> Self {
+        if !tools.is_empty() {
+            self.tools = vec![GeminiTool {
+                function_declarations: tools
+                    .iter()
+                    .map(FunctionDeclaration::from_definition)
+                    .collect(),
+            }];
+        }
+        self
+    }
+
     /// Add generation configuration
     #[allow(dead_code)]
     pub fn with_generation_config(mut self, config: GenerationConfig) -> Self {
@@ -130,10 +385,11 @@ impl Content {
     pub fn user(text: String) -> Self {
         Self {
             role: "user".to_string(),
-            parts: vec![Part { text }],
+            parts: vec![Part::text(text)],
             name: None,
             tool_call_id: None,
             tool_calls: Vec::new(),
+            provider: None,
         }
     }
 
@@ -141,20 +397,259 @@ impl Content {
     pub fn model(text: String) -> Self {
         Self {
             role: "model".to_string(),
-            parts: vec![Part { text }],
+            parts: vec![Part::text(text)],
             name: None,
             tool_call_id: None,
             tool_calls: Vec::new(),
+            provider: None,
         }
     }
+
+    /// Text of all parts, one per line, skipping parts without text (e.g. images)
+    pub fn joined_text(&self) -> String {
+        self.parts
+            .iter()
+            .map(|part| part.text.as_str())
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
 }
 
 impl GenerateContentResponse {
-    /// Get the text from the first candidate
+    /// Get the text from the first candidate, joining all of its parts
     pub fn text(&self) -> Option<String> {
         self.candidates
             .first()
-            .and_then(|c| c.content.parts.first())
-            .map(|p| p.text.clone())
+            .and_then(|c| c.content.as_ref())
+            .filter(|content| !content.parts.is_empty())
+            .map(Content::joined_text)
+    }
+
+    /// Convert the first candidate into a model message, turning function calls into tool calls
+    pub fn to_model_message(&self) -> Result<Content> {
+        let tool_calls: Vec<ModelToolCall> = self
+            .candidates
+            .first()
+            .and_then(|c| c.content.as_ref())
+            .map(|content| {
+                content
+                    .parts
+                    .iter()
+                    .filter_map(|part| part.function_call.as_ref())
+                    .map(|call| ModelToolCall {
+                        id: call.id.clone(),
+                        name: call.name.clone(),
+                        arguments: call.args.clone(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        if tool_calls.is_empty() {
+            return Ok(Content::model(self.text_or_error()?));
+        }
+        let mut message = Content::model(self.text().unwrap_or_default());
+        message.tool_calls = tool_calls;
+        Ok(message)
+    }
+
+    /// Get the response text, explaining why there is none when content was blocked
+    pub fn text_or_error(&self) -> Result<String> {
+        if let Some(text) = self.text() {
+            return Ok(text);
+        }
+        match self.block_reason() {
+            Some(reason) => Err(anyhow!("Response blocked: {}", reason)),
+            None => Err(anyhow!("No response text received")),
+        }
+    }
+
+    /// Describe why the prompt or first candidate was blocked, e.g. `SAFETY (HARM_CATEGORY_HARASSMENT)`
+    pub fn block_reason(&self) -> Option<String> {
+        if let Some(feedback) = &self.prompt_feedback {
+            if let Some(reason) = &feedback.block_reason {
+                return Some(describe_block(reason, &feedback.safety_ratings));
+            }
+        }
+
+        let candidate = self.candidates.first()?;
+        match candidate.finish_reason.as_deref() {
+            None | Some("STOP") | Some("MAX_TOKENS") | Some("FINISH_REASON_UNSPECIFIED") => None,
+            Some(reason) => Some(describe_block(reason, &candidate.safety_ratings)),
+        }
+    }
+}
+
+/// Format a block reason with the safety categories that triggered it
+fn describe_block(reason: &str, ratings: &[SafetyRating]) -> String {
+    let categories: Vec<&str> = ratings
+        .iter()
+        .filter(|rating| {
+            rating.blocked || matches!(rating.probability.as_deref(), Some("MEDIUM" | "HIGH"))
+        })
+        .map(|rating| rating.category.as_str())
+        .collect();
+
+    if categories.is_empty() {
+        reason.to_string()
+    } else {
+        format!("{} ({})", reason, categories.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redact_url_masks_api_key() {
+        let url = reqwest::Url::parse(
+            "https://example.com/v1beta/models/m:streamGenerateContent?alt=sse&key=secret-123",
+        )
+        .unwrap();
+        let redacted = redact_url(&url);
+        assert!(!redacted.contains("secret-123"));
+        assert!(redacted.ends_with("?alt=sse&key=REDACTED"));
+
+        let plain = reqwest::Url::parse("http://localhost:11434/api/chat").unwrap();
+        assert_eq!(redact_url(&plain), "http://localhost:11434/api/chat");
+    }
+
+    #[test]
+    fn blocked_responses_report_the_reason() {
+        let prompt_blocked: GenerateContentResponse = serde_json::from_str(
+            r#"{
+                "promptFeedback": {
+                    "blockReason": "SAFETY",
+                    "safetyRatings": [
+                        {"category": "HARM_CATEGORY_HARASSMENT", "probability": "HIGH", "blocked": true},
+                        {"category": "HARM_CATEGORY_HATE_SPEECH", "probability": "NEGLIGIBLE"}
+                    ]
+                }
+            }"#,
+        )
+        .unwrap();
+        let error = prompt_blocked.text_or_error().unwrap_err().to_string();
+        assert_eq!(error, "Response blocked: SAFETY (HARM_CATEGORY_HARASSMENT)");
+
+        let candidate_blocked: GenerateContentResponse =
+            serde_json::from_str(r#"{"candidates": [{"finishReason": "RECITATION", "index": 0}]}"#)
+                .unwrap();
+        let error = candidate_blocked.text_or_error().unwrap_err().to_string();
+        assert_eq!(error, "Response blocked: RECITATION");
+    }
+
+    #[test]
+    fn multi_part_candidates_keep_every_part() {
+        let response: GenerateContentResponse = serde_json::from_str(
+            r#"{
+                "candidates": [{
+                    "content": {
+                        "role": "model",
+                        "parts": [{"text": "First part."}, {"text": "Second part."}]
+                    },
+                    "finishReason": "STOP",
+                    "index": 0
+                }]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(response.text().unwrap(), "First part.\nSecond part.");
+
+        let mut content = Content::user("Describe this".to_string());
+        content.parts.push(Part::inline_data("image/png", "aGk="));
+        content.parts.push(Part::text("and this"));
+        assert_eq!(content.joined_text(), "Describe this\nand this");
+    }
+
+    #[test]
+    fn tool_definitions_serialize_as_function_declarations() {
+        let tools = [
+            ToolDefinition::new(
+                "read_file",
+                "Read a file",
+                serde_json::json!({
+                    "$schema": "http://json-schema.org/draft-07/schema#",
+                    "type": "object",
+                    "additionalProperties": false,
+                    "properties": {
+                        "path": { "type": "string", "description": "Path to read" },
+                        "tags": {
+                            "type": "array",
+                            "items": { "type": "object", "additionalProperties": false }
+                        }
+                    },
+                    "required": ["path"]
+                }),
+            ),
+            ToolDefinition::new(
+                "list_tools",
+                "No arguments",
+                serde_json::json!({ "type": "object", "properties": {} }),
+            ),
+        ];
+
+        let request = GenerateContentRequest::new(Vec::new()).with_tools(&tools);
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(
+            json["tools"],
+            serde_json::json!([{
+                "functionDeclarations": [
+                    {
+                        "name": "read_file",
+                        "description": "Read a file",
+                        "parameters": {
+                            "type": "object",
+                            "properties": {
+                                "path": { "type": "string", "description": "Path to read" },
+                                "tags": { "type": "array", "items": { "type": "object" } }
+                            },
+                            "required": ["path"]
+                        }
+                    },
+                    { "name": "list_tools", "description": "No arguments" }
+                ]
+            }])
+        );
+
+        let without_tools = serde_json::to_value(GenerateContentRequest::new(Vec::new())).unwrap();
+        assert!(without_tools.get("tools").is_none());
+    }
+
+    #[test]
+    fn function_call_parts_become_tool_calls() {
+        let response: GenerateContentResponse = serde_json::from_str(
+            r#"{
+                "candidates": [{
+                    "content": {
+                        "role": "model",
+                        "parts": [
+                            {"text": "Let me look."},
+                            {"functionCall": {"name": "read_file", "args": {"path": "Cargo.toml"}}}
+                        ]
+                    },
+                    "finishReason": "STOP"
+                }]
+            }"#,
+        )
+        .unwrap();
+
+        let message = response.to_model_message().unwrap();
+        assert_eq!(message.joined_text(), "Let me look.");
+        assert_eq!(message.tool_calls.len(), 1);
+        assert_eq!(message.tool_calls[0].name, "read_file");
+        assert_eq!(
+            message.tool_calls[0].arguments,
+            serde_json::json!({ "path": "Cargo.toml" })
+        );
+
+        let part = Part::function_response(None, "read_file", "not json");
+        assert_eq!(
+            serde_json::to_value(&part).unwrap(),
+            serde_json::json!({
+                "functionResponse": { "name": "read_file", "response": { "content": "not json" } }
+            })
+        );
     }
 }
@@ -1,6 +1,194 @@
-//! Additional model definitions for the Gemini API
+//! Model metadata shared across providers
 //!
-//! This module can be extended with more specific model types and utilities.
+//! Context window sizes are used to show how much of a model's context a
+//! conversation occupies; model listings are cached briefly per client and used
+//! to warn about model names a provider does not know.
 
-// Currently empty - all models are defined in the main api/mod.rs
-// This can be expanded later with model-specific functionality
+use crate::config::ModelProvider;
+use anyhow::Result;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long a fetched model list is reused before querying the provider again
+pub const MODEL_LIST_TTL: Duration = Duration::from_secs(60);
+
+/// Context window assumed for models missing from [`CONTEXT_WINDOWS`]
+pub const DEFAULT_CONTEXT_WINDOW: usize = 8_192;
+
+/// Known context windows in tokens, matched by model name prefix
+///
+/// More specific prefixes must come before shorter ones.
+const CONTEXT_WINDOWS: &[(&str, usize)] = &[
+    ("gemini-2.5", 1_048_576),
+    ("gemini-2.0", 1_048_576),
+    ("gemini-1.5-pro", 2_097_152),
+    ("gemini-1.5", 1_048_576),
+    ("claude", 200_000),
+    ("llama3.1", 131_072),
+    ("llama3.2", 131_072),
+    ("llama3", 8_192),
+    ("qwen2.5", 32_768),
+    ("qwen3", 40_960),
+    ("mistral", 32_768),
+    ("gpt-oss", 131_072),
+];
+
+/// Context window for a model, falling back to [`DEFAULT_CONTEXT_WINDOW`]
+pub fn context_window(model: &str) -> usize {
+    let model = model.to_lowercase();
+    // Ollama tags such as "llama3.1:8b" share the base model's window
+    let base = model.rsplit('/').next().unwrap_or(&model);
+
+    CONTEXT_WINDOWS
+        .iter()
+        .find(|(prefix, _)| base.starts_with(prefix))
+        .map(|(_, window)| *window)
+        .unwrap_or(DEFAULT_CONTEXT_WINDOW)
+}
+
+/// Model families served through Ollama, matched by name prefix
+const OLLAMA_FAMILIES: &[&str] = &[
+    "llama",
+    "codellama",
+    "qwen",
+    "mistral",
+    "mixtral",
+    "phi",
+    "deepseek",
+    "gpt-oss",
+    "granite",
+];
+
+/// Provider a model name most likely belongs to, if the name makes it obvious
+pub fn likely_provider(model: &str) -> Option<ModelProvider> {
+    let model = model.trim().to_lowercase();
+    let model = model.strip_prefix("models/").unwrap_or(&model);
+
+    if model.starts_with("gemini-") {
+        Some(ModelProvider::Gemini)
+    } else if model.starts_with("claude-") {
+        Some(ModelProvider::Anthropic)
+    } else if model.contains(':')
+        || OLLAMA_FAMILIES
+            .iter()
+            .any(|family| model.starts_with(family))
+    {
+        Some(ModelProvider::Ollama)
+    } else {
+        None
+    }
+}
+
+/// Hint for a model name that clearly belongs to a different provider
+pub fn provider_mismatch_hint(model: &str, provider: &ModelProvider) -> Option<String> {
+    let likely = likely_provider(model)?;
+    if &likely == provider {
+        return None;
+    }
+
+    let flag = match likely {
+        ModelProvider::Gemini => "gemini",
+        ModelProvider::Ollama => "ollama",
+        ModelProvider::Anthropic => "anthropic",
+    };
+    Some(format!(
+        "'{}' looks like a {} model, but the provider is {}. Use --provider {} to talk to {}.",
+        model,
+        likely.display_name(),
+        provider.display_name(),
+        flag,
+        likely.display_name()
+    ))
+}
+
+/// Whether a provider's model list includes `model`
+///
+/// Gemini names may carry a `models/` prefix and Ollama names a `:latest` tag.
+pub fn is_listed(model: &str, models: &[String]) -> bool {
+    let normalize = |name: &str| {
+        let name = name.strip_prefix("models/").unwrap_or(name);
+        name.strip_suffix(":latest").unwrap_or(name).to_string()
+    };
+    let wanted = normalize(model);
+    models.iter().any(|listed| normalize(listed) == wanted)
+}
+
+/// Short-lived cache of the models a provider reports
+#[derive(Debug, Default)]
+pub struct ModelListCache {
+    entry: Mutex<Option<(Instant, Vec<String>)>>,
+}
+
+impl ModelListCache {
+    /// Return the cached list while it is fresh, otherwise fetch and store a new one
+    pub async fn get_or_fetch<F, Fut>(&self, fetch: F) -> Result<Vec<String>>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Vec<String>>>,
+    {
+        if let Some((fetched_at, models)) = self.entry.lock().unwrap().as_ref() {
+            if fetched_at.elapsed() < MODEL_LIST_TTL {
+                return Ok(models.clone());
+            }
+        }
+
+        let models = fetch().await?;
+        *self.entry.lock().unwrap() = Some((Instant::now(), models.clone()));
+        Ok(models)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn context_window_matches_prefixes_and_defaults() {
+        assert_eq!(context_window("gemini-2.5-flash"), 1_048_576);
+        assert_eq!(context_window("gemini-1.5-pro-latest"), 2_097_152);
+        assert_eq!(context_window("llama3.1:8b"), 131_072);
+        assert_eq!(context_window("my-custom-model"), DEFAULT_CONTEXT_WINDOW);
+    }
+
+    #[test]
+    fn mismatched_model_names_get_a_provider_hint() {
+        let hint = provider_mismatch_hint("gemini-2.5-flash", &ModelProvider::Ollama).unwrap();
+        assert!(hint.contains("looks like a Gemini model"));
+        assert!(hint.contains("--provider gemini"));
+
+        assert!(
+            provider_mismatch_hint("llama3.1:8b", &ModelProvider::Gemini)
+                .unwrap()
+                .contains("--provider ollama")
+        );
+        assert!(provider_mismatch_hint("claude-sonnet-4-5", &ModelProvider::Gemini).is_some());
+        assert!(provider_mismatch_hint("gemini-2.5-pro", &ModelProvider::Gemini).is_none());
+        assert!(provider_mismatch_hint("my-finetune", &ModelProvider::Ollama).is_none());
+
+        let listed = vec!["llama3.1:latest".to_string(), "qwen3:8b".to_string()];
+        assert!(is_listed("llama3.1", &listed));
+        assert!(is_listed("qwen3:8b", &listed));
+        assert!(!is_listed("qwen3", &listed));
+    }
+
+    #[tokio::test]
+    async fn model_list_cache_reuses_fresh_results() {
+        let cache = ModelListCache::default();
+        let first = cache
+            .get_or_fetch(|| async { Ok(vec!["a".to_string()]) })
+            .await
+            .unwrap();
+        let second = cache
+            .get_or_fetch(|| async { panic!("cached list should be reused") })
+            .await
+            .unwrap();
+        assert_eq!(first, second);
+
+        let empty = ModelListCache::default();
+        assert!(empty
+            .get_or_fetch(|| async { Err(anyhow::anyhow!("offline")) })
+            .await
+            .is_err());
+    }
+}
@@ -1,38 +1,99 @@
-use super::{Content, ModelToolCall, Part, CONNECT_TIMEOUT, REQUEST_TIMEOUT};
+use super::http::{client_builder, HttpSettings};
+use super::models::ModelListCache;
+use super::rate_limit::RateLimiter;
+use super::{Content, GenerationConfig, ModelToolCall, Part};
 use crate::api::llm::{ChatResponse, ToolDefinition};
+use crate::error::ChatterError;
 use anyhow::{anyhow, Context, Result};
 use reqwest::Client;
 use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::{self, Value};
-use std::time::Duration;
 
 /// HTTP client for interacting with an Ollama server
 pub struct OllamaClient {
     client: Client,
     base_url: String,
+    models_cache: ModelListCache,
+    rate_limiter: Option<RateLimiter>,
+    options: Option<OllamaOptions>,
 }
 
 impl OllamaClient {
-    pub fn new(endpoint: String) -> Result<Self> {
+    pub fn new(endpoint: String, http: &HttpSettings) -> Result<Self> {
         let trimmed = endpoint.trim();
         if trimmed.is_empty() {
             return Err(anyhow!("Ollama endpoint cannot be empty"));
         }
 
-        let client = Client::builder()
-            .timeout(REQUEST_TIMEOUT)
-            .connect_timeout(CONNECT_TIMEOUT)
-            .pool_idle_timeout(Duration::from_secs(90))
-            .pool_max_idle_per_host(10)
-            .tcp_keepalive(Duration::from_secs(60))
-            .build()?;
+        let client = client_builder(http)?.build()?;
 
         Ok(Self {
             client,
             base_url: trimmed.trim_end_matches('/').to_string(),
+            models_cache: ModelListCache::default(),
+            rate_limiter: None,
+            options: None,
         })
     }
 
+    /// Limit chat requests to the given number per minute
+    pub fn with_rate_limit(mut self, requests_per_minute: u32) -> Self {
+        self.rate_limiter = Some(RateLimiter::per_minute(requests_per_minute));
+        self
+    }
+
+    /// Forward sampling parameters with every chat request
+    #[allow(dead_code)]
+    pub fn with_generation_config(mut self, config: &GenerationConfig) -> Self {
+        self.options = OllamaOptions::from_generation_config(config);
+        self
+    }
+
+    /// Attach a hint to transport errors that mean the server is not reachable
+    fn send_error(&self, error: reqwest::Error, model: Option<&str>) -> anyhow::Error {
+        if error.is_connect() {
+            tracing::debug!(error = %error, "Ollama connection failed");
+            ChatterError::ProviderUnavailable {
+                provider: "Ollama",
+                message: friendly_error_message(
+                    OllamaErrorKind::Unreachable,
+                    &self.base_url,
+                    model.unwrap_or_default(),
+                ),
+            }
+            .into()
+        } else {
+            error.into()
+        }
+    }
+
+    /// List the models installed on the Ollama server
+    pub async fn list_models(&self) -> Result<Vec<String>> {
+        self.models_cache
+            .get_or_fetch(|| async {
+                let url = format!("{}/api/tags", self.base_url);
+                tracing::debug!(url = %url, "Listing Ollama models");
+
+                let response = self
+                    .client
+                    .get(url)
+                    .send()
+                    .await
+                    .map_err(|e| self.send_error(e, None))?;
+                let status = response.status();
+                tracing::debug!(status = %status, "Ollama model list received");
+
+                if !status.is_success() {
+                    let error_text = response.text().await?;
+                    return Err(anyhow!("Listing Ollama models failed: {}", error_text));
+                }
+
+                let tags: OllamaTagsResponse = response.json().await?;
+                Ok(tags.models.into_iter().map(|model| model.name).collect())
+            })
+            .await
+    }
+
     pub async fn chat(
         &self,
         model: &str,
@@ -62,6 +123,7 @@ impl OllamaClient {
             model,
             messages,
             stream: false,
+            options: self.options.clone(),
             tools: if tools.is_empty() {
                 None
             } else {
@@ -82,6 +144,10 @@ impl OllamaClient {
         };
 
         let url = format!("{}/api/chat", self.base_url);
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.acquire().await;
+        }
+        tracing::debug!(url = %url, model, "Sending Ollama request");
 
         let response = self
             .client
@@ -89,14 +155,25 @@ impl OllamaClient {
             .header("Content-Type", "application/json")
             .json(&request)
             .send()
-            .await?;
+            .await
+            .map_err(|e| self.send_error(e, Some(model)))?;
 
         let status = response.status();
+        tracing::debug!(status = %status, "Ollama response received");
         let bytes = response.bytes().await?;
 
         if !status.is_success() {
             let error_text = String::from_utf8_lossy(&bytes);
-            return Err(anyhow!("Ollama request failed: {}", error_text));
+            let message = if status == reqwest::StatusCode::NOT_FOUND {
+                format!(
+                    "{} (server said: {})",
+                    friendly_error_message(OllamaErrorKind::ModelNotFound, &self.base_url, model),
+                    error_text.trim()
+                )
+            } else {
+                format!("Ollama request failed: {error_text}")
+            };
+            return Err(ChatterError::api(status, message).into());
         }
 
         let response: OllamaChatResponse = serde_json::from_slice(&bytes).with_context(|| {
@@ -122,22 +199,31 @@ impl OllamaClient {
             });
         }
 
+        // Reasoning arrives either in the `thinking` field or inline as <think> tags
+        let mut thinking: Vec<String> = message.thinking.into_iter().collect();
         let mut parts = Vec::new();
         if let Some(text) = message.content {
-            if !text.is_empty() {
-                parts.push(Part { text });
+            let (inline_thinking, answer) = split_thinking(&text);
+            thinking.extend(inline_thinking);
+            if !answer.is_empty() {
+                parts.push(Part::text(answer));
             }
         }
+        thinking.retain(|text| !text.trim().is_empty());
+        let thinking = if thinking.is_empty() {
+            None
+        } else {
+            Some(thinking.join("\n\n"))
+        };
 
         let mut content = if parts.is_empty() {
             Content {
                 role: "model".to_string(),
-                parts: vec![Part {
-                    text: String::new(),
-                }],
+                parts: vec![Part::text(String::new())],
                 name: None,
                 tool_call_id: None,
                 tool_calls: Vec::new(),
+                provider: None,
             }
         } else {
             Content {
@@ -146,15 +232,68 @@ impl OllamaClient {
                 name: None,
                 tool_call_id: None,
                 tool_calls: Vec::new(),
+                provider: None,
             }
         };
 
         content.tool_calls = tool_calls;
 
-        Ok(ChatResponse { message: content })
+        Ok(ChatResponse {
+            message: content,
+            thinking,
+        })
     }
 }
 
+const THINK_OPEN: &str = "<think>";
+const THINK_CLOSE: &str = "</think>";
+
+/// Separate `<think>...</think>` reasoning from the answer
+///
+/// Some chat templates open the block themselves, so a closing tag with no
+/// opening tag marks everything before it as reasoning. An unclosed block runs
+/// to the end of the text.
+fn split_thinking(text: &str) -> (Option<String>, String) {
+    if !text.contains(THINK_OPEN) && !text.contains(THINK_CLOSE) {
+        return (None, text.to_string());
+    }
+
+    let mut thinking = Vec::new();
+    let mut answer = String::new();
+    let mut rest = text;
+
+    if let Some(close) = rest.find(THINK_CLOSE) {
+        if !rest[..close].contains(THINK_OPEN) {
+            thinking.push(&rest[..close]);
+            rest = &rest[close + THINK_CLOSE.len()..];
+        }
+    }
+
+    while let Some(open) = rest.find(THINK_OPEN) {
+        answer.push_str(&rest[..open]);
+        let inside = &rest[open + THINK_OPEN.len()..];
+        match inside.find(THINK_CLOSE) {
+            Some(close) => {
+                thinking.push(&inside[..close]);
+                rest = &inside[close + THINK_CLOSE.len()..];
+            }
+            None => {
+                thinking.push(inside);
+                rest = "";
+            }
+        }
+    }
+    answer.push_str(rest);
+
+    let thinking: Vec<&str> = thinking
+        .into_iter()
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+        .collect();
+    let thinking = (!thinking.is_empty()).then(|| thinking.join("\n\n"));
+    (thinking, answer.trim().to_string())
+}
+
 fn convert_content_to_ollama_message(content: &Content) -> OllamaMessage {
     let role = match content.role.as_str() {
         "user" => "user",
@@ -167,11 +306,7 @@ fn convert_content_to_ollama_message(content: &Content) -> OllamaMessage {
 
     let mut message = OllamaMessage {
         role,
-        content: content
-            .parts
-            .first()
-            .map(|p| p.text.clone())
-            .unwrap_or_default(),
+        content: content.joined_text(),
         name: content.name.clone(),
         tool_call_id: content.tool_call_id.clone(),
         tool_calls: None,
@@ -218,9 +353,37 @@ struct OllamaChatRequest<'a> {
     messages: Vec<OllamaMessage>,
     stream: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
+    options: Option<OllamaOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     tools: Option<Vec<OllamaTool>>,
 }
 
+/// Sampling options using Ollama's parameter names
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+struct OllamaOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_k: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_predict: Option<i32>,
+}
+
+impl OllamaOptions {
+    /// Map a generation config, returning `None` when nothing is set
+    fn from_generation_config(config: &GenerationConfig) -> Option<Self> {
+        let options = Self {
+            temperature: config.temperature,
+            top_p: config.top_p,
+            top_k: config.top_k,
+            num_predict: config.max_output_tokens,
+        };
+        (options != Self::default()).then_some(options)
+    }
+}
+
 #[derive(Debug, Serialize)]
 struct OllamaMessage {
     role: String,
@@ -262,6 +425,18 @@ struct OllamaToolFunction {
     parameters: Value,
 }
 
+/// Response from `GET /api/tags`
+#[derive(Debug, Deserialize)]
+struct OllamaTagsResponse {
+    #[serde(default)]
+    models: Vec<OllamaModelTag>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaModelTag {
+    name: String,
+}
+
 #[derive(Debug, Deserialize)]
 struct OllamaChatResponse {
     message: OllamaResponseMessage,
@@ -274,6 +449,8 @@ struct OllamaResponseMessage {
     #[serde(default)]
     content: Option<String>,
     #[serde(default)]
+    thinking: Option<String>,
+    #[serde(default)]
     tool_calls: Option<Vec<OllamaResponseToolCall>>,
 }
 
@@ -313,10 +490,61 @@ where
     }
 }
 
+/// Common Ollama failures that get a friendlier message than the raw error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OllamaErrorKind {
+    /// Connection refused or the host could not be resolved
+    Unreachable,
+    /// `/api/chat` returned 404, usually a misspelled or unpulled model
+    ModelNotFound,
+}
+
+fn friendly_error_message(kind: OllamaErrorKind, base_url: &str, model: &str) -> String {
+    match kind {
+        OllamaErrorKind::Unreachable => {
+            format!("Could not connect to Ollama at {base_url} — is `ollama serve` running?")
+        }
+        OllamaErrorKind::ModelNotFound => format!(
+            "Model '{model}' was not found on the Ollama server at {base_url} — check the name or run `ollama pull {model}`"
+        ),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn options_include_only_the_set_sampling_fields() {
+        let config = GenerationConfig {
+            temperature: Some(0.5),
+            top_p: None,
+            top_k: Some(40),
+            max_output_tokens: Some(256),
+        };
+        let request = OllamaChatRequest {
+            model: "qwen3",
+            messages: Vec::new(),
+            stream: false,
+            options: OllamaOptions::from_generation_config(&config),
+            tools: None,
+        };
+
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(
+            json["options"],
+            serde_json::json!({ "temperature": 0.5, "top_k": 40, "num_predict": 256 })
+        );
+
+        let unset = GenerationConfig {
+            temperature: None,
+            top_p: None,
+            top_k: None,
+            max_output_tokens: None,
+        };
+        assert!(OllamaOptions::from_generation_config(&unset).is_none());
+    }
+
     #[test]
     fn parses_tool_call_without_type_field() {
         let payload = r#"{
@@ -344,4 +572,62 @@ mod tests {
         assert_eq!(calls[0].function.name, "read_file");
         assert_eq!(calls[0].function.arguments["path"], "Cargo.toml");
     }
+
+    #[test]
+    fn think_tags_are_split_from_the_answer() {
+        let (thinking, answer) = split_thinking(
+            "<think>\nThe user wants a greeting.\n</think>\n\nHello! How can I help?",
+        );
+        assert_eq!(thinking.as_deref(), Some("The user wants a greeting."));
+        assert_eq!(answer, "Hello! How can I help?");
+
+        // Template-opened block, and an unclosed block at the end
+        let (thinking, answer) = split_thinking("Plan first.</think>Done. <think>more");
+        assert_eq!(thinking.as_deref(), Some("Plan first.\n\nmore"));
+        assert_eq!(answer, "Done.");
+
+        let (thinking, answer) = split_thinking("No reasoning here ");
+        assert_eq!(thinking, None);
+        assert_eq!(answer, "No reasoning here ");
+    }
+
+    #[test]
+    fn common_failures_get_actionable_messages() {
+        let unreachable = friendly_error_message(
+            OllamaErrorKind::Unreachable,
+            "http://localhost:11434",
+            "qwen3",
+        );
+        assert_eq!(
+            unreachable,
+            "Could not connect to Ollama at http://localhost:11434 — is `ollama serve` running?"
+        );
+
+        let not_found = friendly_error_message(
+            OllamaErrorKind::ModelNotFound,
+            "http://localhost:11434",
+            "qwen9",
+        );
+        assert!(not_found.contains("Model 'qwen9' was not found"));
+        assert!(not_found.contains("`ollama pull qwen9`"));
+    }
+
+    #[tokio::test]
+    async fn refused_connections_mention_ollama_serve() {
+        // Bind and release a port so nothing is listening on it
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let client =
+            OllamaClient::new(format!("http://127.0.0.1:{port}"), &HttpSettings::default())
+                .unwrap();
+
+        let error = client
+            .chat("qwen3", &[Content::user("hi".to_string())], None, &[])
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("is `ollama serve` running?"));
+    }
 }
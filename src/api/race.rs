@@ -0,0 +1,138 @@
+//! Racing several providers
+//!
+//! Sends the same conversation to every entrant at once and keeps the first
+//! successful answer. Dropping the remaining requests cancels them.
+
+use super::llm::ChatResponse;
+use super::{Content, LlmClient};
+use crate::config::ModelProvider;
+use anyhow::{anyhow, Result};
+use futures_util::stream::{FuturesUnordered, StreamExt};
+use std::time::{Duration, Instant};
+
+/// A provider taking part in a race
+pub struct RaceEntrant {
+    pub provider: ModelProvider,
+    pub model: String,
+    pub client: LlmClient,
+}
+
+/// The first successful response and who produced it
+pub struct RaceWinner {
+    pub provider: ModelProvider,
+    pub model: String,
+    pub response: ChatResponse,
+    pub elapsed: Duration,
+}
+
+/// Run every entrant concurrently and return the first successful response
+///
+/// Fails only when every entrant fails, listing each provider's error.
+pub async fn race(
+    entrants: &[RaceEntrant],
+    conversation: &[Content],
+    system_instruction: Option<&str>,
+) -> Result<RaceWinner> {
+    if entrants.is_empty() {
+        return Err(anyhow!("A race needs at least one provider"));
+    }
+
+    let started = Instant::now();
+    let mut requests: FuturesUnordered<_> = entrants
+        .iter()
+        .map(|entrant| async move {
+            let result = entrant
+                .client
+                .generate(&entrant.model, conversation, system_instruction, &[])
+                .await;
+            (entrant, result)
+        })
+        .collect();
+
+    let mut failures = Vec::new();
+    while let Some((entrant, result)) = requests.next().await {
+        match result {
+            Ok(response) => {
+                return Ok(RaceWinner {
+                    provider: entrant.provider.clone(),
+                    model: entrant.model.clone(),
+                    response,
+                    elapsed: started.elapsed(),
+                });
+            }
+            Err(e) => {
+                tracing::debug!(provider = ?entrant.provider, error = %e, "Race entrant failed");
+                failures.push(format!(
+                    "  {} ({}): {}",
+                    entrant.provider.display_name(),
+                    entrant.model,
+                    e
+                ));
+            }
+        }
+    }
+
+    Err(anyhow!("Every provider failed:\n{}", failures.join("\n")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::mock::MockClient;
+
+    fn entrant(provider: ModelProvider, client: MockClient) -> RaceEntrant {
+        RaceEntrant {
+            model: format!("{}-model", provider.display_name()),
+            provider,
+            client: LlmClient::Mock(client),
+        }
+    }
+
+    #[tokio::test]
+    async fn fastest_successful_provider_wins() {
+        let slow = MockClient::with_replies(["slow answer"]).with_delay(Duration::from_secs(5));
+        let fast = MockClient::with_replies(["fast answer"]).with_delay(Duration::from_millis(10));
+        let entrants = vec![
+            entrant(ModelProvider::Gemini, slow),
+            entrant(ModelProvider::Ollama, fast),
+        ];
+
+        let conversation = [Content::user("hi".to_string())];
+        let winner = race(&entrants, &conversation, None).await.unwrap();
+        assert_eq!(winner.provider, ModelProvider::Ollama);
+        assert_eq!(winner.response.message.joined_text(), "fast answer");
+        assert!(winner.elapsed < Duration::from_secs(5));
+
+        // A fast failure does not end the race
+        let failing = MockClient::default();
+        failing.push_error(anyhow!("quota exceeded"));
+        let steady = MockClient::with_replies(["steady"]).with_delay(Duration::from_millis(20));
+        let entrants = vec![
+            entrant(ModelProvider::Gemini, failing),
+            entrant(ModelProvider::Ollama, steady),
+        ];
+        let winner = race(&entrants, &conversation, None).await.unwrap();
+        assert_eq!(winner.provider, ModelProvider::Ollama);
+    }
+
+    #[tokio::test]
+    async fn all_failures_are_reported_together() {
+        let gemini = MockClient::default();
+        gemini.push_error(anyhow!("quota exceeded"));
+        let ollama = MockClient::default();
+        ollama.push_error(anyhow!("connection refused"));
+        let entrants = vec![
+            entrant(ModelProvider::Gemini, gemini),
+            entrant(ModelProvider::Ollama, ollama),
+        ];
+
+        let error = race(&entrants, &[Content::user("hi".to_string())], None)
+            .await
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(error.starts_with("Every provider failed:"));
+        assert!(error.contains("Gemini (Gemini-model): quota exceeded"));
+        assert!(error.contains("Ollama (Ollama-model): connection refused"));
+    }
+}
@@ -0,0 +1,136 @@
+//! Client-side request rate limiting
+//!
+//! A token bucket that spaces out requests so providers' per-minute quotas are
+//! respected before the server has to answer with 429s.
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Source of the current time, injectable for tests
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+}
+
+/// Clock backed by [`Instant::now`]
+#[derive(Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Token bucket allowing a burst of `requests_per_minute`, refilled continuously
+pub struct RateLimiter {
+    capacity: f64,
+    tokens_per_second: f64,
+    bucket: Mutex<Bucket>,
+    clock: Arc<dyn Clock>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    /// Available tokens; negative when callers are queued for future tokens
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl fmt::Debug for RateLimiter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RateLimiter")
+            .field("capacity", &self.capacity)
+            .field("tokens_per_second", &self.tokens_per_second)
+            .finish()
+    }
+}
+
+impl RateLimiter {
+    /// Create a limiter using the system clock
+    pub fn per_minute(requests_per_minute: u32) -> Self {
+        Self::with_clock(requests_per_minute, Arc::new(SystemClock))
+    }
+
+    /// Create a limiter reading time from the given clock
+    pub fn with_clock(requests_per_minute: u32, clock: Arc<dyn Clock>) -> Self {
+        let capacity = f64::from(requests_per_minute.max(1));
+        Self {
+            capacity,
+            tokens_per_second: capacity / 60.0,
+            bucket: Mutex::new(Bucket {
+                tokens: capacity,
+                last_refill: clock.now(),
+            }),
+            clock,
+        }
+    }
+
+    /// Reserve a request slot and return how long the caller must wait before sending
+    pub fn reserve(&self) -> Duration {
+        let now = self.clock.now();
+        let mut bucket = self.bucket.lock().unwrap();
+
+        let elapsed = now.saturating_duration_since(bucket.last_refill);
+        bucket.last_refill = bucket.last_refill.max(now);
+        bucket.tokens =
+            (bucket.tokens + elapsed.as_secs_f64() * self.tokens_per_second).min(self.capacity);
+        bucket.tokens -= 1.0;
+
+        if bucket.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.tokens * 60.0 / self.capacity)
+        }
+    }
+
+    /// Wait until a request may be sent
+    pub async fn acquire(&self) {
+        let delay = self.reserve();
+        if !delay.is_zero() {
+            tracing::debug!(
+                delay_ms = delay.as_millis() as u64,
+                "Rate limit reached, waiting"
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Clock that only moves when told to
+    struct ManualClock(Mutex<Instant>);
+
+    impl ManualClock {
+        fn advance(&self, by: Duration) {
+            *self.0.lock().unwrap() += by;
+        }
+    }
+
+    impl Clock for ManualClock {
+        fn now(&self) -> Instant {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    #[test]
+    fn requests_beyond_the_burst_are_spaced_evenly() {
+        let clock = Arc::new(ManualClock(Mutex::new(Instant::now())));
+        let limiter = RateLimiter::with_clock(6, clock.clone());
+
+        // A full minute's quota goes out immediately, then one request every 10s
+        let delays: Vec<Duration> = (0..9).map(|_| limiter.reserve()).collect();
+        assert!(delays[..6].iter().all(|d| d.is_zero()));
+        assert_eq!(delays[6], Duration::from_secs(10));
+        assert_eq!(delays[7], Duration::from_secs(20));
+        assert_eq!(delays[8], Duration::from_secs(30));
+
+        // Once the queued requests have gone out, tokens refill at the same pace
+        clock.advance(Duration::from_secs(40));
+        assert_eq!(limiter.reserve(), Duration::ZERO);
+        assert_eq!(limiter.reserve(), Duration::from_secs(10));
+    }
+}
@@ -2,6 +2,8 @@
 //!
 //! Provides commands to control and interact with the agent mode.
 
+use super::theme::{icon, Marker};
+use crate::agent::results::{FileCount, FileMatches, ReadFileResult, SearchMatches, ToolOutput};
 use crate::agent::{Agent, AgentConfig, CompletionStatus};
 use anyhow::Result;
 use colored::*;
@@ -12,25 +14,33 @@ pub async fn handle_agent_command(
     command: &str,
     args: &str,
     agent: &mut Option<Agent>,
+    base_config: &AgentConfig,
 ) -> Result<()> {
     match command {
         "/agent" => {
             match args.trim() {
                 "on" | "enable" => {
                     if agent.is_none() {
-                        let config = AgentConfig::default();
-                        let mut new_agent = Agent::new(config)?;
+                        let mut new_agent = Agent::new(base_config.clone())?;
                         new_agent.set_enabled(true);
                         *agent = Some(new_agent);
-                        println!("🤖 {} Agent mode enabled! I can now use tools to help with file operations.", "AGENT:".bright_green().bold());
+                        println!("{}{} Agent mode enabled! I can now use tools to help with file operations.", icon(Marker::Agent), "AGENT:".bright_green().bold());
                         println!(
                             "   Available tools: {}",
                             agent.as_ref().unwrap().available_tools().join(", ")
                         );
+                        if base_config.dry_run_mode {
+                            println!(
+                                "{}{} Dry-run mode is on. Tool calls are previewed, not performed.",
+                                icon(Marker::DryRun),
+                                "AGENT:".bright_yellow().bold()
+                            );
+                        }
                     } else if let Some(ref mut agent) = agent {
                         agent.set_enabled(true);
                         println!(
-                            "🤖 {} Agent mode re-enabled!",
+                            "{}{} Agent mode re-enabled!",
+                            icon(Marker::Agent),
                             "AGENT:".bright_green().bold()
                         );
                     }
@@ -39,17 +49,22 @@ pub async fn handle_agent_command(
                     if let Some(ref mut agent) = agent {
                         agent.set_enabled(false);
                         println!(
-                            "🤖 {} Agent mode disabled.",
+                            "{}{} Agent mode disabled.",
+                            icon(Marker::Agent),
                             "AGENT:".bright_yellow().bold()
                         );
                     } else {
-                        println!("❌ Agent mode is not initialized.");
+                        println!("{}Agent mode is not initialized.", icon(Marker::Error));
                     }
                 }
                 "status" => {
                     if let Some(ref agent) = agent {
                         let status = agent.status();
-                        println!("🤖 {} Agent Status:", "AGENT:".bright_cyan().bold());
+                        println!(
+                            "{}{} Agent Status:",
+                            icon(Marker::Agent),
+                            "AGENT:".bright_cyan().bold()
+                        );
                         println!(
                             "   Enabled: {}",
                             if status.enabled {
@@ -73,7 +88,7 @@ pub async fn handle_agent_command(
                         );
                         println!("   Available tools: {}", status.available_tools.join(", "));
                     } else {
-                        println!("❌ Agent mode is not initialized.");
+                        println!("{}Agent mode is not initialized.", icon(Marker::Error));
                     }
                 }
                 args if args.starts_with("dry-run") => {
@@ -87,18 +102,25 @@ pub async fn handle_agent_command(
                                 "on" => {
                                     cfg.dry_run_mode = true;
                                     if let Err(e) = agent.update_config(cfg) {
-                                        println!("❌ Failed to enable dry-run: {e}");
+                                        println!(
+                                            "{}Failed to enable dry-run: {e}",
+                                            icon(Marker::Error)
+                                        );
                                     } else {
-                                        println!("🧪 {} Dry-run mode enabled. No changes will be written.", "AGENT:".bright_yellow().bold());
+                                        println!("{}{} Dry-run mode enabled. No changes will be written.", icon(Marker::DryRun), "AGENT:".bright_yellow().bold());
                                     }
                                 }
                                 "off" => {
                                     cfg.dry_run_mode = false;
                                     if let Err(e) = agent.update_config(cfg) {
-                                        println!("❌ Failed to disable dry-run: {e}");
+                                        println!(
+                                            "{}Failed to disable dry-run: {e}",
+                                            icon(Marker::Error)
+                                        );
                                     } else {
                                         println!(
-                                            "✅ {} Dry-run mode disabled.",
+                                            "{}{} Dry-run mode disabled.",
+                                            icon(Marker::Success),
                                             "AGENT:".bright_green().bold()
                                         );
                                     }
@@ -107,17 +129,54 @@ pub async fn handle_agent_command(
                             }
                         }
                     } else {
-                        println!("❌ Agent mode is not initialized.");
+                        println!("{}Agent mode is not initialized.", icon(Marker::Error));
+                    }
+                }
+                args if args.starts_with("confirm") => {
+                    if let Some(ref mut agent) = agent {
+                        let enabled = match args["confirm".len()..].trim() {
+                            "on" => Some(true),
+                            "off" => Some(false),
+                            _ => None,
+                        };
+                        match enabled {
+                            None => println!("Usage: /agent confirm <on|off>"),
+                            Some(enabled) => {
+                                let mut cfg = agent.config().clone();
+                                cfg.confirm_writes = enabled;
+                                if let Err(e) = agent.update_config(cfg) {
+                                    println!(
+                                        "{}Failed to update confirmation: {e}",
+                                        icon(Marker::Error)
+                                    );
+                                } else if enabled {
+                                    println!(
+                                        "{}{} File writes now need your confirmation.",
+                                        icon(Marker::Allowed),
+                                        "AGENT:".bright_yellow().bold()
+                                    );
+                                } else {
+                                    println!(
+                                        "{}{} File writes no longer ask for confirmation.",
+                                        icon(Marker::Success),
+                                        "AGENT:".bright_green().bold()
+                                    );
+                                }
+                            }
+                        }
+                    } else {
+                        println!("{}Agent mode is not initialized.", icon(Marker::Error));
                     }
                 }
                 "history" => {
                     if let Some(ref agent) = agent {
                         let history = agent.tool_history();
                         if history.is_empty() {
-                            println!("📭 No tool execution history.");
+                            println!("{}No tool execution history.", icon(Marker::Empty));
                         } else {
                             println!(
-                                "🤖 {} Tool Execution History:",
+                                "{}{} Tool Execution History:",
+                                icon(Marker::Agent),
                                 "AGENT:".bright_cyan().bold()
                             );
                             for (i, tool_call) in history.iter().enumerate() {
@@ -128,40 +187,72 @@ pub async fn handle_agent_command(
                                     format!("({})", tool_call.parameters.len()).bright_black()
                                 );
                                 if let Some(ref thought) = tool_call.thought {
-                                    println!("      💭 {}", thought.bright_white());
+                                    println!(
+                                        "      {}{}",
+                                        icon(Marker::Thinking),
+                                        thought.bright_white()
+                                    );
                                 }
                             }
                         }
                     } else {
-                        println!("❌ Agent mode is not initialized.");
+                        println!("{}Agent mode is not initialized.", icon(Marker::Error));
+                    }
+                }
+                "stats" => {
+                    if let Some(ref agent) = agent {
+                        let stats = agent.tool_stats();
+                        if stats.total_calls() == 0 {
+                            println!("{}No tool execution history.", icon(Marker::Empty));
+                        } else {
+                            println!(
+                                "{}{} Tool Usage:",
+                                icon(Marker::Agent),
+                                "AGENT:".bright_cyan().bold()
+                            );
+                            for line in stats.table().lines() {
+                                println!("   {line}");
+                            }
+                        }
+                    } else {
+                        println!("{}Agent mode is not initialized.", icon(Marker::Error));
                     }
                 }
                 "clear" => {
                     if let Some(ref mut agent) = agent {
                         agent.clear_history();
                         println!(
-                            "🤖 {} Tool execution history cleared.",
+                            "{}{} Tool execution history cleared.",
+                            icon(Marker::Agent),
                             "AGENT:".bright_green().bold()
                         );
                     } else {
-                        println!("❌ Agent mode is not initialized.");
+                        println!("{}Agent mode is not initialized.", icon(Marker::Error));
                     }
                 }
                 "tools" => {
                     if let Some(ref agent) = agent {
                         let catalog = agent.tool_catalog();
-                        println!("🤖 {} Available Tools:", "AGENT:".bright_cyan().bold());
+                        println!(
+                            "{}{} Available Tools:",
+                            icon(Marker::Agent),
+                            "AGENT:".bright_cyan().bold()
+                        );
                         for entry in catalog {
                             println!("\n{}", entry);
                         }
                     } else {
-                        println!("❌ Agent mode is not initialized.");
+                        println!("{}Agent mode is not initialized.", icon(Marker::Error));
                     }
                 }
                 "config" => {
                     if let Some(ref agent) = agent {
                         let config = agent.config();
-                        println!("🤖 {} Agent Configuration:", "AGENT:".bright_cyan().bold());
+                        println!(
+                            "{}{} Agent Configuration:",
+                            icon(Marker::Agent),
+                            "AGENT:".bright_cyan().bold()
+                        );
                         println!(
                             "   Enabled: {}",
                             if config.enabled {
@@ -191,16 +282,27 @@ pub async fn handle_agent_command(
                                 "No".bright_green()
                             }
                         );
+                        println!(
+                            "   Confirm writes: {}",
+                            if config.confirm_writes {
+                                "Yes".bright_yellow()
+                            } else {
+                                "No".bright_green()
+                            }
+                        );
                         println!(
                             "   Allowed extensions: {}",
                             config.allowed_extensions.join(", ")
                         );
+                        if !config.disabled_tools.is_empty() {
+                            println!("   Disabled tools: {}", config.disabled_tools.join(", "));
+                        }
 
                         let allowed_paths = agent.allowed_paths();
                         if !allowed_paths.is_empty() {
                             println!("   Allowed paths:");
                             for path in allowed_paths {
-                                println!("      • {}", path.display());
+                                println!("      {}{}", icon(Marker::Bullet), path.display());
                             }
                         }
 
@@ -208,11 +310,11 @@ pub async fn handle_agent_command(
                         if !forbidden_paths.is_empty() {
                             println!("   Forbidden paths:");
                             for path in forbidden_paths {
-                                println!("      • {}", path.display());
+                                println!("      {}{}", icon(Marker::Bullet), path.display());
                             }
                         }
                     } else {
-                        println!("❌ Agent mode is not initialized.");
+                        println!("{}Agent mode is not initialized.", icon(Marker::Error));
                     }
                 }
                 args if args.starts_with("allow-path") => {
@@ -222,10 +324,14 @@ pub async fn handle_agent_command(
                             println!("Usage: /agent allow-path <path>");
                         } else {
                             agent.add_allowed_path(PathBuf::from(path));
-                            println!("🛡️  Added allowed path: {}", path.bright_green());
+                            println!(
+                                "{}Added allowed path: {}",
+                                icon(Marker::Allowed),
+                                path.bright_green()
+                            );
                         }
                     } else {
-                        println!("❌ Agent mode is not initialized.");
+                        println!("{}Agent mode is not initialized.", icon(Marker::Error));
                     }
                 }
                 args if args.starts_with("forbid-path") => {
@@ -235,10 +341,14 @@ pub async fn handle_agent_command(
                             println!("Usage: /agent forbid-path <path>");
                         } else {
                             agent.add_forbidden_path(PathBuf::from(path));
-                            println!("🚫 Added forbidden path: {}", path.bright_red());
+                            println!(
+                                "{}Added forbidden path: {}",
+                                icon(Marker::Blocked),
+                                path.bright_red()
+                            );
                         }
                     } else {
-                        println!("❌ Agent mode is not initialized.");
+                        println!("{}Agent mode is not initialized.", icon(Marker::Error));
                     }
                 }
                 args if args.starts_with("check-path") => {
@@ -250,30 +360,71 @@ pub async fn handle_agent_command(
                             let allowed = agent.is_path_allowed(path);
                             if allowed {
                                 println!(
-                                    "✅ Path '{}' is permitted by the safety manager.",
+                                    "{}Path '{}' is permitted by the safety manager.",
+                                    icon(Marker::Success),
                                     path.bright_green()
                                 );
                             } else {
                                 println!(
-                                    "⚠️  Path '{}' would be blocked by safety rules.",
+                                    "{}Path '{}' would be blocked by safety rules.",
+                                    icon(Marker::Warning),
                                     path.bright_red()
                                 );
                             }
                         }
                     } else {
-                        println!("❌ Agent mode is not initialized.");
+                        println!("{}Agent mode is not initialized.", icon(Marker::Error));
+                    }
+                }
+                args if args.starts_with("disable-tool") => {
+                    if let Some(ref mut agent) = agent {
+                        let name = args["disable-tool".len()..].trim();
+                        if name.is_empty() {
+                            println!("Usage: /agent disable-tool <name>");
+                        } else if let Err(e) = agent.disable_tool(name) {
+                            println!("{}Failed to disable tool: {e}", icon(Marker::Error));
+                        } else {
+                            println!(
+                                "{}Disabled tool: {}",
+                                icon(Marker::Blocked),
+                                name.bright_red()
+                            );
+                        }
+                    } else {
+                        println!("{}Agent mode is not initialized.", icon(Marker::Error));
+                    }
+                }
+                args if args.starts_with("enable-tool") => {
+                    if let Some(ref mut agent) = agent {
+                        let name = args["enable-tool".len()..].trim();
+                        if name.is_empty() {
+                            println!("Usage: /agent enable-tool <name>");
+                        } else if let Err(e) = agent.enable_tool(name) {
+                            println!("{}Failed to enable tool: {e}", icon(Marker::Error));
+                        } else {
+                            println!(
+                                "{}Enabled tool: {}",
+                                icon(Marker::Success),
+                                name.bright_green()
+                            );
+                        }
+                    } else {
+                        println!("{}Agent mode is not initialized.", icon(Marker::Error));
                     }
                 }
                 "help" => {
                     display_agent_help();
                 }
                 _ => {
-                    println!("❌ Unknown agent command. Use '/agent help' for available commands.");
+                    println!(
+                        "{}Unknown agent command. Use '/agent help' for available commands.",
+                        icon(Marker::Error)
+                    );
                 }
             }
         }
         _ => {
-            println!("❌ Unknown agent command: {command}");
+            println!("{}Unknown agent command: {command}", icon(Marker::Error));
         }
     }
 
@@ -282,7 +433,11 @@ pub async fn handle_agent_command(
 
 /// Display help for agent commands
 fn display_agent_help() {
-    println!("🤖 {} Agent Commands:", "AGENT:".bright_cyan().bold());
+    println!(
+        "{}{} Agent Commands:",
+        icon(Marker::Agent),
+        "AGENT:".bright_cyan().bold()
+    );
     println!("   {} - Enable agent mode", "/agent on".bright_green());
     println!("   {} - Disable agent mode", "/agent off".bright_yellow());
     println!("   {} - Show agent status", "/agent status".bright_blue());
@@ -290,6 +445,10 @@ fn display_agent_help() {
         "   {} - Show tool execution history",
         "/agent history".bright_blue()
     );
+    println!(
+        "   {} - Summarize tool usage and bytes read/written",
+        "/agent stats".bright_blue()
+    );
     println!(
         "   {} - Clear tool execution history",
         "/agent clear".bright_red()
@@ -306,6 +465,10 @@ fn display_agent_help() {
         "   {} - Toggle dry-run mode (no writes)",
         "/agent dry-run <on|off>".bright_blue()
     );
+    println!(
+        "   {} - Ask before each file write or update",
+        "/agent confirm <on|off>".bright_blue()
+    );
     println!(
         "   {} - Allow an extra path for tool access",
         "/agent allow-path <path>".bright_blue()
@@ -318,16 +481,34 @@ fn display_agent_help() {
         "   {} - Check whether a path is allowed",
         "/agent check-path <path>".bright_blue()
     );
+    println!(
+        "   {} - Stop offering a tool to the model",
+        "/agent disable-tool <name>".bright_blue()
+    );
+    println!(
+        "   {} - Re-enable a disabled tool",
+        "/agent enable-tool <name>".bright_blue()
+    );
     println!("   {} - Show this help", "/agent help".bright_white());
     println!();
     println!(
-        "💡 {} When agent mode is enabled, I can automatically detect and execute",
+        "{}{} When agent mode is enabled, I can automatically detect and execute",
+        icon(Marker::Hint),
         "TIP:".bright_yellow().bold()
     );
     println!("   tool requests in your messages. For example:");
-    println!("   • \"Please read the file config.json\"");
-    println!("   • \"Search for 'TODO' in all Rust files\"");
-    println!("   • \"List all files in the src directory\"");
+    println!(
+        "   {}\"Please read the file config.json\"",
+        icon(Marker::Bullet)
+    );
+    println!(
+        "   {}\"Search for 'TODO' in all Rust files\"",
+        icon(Marker::Bullet)
+    );
+    println!(
+        "   {}\"List all files in the src directory\"",
+        icon(Marker::Bullet)
+    );
 }
 
 /// Check if a message contains agent tool requests and execute them
@@ -351,20 +532,21 @@ pub async fn process_agent_tools(
 
         for tool_call in tool_calls {
             println!(
-                "🔧 {} Executing tool: {}",
+                "{}{} Executing tool: {}",
+                icon(Marker::Tool),
                 "AGENT:".bright_green().bold(),
                 tool_call.tool.bright_yellow()
             );
 
             if let Some(ref thought) = tool_call.thought {
-                println!("   💭 {}", thought.bright_white());
+                println!("   {}{}", icon(Marker::Thinking), thought.bright_white());
             }
 
             match agent.execute_tool(tool_call.clone()).await {
                 Ok(result) => {
                     if result.success {
                         if let Some(ref message) = result.message {
-                            println!("   ✅ {}", message.bright_green());
+                            println!("   {}{}", icon(Marker::Success), message.bright_green());
                         }
 
                         // Format the result for display
@@ -374,13 +556,14 @@ pub async fn process_agent_tools(
                         let error_msg = result
                             .message
                             .unwrap_or_else(|| "Unknown error".to_string());
-                        println!("   ❌ {}", error_msg.bright_red());
+                        println!("   {}{}", icon(Marker::Error), error_msg.bright_red());
                         results.push(format!("Tool {} failed: {}", tool_call.tool, error_msg));
                     }
                 }
                 Err(e) => {
                     println!(
-                        "   ❌ {}",
+                        "   {}{}",
+                        icon(Marker::Error),
                         format!("Tool execution error: {e}").bright_red()
                     );
                     results.push(format!("Tool {} error: {}", tool_call.tool, e));
@@ -397,161 +580,233 @@ pub async fn process_agent_tools(
     Ok(None)
 }
 
+/// Tool result as shown in the terminal, with files syntax highlighted
+///
+/// Falls back to `format_tool_result` when stdout is not a terminal or colors are off.
+pub(crate) fn display_tool_result(tool_name: &str, result: &crate::agent::ToolResult) -> String {
+    use std::io::IsTerminal;
+
+    let colorize =
+        std::io::stdout().is_terminal() && colored::control::SHOULD_COLORIZE.should_colorize();
+    display_tool_result_with(tool_name, result, colorize)
+}
+
+/// `display_tool_result` with the terminal check already made
+pub(crate) fn display_tool_result_with(
+    tool_name: &str,
+    result: &crate::agent::ToolResult,
+    colorize: bool,
+) -> String {
+    if colorize {
+        if let Some(ToolOutput::ReadFile(read)) = ToolOutput::parse(tool_name, &result.data) {
+            if let Some(content) = super::highlight::highlight(&read.path, &read.content) {
+                return format_read_file(&read, &content);
+            }
+        }
+    }
+    format_tool_result(tool_name, result)
+}
+
+/// Header and fenced block for a `read_file` result showing `content`
+fn format_read_file(read: &ReadFileResult, content: &str) -> String {
+    let size = read.size.or(read.bytes_read).unwrap_or(0);
+    format!(
+        "{}**File: {}** ({size} bytes)\n```\n{}\n```",
+        icon(Marker::File),
+        read.path,
+        content
+    )
+}
+
 /// Format tool execution results for display
 pub(crate) fn format_tool_result(tool_name: &str, result: &crate::agent::ToolResult) -> String {
-    match tool_name {
-        "read_file" => {
-            if let Some(content) = result.data.get("content").and_then(|c| c.as_str()) {
-                let path = result
-                    .data
-                    .get("path")
-                    .and_then(|p| p.as_str())
-                    .unwrap_or("unknown");
-                let size = result
-                    .data
-                    .get("size")
-                    .and_then(|s| s.as_u64())
-                    .unwrap_or(0);
-
-                format!("📄 **File: {path}** ({size} bytes)\n```\n{content}\n```")
-            } else {
-                "File read completed".to_string()
+    let Some(output) = ToolOutput::parse(tool_name, &result.data) else {
+        return result
+            .message
+            .clone()
+            .unwrap_or_else(|| "Tool executed successfully".to_string());
+    };
+
+    match output {
+        ToolOutput::ReadFile(read) => format_read_file(&read, &read.content),
+        ToolOutput::WriteFile(write) => format!(
+            "{}**File written:** {} ({} bytes)",
+            icon(Marker::Saved),
+            write.path,
+            write.size
+        ),
+        ToolOutput::UpdateFile(update) => format!(
+            "{}**File updated:** {} (operation: {})",
+            icon(Marker::Edit),
+            update.path,
+            update.operation
+        ),
+        ToolOutput::Search(search) => {
+            let mut output = format!(
+                "{}**Search results for '{}':** {} matches in {} files",
+                icon(Marker::Search),
+                search.pattern,
+                search.matches_found,
+                search.files_searched
+            );
+            if let Some(file) = &search.output_file {
+                output.push_str(&format!("\nFull results written to {file}"));
             }
-        }
-        "write_file" => {
-            let path = result
-                .data
-                .get("path")
-                .and_then(|p| p.as_str())
-                .unwrap_or("unknown");
-            let size = result
-                .data
-                .get("size")
-                .and_then(|s| s.as_u64())
-                .unwrap_or(0);
-            format!("💾 **File written:** {path} ({size} bytes)")
-        }
-        "update_file" => {
-            let path = result
-                .data
-                .get("path")
-                .and_then(|p| p.as_str())
-                .unwrap_or("unknown");
-            let operation = result
-                .data
-                .get("operation")
-                .and_then(|o| o.as_str())
-                .unwrap_or("unknown");
-            format!("✏️ **File updated:** {path} (operation: {operation})")
-        }
-        "search_files" => {
-            let pattern = result
-                .data
-                .get("pattern")
-                .and_then(|p| p.as_str())
-                .unwrap_or("unknown");
-            let matches_found = result
-                .data
-                .get("matches_found")
-                .and_then(|m| m.as_u64())
-                .unwrap_or(0);
-            let files_searched = result
-                .data
-                .get("files_searched")
-                .and_then(|f| f.as_u64())
-                .unwrap_or(0);
-
-            let mut output = format!("🔍 **Search results for '{pattern}':** {matches_found} matches in {files_searched} files");
-
-            if let Some(results) = result.data.get("results").and_then(|r| r.as_array()) {
-                if !results.is_empty() {
+
+            match &search.results {
+                Some(SearchMatches::Grouped(groups)) => {
+                    output.push_str(&format_grouped_matches(groups))
+                }
+                Some(SearchMatches::Flat(results)) if !results.is_empty() => {
                     output.push_str("\n\n**Matches:**");
-                    for (i, match_result) in results.iter().take(10).enumerate() {
-                        if let (Some(file), Some(line), Some(content)) = (
-                            match_result.get("file").and_then(|f| f.as_str()),
-                            match_result.get("line").and_then(|l| l.as_u64()),
-                            match_result.get("content").and_then(|c| c.as_str()),
-                        ) {
-                            output.push_str(&format!(
-                                "\n{}. **{}:{}** `{}`",
-                                i + 1,
-                                file,
-                                line,
-                                content
-                            ));
-                        }
+                    for (i, found) in results.iter().take(10).enumerate() {
+                        output.push_str(&format!(
+                            "\n{}. **{}:{}** `{}`",
+                            i + 1,
+                            found.file.as_deref().unwrap_or("unknown"),
+                            found.line,
+                            found.content
+                        ));
                     }
                     if results.len() > 10 {
                         output.push_str(&format!("\n... and {} more matches", results.len() - 10));
                     }
                 }
+                _ => {}
+            }
+            if let Some(counts) = &search.file_counts {
+                output.push_str(&format_file_counts(counts));
+            }
+            if let Some(files) = search.files.as_ref().filter(|files| !files.is_empty()) {
+                output.push_str(&format!("\n\n**Files with matches:** {}", files.len()));
+                for file in files.iter().take(10) {
+                    output.push_str(&format!("\n- {file}"));
+                }
+                if files.len() > 10 {
+                    output.push_str(&format!("\n... and {} more files", files.len() - 10));
+                }
             }
 
             output
         }
-        "list_directory" => {
-            let path = result
-                .data
-                .get("path")
-                .and_then(|p| p.as_str())
-                .unwrap_or("unknown");
-            let entry_count = result
-                .data
-                .get("entry_count")
-                .and_then(|e| e.as_u64())
-                .unwrap_or(0);
-
-            let mut output =
-                format!("📁 **Directory listing for '{path}':** {entry_count} entries");
-
-            if let Some(entries) = result.data.get("entries").and_then(|e| e.as_array()) {
-                if !entries.is_empty() {
-                    output.push_str("\n\n**Contents:**");
-                    for entry in entries.iter().take(20) {
-                        if let (Some(name), Some(entry_type)) = (
-                            entry.get("name").and_then(|n| n.as_str()),
-                            entry.get("type").and_then(|t| t.as_str()),
-                        ) {
-                            let icon = if entry_type == "directory" {
-                                "📁"
-                            } else {
-                                "📄"
-                            };
-                            output.push_str(&format!("\n{icon} {name}"));
-                        }
-                    }
-                    if entries.len() > 20 {
-                        output.push_str(&format!("\n... and {} more entries", entries.len() - 20));
+        ToolOutput::ListDirectory(listing) => {
+            let mut output = format!(
+                "{}**Directory listing for '{}':** {} entries",
+                icon(Marker::Directory),
+                listing.path,
+                listing.entry_count
+            );
+            if let Some(total) = listing.total_size {
+                output.push_str(&format!(", {} total", format_size(total)));
+            }
+
+            if !listing.entries.is_empty() {
+                output.push_str("\n\n**Contents:**");
+                for entry in listing.entries.iter().take(20) {
+                    let marker = if entry.is_dir() {
+                        Marker::Directory
+                    } else {
+                        Marker::File
+                    };
+                    output.push_str(&format!("\n{}{}", icon(marker), entry.name));
+                    // Sizes are only worth the space when the model asked for them
+                    if listing.total_size.is_some() {
+                        let size = entry.total_size.unwrap_or(entry.size);
+                        output.push_str(&format!(" ({})", format_size(size)));
                     }
                 }
+                if listing.entries.len() > 20 {
+                    output.push_str(&format!(
+                        "\n... and {} more entries",
+                        listing.entries.len() - 20
+                    ));
+                }
             }
 
             output
         }
-        "file_info" => {
-            let path = result
-                .data
-                .get("path")
-                .and_then(|p| p.as_str())
-                .unwrap_or("unknown");
-            let file_type = result
-                .data
-                .get("type")
-                .and_then(|t| t.as_str())
-                .unwrap_or("unknown");
-            let size = result
-                .data
-                .get("size")
-                .and_then(|s| s.as_u64())
-                .unwrap_or(0);
-
-            format!("ℹ️ **File info for '{path}':** {path} ({size} bytes, type: {file_type})")
+        ToolOutput::FileInfo(info) => format!(
+            "{}**File info for '{path}':** {path} ({} bytes, type: {})",
+            icon(Marker::Info),
+            info.size,
+            info.file_type,
+            path = info.path
+        ),
+    }
+}
+
+/// Render a byte count as B, KB, MB, or GB
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 3] = ["KB", "MB", "GB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+/// Per-file match counts from a `count_only` search, busiest files first
+fn format_file_counts(counts: &[FileCount]) -> String {
+    const MAX_FILES: usize = 10;
+
+    if counts.is_empty() {
+        return String::new();
+    }
+    let mut sorted: Vec<&FileCount> = counts.iter().collect();
+    sorted.sort_by_key(|count| std::cmp::Reverse(count.match_count));
+
+    let mut output = String::from("\n\n**Matches per file:**");
+    for count in sorted.iter().take(MAX_FILES) {
+        output.push_str(&format!("\n- {}: {}", count.file, count.match_count));
+    }
+    if counts.len() > MAX_FILES {
+        output.push_str(&format!(
+            "\n... and {} more files",
+            counts.len() - MAX_FILES
+        ));
+    }
+    output
+}
+
+/// Render grouped `search_files` results as `file (N matches):` with indented lines
+fn format_grouped_matches(groups: &[FileMatches]) -> String {
+    const MAX_FILES: usize = 10;
+    const MAX_LINES_PER_FILE: usize = 5;
+
+    let mut output = String::new();
+    for group in groups.iter().take(MAX_FILES) {
+        let noun = if group.match_count == 1 {
+            "match"
+        } else {
+            "matches"
+        };
+        output.push_str(&format!(
+            "\n\n**{}** ({} {noun}):",
+            group.file, group.match_count
+        ));
+
+        for entry in group.matches.iter().take(MAX_LINES_PER_FILE) {
+            output.push_str(&format!("\n  {}: `{}`", entry.line, entry.content));
         }
-        _ => result
-            .message
-            .clone()
-            .unwrap_or_else(|| "Tool executed successfully".to_string()),
+        if group.matches.len() > MAX_LINES_PER_FILE {
+            output.push_str(&format!(
+                "\n  ... and {} more",
+                group.matches.len() - MAX_LINES_PER_FILE
+            ));
+        }
+    }
+    if groups.len() > MAX_FILES {
+        output.push_str(&format!(
+            "\n... and {} more files",
+            groups.len() - MAX_FILES
+        ));
     }
+    output
 }
 
 /// Check if the current task appears to be complete based on recent messages
@@ -0,0 +1,218 @@
+//! Message attachments
+//!
+//! Loads images from disk as inline parts for multimodal models, and text files
+//! as context that works with every provider.
+
+use crate::api::Part;
+use anyhow::{anyhow, Context, Result};
+use base64::Engine;
+use regex::Regex;
+use std::path::{Path, PathBuf};
+
+/// Largest image accepted for inline upload (Gemini's inline request limit)
+const MAX_IMAGE_BYTES: u64 = 20 * 1024 * 1024;
+
+/// Largest text file `/context` will attach
+pub const MAX_CONTEXT_FILE_BYTES: u64 = 256 * 1024;
+
+/// Most files a single `/context` pattern may attach
+const MAX_CONTEXT_FILES: usize = 50;
+
+/// Text files read for `/context`, plus the matches that were left out
+#[derive(Debug, Default)]
+pub struct ContextFiles {
+    /// Message text with every file under a "Contents of <path>:" header
+    pub text: String,
+    pub attached: Vec<PathBuf>,
+    /// Files that were skipped and why
+    pub skipped: Vec<(PathBuf, String)>,
+}
+
+/// MIME type for a supported image file, based on its extension
+pub fn image_mime_type(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    match extension.as_str() {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "webp" => Some("image/webp"),
+        "gif" => Some("image/gif"),
+        "heic" => Some("image/heic"),
+        "heif" => Some("image/heif"),
+        _ => None,
+    }
+}
+
+/// Read an image file into a base64-encoded inline part
+pub fn load_image(path: &Path) -> Result<Part> {
+    let mime_type = image_mime_type(path).ok_or_else(|| {
+        anyhow!(
+            "Unsupported image type: {} (expected png, jpeg, webp, gif, heic, or heif)",
+            path.display()
+        )
+    })?;
+
+    let size = std::fs::metadata(path)
+        .with_context(|| format!("Failed to read image: {}", path.display()))?
+        .len();
+    if size > MAX_IMAGE_BYTES {
+        return Err(anyhow!(
+            "Image is too large: {} bytes (limit is {} bytes)",
+            size,
+            MAX_IMAGE_BYTES
+        ));
+    }
+
+    let bytes =
+        std::fs::read(path).with_context(|| format!("Failed to read image: {}", path.display()))?;
+    let data = base64::engine::general_purpose::STANDARD.encode(bytes);
+
+    Ok(Part::inline_data(mime_type, data))
+}
+
+/// Expand a path whose file name may contain `*` or `?` wildcards
+///
+/// Only the last component is matched, so `src/*.rs` lists `src` but `*/mod.rs`
+/// is rejected. Matches are sorted and limited to regular files.
+pub fn expand_context_paths(pattern: &str) -> Result<Vec<PathBuf>> {
+    let path = Path::new(pattern);
+    if !pattern.contains(['*', '?']) {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let file_pattern = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .filter(|name| name.contains(['*', '?']))
+        .ok_or_else(|| anyhow!("Wildcards are only supported in the file name: {pattern}"))?;
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    if dir.to_string_lossy().contains(['*', '?']) {
+        return Err(anyhow!(
+            "Wildcards are only supported in the file name: {pattern}"
+        ));
+    }
+
+    let regex = Regex::new(&format!(
+        "^{}$",
+        regex::escape(file_pattern)
+            .replace(r"\*", ".*")
+            .replace(r"\?", ".")
+    ))?;
+
+    let mut matches: Vec<PathBuf> = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read directory: {}", dir.display()))?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().map(|t| t.is_file()).unwrap_or(false))
+        .filter(|entry| regex.is_match(&entry.file_name().to_string_lossy()))
+        .map(|entry| dir.join(entry.file_name()))
+        .collect();
+    matches.sort();
+
+    if matches.is_empty() {
+        return Err(anyhow!("No files match {pattern}"));
+    }
+    if matches.len() > MAX_CONTEXT_FILES {
+        return Err(anyhow!(
+            "{} files match {} (limit is {}); use a narrower pattern",
+            matches.len(),
+            pattern,
+            MAX_CONTEXT_FILES
+        ));
+    }
+    Ok(matches)
+}
+
+/// Read text files for `/context`, skipping ones that are too large or not UTF-8
+pub fn load_context_files(paths: &[PathBuf], max_file_bytes: u64) -> ContextFiles {
+    let mut files = ContextFiles::default();
+    let mut sections = Vec::new();
+
+    for path in paths {
+        let size = match std::fs::metadata(path) {
+            Ok(metadata) => metadata.len(),
+            Err(e) => {
+                files.skipped.push((path.clone(), e.to_string()));
+                continue;
+            }
+        };
+        if size > max_file_bytes {
+            files.skipped.push((
+                path.clone(),
+                format!("{size} bytes is over the {max_file_bytes} byte limit"),
+            ));
+            continue;
+        }
+
+        match std::fs::read_to_string(path) {
+            Ok(content) => {
+                sections.push(format!(
+                    "Contents of {}:\n```\n{}\n```",
+                    path.display(),
+                    content.trim_end()
+                ));
+                files.attached.push(path.clone());
+            }
+            Err(e) => files.skipped.push((path.clone(), e.to_string())),
+        }
+    }
+
+    files.text = sections.join("\n\n");
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_supported_images_as_base64() {
+        let path = std::env::temp_dir().join(format!("chatter-image-{}.PNG", uuid::Uuid::new_v4()));
+        std::fs::write(&path, b"hello").unwrap();
+
+        let part = load_image(&path).unwrap();
+        let inline = part.inline_data.unwrap();
+        assert_eq!(inline.mime_type, "image/png");
+        assert_eq!(inline.data, "aGVsbG8=");
+
+        assert!(load_image(Path::new("notes.txt")).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn context_patterns_expand_and_oversized_files_are_skipped() {
+        let dir = std::env::temp_dir().join(format!("chatter-context-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("b.rs"), "fn b() {}\n").unwrap();
+        std::fs::write(dir.join("a.rs"), "fn a() {}\n").unwrap();
+        std::fs::write(dir.join("notes.txt"), "notes").unwrap();
+        std::fs::write(dir.join("big.rs"), "x".repeat(64)).unwrap();
+
+        let pattern = format!("{}/*.rs", dir.display());
+        let paths = expand_context_paths(&pattern).unwrap();
+        let names: Vec<_> = paths
+            .iter()
+            .map(|p| p.file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["a.rs", "b.rs", "big.rs"]);
+
+        let files = load_context_files(&paths, 32);
+        assert_eq!(files.attached.len(), 2);
+        assert_eq!(files.skipped.len(), 1);
+        assert!(files.skipped[0].0.ends_with("big.rs"));
+        assert!(files
+            .text
+            .starts_with(&format!("Contents of {}:", dir.join("a.rs").display())));
+        assert!(files.text.contains("fn b() {}"));
+
+        assert!(expand_context_paths(&format!("{}/*.md", dir.display())).is_err());
+        assert!(expand_context_paths("*/mod.rs").is_err());
+        assert_eq!(
+            expand_context_paths("README.md").unwrap(),
+            vec![PathBuf::from("README.md")]
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,156 @@
+//! Batch mode
+//!
+//! Runs a list of prompts through one session, for regression testing prompts
+//! and capturing every response in a single transcript.
+
+use super::ChatSession;
+use crate::api::LlmClient;
+use anyhow::{anyhow, Result};
+
+/// Outcome counts for a batch run
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct BatchSummary {
+    pub succeeded: usize,
+    pub failed: usize,
+}
+
+/// Parse prompts from a JSON array of strings or from one prompt per non-empty line
+pub fn parse_prompts(input: &str) -> Result<Vec<String>> {
+    let trimmed = input.trim();
+    if trimmed.starts_with('[') {
+        return serde_json::from_str(trimmed)
+            .map_err(|e| anyhow!("Invalid JSON prompt list (expected an array of strings): {e}"));
+    }
+
+    Ok(trimmed
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Send each prompt in turn, reporting every result to `on_result`
+///
+/// Context carries over between prompts unless `fresh` is set, in which case each
+/// prompt starts from the session's initial history (empty, or a template's
+/// examples). Either way the session ends up holding the full transcript. A failed
+/// prompt is dropped from the history and the batch continues.
+pub async fn run_batch<F>(
+    session: &mut ChatSession,
+    client: &LlmClient,
+    prompts: &[String],
+    fresh: bool,
+    mut on_result: F,
+) -> BatchSummary
+where
+    F: FnMut(usize, &str, &Result<String>),
+{
+    let mut summary = BatchSummary::default();
+    let seed = session.history.clone();
+    let mut transcript = std::mem::take(&mut session.history);
+
+    for (index, prompt) in prompts.iter().enumerate() {
+        session.history = if fresh {
+            seed.clone()
+        } else {
+            std::mem::take(&mut transcript)
+        };
+        let start = session.history.len();
+
+        let result = session.send_with_client(client, prompt).await;
+        if result.is_ok() {
+            summary.succeeded += 1;
+        } else {
+            summary.failed += 1;
+            session.history.truncate(start);
+        }
+        on_result(index, prompt, &result);
+
+        if fresh {
+            transcript.extend(session.history.drain(seed.len()..));
+        } else {
+            transcript = std::mem::take(&mut session.history);
+        }
+    }
+
+    session.history = transcript;
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::mock::MockClient;
+    use crate::config::ModelProvider;
+
+    fn prompts_file(content: &str) -> Vec<String> {
+        let path = std::env::temp_dir().join(format!("chatter-batch-{}.txt", uuid::Uuid::new_v4()));
+        std::fs::write(&path, content).unwrap();
+        let prompts = parse_prompts(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        std::fs::remove_file(path).unwrap();
+        prompts
+    }
+
+    #[test]
+    fn prompts_come_from_lines_or_a_json_array() {
+        assert_eq!(
+            prompts_file("first\n\n  second  \n"),
+            vec!["first", "second"]
+        );
+        assert_eq!(
+            parse_prompts(r#"["multi\nline", "two"]"#).unwrap(),
+            vec!["multi\nline", "two"]
+        );
+        assert!(parse_prompts("[not json").is_err());
+    }
+
+    #[tokio::test]
+    async fn batch_keeps_context_unless_fresh() {
+        let prompts = prompts_file("What is Rust?\nAnd its mascot?\n");
+
+        for fresh in [false, true] {
+            let client = LlmClient::Mock(MockClient::with_replies(["A language", "Ferris"]));
+            let mut session = ChatSession::new("mock".to_string(), ModelProvider::Gemini, None);
+            let mut seen = Vec::new();
+
+            let summary = run_batch(&mut session, &client, &prompts, fresh, |i, _, result| {
+                seen.push((i, result.as_ref().unwrap().clone()));
+            })
+            .await;
+
+            assert_eq!(
+                summary,
+                BatchSummary {
+                    succeeded: 2,
+                    failed: 0
+                }
+            );
+            assert_eq!(seen, vec![(0, "A language".into()), (1, "Ferris".into())]);
+            assert_eq!(session.history.len(), 4);
+
+            let LlmClient::Mock(mock) = &client else {
+                unreachable!()
+            };
+            let second_request = &mock.requests()[1];
+            assert_eq!(second_request.len(), if fresh { 1 } else { 3 });
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_prompts_are_counted_and_left_out_of_the_transcript() {
+        let mock = MockClient::default();
+        mock.push_error(anyhow!("quota exceeded"));
+        mock.push_reply(crate::api::Content::model("fine".to_string()));
+        let client = LlmClient::Mock(mock);
+        let mut session = ChatSession::new("mock".to_string(), ModelProvider::Gemini, None);
+
+        let prompts = vec!["one".to_string(), "two".to_string()];
+        let summary = run_batch(&mut session, &client, &prompts, false, |_, _, _| {}).await;
+
+        assert_eq!(summary.failed, 1);
+        assert_eq!(summary.succeeded, 1);
+        assert_eq!(session.history.len(), 2);
+        assert_eq!(session.history[0].parts[0].text, "two");
+    }
+}
@@ -1,6 +1,193 @@
 //! Chat display utilities
 //!
-//! This module can be extended with additional display functionality.
+//! Helpers for terminal output that respect quiet mode.
 
-// Currently empty - display functionality is handled in the main chat module
-// This can be expanded later with display-specific utilities
+use super::theme::{self, icon, Marker};
+use colored::*;
+use indicatif::{ProgressBar, ProgressStyle};
+use std::time::Duration;
+
+/// Format a status line, dropping the decorative marker in quiet mode
+pub fn status_line(quiet: bool, marker: Marker, text: &str) -> String {
+    if quiet {
+        text.to_string()
+    } else {
+        format!("{}{text}", icon(marker))
+    }
+}
+
+/// Render model reasoning shown before the answer
+///
+/// The reasoning itself is only printed when `show` is set, dimmed and indented so
+/// it reads apart from the answer. Otherwise a one-line note says it was hidden.
+pub fn format_thinking(thinking: &str, show: bool, quiet: bool) -> Option<String> {
+    let thinking = thinking.trim();
+    if thinking.is_empty() || (quiet && !show) {
+        return None;
+    }
+    if !show {
+        let lines = thinking.lines().count();
+        let note = format!(
+            "{}Reasoning hidden ({} line{}); use --show-thinking to display it",
+            icon(Marker::Thinking),
+            lines,
+            if lines == 1 { "" } else { "s" }
+        );
+        return Some(note.dimmed().to_string());
+    }
+
+    let body = thinking
+        .lines()
+        .map(|line| format!("│ {line}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    if quiet {
+        Some(format!("THINKING\n{body}"))
+    } else {
+        let heading = format!("{}Thinking", icon(Marker::Thinking));
+        Some(format!("{}\n{}", heading.dimmed(), body.dimmed()))
+    }
+}
+
+/// Create the "thinking" spinner
+///
+/// The spinner shows how long the request has been running. Once `hint_after` has
+/// passed the message changes to hint that the model may be working through a large
+/// context. No progress bar is created in quiet mode so that no control characters
+/// end up in recordings or logs.
+pub fn thinking_spinner(quiet: bool, label: &str, hint_after: Duration) -> Option<ProgressBar> {
+    if quiet {
+        return None;
+    }
+
+    let spinner = ProgressBar::new_spinner();
+    spinner.set_style(
+        ProgressStyle::default_spinner()
+            .template("{spinner:.green} {msg} ({elapsed})")
+            .unwrap()
+            .tick_strings(theme::current().spinner_frames()),
+    );
+    spinner.set_message(format!("{label} is thinking..."));
+    spinner.enable_steady_tick(Duration::from_millis(100));
+
+    // Hold only a weak handle so the timer never keeps a finished spinner alive
+    let weak = spinner.downgrade();
+    let long_message = long_request_message(label);
+    tokio::spawn(async move {
+        tokio::time::sleep(hint_after).await;
+        if let Some(spinner) = weak.upgrade() {
+            if !spinner.is_finished() {
+                spinner.set_message(long_message);
+            }
+        }
+    });
+
+    Some(spinner)
+}
+
+/// Longest typewriter pause per character, whatever `ui.stream_delay_ms` says
+pub const MAX_STREAM_DELAY: Duration = Duration::from_millis(100);
+
+/// Pass a streamed chunk to `emit` one character at a time, pausing `delay` after each
+///
+/// A zero delay passes the whole chunk at once. The delay is capped at
+/// [`MAX_STREAM_DELAY`] and the pause yields to the runtime instead of blocking it.
+pub async fn type_out(chunk: &str, delay: Duration, emit: &mut impl FnMut(&str)) {
+    if delay.is_zero() {
+        emit(chunk);
+        return;
+    }
+    let delay = delay.min(MAX_STREAM_DELAY);
+    for (index, c) in chunk.char_indices() {
+        emit(&chunk[index..index + c.len_utf8()]);
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Spinner message shown once a request exceeds the long-request threshold
+fn long_request_message(label: &str) -> String {
+    format!("{label} is still thinking... large contexts can take a while")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quiet_mode_drops_emoji_and_spinner() {
+        assert_eq!(
+            status_line(true, Marker::Error, "Command error"),
+            "Command error"
+        );
+        assert_eq!(
+            status_line(false, Marker::Error, "Command error"),
+            "❌ Command error"
+        );
+        assert!(thinking_spinner(true, "Gemini", Duration::from_secs(30)).is_none());
+    }
+
+    /// `text` without ANSI color codes, whether or not colors are currently enabled
+    fn plain(text: &str) -> String {
+        let mut output = String::new();
+        let mut rest = text;
+        while let Some(start) = rest.find('\x1b') {
+            output.push_str(&rest[..start]);
+            rest = &rest[start..];
+            rest = rest.find('m').map_or("", |end| &rest[end + 1..]);
+        }
+        output.push_str(rest);
+        output
+    }
+
+    #[test]
+    fn reasoning_is_hidden_unless_requested() {
+        assert_eq!(
+            plain(&format_thinking("step one\nstep two", false, false).unwrap()),
+            "💭 Reasoning hidden (2 lines); use --show-thinking to display it"
+        );
+        assert!(format_thinking("step one", false, true).is_none());
+        assert!(format_thinking("  ", true, false).is_none());
+        assert_eq!(
+            format_thinking("step one\nstep two", true, true).unwrap(),
+            "THINKING\n│ step one\n│ step two"
+        );
+    }
+
+    #[tokio::test]
+    async fn chunks_are_typed_out_with_the_configured_delay() {
+        let mut pieces = Vec::new();
+        let started = std::time::Instant::now();
+        type_out("héllo", Duration::from_millis(20), &mut |piece| {
+            pieces.push(piece.to_string())
+        })
+        .await;
+        assert!(started.elapsed() >= Duration::from_millis(100));
+        assert_eq!(pieces, ["h", "é", "l", "l", "o"]);
+
+        let mut pieces = Vec::new();
+        let started = std::time::Instant::now();
+        type_out(&"x".repeat(1000), Duration::ZERO, &mut |piece| {
+            pieces.push(piece.to_string())
+        })
+        .await;
+        assert!(started.elapsed() < Duration::from_millis(100));
+        assert_eq!(pieces.len(), 1);
+
+        // An oversized delay is capped
+        let started = std::time::Instant::now();
+        type_out("ab", Duration::from_secs(60), &mut |_| {}).await;
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn spinner_switches_to_hint_after_threshold() {
+        let spinner = thinking_spinner(false, "Ollama", Duration::from_millis(10)).unwrap();
+        assert_eq!(spinner.message(), "Ollama is thinking...");
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(spinner.message(), long_request_message("Ollama"));
+
+        spinner.finish_and_clear();
+        assert!(spinner.is_finished());
+    }
+}
@@ -0,0 +1,352 @@
+//! Session export
+//!
+//! Renders a saved chat session as a Markdown document, a plain-text transcript,
+//! or a self-contained HTML page.
+
+use super::highlight::highlight_html;
+use super::ChatSession;
+use crate::api::Content;
+use crate::config::ModelProvider;
+
+/// Output format for exported sessions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Markdown,
+    Text,
+    /// A single HTML file with inline styles
+    Html,
+}
+
+/// Styles embedded in HTML exports so the file needs no other assets
+const HTML_STYLE: &str = "\
+body { font-family: system-ui, sans-serif; max-width: 50rem; margin: 2rem auto; padding: 0 1rem; color: #1f2328; background: #fff; line-height: 1.5; }
+header { border-bottom: 1px solid #d0d7de; margin-bottom: 1.5rem; }
+.meta { color: #59636e; list-style: none; padding: 0; }
+.message { border-radius: 8px; padding: 0.5rem 1rem; margin: 1rem 0; }
+.message h2 { font-size: 0.9rem; text-transform: uppercase; letter-spacing: 0.05em; margin: 0.25rem 0; }
+.user { background: #ddf4ff; }
+.user h2 { color: #0969da; }
+.model { background: #f6f8fa; }
+.model h2 { color: #8250df; }
+.other { background: #fff8c5; }
+details.tool { border: 1px solid #d0d7de; border-radius: 8px; padding: 0.5rem 1rem; margin: 1rem 0; color: #59636e; }
+details.tool summary { cursor: pointer; font-weight: 600; }
+pre { background: #0d1117; color: #e6edf3; border-radius: 6px; padding: 0.75rem 1rem; overflow-x: auto; }
+code { font-family: ui-monospace, SFMono-Regular, Menlo, monospace; font-size: 0.9em; }
+p code { background: rgba(175, 184, 193, 0.3); border-radius: 4px; padding: 0.1em 0.3em; }
+pre code[data-language]::before { content: attr(data-language); display: block; color: #7d8590; font-size: 0.8em; margin-bottom: 0.5em; }
+";
+
+/// Who produced a transcript entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Speaker<'a> {
+    User,
+    Model(Option<&'a ModelProvider>),
+    Tool(&'a str),
+    Other(&'a str),
+}
+
+/// A single renderable message from the session history
+#[derive(Debug)]
+struct TranscriptEntry<'a> {
+    speaker: Speaker<'a>,
+    text: String,
+}
+
+/// Walk the history, skipping messages that carry no text (e.g. bare tool calls)
+fn transcript_entries(history: &[Content]) -> impl Iterator<Item = TranscriptEntry<'_>> {
+    history.iter().filter_map(|content| {
+        let text = content.joined_text();
+
+        if text.trim().is_empty() {
+            return None;
+        }
+
+        let speaker = match content.role.as_str() {
+            "user" => Speaker::User,
+            "model" | "assistant" => Speaker::Model(content.provider.as_ref()),
+            "tool" => Speaker::Tool(content.name.as_deref().unwrap_or("unknown")),
+            other => Speaker::Other(other),
+        };
+
+        Some(TranscriptEntry { speaker, text })
+    })
+}
+
+impl ChatSession {
+    /// Export the session in the requested format
+    pub fn export(&self, format: ExportFormat) -> String {
+        match format {
+            ExportFormat::Markdown => self.export_markdown(),
+            ExportFormat::Text => self.export_text(),
+            ExportFormat::Html => self.export_html(),
+        }
+    }
+
+    /// Export the session as a Markdown document
+    pub fn export_markdown(&self) -> String {
+        let mut output = format!("# Chat session {}\n\n", self.id);
+        output.push_str(&format!(
+            "- Model: {} ({})\n",
+            self.model,
+            self.model_label()
+        ));
+        output.push_str(&format!(
+            "- Created: {}\n",
+            self.created_at.format("%Y-%m-%d %H:%M:%S UTC")
+        ));
+        if let Some(system) = &self.system_instruction {
+            output.push_str(&format!("- System instruction: {system}\n"));
+        }
+
+        for entry in transcript_entries(&self.history) {
+            match entry.speaker {
+                Speaker::User => output.push_str(&format!("\n## You\n\n{}\n", entry.text)),
+                Speaker::Model(provider) => output.push_str(&format!(
+                    "\n## {}\n\n{}\n",
+                    self.producer_label(provider),
+                    entry.text
+                )),
+                Speaker::Tool(name) => output.push_str(&format!(
+                    "\n## Tool: {name}\n\n```json\n{}\n```\n",
+                    entry.text
+                )),
+                Speaker::Other(role) => {
+                    output.push_str(&format!("\n## {role}\n\n{}\n", entry.text))
+                }
+            }
+        }
+
+        output
+    }
+
+    /// Export the session as a plain-text transcript without any terminal styling
+    pub fn export_text(&self) -> String {
+        transcript_entries(&self.history)
+            .map(|entry| match entry.speaker {
+                Speaker::User => format!("You: {}", entry.text),
+                Speaker::Model(provider) => {
+                    format!("{}: {}", self.producer_label(provider), entry.text)
+                }
+                Speaker::Tool(name) => format!("[tool: {name}] {}", entry.text),
+                Speaker::Other(role) => format!("[{role}] {}", entry.text),
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n")
+            + "\n"
+    }
+
+    /// Export the session as a standalone HTML page
+    pub fn export_html(&self) -> String {
+        let heading = match &self.title {
+            Some(title) => title.clone(),
+            None => format!("Chat session {}", self.id),
+        };
+        let mut meta = format!(
+            "<li>Model: {} ({})</li>\n<li>Created: {}</li>\n",
+            escape_html(&self.model),
+            self.model_label(),
+            self.created_at.format("%Y-%m-%d %H:%M:%S UTC")
+        );
+        if let Some(system) = &self.system_instruction {
+            meta.push_str(&format!(
+                "<li>System instruction: {}</li>\n",
+                escape_html(system)
+            ));
+        }
+
+        let mut body = String::new();
+        for entry in transcript_entries(&self.history) {
+            let section = |class: &str, label: &str| {
+                format!(
+                    "<section class=\"message {class}\">\n<h2>{}</h2>\n{}</section>\n",
+                    escape_html(label),
+                    text_to_html(&entry.text)
+                )
+            };
+            body.push_str(&match entry.speaker {
+                Speaker::User => section("user", "You"),
+                Speaker::Model(provider) => section("model", self.producer_label(provider)),
+                Speaker::Tool(name) => format!(
+                    "<details class=\"tool\">\n<summary>Tool: {}</summary>\n<pre><code>{}</code></pre>\n</details>\n",
+                    escape_html(name),
+                    escape_html(&entry.text)
+                ),
+                Speaker::Other(role) => section("other", role),
+            });
+        }
+
+        format!(
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>{title}</title>\n<style>\n{HTML_STYLE}</style>\n</head>\n<body>\n\
+             <header>\n<h1>{title}</h1>\n<ul class=\"meta\">\n{meta}</ul>\n</header>\n\
+             <main>\n{body}</main>\n</body>\n</html>\n",
+            title = escape_html(&heading),
+        )
+    }
+}
+
+/// Escape text for use in HTML content and attribute values
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Render message text: fenced code blocks become `<pre><code>`, the rest paragraphs
+fn text_to_html(text: &str) -> String {
+    let mut html = String::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    // Language and lines of the code block being collected
+    let mut code: Option<(&str, Vec<&str>)> = None;
+
+    for line in text.lines() {
+        let fence = line.trim_start().strip_prefix("```");
+        match (&mut code, fence) {
+            (Some((language, lines)), Some(_)) => {
+                push_code_block(&mut html, language, lines);
+                code = None;
+            }
+            (Some((_, lines)), None) => lines.push(line),
+            (None, Some(info)) => {
+                push_paragraph(&mut html, &mut paragraph);
+                code = Some((info.trim(), Vec::new()));
+            }
+            (None, None) if line.trim().is_empty() => push_paragraph(&mut html, &mut paragraph),
+            (None, None) => paragraph.push(line),
+        }
+    }
+
+    // An unclosed fence still renders as code
+    if let Some((language, lines)) = &code {
+        push_code_block(&mut html, language, lines);
+    }
+    push_paragraph(&mut html, &mut paragraph);
+    html
+}
+
+/// Write a code block, colored with inline styles when its language is known
+fn push_code_block(html: &mut String, language: &str, lines: &[&str]) {
+    let source = lines.join("\n");
+    if language.is_empty() {
+        let code = escape_html(&source);
+        html.push_str(&format!("<pre><code>{code}</code></pre>\n"));
+    } else {
+        let code = highlight_html(language, &source).unwrap_or_else(|| escape_html(&source));
+        let language = escape_html(language);
+        html.push_str(&format!(
+            "<pre><code class=\"language-{language}\" data-language=\"{language}\">{code}</code></pre>\n"
+        ));
+    }
+}
+
+/// Write the collected lines as one paragraph, keeping line breaks and inline code
+fn push_paragraph(html: &mut String, lines: &mut Vec<&str>) {
+    if lines.is_empty() {
+        return;
+    }
+    let text = lines.join("\n");
+    lines.clear();
+
+    let escaped = escape_html(&text);
+    let with_code = if escaped.matches('`').count().is_multiple_of(2) {
+        escaped
+            .split('`')
+            .enumerate()
+            .map(|(index, piece)| {
+                if index % 2 == 1 {
+                    format!("<code>{piece}</code>")
+                } else {
+                    piece.to_string()
+                }
+            })
+            .collect()
+    } else {
+        escaped
+    };
+    html.push_str(&format!("<p>{}</p>\n", with_code.replace('\n', "<br>\n")));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::Part;
+    use crate::config::ModelProvider;
+    use chrono::{TimeZone, Utc};
+
+    fn fixture_session() -> ChatSession {
+        let mut session = ChatSession::new(
+            "gemini-2.5-flash".to_string(),
+            ModelProvider::Gemini,
+            Some("Be brief.".to_string()),
+        );
+        session.id = "fixture".to_string();
+        session.created_at = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        session.history = vec![
+            Content::user("List the files".to_string()),
+            Content {
+                role: "tool".to_string(),
+                parts: vec![Part::text(r#"{"entry_count":1}"#.to_string())],
+                name: Some("list_directory".to_string()),
+                tool_call_id: None,
+                tool_calls: Vec::new(),
+                provider: None,
+            },
+            Content::model("There is one file.".to_string()),
+        ];
+        session
+    }
+
+    #[test]
+    fn exports_plain_text_transcript() {
+        let expected = "You: List the files\n\n\
+                        [tool: list_directory] {\"entry_count\":1}\n\n\
+                        Gemini: There is one file.\n";
+        assert_eq!(fixture_session().export_text(), expected);
+    }
+
+    #[test]
+    fn exports_markdown_document() {
+        let expected = "# Chat session fixture\n\n\
+                        - Model: gemini-2.5-flash (Gemini)\n\
+                        - Created: 2024-05-01 12:00:00 UTC\n\
+                        - System instruction: Be brief.\n\
+                        \n## You\n\nList the files\n\
+                        \n## Tool: list_directory\n\n```json\n{\"entry_count\":1}\n```\n\
+                        \n## Gemini\n\nThere is one file.\n";
+        assert_eq!(fixture_session().export(ExportFormat::Markdown), expected);
+    }
+
+    #[test]
+    fn exports_html_with_code_blocks_and_escaped_content() {
+        let mut session = fixture_session();
+        session.history.push(Content::user(
+            "Is <script>alert(1)</script> safe?".to_string(),
+        ));
+        session.history.push(Content::model(
+            "Use `Vec<u8>`:\n\n```text\nlet bytes: Vec<u8> = vec![1 & 2];\n```\nDone.".to_string(),
+        ));
+
+        let html = session.export(ExportFormat::Html);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<style>"));
+        assert!(!html.contains("<script>"));
+        assert!(html.contains("<p>Is &lt;script&gt;alert(1)&lt;/script&gt; safe?</p>"));
+        assert!(html.contains(
+            "<pre><code class=\"language-text\" data-language=\"text\">let bytes: Vec&lt;u8&gt; = vec![1 &amp; 2];</code></pre>"
+        ));
+        assert!(html.contains("<p>Use <code>Vec&lt;u8&gt;</code>:</p>"));
+        assert!(html.contains("<p>Done.</p>"));
+        assert!(html.contains("<details class=\"tool\">\n<summary>Tool: list_directory</summary>"));
+        assert!(html.contains("{&quot;entry_count&quot;:1}"));
+    }
+}
@@ -0,0 +1,145 @@
+//! Provider fallback
+//!
+//! When the primary provider rejects a request for quota or credential reasons,
+//! the session retries once against a second provider and stays on it.
+
+use crate::api::LlmClient;
+use crate::config::{Config, ModelProvider};
+use std::fmt;
+
+/// Provider, model, and client to switch to when the primary provider refuses a request
+pub struct ProviderFallback {
+    pub provider: ModelProvider,
+    pub model: String,
+    pub client: LlmClient,
+}
+
+impl fmt::Debug for ProviderFallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProviderFallback")
+            .field("provider", &self.provider)
+            .field("model", &self.model)
+            .finish()
+    }
+}
+
+impl ProviderFallback {
+    /// Build the configured fallback for `primary`, if any
+    ///
+    /// A fallback equal to the primary provider is ignored, and one that cannot be
+    /// constructed (e.g. missing API key) is reported and skipped rather than failing
+    /// the whole command.
+    pub fn from_config(config: &Config, primary: &ModelProvider) -> Option<Self> {
+        let provider = config.fallback_provider.clone()?;
+        if &provider == primary {
+            return None;
+        }
+
+        match LlmClient::from_config(config, &provider) {
+            Ok(client) => Some(Self {
+                model: config.default_model_for(&provider).to_string(),
+                provider,
+                client,
+            }),
+            Err(e) => {
+                tracing::warn!(
+                    "Fallback provider {} is unavailable: {}",
+                    provider.display_name(),
+                    e
+                );
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::mock::MockClient;
+    use crate::chat::ChatSession;
+    use crate::error::ChatterError;
+
+    fn api_error(status: u16) -> anyhow::Error {
+        ChatterError::Api {
+            status,
+            body: format!("API request failed: status {status}"),
+        }
+        .into()
+    }
+
+    fn session_with_fallback(reply: &str) -> ChatSession {
+        let mut session =
+            ChatSession::new("gemini-2.5-flash".to_string(), ModelProvider::Gemini, None);
+        session.set_fallback(Some(ProviderFallback {
+            provider: ModelProvider::Ollama,
+            model: "qwen3".to_string(),
+            client: LlmClient::Mock(MockClient::with_replies([reply])),
+        }));
+        session
+    }
+
+    #[tokio::test]
+    async fn quota_errors_switch_to_the_fallback_provider() {
+        let primary = MockClient::default();
+        primary.push_error(api_error(429));
+        let primary = LlmClient::Mock(primary);
+        let mut session = session_with_fallback("from ollama");
+
+        let response = session.send_with_client(&primary, "hello").await.unwrap();
+        assert_eq!(response, "from ollama");
+        assert_eq!(session.provider, ModelProvider::Ollama);
+        assert_eq!(session.model, "qwen3");
+    }
+
+    #[tokio::test]
+    async fn streamed_queries_switch_to_the_fallback_provider() {
+        let primary = MockClient::default();
+        primary.push_error(api_error(401));
+        let primary = LlmClient::Mock(primary);
+        let mut session = session_with_fallback("from ollama");
+
+        let mut chunks = Vec::new();
+        let response = session
+            .send_streaming(&primary, "hello", |chunk| chunks.push(chunk.to_string()))
+            .await
+            .unwrap();
+        assert_eq!(response, "from ollama");
+        assert_eq!(chunks, vec!["from ollama"]);
+        assert_eq!(session.provider, ModelProvider::Ollama);
+        assert_eq!(session.history.len(), 2);
+
+        // A streaming fallback streams its reply
+        let primary = MockClient::default();
+        primary.push_error(api_error(429));
+        let primary = LlmClient::Mock(primary);
+        let backup = MockClient::default();
+        backup.push_stream(["from ", "claude"]);
+        let mut session =
+            ChatSession::new("gemini-2.5-flash".to_string(), ModelProvider::Gemini, None);
+        session.set_fallback(Some(ProviderFallback {
+            provider: ModelProvider::Anthropic,
+            model: "claude-sonnet-4-5".to_string(),
+            client: LlmClient::Mock(backup),
+        }));
+        let mut chunks = Vec::new();
+        let response = session
+            .send_streaming(&primary, "hello", |chunk| chunks.push(chunk.to_string()))
+            .await
+            .unwrap();
+        assert_eq!(response, "from claude");
+        assert_eq!(chunks, vec!["from ", "claude"]);
+        assert_eq!(session.provider, ModelProvider::Anthropic);
+    }
+
+    #[tokio::test]
+    async fn other_errors_do_not_trigger_the_fallback() {
+        let primary = MockClient::default();
+        primary.push_error(api_error(500));
+        let primary = LlmClient::Mock(primary);
+        let mut session = session_with_fallback("unused");
+
+        assert!(session.send_with_client(&primary, "hello").await.is_err());
+        assert_eq!(session.provider, ModelProvider::Gemini);
+    }
+}
@@ -0,0 +1,141 @@
+//! Syntax highlighting for file contents shown in the terminal and code in HTML exports
+//!
+//! Only the terminal display and exported pages are colored; what goes back to
+//! the model stays plain Markdown. Built with the `highlight` feature, which is
+//! on by default.
+
+#[cfg(feature = "highlight")]
+mod syntect_support {
+    use std::sync::OnceLock;
+    use syntect::easy::HighlightLines;
+    use syntect::highlighting::{Theme, ThemeSet};
+    use syntect::parsing::{SyntaxReference, SyntaxSet};
+
+    // Loading the bundled definitions takes a moment, so it is done once
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    static THEMES: OnceLock<ThemeSet> = OnceLock::new();
+
+    pub fn syntaxes() -> &'static SyntaxSet {
+        SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines)
+    }
+
+    /// A highlighter for `syntax`, unless it is plain text
+    pub fn highlighter(syntax: Option<&SyntaxReference>) -> Option<HighlightLines<'static>> {
+        let syntax = syntax.filter(|syntax| syntax.name != "Plain Text")?;
+        let theme: &'static Theme = THEMES
+            .get_or_init(ThemeSet::load_defaults)
+            .themes
+            .get("base16-ocean.dark")?;
+        Some(HighlightLines::new(syntax, theme))
+    }
+}
+
+/// `content` with ANSI colors for the language of `path`
+///
+/// Returns `None` for unknown extensions and plain text, and always when the
+/// `highlight` feature is off.
+#[cfg(feature = "highlight")]
+pub fn highlight(path: &str, content: &str) -> Option<String> {
+    use syntect::util::{as_24_bit_terminal_escaped, LinesWithEndings};
+    use syntect_support::{highlighter, syntaxes};
+
+    let extension = std::path::Path::new(path).extension()?.to_str()?;
+    let syntaxes = syntaxes();
+    let mut highlighter = highlighter(syntaxes.find_syntax_by_extension(extension))?;
+    let mut output = String::new();
+    for line in LinesWithEndings::from(content) {
+        let ranges = highlighter.highlight_line(line, syntaxes).ok()?;
+        output.push_str(&as_24_bit_terminal_escaped(&ranges, false));
+    }
+    output.push_str("\x1b[0m");
+    Some(output)
+}
+
+#[cfg(not(feature = "highlight"))]
+pub fn highlight(_path: &str, _content: &str) -> Option<String> {
+    None
+}
+
+/// `code` as escaped HTML with inline color styles for `language`
+///
+/// `language` is a fence tag such as `rust` or `py`. Returns `None` for unknown
+/// languages, and always when the `highlight` feature is off.
+#[cfg(feature = "highlight")]
+pub fn highlight_html(language: &str, code: &str) -> Option<String> {
+    use syntect::html::{styled_line_to_highlighted_html, IncludeBackground};
+    use syntect::util::LinesWithEndings;
+    use syntect_support::{highlighter, syntaxes};
+
+    let syntaxes = syntaxes();
+    let mut highlighter = highlighter(syntaxes.find_syntax_by_token(language))?;
+    let mut output = String::new();
+    for line in LinesWithEndings::from(code) {
+        let ranges = highlighter.highlight_line(line, syntaxes).ok()?;
+        output.push_str(&styled_line_to_highlighted_html(&ranges, IncludeBackground::No).ok()?);
+    }
+    Some(output)
+}
+
+#[cfg(not(feature = "highlight"))]
+pub fn highlight_html(_language: &str, _code: &str) -> Option<String> {
+    None
+}
+
+#[cfg(all(test, feature = "highlight"))]
+mod tests {
+    use super::*;
+    use crate::agent::ToolResult;
+    use crate::chat::agent_commands::{display_tool_result_with, format_tool_result};
+
+    #[test]
+    fn source_files_are_colored_only_in_the_terminal() {
+        let read = |path: &str| {
+            ToolResult::success(
+                serde_json::json!({
+                    "path": path,
+                    "content": "fn main() {\n    println!(\"hi\");\n}",
+                    "size": 33
+                }),
+                None,
+            )
+        };
+
+        let rust = read("src/main.rs");
+        let shown = display_tool_result_with("read_file", &rust, true);
+        assert!(shown.contains("\x1b[38;2;"));
+        assert!(shown.contains("**File: src/main.rs** (33 bytes)"));
+        // The model and non-terminal output get the plain fenced block
+        let plain = format_tool_result("read_file", &rust);
+        assert!(!plain.contains('\x1b'));
+        assert_eq!(display_tool_result_with("read_file", &rust, false), plain);
+
+        let unknown = read("notes.unknown-ext");
+        assert_eq!(
+            display_tool_result_with("read_file", &unknown, true),
+            format_tool_result("read_file", &unknown)
+        );
+        assert!(highlight("notes.txt", "plain words").is_none());
+    }
+
+    #[test]
+    fn exported_code_gets_inline_styles() {
+        let html = highlight_html("rust", "let v: Vec<u8> = vec![1 & 2];").unwrap();
+        assert!(html.contains("<span style=\"color:#"));
+        assert!(html.contains("&lt;") && html.contains("&amp;"));
+        assert!(!html.contains("<u8"));
+        assert!(highlight_html("no-such-language", "x").is_none());
+
+        let mut session = crate::chat::ChatSession::new(
+            "test".to_string(),
+            crate::config::ModelProvider::Gemini,
+            None,
+        );
+        session.history.push(crate::api::Content::model(
+            "```rust\nfn main() {}\n```".to_string(),
+        ));
+        let page = session.export(crate::chat::export::ExportFormat::Html);
+        assert!(
+            page.contains("<pre><code class=\"language-rust\" data-language=\"rust\"><span style=")
+        );
+    }
+}
@@ -1,6 +1,35 @@
 //! Chat history utilities
 //!
-//! This module can be extended with additional history functionality.
+//! Rough token accounting for the conversation history.
 
-// Currently empty - history functionality is handled in the main chat module
-// This can be expanded later with history-specific utilities
+use crate::api::Content;
+
+/// Estimate the token count of text using the common four-characters-per-token rule
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+/// Estimate the tokens a request would use for the history plus system instruction
+pub fn estimate_history_tokens(history: &[Content], system_instruction: Option<&str>) -> usize {
+    let history_tokens: usize = history
+        .iter()
+        .flat_map(|content| content.parts.iter())
+        .map(|part| estimate_tokens(&part.text))
+        .sum();
+
+    history_tokens + system_instruction.map(estimate_tokens).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimates_history_and_system_tokens() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcde"), 2);
+
+        let history = vec![Content::user("a".repeat(40)), Content::model("b".repeat(8))];
+        assert_eq!(estimate_history_tokens(&history, Some("abcd")), 13);
+    }
+}
@@ -0,0 +1,129 @@
+//! Line editing for the interactive chat
+//!
+//! One editor lives for the whole chat loop so arrow-up recalls lines typed earlier
+//! in the same session, and history is persisted globally or per working directory.
+
+use super::theme::{icon, Marker};
+use anyhow::{anyhow, Result};
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Readline editor with a persistent history file
+pub struct InputReader {
+    editor: DefaultEditor,
+    history_path: PathBuf,
+}
+
+impl InputReader {
+    /// Create the editor and load existing history
+    pub fn new(history_path: PathBuf) -> Result<Self> {
+        let mut editor = DefaultEditor::new()?;
+
+        if let Some(parent) = history_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let _ = editor.load_history(&history_path);
+
+        Ok(Self {
+            editor,
+            history_path,
+        })
+    }
+
+    /// Read one line, recording it in history; Ctrl-C and Ctrl-D exit the program
+    pub fn read_line(&mut self, prompt: &str) -> Result<String> {
+        match self.editor.readline(prompt) {
+            Ok(line) => {
+                if !line.trim().is_empty() {
+                    let _ = self.editor.add_history_entry(line.as_str());
+                    let _ = self.editor.save_history(&self.history_path);
+                }
+                Ok(line)
+            }
+            Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => {
+                println!("{}Goodbye!", icon(Marker::Goodbye));
+                std::process::exit(0);
+            }
+            Err(err) => Err(anyhow!("Failed to read line: {}", err)),
+        }
+    }
+}
+
+/// Location of the input history file
+///
+/// With `per_directory` set, each working directory gets its own file so prompts from
+/// one project do not show up while working in another.
+pub fn history_file_path(per_directory: bool) -> Result<PathBuf> {
+    let data_dir = dirs::data_dir().ok_or_else(|| anyhow!("Failed to find data directory"))?;
+    let working_dir = if per_directory {
+        Some(std::env::current_dir()?)
+    } else {
+        None
+    };
+
+    Ok(history_path_for(&data_dir, working_dir.as_deref()))
+}
+
+fn history_path_for(data_dir: &Path, working_dir: Option<&Path>) -> PathBuf {
+    let base = data_dir.join("chatter");
+
+    match working_dir {
+        None => base.join("history.txt"),
+        Some(dir) => {
+            let label: String = dir
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or("root")
+                .chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                        c
+                    } else {
+                        '_'
+                    }
+                })
+                .collect();
+            let hash = fnv1a(dir.to_string_lossy().as_bytes());
+            base.join("history")
+                .join(format!("{label}-{hash:016x}.txt"))
+        }
+    }
+}
+
+/// Stable 64-bit FNV-1a hash, so file names survive toolchain upgrades
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn history_path_is_global_or_scoped_to_directory() {
+        let data = Path::new("/data");
+        assert_eq!(
+            history_path_for(data, None),
+            PathBuf::from("/data/chatter/history.txt")
+        );
+
+        let project = history_path_for(data, Some(Path::new("/work/my project")));
+        assert!(project.starts_with("/data/chatter/history"));
+        let name = project.file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with("my_project-") && name.ends_with(".txt"));
+
+        // Same name in another location gets a different file; same path is stable
+        assert_ne!(
+            project,
+            history_path_for(data, Some(Path::new("/other/my project")))
+        );
+        assert_eq!(
+            project,
+            history_path_for(data, Some(Path::new("/work/my project")))
+        );
+    }
+}
@@ -0,0 +1,178 @@
+//! Limits on a single model interaction
+//!
+//! A model that keeps requesting tools can otherwise run for a long time and
+//! push large amounts of tool output back into the context. These limits stop
+//! the loop and keep whatever the model said along the way.
+
+use std::fmt;
+use std::time::Duration;
+
+/// Default wall-clock budget for the tool loop of one interaction
+pub const DEFAULT_TIME_BUDGET_SECS: u64 = 120;
+
+/// Default cap on the tool output fed back to the model in one interaction
+pub const DEFAULT_MAX_TOOL_OUTPUT_BYTES: usize = 256 * 1024;
+
+/// Default cap on a single tool result payload before its largest fields are cut
+pub const DEFAULT_MAX_TOOL_RESULT_BYTES: usize = 32 * 1024;
+
+/// Time and tool output budget for one model interaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InteractionLimits {
+    /// Wall-clock budget from the first tool calls to the final answer
+    ///
+    /// It is checked before each request and bounds tool execution; a model
+    /// request in flight runs until the configured request timeout.
+    pub time_budget: Duration,
+    /// Cumulative size of tool result payloads sent back to the model
+    pub max_tool_output_bytes: usize,
+    /// Size of one tool result payload; larger string fields are truncated
+    pub max_tool_result_bytes: usize,
+}
+
+impl Default for InteractionLimits {
+    fn default() -> Self {
+        Self {
+            time_budget: Duration::from_secs(DEFAULT_TIME_BUDGET_SECS),
+            max_tool_output_bytes: DEFAULT_MAX_TOOL_OUTPUT_BYTES,
+            max_tool_result_bytes: DEFAULT_MAX_TOOL_RESULT_BYTES,
+        }
+    }
+}
+
+/// Error returned when an interaction is stopped by one of its limits
+#[derive(Debug, Clone)]
+pub struct InteractionAborted {
+    /// Which limit was hit
+    pub reason: String,
+    /// Text the model produced before the interaction was stopped
+    pub partial_response: String,
+}
+
+impl fmt::Display for InteractionAborted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Stopped the agent loop: {}", self.reason)?;
+        if !self.partial_response.trim().is_empty() {
+            write!(f, "\nPartial response:\n{}", self.partial_response.trim())?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for InteractionAborted {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{Agent, AgentConfig};
+    use crate::api::mock::MockClient;
+    use crate::api::{Content, LlmClient, ModelToolCall};
+    use crate::chat::ChatSession;
+    use crate::config::ModelProvider;
+
+    /// A client that asks for another calculation on every turn
+    fn tool_hungry_client(turns: usize) -> LlmClient {
+        let mock = MockClient::default();
+        for turn in 0..turns {
+            let mut reply = Content::model(format!("Working on step {turn}"));
+            reply.tool_calls.push(ModelToolCall {
+                id: Some(format!("call-{turn}")),
+                name: "calculate".to_string(),
+                arguments: serde_json::json!({ "expression": format!("{turn} + 1") }),
+            });
+            mock.push_reply(reply);
+        }
+        LlmClient::Mock(mock)
+    }
+
+    async fn run_with(limits: InteractionLimits) -> anyhow::Error {
+        let client = tool_hungry_client(6);
+        let mut agent = Agent::new(AgentConfig {
+            enabled: true,
+            ..AgentConfig::default()
+        })
+        .unwrap();
+        let mut session = ChatSession::new("qwen3".to_string(), ModelProvider::Ollama, None);
+        session.set_interaction_limits(limits);
+
+        session
+            .send_with_agent(&client, "keep going", &mut agent)
+            .await
+            .unwrap_err()
+    }
+
+    #[tokio::test]
+    async fn tool_output_cap_stops_the_loop() {
+        let error = run_with(InteractionLimits {
+            max_tool_output_bytes: 10,
+            ..InteractionLimits::default()
+        })
+        .await;
+
+        let aborted = error.downcast_ref::<InteractionAborted>().unwrap();
+        assert_eq!(aborted.reason, "tool output exceeded 10 bytes");
+        assert_eq!(aborted.partial_response, "Working on step 0");
+        assert!(error
+            .to_string()
+            .contains("Partial response:\nWorking on step 0"));
+    }
+
+    #[tokio::test]
+    async fn time_budget_stops_the_loop() {
+        let error = run_with(InteractionLimits {
+            time_budget: Duration::ZERO,
+            ..InteractionLimits::default()
+        })
+        .await;
+
+        let aborted = error.downcast_ref::<InteractionAborted>().unwrap();
+        assert!(aborted.reason.starts_with("time budget"));
+        assert_eq!(aborted.partial_response, "Working on step 0");
+    }
+
+    #[tokio::test]
+    async fn time_budget_does_not_cut_off_a_plain_answer() {
+        let client = LlmClient::Mock(
+            MockClient::with_replies(["Slow but complete"]).with_delay(Duration::from_millis(200)),
+        );
+        let mut session = ChatSession::new("qwen3".to_string(), ModelProvider::Ollama, None);
+        session.set_interaction_limits(InteractionLimits {
+            time_budget: Duration::from_millis(20),
+            ..InteractionLimits::default()
+        });
+
+        let response = session.send_with_client(&client, "hello").await.unwrap();
+        assert_eq!(response, "Slow but complete");
+    }
+
+    #[tokio::test]
+    async fn time_budget_does_not_cut_off_a_request_in_flight() {
+        let mock = MockClient::default().with_delay(Duration::from_millis(200));
+        let mut first = Content::model("Checking".to_string());
+        first.tool_calls.push(ModelToolCall {
+            id: Some("call-0".to_string()),
+            name: "calculate".to_string(),
+            arguments: serde_json::json!({ "expression": "1 + 1" }),
+        });
+        mock.push_reply(first);
+        mock.push_reply(Content::model("It is 2".to_string()));
+        let client = LlmClient::Mock(mock);
+        let mut agent = Agent::new(AgentConfig {
+            enabled: true,
+            ..AgentConfig::default()
+        })
+        .unwrap();
+        let mut session = ChatSession::new("qwen3".to_string(), ModelProvider::Ollama, None);
+        session.set_interaction_limits(InteractionLimits {
+            time_budget: Duration::from_millis(100),
+            ..InteractionLimits::default()
+        });
+
+        // The second request outlasts the budget but was sent within it
+        let response = session
+            .send_with_agent(&client, "what is 1 + 1?", &mut agent)
+            .await
+            .unwrap();
+        assert_eq!(response, "It is 2");
+    }
+}
@@ -2,30 +2,49 @@
 //!
 //! Handles interactive chat sessions, conversation history, and terminal UI.
 
-use crate::agent::{Agent, ToolCall, ToolResult};
+use crate::agent::{Agent, AgentConfig, ToolCall, ToolResult, ToolResultCache};
 use crate::api::{Content, LlmClient, ModelToolCall, Part};
-use crate::config::ModelProvider;
+use crate::config::{Config, ModelProvider, Resolved, Source};
+use crate::error::ChatterError;
 use anyhow::{anyhow, Context, Result};
 use chrono::{DateTime, Utc};
 use colored::*;
-use indicatif::{ProgressBar, ProgressStyle};
-use rustyline::error::ReadlineError;
-use rustyline::DefaultEditor;
+use indicatif::ProgressBar;
 use serde::{Deserialize, Serialize};
 use serde_json::{self, Value};
 use std::collections::HashMap;
 use std::fs;
-use std::io::{self, Write};
+use std::hash::{Hash, Hasher};
+use std::io;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 use tokio_stream::StreamExt;
 use uuid::Uuid;
 
 pub mod agent_commands;
+pub mod attachments;
+pub mod batch;
 pub mod display;
+pub mod export;
+pub mod fallback;
+pub mod highlight;
 pub mod history;
+pub mod input;
+pub mod limits;
+pub mod replay;
 pub mod session;
-
-use agent_commands::format_tool_result;
+pub mod tee;
+pub mod theme;
+
+use agent_commands::{display_tool_result, format_tool_result};
+use display::{format_thinking, status_line, thinking_spinner, type_out};
+use fallback::ProviderFallback;
+use input::InputReader;
+use limits::{InteractionAborted, InteractionLimits};
+use replay::HistoryMatch;
+use tee::TeeFile;
+use theme::{icon, Marker};
 /// A chat session with conversation history
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatSession {
@@ -44,6 +63,37 @@ pub struct ChatSession {
     pub created_at: DateTime<Utc>,
     /// Last updated time
     pub updated_at: DateTime<Utc>,
+    /// ID of the session this one was forked from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub parent_id: Option<String>,
+    /// Short name for the session, set with `/title` or generated after the first exchange
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Values set with `/set`, substituted for `{name}` in outgoing messages
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub variables: HashMap<String, String>,
+    /// Notes added with `/note`; saved with the session but never sent to the model
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<String>,
+    /// Images queued by `/image` for the next user message
+    #[serde(skip)]
+    pending_attachments: Vec<Part>,
+    /// Provider to retry against when the primary one refuses a request
+    #[serde(skip)]
+    fallback: Option<Arc<ProviderFallback>>,
+    /// Whether requests currently go to the fallback provider
+    #[serde(skip)]
+    using_fallback: bool,
+    /// Time and tool output budget for each model interaction
+    #[serde(skip)]
+    limits: InteractionLimits,
+    /// Fingerprint of the JSON last written by auto-save, to skip unchanged writes
+    #[serde(skip)]
+    auto_saved: Option<u64>,
+    /// File last written by auto-save, removed when a new title renames the session
+    #[serde(skip)]
+    auto_save_path: Option<PathBuf>,
 }
 
 fn default_session_provider() -> ModelProvider {
@@ -59,11 +109,198 @@ struct ToolExecutionRecord {
 #[derive(Debug, Clone)]
 struct InteractionResult {
     response_text: String,
+    /// Reasoning from every model turn in the interaction, kept out of the history
+    thinking: Vec<String>,
     tool_executions: Vec<ToolExecutionRecord>,
+    /// The first tool call the safety checks rejected; the model was told and went on
+    safety_violation: Option<String>,
+}
+
+/// Combine a cut-off reply with the model's continuation of it
+///
+/// Some models ignore the partial turn and answer from the beginning; when the
+/// continuation repeats the partial text it already is the whole reply.
+fn join_continuation(partial: &str, continuation: &str) -> String {
+    if continuation.starts_with(partial.trim_end()) && !partial.trim().is_empty() {
+        continuation.to_string()
+    } else {
+        format!("{partial}{continuation}")
+    }
+}
+
+/// Whether `name` can be used as a `{name}` placeholder
+fn is_variable_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Replace `{name}` placeholders with session variables
+///
+/// `{{name}}` gives a literal `{name}`. Any other braces, such as those in JSON or
+/// code, and placeholders without a matching variable are left as written.
+fn substitute_variables(text: &str, variables: &HashMap<String, String>) -> String {
+    if variables.is_empty() {
+        return text.to_string();
+    }
+
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(index) = rest.find('{') {
+        result.push_str(&rest[..index]);
+        rest = &rest[index..];
+
+        let escaped = rest
+            .strip_prefix("{{")
+            .and_then(|after| after.split_once("}}"))
+            .filter(|(name, _)| is_variable_name(name));
+        if let Some((name, after)) = escaped {
+            result.push('{');
+            result.push_str(name);
+            result.push('}');
+            rest = after;
+            continue;
+        }
+
+        let value = rest
+            .strip_prefix('{')
+            .and_then(|after| after.split_once('}'))
+            .and_then(|(name, after)| Some((variables.get(name)?, after)));
+        match value {
+            Some((value, after)) => {
+                result.push_str(value);
+                rest = after;
+            }
+            None => {
+                result.push_str(&rest[..1]);
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Tidy a title typed by the user or written by the model
+///
+/// Keeps the first line, drops a `Title:` label, surrounding quotes and markdown,
+/// collapses whitespace, and caps the length. Returns `None` when nothing is left.
+fn clean_title(raw: &str) -> Option<String> {
+    let line = raw.lines().map(str::trim).find(|line| !line.is_empty())?;
+    let line = match line.get(..6) {
+        Some(label) if label.eq_ignore_ascii_case("title:") => &line[6..],
+        _ => line,
+    };
+    let line = line
+        .trim_matches(|c: char| c.is_whitespace() || matches!(c, '"' | '\'' | '*' | '#' | '`'))
+        .trim_end_matches('.');
+    let title: String = line
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .take(MAX_TITLE_CHARS)
+        .collect();
+    let title = title.trim_end();
+    (!title.is_empty()).then(|| title.to_string())
+}
+
+/// Print the reasoning collected during an interaction, or note that it was hidden
+fn print_thinking(interaction: &InteractionResult, options: &ChatOptions) {
+    let thinking = interaction.thinking.join("\n\n");
+    if let Some(text) = format_thinking(&thinking, options.show_thinking, options.quiet) {
+        println!("{text}");
+    }
 }
 
 const MAX_TOOL_ITERATIONS: usize = 6;
 
+const SUMMARY_SYSTEM_PROMPT: &str = "You compress chat transcripts. Summarize the conversation \
+you are given so it can replace the original turns as context for continuing the chat. Keep \
+facts, decisions, file names, code identifiers and open questions. Reply with the summary only.";
+
+const TITLE_SYSTEM_PROMPT: &str = "You name chat conversations. Reply with a title of at most \
+six words describing the conversation you are given, without quotes or a final full stop.";
+
+/// Longest title kept, in characters
+const MAX_TITLE_CHARS: usize = 60;
+
+const SUMMARY_PREFIX: &str = "Summary of the earlier conversation:";
+
+/// Separates a user message from agent tool results appended to it
+const AGENT_RESULTS_SEPARATOR: &str = "\n\nAgent tool results:\n";
+
+/// Runtime options for an interactive chat session
+#[derive(Debug, Clone)]
+pub struct ChatOptions {
+    /// Save the session after every turn
+    pub auto_save: bool,
+    /// Directory used for auto-saved sessions
+    pub sessions_dir: Option<PathBuf>,
+    /// Stream responses when the provider supports it
+    pub stream: bool,
+    /// Continue a reply from its partial text when the stream drops
+    pub resume_on_stream_drop: bool,
+    /// Keep only this many recent exchanges after each turn
+    pub max_history: Option<usize>,
+    /// Ask the model for a session title after the first exchange
+    pub auto_title: bool,
+    /// Base configuration used when agent mode is enabled
+    pub agent_config: AgentConfig,
+    /// Number of recent messages kept verbatim by /summarize
+    pub summary_keep_turns: usize,
+    /// Hide the banner, spinner, and decorative output
+    pub quiet: bool,
+    /// How long a request runs before the spinner hints at a slow response
+    pub long_request_hint: Duration,
+    /// Keep a separate input history file for each working directory
+    pub per_directory_history: bool,
+    /// Print model reasoning (e.g. `<think>` blocks) instead of hiding it
+    pub show_thinking: bool,
+    /// Emoji, ASCII, or no line markers
+    pub theme: theme::Theme,
+    /// Configuration used to build a new client when `/provider` switches providers
+    pub provider_config: Option<Config>,
+    /// Provider and model the chat started with, shown by `/whoami`
+    pub launch: Option<LaunchSettings>,
+    /// File that replies are copied to as they arrive (`--tee`)
+    pub tee: Option<Arc<TeeFile>>,
+    /// Pause after each streamed character; never set in quiet mode or with `--fast`
+    pub stream_delay: Duration,
+}
+
+/// Provider and model a chat started with, and where each came from
+#[derive(Debug, Clone)]
+pub struct LaunchSettings {
+    pub provider: Resolved<ModelProvider>,
+    pub model: Resolved<String>,
+}
+
+impl Default for ChatOptions {
+    fn default() -> Self {
+        Self {
+            auto_save: false,
+            sessions_dir: None,
+            stream: true,
+            resume_on_stream_drop: false,
+            max_history: None,
+            auto_title: false,
+            agent_config: AgentConfig::default(),
+            summary_keep_turns: 4,
+            quiet: false,
+            long_request_hint: Duration::from_secs(30),
+            per_directory_history: false,
+            show_thinking: false,
+            theme: theme::Theme::default(),
+            provider_config: None,
+            launch: None,
+            tee: None,
+            stream_delay: Duration::ZERO,
+        }
+    }
+}
+
 impl ChatSession {
     /// Create a new chat session
     pub fn new(model: String, provider: ModelProvider, system_instruction: Option<String>) -> Self {
@@ -76,29 +313,533 @@ impl ChatSession {
             history: Vec::new(),
             created_at: now,
             updated_at: now,
+            parent_id: None,
+            title: None,
+            variables: HashMap::new(),
+            notes: Vec::new(),
+            pending_attachments: Vec::new(),
+            fallback: None,
+            using_fallback: false,
+            limits: InteractionLimits::default(),
+            auto_saved: None,
+            auto_save_path: None,
+        }
+    }
+
+    /// Retry requests against `fallback` when the primary provider hits a quota or auth error
+    pub fn set_fallback(&mut self, fallback: Option<ProviderFallback>) {
+        self.fallback = fallback.map(Arc::new);
+    }
+
+    /// Set the time and tool output budget for each model interaction
+    pub fn set_interaction_limits(&mut self, limits: InteractionLimits) {
+        self.limits = limits;
+    }
+
+    /// Client requests should go to: the fallback once the session has switched to it
+    fn active_client<'a>(
+        &self,
+        client: &'a LlmClient,
+        fallback: &'a Option<Arc<ProviderFallback>>,
+    ) -> &'a LlmClient {
+        match fallback {
+            Some(fallback) if self.using_fallback => &fallback.client,
+            _ => client,
+        }
+    }
+
+    /// Generate a reply, switching to the fallback provider once if the primary refuses
+    async fn generate_reply(
+        &mut self,
+        client: &LlmClient,
+        tools: &[crate::api::ToolDefinition],
+    ) -> Result<crate::api::llm::ChatResponse> {
+        let fallback = self.fallback.clone();
+        let active = self.active_client(client, &fallback);
+        let result = active
+            .generate(
+                &self.model,
+                &self.history,
+                self.system_instruction.as_deref(),
+                tools,
+            )
+            .await;
+
+        let error = match result {
+            Ok(response) => return Ok(response),
+            Err(error) => error,
+        };
+        let Some(fallback) = self.switch_to_fallback(&error) else {
+            return Err(error.into());
+        };
+
+        Ok(fallback
+            .client
+            .generate(
+                &self.model,
+                &self.history,
+                self.system_instruction.as_deref(),
+                tools,
+            )
+            .await?)
+    }
+
+    /// Switch to the fallback provider after the primary refused with `error`
+    ///
+    /// Returns the fallback to retry with, or `None` when the error is not a quota
+    /// or credential problem, no fallback is configured, or it is already in use.
+    fn switch_to_fallback(&mut self, error: &ChatterError) -> Option<Arc<ProviderFallback>> {
+        if self.using_fallback || !error.is_quota_or_auth() {
+            return None;
+        }
+        let fallback = self.fallback.clone()?;
+        eprintln!(
+            "{} rejected the request ({}); switching to {} ({})",
+            self.provider.display_name(),
+            error,
+            fallback.provider.display_name(),
+            fallback.model
+        );
+        self.provider = fallback.provider.clone();
+        self.model = fallback.model.clone();
+        self.using_fallback = true;
+        Some(fallback)
+    }
+
+    /// Create a branch of this session with a copy of its history
+    ///
+    /// The fork gets a fresh ID and timestamps and records this session as its parent.
+    pub fn fork(&self) -> ChatSession {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4().to_string(),
+            model: self.model.clone(),
+            provider: self.provider.clone(),
+            system_instruction: self.system_instruction.clone(),
+            history: self.history.clone(),
+            created_at: now,
+            updated_at: now,
+            parent_id: Some(self.id.clone()),
+            title: self.title.clone(),
+            variables: self.variables.clone(),
+            notes: self.notes.clone(),
+            pending_attachments: Vec::new(),
+            fallback: self.fallback.clone(),
+            using_fallback: self.using_fallback,
+            limits: self.limits,
+            auto_saved: None,
+            auto_save_path: None,
         }
     }
 
     /// Load a chat session from file
+    ///
+    /// Damaged files (for example an interrupted auto-save) are recovered up to the
+    /// first unreadable message, with a warning about what was dropped.
     pub async fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
         let content = fs::read_to_string(path)?;
-        let session: ChatSession = serde_json::from_str(&content)?;
-        Ok(session)
+        let error = match serde_json::from_str::<ChatSession>(&content) {
+            Ok(session) => return Ok(session),
+            Err(error) => error,
+        };
+
+        let recovered = session::recover_session(&content).map_err(|recovery_error| {
+            anyhow!(
+                "Failed to parse session file {}: {error} (recovery failed: {recovery_error})",
+                path.display()
+            )
+        })?;
+
+        eprintln!(
+            "{}Session file {} is damaged ({error}); recovered {} message(s), dropped {}",
+            icon(Marker::Warning),
+            path.display(),
+            recovered.session.history.len(),
+            recovered.dropped_messages
+        );
+        Ok(recovered.session)
     }
 
     /// Save the chat session to file
     pub async fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
         let content = serde_json::to_string_pretty(self)?;
-        fs::write(path, content)?;
-        Ok(())
+        session::write_atomically(path.as_ref(), &content)
+    }
+
+    /// Save for `--auto-save`, skipping the write when nothing changed since the last one
+    ///
+    /// Returns whether the file was written.
+    async fn auto_save_to(&mut self, path: &Path) -> Result<bool> {
+        let previous = self.auto_save_path.replace(path.to_path_buf());
+        let content = serde_json::to_string_pretty(self)?;
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        content.hash(&mut hasher);
+        let fingerprint = hasher.finish();
+        if self.auto_saved == Some(fingerprint) && path.exists() {
+            return Ok(false);
+        }
+
+        session::write_atomically(path, &content)?;
+        self.auto_saved = Some(fingerprint);
+        // A new title renames the file; drop the copy under the old name
+        if let Some(previous) = previous.filter(|previous| previous != path) {
+            let _ = fs::remove_file(previous);
+        }
+        Ok(true)
+    }
+
+    /// Index and text of the most recent user message
+    ///
+    /// Tool results appended to the message by agent mode are not part of the text.
+    fn last_user_turn(&self) -> Option<(usize, String)> {
+        let index = self.history.iter().rposition(|c| c.role == "user")?;
+        let text = self.history[index].joined_text();
+        let text = match text.split_once(AGENT_RESULTS_SEPARATOR) {
+            Some((message, _)) => message.to_string(),
+            None => text,
+        };
+        Some((index, text))
+    }
+
+    /// Open the last user message in `$EDITOR` and drop it and its reply from history
+    ///
+    /// Returns the edited message to resend, or `None` when there is nothing to edit or
+    /// the editor was closed without saving (history is left untouched).
+    fn edit_last_user_message(&mut self) -> Result<Option<String>> {
+        let Some((index, text)) = self.last_user_turn() else {
+            println!("{}No previous message to edit", icon(Marker::Empty));
+            return Ok(None);
+        };
+
+        let edited = match dialoguer::Editor::new().edit(&text)? {
+            Some(edited) if !edited.trim().is_empty() => edited.trim().to_string(),
+            _ => {
+                println!("{}Edit cancelled", icon(Marker::Cancelled));
+                return Ok(None);
+            }
+        };
+
+        self.history.truncate(index);
+        self.updated_at = Utc::now();
+        Ok(Some(edited))
+    }
+
+    /// Drop all but the last `max_exchanges` exchanges, returning how many messages went
+    ///
+    /// An exchange starts at a user message and includes the model replies and tool
+    /// messages that follow it, so tool calls are never separated from their results.
+    /// The system instruction is stored separately and is unaffected.
+    pub fn trim_history(&mut self, max_exchanges: usize) -> usize {
+        let starts: Vec<usize> = self
+            .history
+            .iter()
+            .enumerate()
+            .filter(|(_, content)| content.role == "user")
+            .map(|(index, _)| index)
+            .collect();
+        if starts.len() <= max_exchanges {
+            return 0;
+        }
+
+        let keep_from = match max_exchanges {
+            0 => self.history.len(),
+            n => starts[starts.len() - n],
+        };
+        self.history.drain(..keep_from);
+        self.updated_at = Utc::now();
+        keep_from
+    }
+
+    /// Use a template's content as the system instruction and seed its examples
+    pub fn apply_template(&mut self, template: &crate::templates::Template) {
+        self.system_instruction = Some(template.content.clone());
+        self.seed_examples(&template.examples);
+    }
+
+    /// Put few-shot example turns at the start of the history
+    ///
+    /// Nothing is added when the history already starts with the same turns, so
+    /// applying a template twice does not duplicate its examples.
+    pub fn seed_examples(&mut self, examples: &[(String, String)]) {
+        let turns = crate::templates::example_turns(examples);
+        let already_seeded = self.history.len() >= turns.len()
+            && turns.iter().zip(&self.history).all(|(example, existing)| {
+                example.role == existing.role && example.joined_text() == existing.joined_text()
+            });
+        if turns.is_empty() || already_seeded {
+            return;
+        }
+
+        self.history.splice(0..0, turns);
+        self.updated_at = Utc::now();
+    }
+
+    /// Set the session title, or clear it when `title` is blank
+    pub fn set_title(&mut self, title: &str) {
+        self.title = clean_title(title);
+        self.updated_at = Utc::now();
+    }
+
+    /// Ask the model for a title once the first exchange is complete
+    ///
+    /// Does nothing when the session already has a title or no reply yet.
+    pub async fn generate_title(&mut self, client: &LlmClient) -> Result<Option<String>> {
+        if self.title.is_some() {
+            return Ok(None);
+        }
+        let first_text = |role: &str| {
+            self.history
+                .iter()
+                .filter(|content| content.role == role)
+                .map(Content::joined_text)
+                .find(|text| !text.trim().is_empty())
+                .map(|text| text.chars().take(1000).collect::<String>())
+        };
+        let (Some(question), Some(answer)) = (first_text("user"), first_text("model")) else {
+            return Ok(None);
+        };
+
+        let fallback = self.fallback.clone();
+        let response = self
+            .active_client(client, &fallback)
+            .generate(
+                &self.model,
+                &[Content::user(format!(
+                    "user: {question}\n\nmodel: {answer}"
+                ))],
+                Some(TITLE_SYSTEM_PROMPT),
+                &[],
+            )
+            .await?;
+
+        self.title = clean_title(&response.message.joined_text());
+        Ok(self.title.clone())
+    }
+
+    /// Store a variable from a `name=value` assignment, returning its name
+    fn set_variable(&mut self, assignment: &str) -> Result<String> {
+        let (name, value) = assignment
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Usage: /set name=value"))?;
+        let name = name.trim();
+        if !is_variable_name(name) {
+            return Err(anyhow!(
+                "Invalid variable name '{}'. Use letters, digits, '_' or '-'",
+                name
+            ));
+        }
+        self.variables
+            .insert(name.to_string(), value.trim().to_string());
+        self.updated_at = Utc::now();
+        Ok(name.to_string())
+    }
+
+    /// Variables as `name = value` lines, sorted by name
+    fn variable_lines(&self) -> Vec<String> {
+        let mut names: Vec<&String> = self.variables.keys().collect();
+        names.sort();
+        names
+            .into_iter()
+            .map(|name| format!("{} = {}", name, self.variables[name]))
+            .collect()
+    }
+
+    /// Append a note, returning how many the session has
+    fn add_note(&mut self, text: &str) -> Result<usize> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Err(anyhow!("Usage: /note <text> or /note clear"));
+        }
+        self.notes.push(text.to_string());
+        self.updated_at = Utc::now();
+        Ok(self.notes.len())
+    }
+
+    /// Remove all notes, returning how many there were
+    fn clear_notes(&mut self) -> usize {
+        let removed = self.notes.len();
+        if removed > 0 {
+            self.notes.clear();
+            self.updated_at = Utc::now();
+        }
+        removed
+    }
+
+    /// Notes as numbered lines, oldest first
+    fn note_lines(&self) -> Vec<String> {
+        self.notes
+            .iter()
+            .enumerate()
+            .map(|(i, note)| format!("{}. {}", i + 1, note))
+            .collect()
+    }
+
+    /// Build a user message, attaching any images queued with `/image`
+    fn user_message(&mut self, text: String) -> Content {
+        let mut message = Content::user(text);
+        message.parts.append(&mut self.pending_attachments);
+        message
     }
 
     /// Add a message to the conversation history
-    pub fn add_message(&mut self, content: Content) {
+    ///
+    /// Model messages are stamped with the current provider unless they already
+    /// name one.
+    pub fn add_message(&mut self, mut content: Content) {
+        if content.role == "model" && content.provider.is_none() {
+            content.provider = Some(self.provider.clone());
+        }
         self.history.push(content);
         self.updated_at = Utc::now();
     }
 
+    /// Index where the verbatim tail starts when keeping the last `keep_last` messages
+    ///
+    /// The split is moved back to a user turn so tool results are never separated
+    /// from the call that produced them. Returns `None` when there is nothing to summarize.
+    fn summary_split_index(&self, keep_last: usize) -> Option<usize> {
+        let mut split = self.history.len().checked_sub(keep_last)?;
+        while split > 0 && self.history.get(split).map(|c| c.role.as_str()) != Some("user") {
+            split -= 1;
+        }
+        if split == 0 {
+            None
+        } else {
+            Some(split)
+        }
+    }
+
+    /// Replace everything before the last `keep_last` messages with a summary turn
+    ///
+    /// The summary is stored as a `user` turn so every provider keeps it in context.
+    pub fn apply_summary(&mut self, summary: &str, keep_last: usize) -> bool {
+        let Some(split) = self.summary_split_index(keep_last) else {
+            return false;
+        };
+
+        let tail = self.history.split_off(split);
+        self.history = vec![Content::user(format!("{SUMMARY_PREFIX}\n{summary}"))];
+        self.history.extend(tail);
+        self.updated_at = Utc::now();
+        true
+    }
+
+    /// Collapse tool calls and their JSON results into one readable line per call
+    ///
+    /// Each run of tool-calling model turns and tool results is merged with the
+    /// model's following answer, prefixed by a `[Tool activity]` list. Returns the
+    /// number of messages removed.
+    pub fn compact_tool_turns(&mut self) -> usize {
+        let before = self.history.len();
+        let mut compacted: Vec<Content> = Vec::with_capacity(before);
+        // Text and summary lines of the tool run being collapsed
+        let mut pending: Option<(Vec<String>, Vec<String>)> = None;
+
+        for content in std::mem::take(&mut self.history) {
+            let is_call = content.role != "tool" && !content.tool_calls.is_empty();
+            if is_call || (content.role == "tool" && pending.is_some()) {
+                let (texts, lines) = pending.get_or_insert_with(Default::default);
+                if content.role == "tool" {
+                    lines.push(summarize_tool_message(&content));
+                } else {
+                    let text = content.joined_text();
+                    if !text.trim().is_empty() {
+                        texts.push(text);
+                    }
+                }
+                continue;
+            }
+
+            match pending.take() {
+                Some((mut texts, lines)) if content.role == "model" => {
+                    texts.push(content.joined_text());
+                    compacted.push(compacted_tool_message(&texts, &lines));
+                }
+                Some((texts, lines)) => {
+                    compacted.push(compacted_tool_message(&texts, &lines));
+                    compacted.push(content);
+                }
+                None => compacted.push(content),
+            }
+        }
+        if let Some((texts, lines)) = pending {
+            compacted.push(compacted_tool_message(&texts, &lines));
+        }
+
+        self.history = compacted;
+        let removed = before - self.history.len();
+        if removed > 0 {
+            self.updated_at = Utc::now();
+        }
+        removed
+    }
+
+    /// Save a copy of the session next to the auto-saves before a destructive change
+    async fn write_backup(
+        &self,
+        sessions_dir: Option<&Path>,
+        file_name: String,
+    ) -> Result<PathBuf> {
+        let backup_path = match sessions_dir {
+            Some(dir) => {
+                fs::create_dir_all(dir)?;
+                dir.join(file_name)
+            }
+            None => PathBuf::from(file_name),
+        };
+        self.save_to_file(&backup_path).await?;
+        Ok(backup_path)
+    }
+
+    /// Ask the model to summarize older turns and replace them with the summary
+    ///
+    /// Returns the summary, or `None` when the history is already short enough.
+    pub async fn summarize(
+        &mut self,
+        client: &LlmClient,
+        keep_last: usize,
+    ) -> Result<Option<String>> {
+        let Some(split) = self.summary_split_index(keep_last) else {
+            return Ok(None);
+        };
+
+        let transcript = self.history[..split]
+            .iter()
+            .map(|content| {
+                let text: Vec<&str> = content.parts.iter().map(|p| p.text.as_str()).collect();
+                format!("{}: {}", content.role, text.join("\n"))
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n");
+
+        let response = client
+            .generate(
+                &self.model,
+                &[Content::user(transcript)],
+                Some(SUMMARY_SYSTEM_PROMPT),
+                &[],
+            )
+            .await?;
+
+        let summary = response
+            .message
+            .parts
+            .iter()
+            .map(|p| p.text.as_str())
+            .collect::<Vec<_>>()
+            .join("")
+            .trim()
+            .to_string();
+
+        if summary.is_empty() {
+            return Err(anyhow!("Model returned an empty summary"));
+        }
+
+        self.apply_summary(&summary, keep_last);
+        Ok(Some(summary))
+    }
+
     async fn run_model_interaction(
         &mut self,
         client: &LlmClient,
@@ -106,6 +847,19 @@ impl ChatSession {
     ) -> Result<InteractionResult> {
         let mut tool_executions = Vec::new();
         let mut iterations = 0;
+        // Repeated read-only calls within this interaction reuse earlier results
+        let mut tool_cache = ToolResultCache::default();
+        // The time budget covers the tool loop, so it starts with the first tool calls
+        let mut deadline = None;
+        let time_limit = format!(
+            "time budget of {}s exceeded",
+            self.limits.time_budget.as_secs()
+        );
+        let mut tool_output_bytes = 0;
+        // Assistant text from turns that also requested tools, returned if a limit is hit
+        let mut partial_response = String::new();
+        let mut thinking = Vec::new();
+        let mut safety_violation = None;
 
         loop {
             iterations += 1;
@@ -115,8 +869,15 @@ impl ChatSession {
                     MAX_TOOL_ITERATIONS
                 ));
             }
+            if deadline.is_some_and(|deadline| tokio::time::Instant::now() >= deadline) {
+                return Err(InteractionAborted {
+                    reason: time_limit,
+                    partial_response,
+                }
+                .into());
+            }
 
-            let tool_definitions = if matches!(self.provider, ModelProvider::Ollama) {
+            let tool_definitions = if self.provider.supports_tools() {
                 if let Some(agent_ref) = agent.as_mut() {
                     if agent_ref.is_enabled() {
                         agent_ref.tool_definitions()
@@ -130,28 +891,17 @@ impl ChatSession {
                 Vec::new()
             };
 
-            let chat_response = client
-                .generate(
-                    &self.model,
-                    &self.history,
-                    self.system_instruction.as_deref(),
-                    &tool_definitions,
-                )
-                .await?;
+            // A request already sent is bounded by the request timeout, not the budget
+            let chat_response = self.generate_reply(client, &tool_definitions).await?;
 
+            thinking.extend(chat_response.thinking);
             let mut assistant_message = chat_response.message;
 
             if assistant_message.parts.is_empty() {
-                assistant_message.parts.push(Part {
-                    text: String::new(),
-                });
+                assistant_message.parts.push(Part::text(String::new()));
             }
 
-            let response_text = assistant_message
-                .parts
-                .first()
-                .map(|p| p.text.clone())
-                .unwrap_or_default();
+            let response_text = assistant_message.joined_text();
 
             let tool_calls = assistant_message.tool_calls.clone();
 
@@ -160,11 +910,13 @@ impl ChatSession {
             if tool_calls.is_empty() {
                 return Ok(InteractionResult {
                     response_text,
+                    thinking,
                     tool_executions,
+                    safety_violation,
                 });
             }
 
-            if !matches!(self.provider, ModelProvider::Ollama) {
+            if !self.provider.supports_tools() {
                 return Err(anyhow!(
                     "Received tool call from unsupported provider: {:?}",
                     self.provider
@@ -181,28 +933,76 @@ impl ChatSession {
                 ));
             }
 
+            if !response_text.trim().is_empty() {
+                if !partial_response.is_empty() {
+                    partial_response.push('\n');
+                }
+                partial_response.push_str(&response_text);
+            }
+
+            // Once a limit is hit, the remaining calls still get a result so the
+            // history stays valid for the next request
+            let mut stop_reason: Option<String> = None;
+            let deadline = *deadline
+                .get_or_insert_with(|| tokio::time::Instant::now() + self.limits.time_budget);
+
             for call in tool_calls {
                 let tool_call = convert_model_tool_call(&call)?;
                 let tool_name = tool_call.tool.clone();
                 let call_id = call.id.clone();
 
-                let execution_result = match agent_ref.execute_tool(tool_call.clone()).await {
-                    Ok(result) => result,
-                    Err(e) => ToolResult::error(format!("Tool execution error: {e}")),
+                let execution_result = if let Some(reason) = &stop_reason {
+                    ToolResult::error(format!("Not executed: {reason}"))
+                } else {
+                    match tokio::time::timeout_at(
+                        deadline,
+                        tool_cache.execute(agent_ref, tool_call),
+                    )
+                    .await
+                    {
+                        Ok(Ok(result)) => result,
+                        Ok(Err(e)) => {
+                            if let Some(ChatterError::SafetyViolation(reason)) =
+                                e.downcast_ref::<ChatterError>()
+                            {
+                                safety_violation.get_or_insert_with(|| reason.clone());
+                            }
+                            ToolResult::error(format!("Tool execution error: {e}"))
+                        }
+                        Err(_) => {
+                            stop_reason = Some(time_limit.clone());
+                            ToolResult::error(format!("Not completed: {time_limit}"))
+                        }
+                    }
                 };
 
-                let payload_json = build_tool_result_payload(&tool_name, &execution_result);
-                let payload_string = serde_json::to_string(&payload_json)
+                let mut payload_json = build_tool_result_payload(&tool_name, &execution_result);
+                truncate_tool_payload(&mut payload_json, self.limits.max_tool_result_bytes);
+                let mut payload_string = serde_json::to_string(&payload_json)
                     .context("Failed to encode tool result payload")?;
 
+                tool_output_bytes += payload_string.len();
+                if stop_reason.is_none() && tool_output_bytes > self.limits.max_tool_output_bytes {
+                    let reason = format!(
+                        "tool output exceeded {} bytes",
+                        self.limits.max_tool_output_bytes
+                    );
+                    payload_json = build_tool_result_payload(
+                        &tool_name,
+                        &ToolResult::error(format!("Output omitted: {reason}")),
+                    );
+                    payload_string = serde_json::to_string(&payload_json)
+                        .context("Failed to encode tool result payload")?;
+                    stop_reason = Some(reason);
+                }
+
                 let tool_message = Content {
                     role: "tool".to_string(),
-                    parts: vec![Part {
-                        text: payload_string.clone(),
-                    }],
+                    parts: vec![Part::text(payload_string.clone())],
                     name: Some(tool_name.clone()),
                     tool_call_id: call_id.clone(),
                     tool_calls: Vec::new(),
+                    provider: None,
                 };
                 self.add_message(tool_message);
 
@@ -212,6 +1012,14 @@ impl ChatSession {
                 });
             }
 
+            if let Some(reason) = stop_reason {
+                return Err(InteractionAborted {
+                    reason,
+                    partial_response,
+                }
+                .into());
+            }
+
             // Loop to let the model incorporate tool outputs
         }
     }
@@ -220,10 +1028,9 @@ impl ChatSession {
     pub async fn start_interactive_chat(
         &mut self,
         client: &LlmClient,
-        auto_save: bool,
-        sessions_dir: Option<PathBuf>,
+        options: ChatOptions,
     ) -> Result<()> {
-        self.start_interactive_chat_with_agent(client, auto_save, sessions_dir, None)
+        self.start_interactive_chat_with_agent(client, options, None)
             .await
     }
 
@@ -231,18 +1038,23 @@ impl ChatSession {
     pub async fn start_interactive_chat_with_agent(
         &mut self,
         client: &LlmClient,
-        auto_save: bool,
-        sessions_dir: Option<PathBuf>,
+        options: ChatOptions,
         mut agent: Option<Agent>,
     ) -> Result<()> {
+        let quiet = options.quiet;
+        theme::set_theme(options.theme);
+
         // Display welcome message
-        self.display_welcome();
+        if !quiet {
+            self.display_welcome();
+        }
 
         // Show agent status if available
         if let Some(ref agent) = agent {
-            if agent.is_enabled() {
+            if agent.is_enabled() && !quiet {
                 println!(
-                    "🤖 {} Agent mode is active! I can help with file operations.",
+                    "{}{} Agent mode is active! I can help with file operations.",
+                    icon(Marker::Agent),
                     "AGENT:".bright_green().bold()
                 );
                 println!("   Use '/agent help' for agent commands.");
@@ -252,6 +1064,16 @@ impl ChatSession {
         // Track recent messages for completion detection
         let mut recent_messages = Vec::new();
 
+        // One editor for the whole loop so in-session history works
+        let mut input_reader =
+            InputReader::new(input::history_file_path(options.per_directory_history)?)?;
+
+        // Message rewritten by /edit, sent in place of reading new input
+        let mut resend: Option<String> = None;
+
+        // Client built by /provider, used in place of the one passed in
+        let mut switched_client: Option<LlmClient> = None;
+
         // Main chat loop
         loop {
             // Get user input
@@ -260,8 +1082,15 @@ impl ChatSession {
 {} ",
                 "You:".bright_blue().bold()
             );
-            let input = read_input_with_features(&prompt)?;
+            let input = match resend.take() {
+                Some(edited) => {
+                    println!("{prompt}{edited}");
+                    edited
+                }
+                None => input_reader.read_line(&prompt)?,
+            };
             let input = input.trim();
+            let client = switched_client.as_ref().unwrap_or(client);
 
             // Handle special commands
             if input.is_empty() {
@@ -269,7 +1098,9 @@ impl ChatSession {
             }
 
             if input == "exit" || input == "quit" {
-                println!("👋 Goodbye!");
+                if !quiet {
+                    println!("{}Goodbye!", icon(Marker::Goodbye));
+                }
                 break;
             }
 
@@ -278,87 +1109,178 @@ impl ChatSession {
                 if input.starts_with("/agent") {
                     let parts: Vec<&str> = input.splitn(2, ' ').collect();
                     let args = parts.get(1).unwrap_or(&"");
-                    if let Err(e) =
-                        agent_commands::handle_agent_command("/agent", args, &mut agent).await
+                    if let Err(e) = agent_commands::handle_agent_command(
+                        "/agent",
+                        args,
+                        &mut agent,
+                        &options.agent_config,
+                    )
+                    .await
+                    {
+                        println!(
+                            "{}",
+                            status_line(quiet, Marker::Error, &format!("Agent command error: {e}"))
+                        );
+                    }
+                    continue;
+                }
+
+                if input == "/edit" {
+                    match self.edit_last_user_message() {
+                        Ok(Some(edited)) => resend = Some(edited),
+                        Ok(None) => {}
+                        Err(e) => {
+                            println!(
+                                "{}",
+                                status_line(quiet, Marker::Error, &format!("Edit failed: {e}"))
+                            )
+                        }
+                    }
+                    continue;
+                }
+
+                if let Some(args) = input
+                    .strip_prefix("/provider")
+                    .filter(|rest| rest.is_empty() || rest.starts_with(' '))
+                {
+                    match self
+                        .handle_provider_command(args.trim(), options.provider_config.as_ref())
                     {
-                        println!("❌ Agent command error: {e}");
+                        Ok(Some(new_client)) => switched_client = Some(new_client),
+                        Ok(None) => {}
+                        Err(e) => println!(
+                            "{}",
+                            status_line(
+                                quiet,
+                                Marker::Error,
+                                &format!("Provider switch failed: {e}")
+                            )
+                        ),
+                    }
+                    continue;
+                }
+
+                if input == "/whoami" {
+                    println!("{}Effective configuration:", icon(Marker::Settings));
+                    for line in self.whoami_lines(&options) {
+                        println!("  {line}");
+                    }
+                    continue;
+                }
+
+                if input == "/models" {
+                    if let Err(e) = self.show_models(client).await {
+                        println!(
+                            "{}",
+                            status_line(
+                                quiet,
+                                Marker::Error,
+                                &format!("Listing models failed: {e}")
+                            )
+                        );
+                    }
+                    continue;
+                }
+
+                // `/model` shows the model and `/model <name>` switches it
+                if let Some(name) = input
+                    .strip_prefix("/model")
+                    .filter(|rest| rest.is_empty() || rest.starts_with(' '))
+                {
+                    self.switch_model(client, name.trim()).await;
+                    continue;
+                }
+
+                if input.starts_with("/summarize") {
+                    if let Err(e) = self.handle_summarize(input, client, &options).await {
+                        println!(
+                            "{}",
+                            status_line(quiet, Marker::Error, &format!("Summarize failed: {e}"))
+                        );
                     }
                     continue;
                 }
 
                 // Handle regular commands
-                if let Err(e) = self.handle_command(input).await {
-                    println!("❌ Command error: {e}");
+                if let Err(e) = self
+                    .handle_command(input, options.sessions_dir.as_deref())
+                    .await
+                {
+                    println!(
+                        "{}",
+                        status_line(quiet, Marker::Error, &format!("Command error: {e}"))
+                    );
                 }
                 continue;
             }
 
+            let substituted = substitute_variables(input, &self.variables);
+            let input = substituted.as_str();
+
             // Process agent tools if enabled
             if let Ok(Some(tool_result)) =
                 agent_commands::process_agent_tools(input, &mut agent).await
             {
                 // If agent tools were executed, include their results in the conversation
-                let enhanced_message = format!("{input}\n\nAgent tool results:\n{tool_result}");
+                let enhanced_message = format!("{input}{AGENT_RESULTS_SEPARATOR}{tool_result}");
 
                 // Add user message and tool results to history
-                self.add_message(Content::user(enhanced_message.clone()));
+                let message = self.user_message(enhanced_message.clone());
+                self.add_message(message);
 
                 // Continue with AI response using the enhanced message
                 // Show thinking indicator
-                let spinner = ProgressBar::new_spinner();
-                spinner.set_style(
-                    ProgressStyle::default_spinner()
-                        .template("{spinner:.green} {msg}")
-                        .unwrap()
-                        .tick_strings(&["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"]),
-                );
-                spinner.set_message(format!("{} is thinking...", self.model_label()));
-                spinner.enable_steady_tick(std::time::Duration::from_millis(100));
+                let spinner =
+                    thinking_spinner(quiet, self.model_label(), options.long_request_hint);
 
                 // Send enhanced message to AI
                 match self
-                    .send_ai_response(client, &spinner, agent.as_mut())
+                    .send_ai_response(client, spinner.as_ref(), agent.as_mut(), &options)
                     .await
                 {
                     Ok(response) => {
                         recent_messages.push(response);
                     }
                     Err(e) => {
-                        println!("❌ AI response failed: {e}");
+                        println!(
+                            "{}",
+                            status_line(quiet, Marker::Error, &format!("AI response failed: {e}"))
+                        );
                         continue;
                     }
                 }
             } else {
                 // Regular message without agent tools
-                self.add_message(Content::user(input.to_string()));
+                let message = self.user_message(input.to_string());
+                self.add_message(message);
                 recent_messages.push(input.to_string());
 
                 // Show thinking indicator
-                let spinner = ProgressBar::new_spinner();
-                spinner.set_style(
-                    ProgressStyle::default_spinner()
-                        .template("{spinner:.green} {msg}")
-                        .unwrap()
-                        .tick_strings(&["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"]),
-                );
-                spinner.set_message(format!("{} is thinking...", self.model_label()));
-                spinner.enable_steady_tick(std::time::Duration::from_millis(100));
+                let spinner =
+                    thinking_spinner(quiet, self.model_label(), options.long_request_hint);
 
                 // Send regular message to AI
                 match self
-                    .send_ai_response(client, &spinner, agent.as_mut())
+                    .send_ai_response(client, spinner.as_ref(), agent.as_mut(), &options)
                     .await
                 {
                     Ok(response) => {
                         recent_messages.push(response);
                     }
                     Err(e) => {
-                        println!("❌ AI response failed: {e}");
+                        println!(
+                            "{}",
+                            status_line(quiet, Marker::Error, &format!("AI response failed: {e}"))
+                        );
                         continue;
                     }
                 }
             }
 
+            if let Some(max_history) = options.max_history {
+                self.trim_history(max_history);
+            }
+
             // Keep only recent messages for completion detection
             if recent_messages.len() > 10 {
                 recent_messages.drain(0..recent_messages.len() - 10);
@@ -366,10 +1288,10 @@ impl ChatSession {
 
             // Check for task completion
             if let Some((status, confidence, patterns)) =
-                agent_commands::check_task_completion(&recent_messages, &agent)
+                agent_commands::check_task_completion(&recent_messages, &agent).filter(|_| !quiet)
             {
                 println!(
-                    "\n🎉 {} Task appears to be complete! The agent has finished the requested work.",
+                    "\n{}{} Task appears to be complete! The agent has finished the requested work.", icon(Marker::Done),
                     "AGENT:".bright_green().bold()
                 );
                 println!("   {}", status.description());
@@ -377,26 +1299,46 @@ impl ChatSession {
                 if !patterns.is_empty() {
                     println!("   Matching patterns:");
                     for pattern in patterns {
-                        println!("      • {}", pattern);
+                        println!("      {}{}", icon(Marker::Bullet), pattern);
                     }
                 }
                 println!("   You can continue the conversation or type 'exit' to quit.");
             }
 
+            if options.auto_title && self.title.is_none() {
+                if let Err(e) = self.generate_title(client).await {
+                    tracing::debug!(error = %e, "Title generation failed");
+                }
+            }
+
             // Auto-save if enabled
-            if auto_save {
-                let filename = format!("session_{}.json", self.id);
-                let path = if let Some(ref dir) = sessions_dir {
+            if options.auto_save {
+                let filename = session::auto_save_file_name(&self.id, self.title.as_deref());
+                let path = if let Some(ref dir) = options.sessions_dir {
                     if let Err(e) = fs::create_dir_all(dir) {
-                        println!("⚠️  Failed to ensure sessions directory exists: {e}");
+                        println!(
+                            "{}",
+                            status_line(
+                                quiet,
+                                Marker::Warning,
+                                &format!("Failed to ensure sessions directory exists: {e}")
+                            )
+                        );
                     }
                     dir.join(filename)
                 } else {
                     PathBuf::from(&filename)
                 };
 
-                if let Err(e) = self.save_to_file(&path).await {
-                    println!("⚠️  Failed to auto-save session: {e}");
+                if let Err(e) = self.auto_save_to(&path).await {
+                    println!(
+                        "{}",
+                        status_line(
+                            quiet,
+                            Marker::Warning,
+                            &format!("Failed to auto-save session: {e}")
+                        )
+                    );
                 }
             }
         }
@@ -404,95 +1346,232 @@ impl ChatSession {
         Ok(())
     }
 
-    /// Display welcome message
-    fn display_welcome(&self) {
-        println!(
+    /// Handle the /summarize command, backing up the session before compressing it
+    async fn handle_summarize(
+        &mut self,
+        input: &str,
+        client: &LlmClient,
+        options: &ChatOptions,
+    ) -> Result<()> {
+        let args = input.strip_prefix("/summarize").unwrap_or("").trim();
+        let keep_last = if args.is_empty() {
+            options.summary_keep_turns
+        } else {
+            args.parse::<usize>()
+                .map_err(|_| anyhow!("Usage: /summarize [messages to keep]"))?
+        };
+
+        if self.summary_split_index(keep_last).is_none() {
+            println!("{}Nothing to summarize yet", icon(Marker::Info));
+            return Ok(());
+        }
+
+        let backup_name = session::summary_backup_file_name(
+            &self.id,
+            &Utc::now().format("%Y%m%d%H%M%S").to_string(),
+        );
+        let backup_path = self
+            .write_backup(options.sessions_dir.as_deref(), backup_name)
+            .await?;
+
+        let before = self.history.len();
+        if self.summarize(client, keep_last).await?.is_some() {
+            println!(
+                "{}Summarized {} messages into 1 (kept the last {})",
+                icon(Marker::Summary),
+                before - (self.history.len() - 1),
+                self.history.len() - 1
+            );
+            println!(
+                "{}Original session backed up to {}",
+                icon(Marker::Saved),
+                backup_path.display()
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Display welcome message
+    fn display_welcome(&self) {
+        println!(
             "{}",
-            format!("🤖 Chatter - {} AI Chat", self.model_label())
-                .bright_cyan()
-                .bold()
+            format!(
+                "{}Chatter - {} AI Chat",
+                icon(Marker::Agent),
+                self.model_label()
+            )
+            .bright_cyan()
+            .bold()
         );
         println!(
             "Model: {} | Provider: {} | Session: {}",
             self.model.bright_yellow(),
             self.model_label().bright_cyan(),
-            self.id[..8].bright_magenta()
+            self.short_id().bright_magenta()
         );
 
         if let Some(ref instruction) = self.system_instruction {
             println!("System: {}", instruction.bright_white());
         }
 
-        println!("{}", "─".repeat(60).bright_black());
+        println!("{}", theme::rule(60).bright_black());
         println!("Type 'exit' to quit, '/help' for commands");
 
         // Show conversation history if any
         if !self.history.is_empty() {
-            println!("\n{}", "📜 Previous conversation:".bright_white().bold());
+            println!(
+                "\n{}{}",
+                icon(Marker::History),
+                "Previous conversation:".bright_white().bold()
+            );
             for content in &self.history {
                 self.display_message(content);
             }
         }
     }
 
+    /// Label shown before a message in the history
+    ///
+    /// Model messages use the provider that produced them, falling back to the
+    /// session's provider for messages saved before providers were recorded.
+    fn message_label(&self, content: &Content) -> String {
+        match content.role.as_str() {
+            "user" => "You:".to_string(),
+            "model" => format!("{}:", self.producer_label(content.provider.as_ref())),
+            _ => "System:".to_string(),
+        }
+    }
+
     /// Display a single message
     fn display_message(&self, content: &Content) {
-        let (prefix, color) = match content.role.as_str() {
-            "user" => ("You:", "bright_blue"),
-            "model" => ("Gemini:", "bright_green"),
-            _ => ("System:", "bright_yellow"),
-        };
-
-        if let Some(part) = content.parts.first() {
-            match color {
-                "bright_blue" => println!("\n{} {}", prefix.bright_blue().bold(), part.text),
-                "bright_green" => println!("\n{} {}", prefix.bright_green().bold(), part.text),
-                _ => println!("\n{} {}", prefix.bright_yellow().bold(), part.text),
+        if !content.parts.is_empty() {
+            let prefix = self.message_label(content);
+            let text = content.joined_text();
+            match content.role.as_str() {
+                "user" => println!("\n{} {}", prefix.bright_blue().bold(), text),
+                "model" => println!("\n{} {}", prefix.bright_green().bold(), text),
+                _ => println!("\n{} {}", prefix.bright_yellow().bold(), text),
             }
         }
     }
 
     /// Handle special commands
-    async fn handle_command(&mut self, command: &str) -> Result<()> {
+    async fn handle_command(&mut self, command: &str, sessions_dir: Option<&Path>) -> Result<()> {
         let parts: Vec<&str> = command.splitn(2, ' ').collect();
         let cmd = parts[0];
         let args = parts.get(1).unwrap_or(&"");
 
         match cmd {
             "/help" => {
-                println!("📋 Available commands:");
+                println!("{}Available commands:", icon(Marker::List));
                 println!("  /help                    - Show this help");
                 println!("  /clear                   - Clear conversation history");
-                println!("  /save <file>             - Save session to file");
+                println!("  /summarize [n]           - Summarize all but the last n messages");
+                println!(
+                    "  /tokens                  - Estimate context usage for the current model"
+                );
+                println!("  /save [file] [--force]   - Save session (auto-named if no file given)");
+                println!("  /compact                 - Collapse tool calls and results in history (also /save --compact)");
                 println!("  /load <file>             - Load session from file");
+                println!(
+                    "  /fork <file>             - Save session to file and continue on a branch"
+                );
+                println!(
+                    "  /edit                    - Edit the last message in $EDITOR and resend"
+                );
                 println!("  /model <name>            - Switch model");
+                println!("  /models                  - List models available from the provider");
+                println!("  /provider [name] [model] - Show or switch provider (gemini, ollama, anthropic)");
                 println!("  /system <text>           - Set system instruction");
-                println!("  /template <name>         - Use template as system instruction");
+                println!("  /image <path>            - Attach an image to the next message");
+                println!("  /context <path|glob>     - Add file contents to the conversation");
+                println!("  /suggest-template        - Suggest templates for recent messages");
+                println!("  /template [name]         - Use template as system instruction (menu if no name)");
                 println!("  /templates               - List available templates");
                 println!(
                     "  /save-template <name>    - Save current system instruction as template"
                 );
+                println!(
+                    "  /set <name>=<value>      - Set a variable used as {{name}} in messages"
+                );
+                println!("  /unset <name>            - Remove a variable");
+                println!("  /vars                    - List session variables");
+                println!("  /title [text]            - Show or set the session title");
+                println!("  /note <text>             - Add a note to the session (not sent to the model)");
+                println!(
+                    "  /notes                   - List session notes (/note clear removes them)"
+                );
                 println!("  /history                 - Show conversation history");
+                println!(
+                    "  /find <text>             - Find messages containing text (ignores case)"
+                );
+                println!("  /goto <turn>             - Show a turn found with /find");
                 println!("  /info                    - Show session info");
+                println!("  /whoami                  - Show the provider settings in effect and their source");
             }
             "/template" => {
-                if args.is_empty() {
-                    println!("Usage: /template <name>");
+                // Load template manager
+                let manager = crate::templates::TemplateManager::new().await?;
+                let template = if args.is_empty() {
+                    manager.select_interactive()?
+                } else {
+                    manager.get(args)
+                };
+
+                if let Some(template) = template {
+                    self.apply_template(template);
+                    println!(
+                        "{}Applied template: {} - {}",
+                        icon(Marker::Template),
+                        template.name.bright_green(),
+                        template.description
+                    );
+                    if !template.examples.is_empty() {
+                        println!(
+                            "   Added {} example exchange(s) to the start of the conversation",
+                            template.examples.len()
+                        );
+                    }
+                } else if !args.is_empty() {
+                    println!("{}{}", icon(Marker::Error), manager.not_found_message(args));
+                }
+            }
+            "/suggest-template" => {
+                // Base the suggestion on the most recent user messages
+                let recent: Vec<&str> = self
+                    .history
+                    .iter()
+                    .rev()
+                    .filter(|content| content.role == "user")
+                    .take(3)
+                    .flat_map(|content| content.parts.iter().map(|p| p.text.as_str()))
+                    .collect();
+
+                if recent.is_empty() {
+                    println!(
+                        "{}Send a message first, then ask for a template suggestion",
+                        icon(Marker::Chat)
+                    );
                     return Ok(());
                 }
 
-                // Load template manager
                 let manager = crate::templates::TemplateManager::new().await?;
-                if let Some(template) = manager.get(args) {
-                    self.system_instruction = Some(template.content.clone());
+                let suggestions = manager.recommend(&recent.join(" "));
+                if suggestions.is_empty() {
+                    println!("{}No matching templates found", icon(Marker::Empty));
+                    return Ok(());
+                }
+
+                println!("{}Suggested templates:", icon(Marker::Hint));
+                for template in suggestions.into_iter().take(3) {
                     println!(
-                        "📝 Applied template: {} - {}",
+                        "  {} - {}",
                         template.name.bright_green(),
                         template.description
                     );
-                } else {
-                    println!("❌ Template '{args}' not found");
                 }
+                println!("Apply one with /template <name>");
             }
             "/templates" => {
                 // Load template manager and list templates
@@ -500,11 +1579,11 @@ impl ChatSession {
                 let templates = manager.list_all();
 
                 if templates.is_empty() {
-                    println!("📭 No templates available");
+                    println!("{}No templates available", icon(Marker::Empty));
                     return Ok(());
                 }
 
-                println!("📋 Available Templates:");
+                println!("{}Available Templates:", icon(Marker::List));
 
                 // Group by category
                 let mut by_category: std::collections::HashMap<String, Vec<_>> =
@@ -536,21 +1615,158 @@ impl ChatSession {
             }
             "/clear" => {
                 self.history.clear();
-                println!("🗑️  Conversation history cleared");
+                println!("{}Conversation history cleared", icon(Marker::Cleared));
+            }
+            "/compact" => {
+                if !self.history.iter().any(|content| content.role == "tool") {
+                    println!("{}No tool results to compact", icon(Marker::Info));
+                    return Ok(());
+                }
+
+                let backup_name = session::compact_backup_file_name(
+                    &self.id,
+                    &Utc::now().format("%Y%m%d%H%M%S").to_string(),
+                );
+                let backup_path = self.write_backup(sessions_dir, backup_name).await?;
+                let removed = self.compact_tool_turns();
+                println!(
+                    "{}Compacted tool activity: {} messages removed, {} remain",
+                    icon(Marker::Summary),
+                    removed,
+                    self.history.len()
+                );
+                println!(
+                    "{}Full history backed up to {}",
+                    icon(Marker::Saved),
+                    backup_path.display()
+                );
             }
             "/save" => {
+                let (args, compact) = session::take_flag(args, "--compact");
+                let (name, force) = session::parse_save_args(&args);
+                let path = match name {
+                    Some(name) => session::resolve_save_path(name, sessions_dir),
+                    None => {
+                        let name = session::generated_save_file_name(
+                            &self.id,
+                            &Utc::now().format("%Y%m%d%H%M%S").to_string(),
+                        );
+                        session::resolve_save_path(&name, sessions_dir)
+                    }
+                };
+
+                if path.exists() && !force {
+                    let overwrite = dialoguer::Confirm::new()
+                        .with_prompt(format!("{} exists. Overwrite?", path.display()))
+                        .default(false)
+                        .interact()?;
+                    if !overwrite {
+                        println!("Save cancelled");
+                        return Ok(());
+                    }
+                }
+
+                if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                    fs::create_dir_all(parent)?;
+                }
+                if compact {
+                    let mut copy = self.clone();
+                    copy.compact_tool_turns();
+                    copy.save_to_file(&path).await?;
+                } else {
+                    self.save_to_file(&path).await?;
+                }
+                println!("{}Session saved to {}", icon(Marker::Saved), path.display());
+            }
+            "/fork" => {
                 if args.is_empty() {
-                    return Err(anyhow!("Please specify a filename"));
+                    println!("Usage: /fork <file>");
+                    return Ok(());
                 }
                 self.save_to_file(args).await?;
-                println!("💾 Session saved to {args}");
+                let branch = self.fork();
+                println!("{}Session saved to {args}", icon(Marker::Saved));
+                println!(
+                    "{}Forked session {} from {}",
+                    icon(Marker::Fork),
+                    branch.short_id().bright_magenta(),
+                    self.short_id().bright_magenta()
+                );
+                *self = branch;
             }
-            "/model" => {
+            "/image" => {
                 if args.is_empty() {
-                    println!("Current model: {}", self.model);
+                    if self.pending_attachments.is_empty() {
+                        println!("Usage: /image <path>");
+                    } else {
+                        println!(
+                            "{}{} image(s) will be sent with your next message",
+                            icon(Marker::Attach),
+                            self.pending_attachments.len()
+                        );
+                    }
                 } else {
-                    self.model = args.to_string();
-                    println!("🔄 Switched to model: {}", self.model);
+                    let part = attachments::load_image(Path::new(args))?;
+                    self.pending_attachments.push(part);
+                    println!(
+                        "{}Attached {args} to your next message",
+                        icon(Marker::Attach)
+                    );
+                    if self.provider != ModelProvider::Gemini {
+                        println!(
+                            "{}Images are only sent to Gemini; {} will ignore them",
+                            icon(Marker::Warning),
+                            self.model_label()
+                        );
+                    }
+                }
+            }
+            "/context" => {
+                if args.is_empty() {
+                    println!("Usage: /context <path|glob>  (e.g. /context src/*.rs)");
+                    return Ok(());
+                }
+
+                let paths = attachments::expand_context_paths(args.trim())?;
+                let files =
+                    attachments::load_context_files(&paths, attachments::MAX_CONTEXT_FILE_BYTES);
+                for (path, reason) in &files.skipped {
+                    println!(
+                        "{}Skipped {}: {}",
+                        icon(Marker::Warning),
+                        path.display(),
+                        reason
+                    );
+                }
+                if files.attached.is_empty() {
+                    println!("{}No files were attached", icon(Marker::Empty));
+                    return Ok(());
+                }
+
+                let used = history::estimate_history_tokens(
+                    &self.history,
+                    self.system_instruction.as_deref(),
+                ) + history::estimate_tokens(&files.text);
+                let window = crate::api::models::context_window(&self.model);
+                if used > window {
+                    println!(
+                        "{}Not attached: ~{used} tokens would exceed the {window} token context of {}", icon(Marker::Error),
+                        self.model
+                    );
+                    return Ok(());
+                }
+
+                self.add_message(Content::user(files.text));
+                println!(
+                    "{}Added {} file(s) to the conversation",
+                    icon(Marker::Attach),
+                    files.attached.len()
+                );
+                let percent = used as f64 / window as f64 * 100.0;
+                if percent >= 80.0 {
+                    println!(
+                        "{}Context is now ~{used} / {window} tokens ({percent:.1}%); consider /summarize", icon(Marker::Warning)
+                    );
                 }
             }
             "/system" => {
@@ -561,14 +1777,54 @@ impl ChatSession {
                     }
                 } else {
                     self.system_instruction = Some(args.to_string());
-                    println!("⚙️  System instruction updated");
+                    println!("{}System instruction updated", icon(Marker::Settings));
                 }
             }
+            "/find" => {
+                let query = args.trim();
+                if query.is_empty() {
+                    println!("Usage: /find <text>");
+                    return Ok(());
+                }
+                let found = replay::find_in_history(&self.history, query);
+                if found.is_empty() {
+                    println!("{}No messages contain '{}'", icon(Marker::Empty), query);
+                    return Ok(());
+                }
+
+                println!(
+                    "{}{} message(s) contain '{}':",
+                    icon(Marker::Search),
+                    found.len(),
+                    query
+                );
+                for HistoryMatch { turn, index, range } in found {
+                    let content = &self.history[index];
+                    println!(
+                        "  [turn {}] {} {}",
+                        turn,
+                        self.message_label(content).bright_blue(),
+                        replay::snippet(&content.joined_text(), range)
+                    );
+                }
+                println!("Use /goto <turn> to show a turn in full");
+            }
+            "/goto" => {
+                let turn = args
+                    .trim()
+                    .parse()
+                    .map_err(|_| anyhow!("Usage: /goto <turn>"))?;
+                replay::show_turn(self, turn)?;
+            }
             "/history" => {
                 if self.history.is_empty() {
-                    println!("📭 No conversation history");
+                    println!("{}No conversation history", icon(Marker::Empty));
                 } else {
-                    println!("📜 Conversation history ({} messages):", self.history.len());
+                    println!(
+                        "{}Conversation history ({} messages):",
+                        icon(Marker::History),
+                        self.history.len()
+                    );
                     for content in &self.history {
                         self.display_message(content);
                     }
@@ -618,19 +1874,116 @@ impl ChatSession {
                     let mut manager = crate::templates::TemplateManager::new().await?;
                     match manager.create(template).await {
                         Ok(()) => {
-                            println!("✅ Template '{args}' saved successfully!");
+                            println!(
+                                "{}Template '{args}' saved successfully!",
+                                icon(Marker::Success)
+                            );
                         }
                         Err(e) => {
-                            println!("❌ Failed to save template: {e}");
+                            println!("{}Failed to save template: {e}", icon(Marker::Error));
                         }
                     }
                 } else {
-                    println!("❌ No system instruction set. Use /system <text> first.");
+                    println!(
+                        "{}No system instruction set. Use /system <text> first.",
+                        icon(Marker::Error)
+                    );
+                }
+            }
+            "/tokens" => {
+                let used = history::estimate_history_tokens(
+                    &self.history,
+                    self.system_instruction.as_deref(),
+                );
+                let window = crate::api::models::context_window(&self.model);
+                let percent = used as f64 / window as f64 * 100.0;
+                let usage = format!("~{used} / {window} tokens ({percent:.1}%)");
+
+                println!(
+                    "{}Estimated context usage for {}:",
+                    icon(Marker::Stats),
+                    self.model
+                );
+                if percent >= 80.0 {
+                    println!("  {}", usage.bright_red().bold());
+                    println!("  Consider /summarize or /clear before sending large messages");
+                } else {
+                    println!("  {}", usage.bright_green());
+                }
+            }
+            "/set" => {
+                let name = self.set_variable(args)?;
+                println!(
+                    "{}Set {} = {}",
+                    icon(Marker::Success),
+                    name.bright_green(),
+                    self.variables[&name]
+                );
+            }
+            "/unset" => {
+                if self.variables.remove(args.trim()).is_some() {
+                    self.updated_at = Utc::now();
+                    println!("{}Removed variable {}", icon(Marker::Cleared), args.trim());
+                } else {
+                    println!(
+                        "{}No variable named '{}'",
+                        icon(Marker::Warning),
+                        args.trim()
+                    );
+                }
+            }
+            "/title" => {
+                if !args.trim().is_empty() {
+                    self.set_title(args);
+                }
+                match &self.title {
+                    Some(title) => {
+                        println!("{}Title: {}", icon(Marker::Info), title.bright_white())
+                    }
+                    None => println!("{}No title set. Use /title <text>", icon(Marker::Empty)),
+                }
+            }
+            "/note" => {
+                if args.trim() == "clear" {
+                    let removed = self.clear_notes();
+                    println!("{}Removed {} note(s)", icon(Marker::Cleared), removed);
+                } else {
+                    let count = self.add_note(args)?;
+                    println!("{}Added note #{}", icon(Marker::Success), count);
+                }
+            }
+            "/notes" => {
+                if self.notes.is_empty() {
+                    println!("{}No notes yet. Use /note <text>", icon(Marker::Empty));
+                } else {
+                    println!("{}Session notes:", icon(Marker::List));
+                    for line in self.note_lines() {
+                        println!("  {line}");
+                    }
+                }
+            }
+            "/vars" => {
+                if self.variables.is_empty() {
+                    println!(
+                        "{}No variables set. Use /set name=value",
+                        icon(Marker::Empty)
+                    );
+                } else {
+                    println!("{}Session variables:", icon(Marker::List));
+                    for line in self.variable_lines() {
+                        println!("  {line}");
+                    }
                 }
             }
             "/info" => {
-                println!("📊 Session Information:");
+                println!("{}Session Information:", icon(Marker::Stats));
                 println!("  ID: {}", self.id);
+                if let Some(ref title) = self.title {
+                    println!("  Title: {title}");
+                }
+                if let Some(ref parent_id) = self.parent_id {
+                    println!("  Forked from: {parent_id}");
+                }
                 println!("  Model: {}", self.model);
                 println!("  Messages: {}", self.history.len());
                 println!(
@@ -641,6 +1994,12 @@ impl ChatSession {
                     "  Updated: {}",
                     self.updated_at.format("%Y-%m-%d %H:%M:%S UTC")
                 );
+                if !self.notes.is_empty() {
+                    println!("  Notes:");
+                    for line in self.note_lines() {
+                        println!("    {line}");
+                    }
+                }
             }
             _ => {
                 return Err(anyhow!(
@@ -657,100 +2016,377 @@ impl ChatSession {
     async fn send_ai_response(
         &mut self,
         client: &LlmClient,
-        spinner: &ProgressBar,
+        spinner: Option<&ProgressBar>,
         agent: Option<&mut Agent>,
+        options: &ChatOptions,
     ) -> Result<String> {
-        match self.provider {
-            ModelProvider::Gemini => {
-                // Streaming path for Gemini
-                match client
-                    .generate_stream(
-                        &self.model,
-                        &self.history,
-                        self.system_instruction.as_deref(),
-                    )
-                    .await
-                {
-                    Ok(mut stream) => {
-                        spinner.finish_and_clear();
-                        print!("\n{} ", self.model_label().bright_green().bold());
-                        io::stdout().flush()?;
-
-                        let mut full_response = String::new();
-                        let mut stream_failed = false;
-
-                        while let Some(chunk_result) = stream.next().await {
-                            match chunk_result {
-                                Ok(chunk) => {
-                                    print!("{chunk}");
-                                    io::stdout().flush()?;
-                                    full_response.push_str(&chunk);
-                                }
-                                Err(e) => {
-                                    println!("\n⚠️  Stream error: {e}");
-                                    println!("🔄 Falling back to non-streaming mode...");
-                                    stream_failed = true;
-                                    break;
-                                }
-                            }
-                        }
+        let quiet = options.quiet;
+        let fallback = self.fallback.clone();
+        let client = self.active_client(client, &fallback);
+        let clear_spinner = || {
+            if let Some(spinner) = spinner {
+                spinner.finish_and_clear();
+            }
+        };
+
+        // Tool calls arrive only on the non-streaming path, so stay there while tools are offered
+        let tools_active = self.provider.supports_tools()
+            && agent.as_ref().is_some_and(|agent| agent.is_enabled());
+        let stream = options.stream && self.provider.supports_streaming() && !tools_active;
 
-                        if stream_failed {
-                            let interaction = self.run_model_interaction(client, agent).await?;
+        let tee = options.tee.as_deref();
+        if stream {
+            let prefix = self.response_prefix(quiet);
+            let delay = if quiet {
+                Duration::ZERO
+            } else {
+                options.stream_delay
+            };
+            let mut started = false;
+            let mut print_chunk = |chunk: &str| {
+                if !started {
+                    clear_spinner();
+                    print!("{prefix}");
+                    started = true;
+                }
+                print!("{chunk}");
+                let _ = io::Write::flush(&mut io::stdout());
+                if let Some(tee) = tee {
+                    tee.write_chunk(chunk);
+                }
+            };
+
+            match self.stream_reply(client, delay, &mut print_chunk).await {
+                Ok((full_response, error)) => {
+                    clear_spinner();
+                    if !started {
+                        print!("{prefix}");
+                    }
+
+                    let stream_failed = error.is_some();
+                    if let Some(e) = error {
+                        println!();
+                        println!(
+                            "{}",
+                            status_line(quiet, Marker::Warning, &format!("Stream error: {e}"))
+                        );
+                        if let Some(tee) = tee.filter(|_| !full_response.is_empty()) {
+                            tee.note_interrupted(&e);
                             println!(
-                                "\n{} {}",
-                                self.model_label().bright_green().bold(),
-                                interaction.response_text
+                                "{}",
+                                status_line(
+                                    quiet,
+                                    Marker::Info,
+                                    &format!(
+                                        "The partial reply in {} is marked as incomplete",
+                                        tee.path().display()
+                                    )
+                                )
                             );
-                            Ok(interaction.response_text)
-                        } else {
-                            if !full_response.is_empty() {
-                                self.add_message(Content::model(full_response.clone()));
+                        }
+                        println!(
+                            "{}",
+                            status_line(
+                                quiet,
+                                Marker::Switch,
+                                "Falling back to non-streaming mode..."
+                            )
+                        );
+                    }
+
+                    if stream_failed && options.resume_on_stream_drop && !full_response.is_empty() {
+                        println!(
+                            "{}",
+                            status_line(
+                                quiet,
+                                Marker::Switch,
+                                "Resuming from the partial response..."
+                            )
+                        );
+                        match self.resume_partial_response(client, &full_response).await {
+                            Ok(full) => {
+                                match full.strip_prefix(full_response.as_str()) {
+                                    Some(rest) => {
+                                        println!("{}…{}", self.response_prefix(quiet), rest)
+                                    }
+                                    None => println!("{}{}", self.response_prefix(quiet), full),
+                                }
+                                if let Some(tee) = tee {
+                                    tee.write_reply(&full);
+                                }
+                                return Ok(full);
                             }
-                            println!();
-                            Ok(full_response)
+                            Err(e) => println!(
+                                "{}",
+                                status_line(
+                                    quiet,
+                                    Marker::Warning,
+                                    &format!("Resuming failed: {e}")
+                                )
+                            ),
                         }
                     }
-                    Err(e) => {
-                        spinner.finish_and_clear();
-                        println!("⚠️  Streaming failed: {e}");
-                        println!("🔄 Trying non-streaming mode...");
+
+                    if stream_failed {
                         let interaction = self.run_model_interaction(client, agent).await?;
+                        print_thinking(&interaction, options);
                         println!(
-                            "\n{} {}",
-                            self.model_label().bright_green().bold(),
+                            "{}{}",
+                            self.response_prefix(quiet),
                             interaction.response_text
                         );
+                        if let Some(tee) = tee {
+                            tee.write_reply(&interaction.response_text);
+                        }
                         Ok(interaction.response_text)
+                    } else {
+                        if !full_response.is_empty() {
+                            self.add_message(Content::model(full_response.clone()));
+                        }
+                        if let Some(tee) = tee {
+                            tee.end_reply();
+                        }
+                        println!();
+                        Ok(full_response)
+                    }
+                }
+                Err(e) => {
+                    clear_spinner();
+                    println!(
+                        "{}",
+                        status_line(quiet, Marker::Warning, &format!("Streaming failed: {e}"))
+                    );
+                    println!(
+                        "{}",
+                        status_line(quiet, Marker::Switch, "Trying non-streaming mode...")
+                    );
+                    let interaction = self.run_model_interaction(client, agent).await?;
+                    print_thinking(&interaction, options);
+                    println!(
+                        "{}{}",
+                        self.response_prefix(quiet),
+                        interaction.response_text
+                    );
+                    if let Some(tee) = tee {
+                        tee.write_reply(&interaction.response_text);
                     }
+                    Ok(interaction.response_text)
                 }
             }
-            ModelProvider::Ollama => {
-                spinner.finish_and_clear();
-                let interaction = self.run_model_interaction(client, agent).await?;
+        } else {
+            clear_spinner();
+            let interaction = self.run_model_interaction(client, agent).await?;
 
-                for record in &interaction.tool_executions {
+            for record in &interaction.tool_executions {
+                if quiet {
                     let summary = format_tool_result(&record.tool_name, &record.result);
-                    println!("\n🔧 {} {}", "TOOL".bright_green().bold(), summary);
-                }
-
-                if !interaction.response_text.is_empty() {
+                    println!("TOOL {summary}");
+                } else {
                     println!(
-                        "\n{} {}",
-                        self.model_label().bright_green().bold(),
-                        interaction.response_text
+                        "\n{}{} {}",
+                        icon(Marker::Tool),
+                        "TOOL".bright_green().bold(),
+                        display_tool_result(&record.tool_name, &record.result)
                     );
                 }
+            }
 
-                Ok(interaction.response_text)
+            print_thinking(&interaction, options);
+            if !interaction.response_text.is_empty() {
+                println!(
+                    "{}{}",
+                    self.response_prefix(quiet),
+                    interaction.response_text
+                );
+                if let Some(tee) = tee {
+                    tee.write_reply(&interaction.response_text);
+                }
             }
+
+            Ok(interaction.response_text)
         }
     }
 
+    /// Ask the model to finish a reply that was cut off mid-stream
+    ///
+    /// The partial text is sent as the last model turn so the model continues it
+    /// rather than starting over. On success the history ends with one model
+    /// message holding the whole reply; on failure the partial turn is removed.
+    async fn resume_partial_response(
+        &mut self,
+        client: &LlmClient,
+        partial: &str,
+    ) -> Result<String> {
+        self.add_message(Content::model(partial.to_string()));
+        let response = client
+            .generate(
+                &self.model,
+                &self.history,
+                self.system_instruction.as_deref(),
+                &[],
+            )
+            .await;
+
+        match response {
+            Ok(response) => {
+                let full = join_continuation(partial, &response.message.joined_text());
+                self.history.pop();
+                self.add_message(Content::model(full.clone()));
+                Ok(full)
+            }
+            Err(e) => {
+                self.history.pop();
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Switch to another model, warning when the provider is unlikely to know it
+    ///
+    /// An empty name shows the current model instead.
+    async fn switch_model(&mut self, client: &LlmClient, name: &str) {
+        if name.is_empty() {
+            println!("Current model: {}", self.model);
+            return;
+        }
+
+        self.model = name.to_string();
+        println!("{}Switched to model: {}", icon(Marker::Switch), self.model);
+
+        let fallback = self.fallback.clone();
+        let client = self.active_client(client, &fallback);
+        if let Some(warning) = client.model_warning(&self.provider, &self.model).await {
+            println!("{}{warning}", icon(Marker::Warning));
+        }
+    }
+
+    /// `/provider [name] [model]`: show the provider, or switch to another one
+    ///
+    /// Returns the client for the new provider. Nothing changes when the client
+    /// cannot be built, e.g. because the provider's API key is missing.
+    fn handle_provider_command(
+        &mut self,
+        args: &str,
+        config: Option<&Config>,
+    ) -> Result<Option<LlmClient>> {
+        if args.is_empty() {
+            println!("Current provider: {} ({})", self.model_label(), self.model);
+            return Ok(None);
+        }
+
+        let config = config.ok_or_else(|| anyhow!("Switching providers is not available here"))?;
+        let client = self.switch_provider(config, args)?;
+        println!(
+            "{}Switched to {} with model {}",
+            icon(Marker::Switch),
+            self.model_label(),
+            self.model
+        );
+        Ok(Some(client))
+    }
+
+    /// Move the session to another provider, given as `name [model]`
+    ///
+    /// The model defaults to the provider's configured default, and any fallback is
+    /// rebuilt for the new provider.
+    fn switch_provider(&mut self, config: &Config, args: &str) -> Result<LlmClient> {
+        let mut words = args.split_whitespace();
+        let provider: ModelProvider = words.next().unwrap_or_default().parse()?;
+        let model = words
+            .next()
+            .map(str::to_string)
+            .unwrap_or_else(|| config.default_model_for(&provider).to_string());
+
+        let client = LlmClient::from_config(config, &provider)?;
+        self.set_fallback(ProviderFallback::from_config(config, &provider));
+        self.using_fallback = false;
+        self.provider = provider;
+        self.model = model;
+        self.updated_at = Utc::now();
+        Ok(client)
+    }
+
+    /// Provider settings in effect, each with where it came from, for `/whoami`
+    fn whoami_lines(&self, options: &ChatOptions) -> Vec<String> {
+        // Anything that no longer matches the launch settings was changed in the chat
+        let launch = options
+            .launch
+            .as_ref()
+            .filter(|launch| launch.provider.value == self.provider);
+        let provider_source = launch.map_or(Source::Session, |launch| launch.provider.source);
+        let model_source = launch
+            .filter(|launch| launch.model.value == self.model)
+            .map_or(Source::Session, |launch| launch.model.source);
+
+        let mut lines = vec![
+            format!("Provider: {} ({})", self.model_label(), provider_source),
+            format!("Model: {} ({})", self.model, model_source),
+        ];
+        if let Some(config) = &options.provider_config {
+            if let Some(endpoint) = config.resolve_endpoint(&self.provider) {
+                lines.push(format!(
+                    "Endpoint: {} ({})",
+                    endpoint.value, endpoint.source
+                ));
+            }
+            lines.push(match config.api_key_source(&self.provider) {
+                Some(source) => format!("API key: set ({source})"),
+                None if self.provider.requires_api_key() => "API key: not set".to_string(),
+                None => "API key: not needed".to_string(),
+            });
+        }
+        lines.push(format!(
+            "Temperature: provider default ({})",
+            Source::Default
+        ));
+        lines
+    }
+
+    /// Print the models offered by the current provider, marking the active one
+    async fn show_models(&self, client: &LlmClient) -> Result<()> {
+        let models = client.list_models().await?;
+        if models.is_empty() {
+            println!(
+                "{}No models reported by {}",
+                icon(Marker::Empty),
+                self.model_label()
+            );
+            return Ok(());
+        }
+
+        println!("{}{} models:", icon(Marker::List), self.model_label());
+        for model in models {
+            if model == self.model {
+                println!("  {} {}", model.bright_green(), "(current)".bright_black());
+            } else {
+                println!("  {model}");
+            }
+        }
+        Ok(())
+    }
+
+    /// The first eight characters of the session id, or all of a shorter id
+    ///
+    /// Loaded session files may carry hand-written ids of any length.
+    pub fn short_id(&self) -> &str {
+        self.id.get(..8).unwrap_or(&self.id)
+    }
+
     fn model_label(&self) -> &'static str {
-        match self.provider {
-            ModelProvider::Gemini => "Gemini",
-            ModelProvider::Ollama => "Ollama",
+        self.provider.display_name()
+    }
+
+    /// Name of the provider recorded on a model message, or the session's own
+    fn producer_label(&self, provider: Option<&ModelProvider>) -> &'static str {
+        provider.unwrap_or(&self.provider).display_name()
+    }
+
+    /// Text printed before a model response; quiet mode prints the response alone
+    fn response_prefix(&self, quiet: bool) -> String {
+        if quiet {
+            String::new()
+        } else {
+            format!("\n{} ", self.model_label().bright_green().bold())
         }
     }
 
@@ -760,6 +2396,112 @@ impl ChatSession {
         let result = self.run_model_interaction(client, None).await?;
         Ok(result.response_text)
     }
+
+    /// One-shot request that lets the model use agent tools
+    ///
+    /// Tool executions are summarised on stderr so stdout carries only the answer.
+    /// A tool call rejected by the safety checks fails the request, so scripts see
+    /// the safety exit code rather than an answer to a task that was not done.
+    pub async fn send_with_agent(
+        &mut self,
+        client: &LlmClient,
+        message: &str,
+        agent: &mut Agent,
+    ) -> Result<String> {
+        self.add_message(Content::user(message.to_string()));
+        let result = self.run_model_interaction(client, Some(agent)).await?;
+
+        for record in &result.tool_executions {
+            eprintln!(
+                "TOOL {}",
+                format_tool_result(&record.tool_name, &record.result)
+            );
+        }
+
+        if let Some(reason) = result.safety_violation {
+            return Err(ChatterError::SafetyViolation(reason).into());
+        }
+        Ok(result.response_text)
+    }
+
+    /// Send a message and pass each streamed chunk to `on_chunk`, returning the full response
+    ///
+    /// Providers without streaming support deliver the whole response as a single chunk.
+    pub async fn send_streaming<F: FnMut(&str)>(
+        &mut self,
+        client: &LlmClient,
+        message: &str,
+        mut on_chunk: F,
+    ) -> Result<String> {
+        self.add_message(Content::user(message.to_string()));
+
+        // Runs a second time at most, after switching to the fallback provider
+        loop {
+            if !self.provider.supports_streaming() {
+                let result = self.run_model_interaction(client, None).await?;
+                on_chunk(&result.response_text);
+                return Ok(result.response_text);
+            }
+
+            let fallback = self.fallback.clone();
+            let active = self.active_client(client, &fallback);
+            let attempt = self
+                .stream_reply(active, Duration::ZERO, &mut on_chunk)
+                .await;
+            // A refusal arrives before any text, either on connecting or as the first item
+            let refusal = match &attempt {
+                Err(e) => Some(e),
+                Ok((text, Some(e))) if text.is_empty() => Some(e),
+                _ => None,
+            };
+            if refusal
+                .and_then(|e| e.downcast_ref::<ChatterError>())
+                .is_some_and(|e| self.switch_to_fallback(e).is_some())
+            {
+                continue;
+            }
+
+            let (full_response, error) = attempt?;
+            if let Some(e) = error {
+                return Err(e);
+            }
+            self.add_message(Content::model(full_response.clone()));
+            return Ok(full_response);
+        }
+    }
+
+    /// Stream a reply to the current history, passing each chunk to `on_chunk`
+    ///
+    /// Returns the text received along with the error that cut the stream short, if
+    /// any, and fails only when the stream cannot be started. Nothing is printed and
+    /// the history is left unchanged. A non-zero `delay` types each chunk out one
+    /// character at a time.
+    async fn stream_reply<F: FnMut(&str)>(
+        &self,
+        client: &LlmClient,
+        delay: Duration,
+        on_chunk: &mut F,
+    ) -> Result<(String, Option<anyhow::Error>)> {
+        let mut stream = client
+            .generate_stream(
+                &self.model,
+                &self.history,
+                self.system_instruction.as_deref(),
+            )
+            .await?;
+
+        let mut full_response = String::new();
+        while let Some(chunk) = stream.next().await {
+            match chunk {
+                Ok(chunk) => {
+                    type_out(&chunk, delay, on_chunk).await;
+                    full_response.push_str(&chunk);
+                }
+                Err(e) => return Ok((full_response, Some(e))),
+            }
+        }
+        Ok((full_response, None))
+    }
 }
 
 fn convert_model_tool_call(call: &ModelToolCall) -> Result<ToolCall> {
@@ -793,6 +2535,53 @@ fn extract_argument_map(value: &Value) -> Result<HashMap<String, Value>> {
     }
 }
 
+/// One line describing a tool result message, e.g. `read_file: File: src/main.rs (120 bytes)`
+fn summarize_tool_message(content: &Content) -> String {
+    let name = content.name.as_deref().unwrap_or("tool");
+    let payload: Value = serde_json::from_str(&content.joined_text()).unwrap_or(Value::Null);
+    let Some(success) = payload.get("success").and_then(Value::as_bool) else {
+        return format!("{name}: (result omitted)");
+    };
+    let message = payload
+        .get("message")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    if !success {
+        return format!(
+            "{name}: failed - {}",
+            message.unwrap_or_else(|| "no details".to_string())
+        );
+    }
+
+    let result = ToolResult {
+        success,
+        data: payload.get("data").cloned().unwrap_or(Value::Null),
+        message,
+        modified_files: Vec::new(),
+    };
+    let formatted = format_tool_result(name, &result);
+    let first_line = formatted
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .replace("**", "");
+    format!("{name}: {}", first_line.trim())
+}
+
+/// Model message standing in for a collapsed run of tool calls
+fn compacted_tool_message(texts: &[String], lines: &[String]) -> Content {
+    let mut text = String::from("[Tool activity]");
+    for line in lines {
+        text.push_str("\n- ");
+        text.push_str(line);
+    }
+    for answer in texts.iter().filter(|t| !t.trim().is_empty()) {
+        text.push_str("\n\n");
+        text.push_str(answer);
+    }
+    Content::model(text)
+}
+
 fn build_tool_result_payload(tool_name: &str, result: &ToolResult) -> Value {
     let modified_files: Vec<Value> = result
         .modified_files
@@ -809,6 +2598,108 @@ fn build_tool_result_payload(tool_name: &str, result: &ToolResult) -> Value {
     })
 }
 
+/// Room left for the note appended to a truncated field
+const TRUNCATION_NOTE_BYTES: usize = 64;
+
+/// Strings shorter than this are never cut; a payload made of many of them is summarized
+const MIN_TRUNCATED_FIELD_BYTES: usize = 1024;
+
+/// Shorten the longest strings in a payload's `data` until it fits in `max_bytes`
+///
+/// Each shortened value ends with a note saying how much of it was kept, so the
+/// model can ask for the rest in smaller pieces. Small fields are left alone; when
+/// cutting the large ones is not enough, `data` is replaced by a summary of its shape.
+fn truncate_tool_payload(payload: &mut Value, max_bytes: usize) {
+    let mut size = payload.to_string().len();
+    if size <= max_bytes {
+        return;
+    }
+    payload["truncated"] = Value::Bool(true);
+    size += r#","truncated":true"#.len();
+
+    let mut strings = Vec::new();
+    collect_string_fields(&payload["data"], "/data".to_string(), &mut strings);
+    strings.sort_by_key(|(_, len)| std::cmp::Reverse(*len));
+
+    // Sizes are tracked per field, so the payload is serialized only once more at most
+    let encoded_len = |text: &str| Value::from(text).to_string().len();
+    for (pointer, len) in strings {
+        if size <= max_bytes || len < MIN_TRUNCATED_FIELD_BYTES {
+            break;
+        }
+        let Some(Value::String(text)) = payload.pointer_mut(&pointer) else {
+            continue;
+        };
+        let before = encoded_len(text);
+        let mut keep = len.saturating_sub(size - max_bytes + TRUNCATION_NOTE_BYTES);
+        while !text.is_char_boundary(keep) {
+            keep -= 1;
+        }
+        text.truncate(keep);
+        text.push_str(&format!(
+            "\n[content truncated; {keep} of {len} bytes shown]"
+        ));
+        size = size - before + encoded_len(text);
+    }
+
+    if size > max_bytes {
+        let original = payload["data"].to_string().len();
+        payload["data"] = summarize_data(&payload["data"], original);
+        if payload.to_string().len() > max_bytes {
+            payload["data"] = Value::from(format!(
+                "[{original} bytes of data omitted; request a smaller part]"
+            ));
+        }
+    }
+}
+
+/// Top-level fields of `data`, with long strings and lists reduced to their sizes
+fn summarize_data(data: &Value, original_bytes: usize) -> Value {
+    let shape = |value: &Value| match value {
+        Value::String(text) if text.len() > 256 => Value::from(format!("[{} bytes]", text.len())),
+        Value::Array(items) => Value::from(format!("[{} items]", items.len())),
+        Value::Object(map) => Value::from(format!("[object with {} fields]", map.len())),
+        scalar => scalar.clone(),
+    };
+    let mut summary = serde_json::Map::new();
+    summary.insert(
+        "note".to_string(),
+        Value::from(format!(
+            "{original_bytes} bytes of data were too large to include; request a smaller part"
+        )),
+    );
+    match data {
+        Value::Object(map) => {
+            for (key, value) in map {
+                summary.insert(key.clone(), shape(value));
+            }
+        }
+        other => {
+            summary.insert("value".to_string(), shape(other));
+        }
+    }
+    Value::Object(summary)
+}
+
+/// JSON pointers and byte lengths of every string inside `value`
+fn collect_string_fields(value: &Value, pointer: String, out: &mut Vec<(String, usize)>) {
+    match value {
+        Value::String(text) => out.push((pointer, text.len())),
+        Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                collect_string_fields(item, format!("{pointer}/{index}"), out);
+            }
+        }
+        Value::Object(map) => {
+            for (key, item) in map {
+                let escaped = key.replace('~', "~0").replace('/', "~1");
+                collect_string_fields(item, format!("{pointer}/{escaped}"), out);
+            }
+        }
+        _ => {}
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -823,6 +2714,521 @@ mod tests {
         }
     }
 
+    #[test]
+    fn fork_copies_history_without_sharing_it() {
+        let mut parent =
+            ChatSession::new("gemini-2.5-flash".to_string(), ModelProvider::Gemini, None);
+        parent.add_message(Content::user("Hello".to_string()));
+        parent.add_message(Content::model("Hi there".to_string()));
+
+        let mut branch = parent.fork();
+        assert_ne!(branch.id, parent.id);
+        assert_eq!(branch.parent_id.as_deref(), Some(parent.id.as_str()));
+        assert_eq!(branch.history.len(), 2);
+
+        branch.add_message(Content::user("Another direction".to_string()));
+        assert_eq!(branch.history.len(), 3);
+        assert_eq!(parent.history.len(), 2);
+        assert!(parent.parent_id.is_none());
+    }
+
+    #[test]
+    fn last_user_turn_skips_replies_and_agent_results() {
+        let mut session = ChatSession::new("test".to_string(), ModelProvider::Ollama, None);
+        assert!(session.last_user_turn().is_none());
+
+        session.add_message(Content::user("first".to_string()));
+        session.add_message(Content::model("reply".to_string()));
+        session.add_message(Content::user(format!(
+            "read it{AGENT_RESULTS_SEPARATOR}{{\"ok\": true}}"
+        )));
+        session.add_message(Content::model("done".to_string()));
+
+        let (index, text) = session.last_user_turn().unwrap();
+        assert_eq!(index, 2);
+        assert_eq!(text, "read it");
+    }
+
+    #[test]
+    fn apply_summary_keeps_tail_from_a_user_turn() {
+        let mut session = ChatSession::new("test".to_string(), ModelProvider::Ollama, None);
+        session.add_message(Content::user("first".to_string()));
+        session.add_message(Content::model("reply".to_string()));
+        session.add_message(Content::user("second".to_string()));
+        session.add_message(Content::model("calling tool".to_string()));
+        session.add_message(Content {
+            role: "tool".to_string(),
+            parts: vec![Part::text("{}".to_string())],
+            name: Some("read_file".to_string()),
+            tool_call_id: None,
+            tool_calls: Vec::new(),
+            provider: None,
+        });
+        session.add_message(Content::model("done".to_string()));
+
+        // Keeping 2 would start at the tool result; the split moves back to "second"
+        assert!(session.apply_summary("short version", 2));
+
+        assert_eq!(session.history.len(), 5);
+        assert_eq!(session.history[0].role, "user");
+        assert!(session.history[0].parts[0].text.contains("short version"));
+        assert_eq!(session.history[1].parts[0].text, "second");
+    }
+
+    #[test]
+    fn apply_summary_skips_short_history() {
+        let mut session = ChatSession::new("test".to_string(), ModelProvider::Gemini, None);
+        session.add_message(Content::user("only".to_string()));
+        session.add_message(Content::model("reply".to_string()));
+
+        assert!(!session.apply_summary("unused", 4));
+        assert_eq!(session.history.len(), 2);
+    }
+
+    #[test]
+    fn model_turns_are_labelled_with_their_provider() {
+        let mut session = ChatSession::new("llama3.2".to_string(), ModelProvider::Ollama, None);
+        session.add_message(Content::user("Hi".to_string()));
+        session.add_message(Content::model("Hello".to_string()));
+        assert_eq!(session.history[1].provider, Some(ModelProvider::Ollama));
+        assert_eq!(session.message_label(&session.history[0]), "You:");
+        assert_eq!(session.message_label(&session.history[1]), "Ollama:");
+
+        // Older sessions without a recorded provider use the session's provider
+        let legacy = Content::model("Hello".to_string());
+        assert_eq!(session.message_label(&legacy), "Ollama:");
+
+        // Turns keep their label after switching providers
+        session.provider = ModelProvider::Gemini;
+        session.add_message(Content::model("Hi again".to_string()));
+        assert_eq!(session.message_label(&session.history[1]), "Ollama:");
+        assert_eq!(session.message_label(&session.history[2]), "Gemini:");
+    }
+
+    #[tokio::test]
+    async fn model_command_shows_or_switches_the_model() {
+        let client = LlmClient::Mock(crate::api::mock::MockClient::default());
+        let mut session = ChatSession::new("qwen3".to_string(), ModelProvider::Ollama, None);
+
+        session.switch_model(&client, "").await;
+        assert_eq!(session.model, "qwen3");
+        session.switch_model(&client, "mock").await;
+        assert_eq!(session.model, "mock");
+    }
+
+    #[test]
+    fn variables_are_set_listed_and_substituted() {
+        let mut session = ChatSession::new("test".to_string(), ModelProvider::Gemini, None);
+        assert_eq!(session.set_variable("lang = Rust").unwrap(), "lang");
+        session.set_variable("file=src/main.rs").unwrap();
+        assert!(session.set_variable("no assignment").is_err());
+        assert!(session.set_variable("bad name=x").is_err());
+        assert_eq!(
+            session.variable_lines(),
+            vec!["file = src/main.rs", "lang = Rust"]
+        );
+
+        assert_eq!(
+            substitute_variables("Review {file} as a {lang} expert", &session.variables),
+            "Review src/main.rs as a Rust expert"
+        );
+        // Unknown placeholders stay, an escaped placeholder becomes literal
+        assert_eq!(
+            substitute_variables("{unknown} {{lang}} }} {", &session.variables),
+            "{unknown} {lang} }} {"
+        );
+        // Other braces are untouched, even doubled ones
+        assert_eq!(
+            substitute_variables(
+                r#"{"a": {"b": 1}} in {lang}: fn f() {{ g() }}"#,
+                &session.variables
+            ),
+            r#"{"a": {"b": 1}} in Rust: fn f() {{ g() }}"#
+        );
+        assert_eq!(
+            substitute_variables("fn main() {{}}", &HashMap::new()),
+            "fn main() {{}}"
+        );
+
+        let branch = session.fork();
+        assert_eq!(branch.variables.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn notes_are_saved_but_not_sent_to_the_model() {
+        let mut session = ChatSession::new("mock".to_string(), ModelProvider::Gemini, None);
+        assert_eq!(
+            session.add_note("cache misses only on cold start").unwrap(),
+            1
+        );
+        assert_eq!(session.add_note("  check the retry path  ").unwrap(), 2);
+        assert!(session.add_note("   ").is_err());
+        assert_eq!(
+            session.note_lines(),
+            vec![
+                "1. cache misses only on cold start",
+                "2. check the retry path"
+            ]
+        );
+
+        let client = LlmClient::Mock(crate::api::mock::MockClient::with_replies(["ok"]));
+        session.send_with_client(&client, "hello").await.unwrap();
+        let LlmClient::Mock(mock) = &client else {
+            unreachable!()
+        };
+        let sent = serde_json::to_string(&mock.requests()).unwrap();
+        assert!(!sent.contains("cold start"));
+
+        let path =
+            std::env::temp_dir().join(format!("chatter-notes-{}.json", uuid::Uuid::new_v4()));
+        session.save_to_file(&path).await.unwrap();
+        let loaded = ChatSession::load_from_file(&path).await.unwrap();
+        assert_eq!(loaded.notes, session.notes);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(session.clear_notes(), 2);
+        assert!(session.note_lines().is_empty());
+        // Sessions saved before notes existed still load
+        let json = serde_json::to_value(&session).unwrap();
+        assert!(json.get("notes").is_none());
+        let old: ChatSession = serde_json::from_value(json).unwrap();
+        assert!(old.notes.is_empty());
+    }
+
+    #[test]
+    fn compact_collapses_tool_turns_into_summaries() {
+        let mut session = ChatSession::new("test".to_string(), ModelProvider::Ollama, None);
+        let tool_message = |name: &str, result: &ToolResult| Content {
+            role: "tool".to_string(),
+            parts: vec![Part::text(
+                build_tool_result_payload(name, result).to_string(),
+            )],
+            name: Some(name.to_string()),
+            tool_call_id: None,
+            tool_calls: Vec::new(),
+            provider: None,
+        };
+        let call_turn = |name: &str| {
+            let mut turn = Content::model(String::new());
+            turn.tool_calls.push(ModelToolCall {
+                id: None,
+                name: name.to_string(),
+                arguments: serde_json::json!({}),
+            });
+            turn
+        };
+
+        session.add_message(Content::user("Look at the project".to_string()));
+        session.add_message(call_turn("read_file"));
+        session.add_message(tool_message(
+            "read_file",
+            &ToolResult::success(
+                serde_json::json!({
+                    "path": "Cargo.toml",
+                    "size": 120,
+                    "content": "[package]\nname = \"chatter\"",
+                }),
+                None,
+            ),
+        ));
+        session.add_message(call_turn("list_directory"));
+        session.add_message(tool_message(
+            "list_directory",
+            &ToolResult::error("Path not allowed".to_string()),
+        ));
+        session.add_message(Content::model("It is a Rust crate.".to_string()));
+        session.add_message(Content::user("Thanks".to_string()));
+        session.add_message(Content::model("You're welcome".to_string()));
+
+        assert_eq!(session.compact_tool_turns(), 4);
+        let roles: Vec<&str> = session.history.iter().map(|c| c.role.as_str()).collect();
+        assert_eq!(roles, vec!["user", "model", "user", "model"]);
+
+        let summary = session.history[1].joined_text();
+        assert!(summary.starts_with("[Tool activity]"));
+        assert!(summary.contains("- read_file: "));
+        assert!(summary.contains("Cargo.toml (120 bytes)"));
+        assert!(!summary.contains("name = "));
+        assert!(summary.contains("- list_directory: failed - Path not allowed"));
+        assert!(summary.ends_with("It is a Rust crate."));
+        assert!(session.history.iter().all(|c| c.tool_calls.is_empty()));
+
+        assert_eq!(session.compact_tool_turns(), 0);
+    }
+
+    #[test]
+    fn templates_with_examples_seed_the_history() {
+        let mut template = crate::templates::Template::new(
+            "sentiment".to_string(),
+            "Classify sentiment".to_string(),
+            "Answer with one word.".to_string(),
+            "analysis".to_string(),
+            Vec::new(),
+        );
+        template.examples = vec![
+            ("I love it".to_string(), "positive".to_string()),
+            ("This is awful".to_string(), "negative".to_string()),
+        ];
+
+        let mut session = ChatSession::new("test".to_string(), ModelProvider::Gemini, None);
+        session.add_message(Content::user("Meh, it's fine".to_string()));
+        session.apply_template(&template);
+        session.apply_template(&template);
+
+        assert_eq!(
+            session.system_instruction.as_deref(),
+            Some("Answer with one word.")
+        );
+        let turns: Vec<(&str, String)> = session
+            .history
+            .iter()
+            .map(|c| (c.role.as_str(), c.joined_text()))
+            .collect();
+        assert_eq!(
+            turns,
+            vec![
+                ("user", "I love it".to_string()),
+                ("model", "positive".to_string()),
+                ("user", "This is awful".to_string()),
+                ("model", "negative".to_string()),
+                ("user", "Meh, it's fine".to_string()),
+            ]
+        );
+
+        // Templates saved before examples existed still load
+        let legacy: crate::templates::Template = serde_json::from_str(
+            r#"{"name":"t","description":"","content":"c","category":"general",
+                "created_at":"2025-01-01T00:00:00Z","updated_at":"2025-01-01T00:00:00Z",
+                "tags":[],"builtin":false}"#,
+        )
+        .unwrap();
+        assert!(legacy.examples.is_empty());
+    }
+
+    #[tokio::test]
+    async fn title_is_generated_once_after_the_first_exchange() {
+        let mock = crate::api::mock::MockClient::with_replies(["Title: **Weekend hiking plans.**"]);
+        let client = LlmClient::Mock(mock);
+        let mut session = ChatSession::new("test".to_string(), ModelProvider::Gemini, None);
+
+        session.add_message(Content::user("Where should I hike?".to_string()));
+        assert_eq!(session.generate_title(&client).await.unwrap(), None);
+
+        session.add_message(Content::model("Try the coastal trail.".to_string()));
+        let title = session.generate_title(&client).await.unwrap();
+        assert_eq!(title.as_deref(), Some("Weekend hiking plans"));
+        assert_eq!(session.title, title);
+        assert_eq!(session.history.len(), 2);
+
+        // An existing title is kept without asking the model again
+        assert_eq!(session.generate_title(&client).await.unwrap(), None);
+        let LlmClient::Mock(mock) = &client else {
+            unreachable!()
+        };
+        assert_eq!(mock.requests().len(), 1);
+
+        session.set_title("   ");
+        assert_eq!(session.title, None);
+    }
+
+    #[test]
+    fn provider_switch_updates_the_session_only_when_the_client_builds() {
+        assert_eq!(
+            "Claude".parse::<ModelProvider>().unwrap(),
+            ModelProvider::Anthropic
+        );
+        assert!("openai".parse::<ModelProvider>().is_err());
+
+        let mut config = Config::default();
+        config
+            .default_models
+            .insert(ModelProvider::Ollama, "qwen3".to_string());
+        let mut session =
+            ChatSession::new("gemini-2.5-flash".to_string(), ModelProvider::Gemini, None);
+
+        let client = session.switch_provider(&config, "ollama").unwrap();
+        assert!(matches!(client, LlmClient::Ollama(_)));
+        assert_eq!(session.provider, ModelProvider::Ollama);
+        assert_eq!(session.model, "qwen3");
+
+        session.switch_provider(&config, "OLLAMA llama3.2").unwrap();
+        assert_eq!(session.model, "llama3.2");
+
+        // Gemini needs an API key, so the session stays where it was
+        assert!(session.switch_provider(&config, "gemini").is_err());
+        assert!(session.switch_provider(&config, "nope").is_err());
+        assert_eq!(session.provider, ModelProvider::Ollama);
+        assert_eq!(session.model, "llama3.2");
+    }
+
+    #[test]
+    fn whoami_marks_settings_changed_in_the_chat() {
+        let mut session =
+            ChatSession::new("gemini-2.5-flash".to_string(), ModelProvider::Gemini, None);
+        let options = ChatOptions {
+            provider_config: Some(Config::default()),
+            launch: Some(LaunchSettings {
+                provider: Resolved::new(ModelProvider::Gemini, Source::Cli),
+                model: Resolved::new("gemini-2.5-flash".to_string(), Source::Default),
+            }),
+            ..ChatOptions::default()
+        };
+
+        let lines = session.whoami_lines(&options);
+        assert_eq!(lines[0], "Provider: Gemini (cli)");
+        assert_eq!(lines[1], "Model: gemini-2.5-flash (default)");
+        assert_eq!(lines[2], "API key: not set");
+
+        session
+            .switch_provider(&Config::default(), "ollama llama3.2")
+            .unwrap();
+        let lines = session.whoami_lines(&options);
+        assert_eq!(lines[0], "Provider: Ollama (session)");
+        assert_eq!(lines[1], "Model: llama3.2 (session)");
+        assert_eq!(lines[2], "Endpoint: http://localhost:11434 (default)");
+        assert_eq!(lines[3], "API key: not needed");
+    }
+
+    #[tokio::test]
+    async fn streaming_callback_sees_every_chunk_in_order() {
+        let mock = crate::api::mock::MockClient::default();
+        mock.push_stream(["Once ", "upon ", "a time."]);
+        let client = LlmClient::Mock(mock);
+        let mut session = ChatSession::new("test".to_string(), ModelProvider::Gemini, None);
+
+        let mut chunks = Vec::new();
+        let response = session
+            .send_streaming(&client, "Tell me a story", |chunk| {
+                chunks.push(chunk.to_string())
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(chunks, vec!["Once ", "upon ", "a time."]);
+        assert_eq!(response, chunks.concat());
+        assert_eq!(session.history.len(), 2);
+        assert_eq!(session.history[1].joined_text(), response);
+    }
+
+    #[tokio::test]
+    async fn resumed_stream_keeps_the_partial_reply() {
+        let mock = crate::api::mock::MockClient::with_replies([" and then it rained."]);
+        mock.push_error(anyhow!("still down"));
+        let client = LlmClient::Mock(mock);
+        let mut session = ChatSession::new("test".to_string(), ModelProvider::Gemini, None);
+        session.add_message(Content::user("Tell me a story".to_string()));
+
+        let full = session
+            .resume_partial_response(&client, "It was sunny")
+            .await
+            .unwrap();
+        assert_eq!(full, "It was sunny and then it rained.");
+        assert_eq!(session.history.len(), 2);
+        assert_eq!(session.history[1].joined_text(), full);
+
+        // The continuation request ends with the partial model turn
+        let LlmClient::Mock(mock) = &client else {
+            unreachable!()
+        };
+        let sent = &mock.requests()[0];
+        assert_eq!(sent.last().unwrap().role, "model");
+        assert_eq!(sent.last().unwrap().joined_text(), "It was sunny");
+
+        // A failed continuation leaves the history as it was
+        session.history.truncate(1);
+        assert!(session
+            .resume_partial_response(&client, "Partial")
+            .await
+            .is_err());
+        assert_eq!(session.history.len(), 1);
+
+        // Models that start over repeat the partial text
+        assert_eq!(
+            join_continuation("Once upon ", "Once upon a time"),
+            "Once upon a time"
+        );
+        assert_eq!(join_continuation("", "Hello"), "Hello");
+    }
+
+    #[tokio::test]
+    async fn auto_save_replaces_the_file_and_skips_unchanged_sessions() {
+        let dir = std::env::temp_dir().join(format!("chatter-autosave-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("session.json");
+        fs::write(&path, "{ stale").unwrap();
+
+        let mut session = ChatSession::new("test".to_string(), ModelProvider::Gemini, None);
+        session.add_message(Content::user("hello".to_string()));
+        assert!(session.auto_save_to(&path).await.unwrap());
+        let loaded = ChatSession::load_from_file(&path).await.unwrap();
+        assert_eq!(loaded.history.len(), 1);
+        // Only the session file is left behind, no temporary files
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        assert!(!session.auto_save_to(&path).await.unwrap());
+        session.add_message(Content::model("hi".to_string()));
+        assert!(session.auto_save_to(&path).await.unwrap());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn trimming_keeps_the_last_exchanges_with_their_tool_messages() {
+        let mut session = ChatSession::new(
+            "test".to_string(),
+            ModelProvider::Ollama,
+            Some("Be brief".to_string()),
+        );
+        for turn in 0..5 {
+            session.add_message(Content::user(format!("question {turn}")));
+            if turn == 3 {
+                let mut call = Content::model(String::new());
+                call.tool_calls.push(ModelToolCall {
+                    id: None,
+                    name: "read_file".to_string(),
+                    arguments: serde_json::json!({}),
+                });
+                session.add_message(call);
+                let mut result = Content::user("{}".to_string());
+                result.role = "tool".to_string();
+                session.add_message(result);
+            }
+            session.add_message(Content::model(format!("answer {turn}")));
+        }
+
+        assert_eq!(session.trim_history(2), 6);
+        let roles: Vec<&str> = session.history.iter().map(|c| c.role.as_str()).collect();
+        assert_eq!(
+            roles,
+            vec!["user", "model", "tool", "model", "user", "model"]
+        );
+        assert_eq!(session.history[0].joined_text(), "question 3");
+        assert_eq!(session.system_instruction.as_deref(), Some("Be brief"));
+
+        assert_eq!(session.trim_history(2), 0);
+        session.add_message(Content::user("question 5".to_string()));
+        session.add_message(Content::model("answer 5".to_string()));
+        session.trim_history(2);
+        let users = session.history.iter().filter(|c| c.role == "user").count();
+        assert_eq!(users, 2);
+        assert_eq!(session.history[0].joined_text(), "question 4");
+    }
+
+    #[test]
+    fn sessions_without_parent_id_still_load() {
+        let json = r#"{
+            "id": "abc",
+            "model": "gemini-2.5-flash",
+            "system_instruction": null,
+            "history": [],
+            "created_at": "2025-01-01T00:00:00Z",
+            "updated_at": "2025-01-01T00:00:00Z"
+        }"#;
+        let session: ChatSession = serde_json::from_str(json).expect("legacy session parses");
+        assert!(session.parent_id.is_none());
+        assert!(session.variables.is_empty());
+    }
+
     #[test]
     fn convert_model_tool_call_extracts_parameters() {
         let call = ModelToolCall {
@@ -868,37 +3274,79 @@ mod tests {
         assert!(modified.iter().any(|v| v == "foo.txt"));
         assert!(modified.iter().any(|v| v == "bar/baz.rs"));
     }
-}
-/// Read user input with support for arrow keys, backspace, and multiline input.
-fn read_input_with_features(prompt: &str) -> Result<String> {
-    let mut rl = DefaultEditor::new()?;
-
-    let history_path = dirs::data_dir()
-        .ok_or_else(|| anyhow!("Failed to find data directory"))?
-        .join("chatter/history.txt");
 
-    if let Some(parent) = history_path.parent() {
-        fs::create_dir_all(parent)?;
+    #[test]
+    fn oversized_tool_payload_fields_are_truncated() {
+        let result = ToolResult::success(
+            serde_json::json!({
+                "path": "big.log",
+                "content": "é".repeat(50_000),
+                "total_lines": 1,
+            }),
+            Some("Read file".to_string()),
+        );
+        let mut payload = build_tool_result_payload("read_file", &result);
+        truncate_tool_payload(&mut payload, 4096);
+
+        assert!(payload.to_string().len() <= 4096);
+        assert_eq!(payload["truncated"], true);
+        assert_eq!(payload["data"]["path"], "big.log");
+        assert_eq!(payload["data"]["total_lines"], 1);
+        let content = payload["data"]["content"].as_str().unwrap();
+        assert!(content.starts_with("éé"));
+        assert!(content.ends_with(" of 100000 bytes shown]"));
+
+        // Many small fields are not cut to nothing; the data is summarized instead
+        let entries: Vec<Value> = (0..3000)
+            .map(|n| serde_json::json!({"name": format!("file_{n:04}.rs"), "type": "file"}))
+            .collect();
+        let listing = ToolResult::success(
+            serde_json::json!({"path": "src", "entries": entries, "entry_count": 3000}),
+            None,
+        );
+        let mut payload = build_tool_result_payload("list_directory", &listing);
+        let started = std::time::Instant::now();
+        truncate_tool_payload(&mut payload, 4096);
+        assert!(started.elapsed() < std::time::Duration::from_secs(2));
+        assert!(payload.to_string().len() <= 4096);
+        assert_eq!(payload["data"]["path"], "src");
+        assert_eq!(payload["data"]["entries"], "[3000 items]");
+        assert_eq!(payload["data"]["entry_count"], 3000);
+
+        // Payloads under the limit pass through untouched
+        let mut small = build_tool_result_payload("read_file", &sample_tool_result());
+        let before = small.clone();
+        truncate_tool_payload(&mut small, 4096);
+        assert_eq!(small, before);
     }
 
-    let _ = rl.load_history(&history_path);
-
-    let input = match rl.readline(prompt) {
-        Ok(line) => {
-            let _ = rl.add_history_entry(line.as_str());
-            let _ = rl.save_history(&history_path);
-            Ok(line)
-        }
-        Err(ReadlineError::Interrupted) => {
-            println!("👋 Goodbye!");
-            std::process::exit(0);
-        }
-        Err(ReadlineError::Eof) => {
-            println!("👋 Goodbye!");
-            std::process::exit(0);
-        }
-        Err(err) => Err(anyhow!("Failed to read line: {}", err)),
-    };
-
-    input
+    #[tokio::test]
+    async fn one_shot_agent_runs_fail_on_safety_rejections() {
+        let mock = crate::api::mock::MockClient::default();
+        let mut reply = Content::model("Reading it".to_string());
+        reply.tool_calls.push(ModelToolCall {
+            id: Some("call-0".to_string()),
+            name: "read_file".to_string(),
+            arguments: serde_json::json!({ "path": "/etc/shadow" }),
+        });
+        mock.push_reply(reply);
+        mock.push_reply(Content::model("I could not read it".to_string()));
+        let client = LlmClient::Mock(mock);
+        let mut agent = Agent::new(AgentConfig {
+            enabled: true,
+            ..AgentConfig::default()
+        })
+        .unwrap();
+        let mut session = ChatSession::new("qwen3".to_string(), ModelProvider::Ollama, None);
+
+        let error = session
+            .send_with_agent(&client, "show /etc/shadow", &mut agent)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<ChatterError>(),
+            Some(ChatterError::SafetyViolation(_))
+        ));
+        assert_eq!(crate::error::exit_code(&error), crate::error::EXIT_SAFETY);
+    }
 }
@@ -0,0 +1,284 @@
+//! Session replay
+//!
+//! Steps through a saved session one exchange at a time instead of printing the
+//! whole history at once. `/find` and `/goto` use the same turn numbers, so a
+//! match found in a live chat can be shown again or replayed from.
+
+use super::theme::{icon, rule, Marker};
+use super::ChatSession;
+use crate::api::Content;
+use anyhow::{anyhow, Result};
+use colored::*;
+use std::io::{self, BufRead, Write};
+use std::ops::Range;
+use std::time::Duration;
+
+/// Characters of context shown on each side of a match
+const SNIPPET_CONTEXT: usize = 40;
+
+/// How replay moves from one turn to the next
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Advance {
+    /// Wait for Enter between turns; `q` stops the replay
+    Manual,
+    /// Move on automatically after the delay
+    Delay(Duration),
+}
+
+/// Split a history into turns, each starting at a user message
+///
+/// Model replies and tool messages stay with the user message that prompted them.
+/// Anything before the first user message forms a turn of its own.
+pub fn group_turns(history: &[Content]) -> Vec<&[Content]> {
+    let mut turns = Vec::new();
+    let mut start = 0;
+    for (index, content) in history.iter().enumerate() {
+        if content.role == "user" && index > start {
+            turns.push(&history[start..index]);
+            start = index;
+        }
+    }
+    if start < history.len() {
+        turns.push(&history[start..]);
+    }
+    turns
+}
+
+/// Message that matched a `/find` query
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryMatch {
+    /// 1-based turn, as taken by `/goto` and `chatter replay --from`
+    pub turn: usize,
+    /// Position of the message in the history
+    pub index: usize,
+    /// Byte range of the first match in the message text
+    pub range: Range<usize>,
+}
+
+/// User and model messages containing `query`, ignoring case
+pub fn find_in_history(history: &[Content], query: &str) -> Vec<HistoryMatch> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Vec::new();
+    }
+    // The regex engine keeps byte offsets right where lowercasing would change lengths
+    let Ok(pattern) = regex::RegexBuilder::new(&regex::escape(query))
+        .case_insensitive(true)
+        .build()
+    else {
+        return Vec::new();
+    };
+
+    let mut matches = Vec::new();
+    let mut index = 0;
+    for (turn, messages) in group_turns(history).into_iter().enumerate() {
+        for content in messages {
+            if matches!(content.role.as_str(), "user" | "model") {
+                if let Some(found) = pattern.find(&content.joined_text()) {
+                    matches.push(HistoryMatch {
+                        turn: turn + 1,
+                        index,
+                        range: found.range(),
+                    });
+                }
+            }
+            index += 1;
+        }
+    }
+    matches
+}
+
+/// One line of `text` around `range`, with the match highlighted
+pub fn snippet(text: &str, range: Range<usize>) -> String {
+    let flat = |part: &str| part.replace(['\n', '\r', '\t'], " ");
+
+    let before = &text[..range.start];
+    let skip = before.chars().count().saturating_sub(SNIPPET_CONTEXT);
+    let mut lead: String = before.chars().skip(skip).collect();
+    if skip > 0 {
+        lead.insert(0, '…');
+    }
+    let after = &text[range.end..];
+    let mut tail: String = after.chars().take(SNIPPET_CONTEXT).collect();
+    if tail.len() < after.len() {
+        tail.push('…');
+    }
+
+    format!(
+        "{}{}{}",
+        flat(&lead),
+        flat(&text[range]).bright_yellow().bold(),
+        flat(&tail)
+    )
+}
+
+/// Print the 1-based turn `turn` of the session
+pub fn show_turn(session: &ChatSession, turn: usize) -> Result<()> {
+    let turns = group_turns(&session.history);
+    if turn == 0 || turn > turns.len() {
+        return Err(anyhow!(
+            "Turn {} is out of range; the session has {} turns",
+            turn,
+            turns.len()
+        ));
+    }
+    print_turn(session, turns[turn - 1], turn, turns.len());
+    Ok(())
+}
+
+/// Print a turn under a `── Turn n/total ──` header
+fn print_turn(session: &ChatSession, turn: &[Content], number: usize, total: usize) {
+    println!(
+        "\n{}",
+        format!("{} Turn {number}/{total} {}", rule(2), rule(2)).bright_black()
+    );
+    for content in turn {
+        session.display_message(content);
+    }
+}
+
+/// Print the session turn by turn, starting at the 1-based turn `from`
+pub fn replay(session: &ChatSession, from: usize, advance: Advance) -> Result<()> {
+    let turns = group_turns(&session.history);
+    if turns.is_empty() {
+        println!(
+            "{}Session {} has no messages",
+            icon(Marker::Empty),
+            session.short_id()
+        );
+        return Ok(());
+    }
+    if from == 0 || from > turns.len() {
+        return Err(anyhow!(
+            "Turn {} is out of range; the session has {} turns",
+            from,
+            turns.len()
+        ));
+    }
+
+    println!(
+        "{}Replaying session {} ({} turns, {})",
+        icon(Marker::Replay),
+        session.short_id().bright_magenta(),
+        turns.len(),
+        session.model.bright_yellow()
+    );
+    if let Some(instruction) = &session.system_instruction {
+        println!("System: {}", instruction.bright_white());
+    }
+
+    let stdin = io::stdin();
+    for (index, turn) in turns.iter().enumerate().skip(from - 1) {
+        print_turn(session, turn, index + 1, turns.len());
+
+        if index + 1 == turns.len() {
+            break;
+        }
+        match advance {
+            Advance::Delay(delay) => std::thread::sleep(delay),
+            Advance::Manual => {
+                print!("{}", "\n[Enter] next turn, [q] quit ".bright_black());
+                io::stdout().flush()?;
+                let mut line = String::new();
+                // Stop pausing once input is exhausted, e.g. when stdin is not a terminal
+                if stdin.lock().read_line(&mut line)? > 0 && line.trim() == "q" {
+                    break;
+                }
+            }
+        }
+    }
+
+    println!(
+        "\n{}",
+        format!("{}End of replay", icon(Marker::Finish)).bright_black()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: &str, text: &str) -> Content {
+        let mut content = Content::user(text.to_string());
+        content.role = role.to_string();
+        content
+    }
+
+    #[test]
+    fn short_or_unusual_session_ids_replay_without_panicking() {
+        let mut session = ChatSession::new(
+            "mock".to_string(),
+            crate::config::ModelProvider::Gemini,
+            None,
+        );
+        session.id = "abc".to_string();
+        assert_eq!(session.short_id(), "abc");
+        replay(&session, 1, Advance::Delay(Duration::ZERO)).unwrap();
+
+        // Byte 8 falls inside a character
+        session.id = "aéééé".to_string();
+        assert_eq!(session.short_id(), "aéééé");
+        session.history.push(Content::user("hi".to_string()));
+        replay(&session, 1, Advance::Delay(Duration::ZERO)).unwrap();
+    }
+
+    #[test]
+    fn turns_group_replies_and_tool_messages_with_their_prompt() {
+        let history = vec![
+            message("model", "Summary of earlier conversation"),
+            message("user", "Read Cargo.toml"),
+            message("model", ""),
+            message("tool", "{\"tool\":\"read_file\"}"),
+            message("model", "It is a Rust crate"),
+            message("user", "Thanks"),
+            message("model", "You're welcome"),
+        ];
+
+        let turns = group_turns(&history);
+        let roles: Vec<Vec<&str>> = turns
+            .iter()
+            .map(|turn| turn.iter().map(|c| c.role.as_str()).collect())
+            .collect();
+        assert_eq!(
+            roles,
+            vec![
+                vec!["model"],
+                vec!["user", "model", "tool", "model"],
+                vec!["user", "model"],
+            ]
+        );
+        assert!(group_turns(&[]).is_empty());
+    }
+
+    #[test]
+    fn find_returns_turns_and_indices_of_matching_messages() {
+        let history = vec![
+            message("user", "How do I configure the Proxy?"),
+            message("model", "Set proxy in config.json"),
+            message("tool", "{\"proxy\": true}"),
+            message("user", "Thanks"),
+            message("model", "Anything else about timeouts?"),
+            message("user", "Yes: PROXY and timeouts together"),
+        ];
+
+        let found = find_in_history(&history, "proxy");
+        let positions: Vec<(usize, usize)> = found.iter().map(|m| (m.turn, m.index)).collect();
+        // Tool messages are skipped; case is ignored in both roles
+        assert_eq!(positions, vec![(1, 0), (1, 1), (3, 5)]);
+        assert_eq!(found[0].range, 23..28);
+        assert_eq!(&history[5].joined_text()[found[2].range.clone()], "PROXY");
+
+        assert_eq!(find_in_history(&history, "timeouts").len(), 2);
+        assert!(find_in_history(&history, "kubernetes").is_empty());
+        assert!(find_in_history(&history, "  ").is_empty());
+        // Regex syntax in the query is taken literally
+        assert!(find_in_history(&history, "proxy.in").is_empty());
+        assert_eq!(find_in_history(&history, "proxy?").len(), 1);
+
+        let text = format!("{}needle\nin the haystack", "x".repeat(60));
+        let line = snippet(&text, 60..66);
+        assert!(line.starts_with(&format!("…{}", "x".repeat(40))));
+        assert!(line.ends_with(" in the haystack"));
+    }
+}
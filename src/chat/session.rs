@@ -1,6 +1,507 @@
 //! Chat session utilities
 //!
-//! This module can be extended with additional session functionality.
+//! Helpers for locating session files saved in the sessions directory and for
+//! recovering sessions from damaged files.
 
-// Currently empty - session functionality is handled in the main chat module
-// This can be expanded later with session-specific utilities
+use super::ChatSession;
+use crate::api::Content;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Marker in the file name of backups written by /summarize
+const SUMMARY_BACKUP_MARKER: &str = "_before_summary_";
+
+/// Marker in the file name of backups written by /compact
+const COMPACT_BACKUP_MARKER: &str = "_before_compact_";
+
+/// File name used for the backup written before summarizing a session
+pub fn summary_backup_file_name(session_id: &str, timestamp: &str) -> String {
+    format!("session_{session_id}{SUMMARY_BACKUP_MARKER}{timestamp}.json")
+}
+
+/// File name used for the backup written before compacting a session
+pub fn compact_backup_file_name(session_id: &str, timestamp: &str) -> String {
+    format!("session_{session_id}{COMPACT_BACKUP_MARKER}{timestamp}.json")
+}
+
+/// File name for a `/save` without an explicit name
+pub fn generated_save_file_name(session_id: &str, timestamp: &str) -> String {
+    format!("session_{session_id}_{timestamp}.json")
+}
+
+/// File name used by `--auto-save`, leading with the title when there is one
+pub fn auto_save_file_name(session_id: &str, title: Option<&str>) -> String {
+    match title.map(title_slug).filter(|slug| !slug.is_empty()) {
+        Some(slug) => format!("session_{slug}_{session_id}.json"),
+        None => format!("session_{session_id}.json"),
+    }
+}
+
+/// Lowercase ASCII words of a title joined by dashes, at most 40 characters
+fn title_slug(title: &str) -> String {
+    let words: Vec<String> = title
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect();
+    let mut slug = String::new();
+    for word in words {
+        if !slug.is_empty() && slug.len() + 1 + word.len() > 40 {
+            break;
+        }
+        if !slug.is_empty() {
+            slug.push('-');
+        }
+        slug.push_str(&word[..word.len().min(40)]);
+    }
+    slug
+}
+
+/// Split `/save` arguments into the file name and whether `--force` was given
+pub fn parse_save_args(args: &str) -> (Option<&str>, bool) {
+    let args = args.trim();
+    let (name, force) = match args.strip_suffix("--force") {
+        Some(rest) if rest.is_empty() || rest.ends_with(char::is_whitespace) => (rest.trim(), true),
+        _ => (args, false),
+    };
+    ((!name.is_empty()).then_some(name), force)
+}
+
+/// Remove a standalone `flag` from command arguments, reporting whether it was there
+pub fn take_flag(args: &str, flag: &str) -> (String, bool) {
+    let mut found = false;
+    let rest: Vec<&str> = args
+        .split_whitespace()
+        .filter(|token| {
+            let matched = *token == flag;
+            found |= matched;
+            !matched
+        })
+        .collect();
+    (rest.join(" "), found)
+}
+
+/// Write `contents` to `path` through a temporary file in the same directory
+///
+/// The final rename replaces the file in one step, so an interrupted save leaves
+/// either the previous file or the complete new one, never a partial write.
+pub fn write_atomically(path: &Path, contents: &str) -> Result<()> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("{} is not a file path", path.display()))?;
+    let temp_path = path.with_file_name(format!(
+        ".{}.{}.tmp",
+        file_name.to_string_lossy(),
+        uuid::Uuid::new_v4()
+    ));
+
+    fs::write(&temp_path, contents)?;
+    if let Err(e) = fs::rename(&temp_path, path) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+/// Resolve a save target: relative names go in the sessions directory, like auto-saves
+pub fn resolve_save_path(name: &str, sessions_dir: Option<&Path>) -> PathBuf {
+    let path = Path::new(name);
+    match sessions_dir {
+        Some(dir) if path.is_relative() => dir.join(path),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Whether a file name looks like a saved session (`session_*.json`), excluding backups
+pub fn is_session_file_name(name: &str) -> bool {
+    name.starts_with("session_")
+        && name.ends_with(".json")
+        && !name.contains(SUMMARY_BACKUP_MARKER)
+        && !name.contains(COMPACT_BACKUP_MARKER)
+}
+
+/// Find the most recently modified session file in `dir`
+///
+/// Returns `None` when the directory does not exist or holds no sessions.
+pub fn find_latest_session(dir: &Path) -> Result<Option<PathBuf>> {
+    if !dir.exists() {
+        return Ok(None);
+    }
+
+    let mut latest: Option<(std::time::SystemTime, PathBuf)> = None;
+
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        if !is_session_file_name(&name.to_string_lossy()) {
+            continue;
+        }
+
+        let metadata = entry.metadata()?;
+        if !metadata.is_file() {
+            continue;
+        }
+
+        let modified = metadata.modified()?;
+        if latest.as_ref().is_none_or(|(time, _)| modified > *time) {
+            latest = Some((modified, entry.path()));
+        }
+    }
+
+    Ok(latest.map(|(_, path)| path))
+}
+
+/// One saved session as shown by `chatter sessions list`
+#[derive(Debug, Clone)]
+pub struct SessionSummary {
+    pub path: PathBuf,
+    pub id: String,
+    pub title: Option<String>,
+    pub model: String,
+    pub messages: usize,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl SessionSummary {
+    pub fn from_session(path: PathBuf, session: &ChatSession) -> Self {
+        Self {
+            path,
+            id: session.id.clone(),
+            title: session.title.clone(),
+            model: session.model.clone(),
+            messages: session.history.len(),
+            updated_at: session.updated_at,
+        }
+    }
+}
+
+/// Summaries of the sessions saved in `dir`, most recently updated first
+///
+/// Files that cannot be parsed are skipped with a warning.
+pub fn list_sessions(dir: &Path) -> Result<Vec<SessionSummary>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut summaries = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if !is_session_file_name(&entry.file_name().to_string_lossy()) {
+            continue;
+        }
+
+        let path = entry.path();
+        let parsed = fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|content| Ok(serde_json::from_str::<ChatSession>(&content)?));
+        match parsed {
+            Ok(session) => summaries.push(SessionSummary::from_session(path, &session)),
+            Err(e) => tracing::warn!("Skipping {}: {}", path.display(), e),
+        }
+    }
+
+    summaries.sort_by_key(|summary| std::cmp::Reverse(summary.updated_at));
+    Ok(summaries)
+}
+
+/// Outcome of recovering a damaged session file
+#[derive(Debug)]
+pub struct RecoveredSession {
+    pub session: ChatSession,
+    /// Messages that could not be recovered, counted from the damaged tail
+    pub dropped_messages: usize,
+}
+
+/// Recover a session from JSON that failed to parse strictly
+///
+/// Top-level fields are read in order until the first damaged one. The history keeps
+/// every message up to the first one that cannot be parsed, and fields that were
+/// lost are filled with defaults. Only the model name is required.
+pub fn recover_session(content: &str) -> Result<RecoveredSession> {
+    let mut fields = Map::new();
+    let mut history = Vec::new();
+    let mut dropped_messages = 0;
+
+    let mut rest = content.trim_start();
+    rest = rest
+        .strip_prefix('{')
+        .ok_or_else(|| anyhow!("Session file is not a JSON object"))?;
+
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+        if rest.is_empty() || rest.starts_with('}') {
+            break;
+        }
+
+        let Some((key, after_key)) = parse_prefix::<String>(rest) else {
+            break;
+        };
+        let Some(after_colon) = after_key.trim_start().strip_prefix(':') else {
+            break;
+        };
+
+        if key == "history" {
+            let (messages, dropped, after_history) = recover_history(after_colon);
+            history = messages;
+            dropped_messages = dropped;
+            match after_history {
+                Some(next) => rest = next,
+                None => break,
+            }
+        } else {
+            let Some((value, next)) = parse_prefix::<Value>(after_colon) else {
+                break;
+            };
+            fields.insert(key, value);
+            rest = next;
+        }
+    }
+
+    let model = fields
+        .get("model")
+        .and_then(Value::as_str)
+        .ok_or_else(|| anyhow!("Session file is missing the model name"))?
+        .to_string();
+    let provider = fields
+        .get("provider")
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+        .unwrap_or_default();
+    let system_instruction = fields
+        .get("system_instruction")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+
+    let mut session = ChatSession::new(model, provider, system_instruction);
+    if let Some(id) = fields.get("id").and_then(Value::as_str) {
+        session.id = id.to_string();
+    }
+    if let Some(created_at) = timestamp_field(&fields, "created_at") {
+        session.created_at = created_at;
+        session.updated_at = created_at;
+    }
+    if let Some(updated_at) = timestamp_field(&fields, "updated_at") {
+        session.updated_at = updated_at;
+    }
+    session.parent_id = fields
+        .get("parent_id")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    session.title = fields
+        .get("title")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    session.history = history;
+
+    Ok(RecoveredSession {
+        session,
+        dropped_messages,
+    })
+}
+
+/// Parse the history array element by element, stopping at the first bad message
+///
+/// Returns the recovered messages, the number of dropped messages, and the input
+/// after the array when it was read completely.
+fn recover_history(input: &str) -> (Vec<Content>, usize, Option<&str>) {
+    let mut messages = Vec::new();
+    let Some(mut rest) = input.trim_start().strip_prefix('[') else {
+        return (messages, 0, None);
+    };
+
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+        if let Some(after) = rest.strip_prefix(']') {
+            return (messages, 0, Some(after));
+        }
+
+        match parse_prefix::<Content>(rest) {
+            Some((message, next)) => {
+                messages.push(message);
+                rest = next;
+            }
+            None => {
+                // Each message carries exactly one unescaped "role" key
+                let dropped = rest.matches("\"role\"").count().max(1);
+                return (messages, dropped, None);
+            }
+        }
+    }
+}
+
+/// Parse a single JSON value from the start of `input`, returning it and the remainder
+fn parse_prefix<T: DeserializeOwned>(input: &str) -> Option<(T, &str)> {
+    let mut stream = serde_json::Deserializer::from_str(input).into_iter::<T>();
+    match stream.next() {
+        Some(Ok(value)) => Some((value, &input[stream.byte_offset()..])),
+        _ => None,
+    }
+}
+
+fn timestamp_field(fields: &Map<String, Value>, key: &str) -> Option<DateTime<Utc>> {
+    fields
+        .get(key)
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn save_names_and_paths() {
+        let name = generated_save_file_name("abc", "20250101120000");
+        assert_eq!(name, "session_abc_20250101120000.json");
+        assert!(is_session_file_name(&name));
+
+        assert_eq!(parse_save_args(""), (None, false));
+        assert_eq!(parse_save_args("--force"), (None, true));
+        assert_eq!(parse_save_args("chat.json"), (Some("chat.json"), false));
+        assert_eq!(
+            parse_save_args("chat.json --force"),
+            (Some("chat.json"), true)
+        );
+        assert_eq!(parse_save_args("my--force"), (Some("my--force"), false));
+        assert_eq!(
+            take_flag("chat.json --compact --force", "--compact"),
+            ("chat.json --force".to_string(), true)
+        );
+        assert!(!take_flag("chat.json", "--compact").1);
+
+        let dir = Path::new("/data/sessions");
+        assert_eq!(
+            resolve_save_path("chat.json", Some(dir)),
+            PathBuf::from("/data/sessions/chat.json")
+        );
+        assert_eq!(
+            resolve_save_path("/tmp/chat.json", Some(dir)),
+            PathBuf::from("/tmp/chat.json")
+        );
+        assert_eq!(
+            resolve_save_path("chat.json", None),
+            PathBuf::from("chat.json")
+        );
+    }
+
+    #[test]
+    fn find_latest_session_picks_newest_session_file() {
+        let dir = std::env::temp_dir().join(format!("chatter-sessions-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let now = SystemTime::now();
+        let backup = summary_backup_file_name("new", "20250101000000");
+        let files = [
+            ("session_old.json", now - Duration::from_secs(300)),
+            ("session_new.json", now - Duration::from_secs(60)),
+            (backup.as_str(), now),
+            ("notes.json", now),
+        ];
+        for (name, modified) in files {
+            let file = fs::File::create(dir.join(name)).unwrap();
+            file.set_modified(modified).unwrap();
+        }
+
+        let latest = find_latest_session(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(latest, Some(dir.join("session_new.json")));
+    }
+
+    #[test]
+    fn recover_session_keeps_valid_prefix_of_truncated_file() {
+        let content = r#"{
+  "id": "abc",
+  "model": "llama3.1",
+  "provider": "ollama",
+  "system_instruction": null,
+  "history": [
+    {"role": "user", "parts": [{"text": "first"}]},
+    {"role": "model", "parts": [{"text": "second"}]},
+    {"role": "user", "parts": [{"text": "thi"#;
+
+        assert!(serde_json::from_str::<ChatSession>(content).is_err());
+
+        let recovered = recover_session(content).unwrap();
+        assert_eq!(recovered.session.id, "abc");
+        assert_eq!(
+            recovered.session.provider,
+            crate::config::ModelProvider::Ollama
+        );
+        assert_eq!(recovered.session.history.len(), 2);
+        assert_eq!(recovered.session.history[1].parts[0].text, "second");
+        assert_eq!(recovered.dropped_messages, 1);
+    }
+
+    #[test]
+    fn recover_session_fills_missing_provider() {
+        let content = r#"{
+  "id": "abc",
+  "model": "gemini-2.5-flash",
+  "system_instruction": "Be brief",
+  "history": [{"role": "user", "parts": [{"text": "hi"}]}],
+  "created_at": "2025-01-01T00:00:00Z"
+}"#;
+
+        let recovered = recover_session(content).unwrap();
+        assert_eq!(
+            recovered.session.provider,
+            crate::config::ModelProvider::Gemini
+        );
+        assert_eq!(
+            recovered.session.system_instruction.as_deref(),
+            Some("Be brief")
+        );
+        assert_eq!(
+            recovered.session.created_at.to_rfc3339(),
+            "2025-01-01T00:00:00+00:00"
+        );
+        assert_eq!(recovered.session.history.len(), 1);
+        assert_eq!(recovered.dropped_messages, 0);
+    }
+
+    #[test]
+    fn find_latest_session_handles_missing_directory() {
+        let dir = std::env::temp_dir().join(format!("chatter-missing-{}", uuid::Uuid::new_v4()));
+        assert_eq!(find_latest_session(&dir).unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn titles_name_auto_saves_and_appear_in_session_lists() {
+        let mut session =
+            ChatSession::new("gemini-2.5-flash".to_string(), Default::default(), None);
+        session.add_message(Content::user("How do lifetimes work?".to_string()));
+        session.set_title("  \"Rust lifetimes, explained\"  ");
+        assert_eq!(session.title.as_deref(), Some("Rust lifetimes, explained"));
+
+        let name = auto_save_file_name(&session.id, session.title.as_deref());
+        assert_eq!(
+            name,
+            format!("session_rust-lifetimes-explained_{}.json", session.id)
+        );
+        assert!(is_session_file_name(&name));
+        assert_eq!(
+            auto_save_file_name("abc", Some("???")),
+            "session_abc.json".to_string()
+        );
+
+        let dir = std::env::temp_dir().join(format!("chatter-list-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        session.save_to_file(dir.join(&name)).await.unwrap();
+        fs::write(dir.join("session_broken.json"), "{ not json").unwrap();
+
+        let summaries = list_sessions(&dir).unwrap();
+        assert_eq!(summaries.len(), 1);
+        assert_eq!(summaries[0].id, session.id);
+        assert_eq!(
+            summaries[0].title.as_deref(),
+            Some("Rust lifetimes, explained")
+        );
+        assert_eq!(summaries[0].messages, 1);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,143 @@
+//! Copy of model replies written to a file as they arrive
+//!
+//! `--tee <file>` appends every streamed chunk to the file and flushes it, so a
+//! long reply can be followed with `tail -f` while it is still being generated.
+
+use anyhow::{Context, Result};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// File that replies are appended to
+#[derive(Debug)]
+pub struct TeeFile {
+    path: PathBuf,
+    file: File,
+    /// Set after the first failed write, so the warning is shown once
+    failed: AtomicBool,
+}
+
+impl TeeFile {
+    /// Open `path` for appending, creating it if needed
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Could not open tee file {}", path.display()))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            failed: AtomicBool::new(false),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append a streamed chunk and flush it
+    ///
+    /// A failing tee file never interrupts the reply; the problem is logged once.
+    pub fn write_chunk(&self, chunk: &str) {
+        let result = (&self.file)
+            .write_all(chunk.as_bytes())
+            .and_then(|_| (&self.file).flush());
+        if let Err(e) = result {
+            if !self.failed.swap(true, Ordering::Relaxed) {
+                tracing::warn!("Could not write to {}: {}", self.path.display(), e);
+            }
+        }
+    }
+
+    /// End the current reply, keeping replies on separate lines
+    pub fn end_reply(&self) {
+        self.write_chunk("\n\n");
+    }
+
+    /// Append a whole reply that was not streamed
+    pub fn write_reply(&self, text: &str) {
+        self.write_chunk(text);
+        self.end_reply();
+    }
+
+    /// Mark the text written so far as an incomplete reply
+    pub fn note_interrupted(&self, error: &dyn std::fmt::Display) {
+        self.write_chunk(&format!(
+            "\n[stream interrupted: {error}; the reply above is incomplete]\n\n"
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::mock::MockClient;
+    use crate::api::LlmClient;
+    use crate::chat::ChatSession;
+    use crate::config::ModelProvider;
+
+    #[tokio::test]
+    async fn streamed_chunks_are_appended_in_order() {
+        let path = std::env::temp_dir().join(format!("chatter-tee-{}.txt", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "earlier\n").unwrap();
+        let tee = TeeFile::open(&path).unwrap();
+
+        let mock = MockClient::default();
+        mock.push_stream(["Once ", "upon ", "a time"]);
+        let client = LlmClient::Mock(mock);
+        let mut session = ChatSession::new("mock".to_string(), ModelProvider::Anthropic, None);
+        session
+            .send_streaming(&client, "Tell a story", |chunk| {
+                tee.write_chunk(chunk);
+                // Each chunk is on disk before the next one arrives
+                assert!(std::fs::read_to_string(&path).unwrap().ends_with(chunk));
+            })
+            .await
+            .unwrap();
+        tee.end_reply();
+        tee.note_interrupted(&"connection reset");
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert!(written.starts_with("earlier\nOnce upon a time\n\n"));
+        assert!(written.contains("[stream interrupted: connection reset;"));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn chat_replies_reach_the_tee_file_through_the_options() {
+        use crate::api::Content;
+        use crate::chat::ChatOptions;
+        use std::sync::Arc;
+
+        let path = std::env::temp_dir().join(format!("chatter-tee-{}.txt", uuid::Uuid::new_v4()));
+        let mock = MockClient::default();
+        mock.push_stream(["Streamed ", "reply"]);
+        mock.push_reply(Content::model("Whole reply".to_string()));
+        let client = LlmClient::Mock(mock);
+        let mut session = ChatSession::new("mock".to_string(), ModelProvider::Anthropic, None);
+        let mut options = ChatOptions {
+            stream: true,
+            quiet: true,
+            tee: Some(Arc::new(TeeFile::open(&path).unwrap())),
+            ..ChatOptions::default()
+        };
+
+        session.add_message(Content::user("first".to_string()));
+        session
+            .send_ai_response(&client, None, None, &options)
+            .await
+            .unwrap();
+        options.stream = false;
+        session.add_message(Content::user("second".to_string()));
+        session
+            .send_ai_response(&client, None, None, &options)
+            .await
+            .unwrap();
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(written, "Streamed reply\n\nWhole reply\n\n");
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,257 @@
+//! Line markers for chat output
+//!
+//! Decorative prefixes are looked up by meaning so that terminals without emoji
+//! support can use ASCII tags or no markers at all.
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// How line markers are drawn
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    /// Emoji such as ✅ and 🤖
+    #[default]
+    Emoji,
+    /// Bracketed tags such as `[OK]` and `[AGENT]`
+    Ascii,
+    /// No markers; bullets become dashes
+    Plain,
+}
+
+/// What a line marker means
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Marker {
+    Success,
+    Error,
+    Warning,
+    Info,
+    Hint,
+    Agent,
+    Tool,
+    DryRun,
+    Thinking,
+    Empty,
+    Saved,
+    List,
+    History,
+    Switch,
+    Attach,
+    Cancelled,
+    Goodbye,
+    Done,
+    Summary,
+    Cleared,
+    Fork,
+    Template,
+    Settings,
+    Stats,
+    Chat,
+    File,
+    Directory,
+    Search,
+    Edit,
+    Allowed,
+    Blocked,
+    Replay,
+    Finish,
+    Bullet,
+}
+
+#[cfg(test)]
+impl Marker {
+    /// Every marker, for checking themes
+    pub const ALL: [Marker; 34] = [
+        Marker::Success,
+        Marker::Error,
+        Marker::Warning,
+        Marker::Info,
+        Marker::Hint,
+        Marker::Agent,
+        Marker::Tool,
+        Marker::DryRun,
+        Marker::Thinking,
+        Marker::Empty,
+        Marker::Saved,
+        Marker::List,
+        Marker::History,
+        Marker::Switch,
+        Marker::Attach,
+        Marker::Cancelled,
+        Marker::Goodbye,
+        Marker::Done,
+        Marker::Summary,
+        Marker::Cleared,
+        Marker::Fork,
+        Marker::Template,
+        Marker::Settings,
+        Marker::Stats,
+        Marker::Chat,
+        Marker::File,
+        Marker::Directory,
+        Marker::Search,
+        Marker::Edit,
+        Marker::Allowed,
+        Marker::Blocked,
+        Marker::Replay,
+        Marker::Finish,
+        Marker::Bullet,
+    ];
+}
+
+impl Theme {
+    /// Prefix for a marker, including the space before the text
+    ///
+    /// Emoji with a variation selector get two spaces, since many terminals draw
+    /// them one column wider than they report.
+    pub fn icon(self, marker: Marker) -> &'static str {
+        match self {
+            Theme::Emoji => emoji(marker),
+            Theme::Ascii => ascii(marker),
+            Theme::Plain => match marker {
+                Marker::Bullet => "- ",
+                _ => "",
+            },
+        }
+    }
+
+    /// Animation frames for the progress spinner
+    pub fn spinner_frames(self) -> &'static [&'static str] {
+        match self {
+            Theme::Emoji => &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"],
+            Theme::Ascii | Theme::Plain => &["|", "/", "-", "\\"],
+        }
+    }
+}
+
+fn emoji(marker: Marker) -> &'static str {
+    match marker {
+        Marker::Success => "✅ ",
+        Marker::Error => "❌ ",
+        Marker::Warning => "⚠️  ",
+        Marker::Info => "ℹ️  ",
+        Marker::Hint => "💡 ",
+        Marker::Agent => "🤖 ",
+        Marker::Tool => "🔧 ",
+        Marker::DryRun => "🧪 ",
+        Marker::Thinking => "💭 ",
+        Marker::Empty => "📭 ",
+        Marker::Saved => "💾 ",
+        Marker::List => "📋 ",
+        Marker::History => "📜 ",
+        Marker::Switch => "🔄 ",
+        Marker::Attach => "📎 ",
+        Marker::Cancelled => "✋ ",
+        Marker::Goodbye => "👋 ",
+        Marker::Done => "🎉 ",
+        Marker::Summary => "🗜️  ",
+        Marker::Cleared => "🗑️  ",
+        Marker::Fork => "🌿 ",
+        Marker::Template => "📝 ",
+        Marker::Settings => "⚙️  ",
+        Marker::Stats => "📊 ",
+        Marker::Chat => "💬 ",
+        Marker::File => "📄 ",
+        Marker::Directory => "📁 ",
+        Marker::Search => "🔍 ",
+        Marker::Edit => "✏️  ",
+        Marker::Allowed => "🛡️  ",
+        Marker::Blocked => "🚫 ",
+        Marker::Replay => "🎬 ",
+        Marker::Finish => "🏁 ",
+        Marker::Bullet => "• ",
+    }
+}
+
+fn ascii(marker: Marker) -> &'static str {
+    match marker {
+        Marker::Success => "[OK] ",
+        Marker::Error => "[ERR] ",
+        Marker::Warning => "[WARN] ",
+        Marker::Info => "[INFO] ",
+        Marker::Hint => "[TIP] ",
+        Marker::Agent => "[AGENT] ",
+        Marker::Tool => "[TOOL] ",
+        Marker::DryRun => "[DRY RUN] ",
+        Marker::Thinking => "[THINKING] ",
+        Marker::Empty => "[EMPTY] ",
+        Marker::Saved => "[SAVED] ",
+        Marker::List => "[LIST] ",
+        Marker::History => "[HISTORY] ",
+        Marker::Switch => "[SWITCH] ",
+        Marker::Attach => "[ATTACH] ",
+        Marker::Cancelled => "[CANCELLED] ",
+        Marker::Goodbye => "[BYE] ",
+        Marker::Done => "[DONE] ",
+        Marker::Summary => "[SUMMARY] ",
+        Marker::Cleared => "[CLEARED] ",
+        Marker::Fork => "[FORK] ",
+        Marker::Template => "[TEMPLATE] ",
+        Marker::Settings => "[CONFIG] ",
+        Marker::Stats => "[STATS] ",
+        Marker::Chat => "[CHAT] ",
+        Marker::File => "[FILE] ",
+        Marker::Directory => "[DIR] ",
+        Marker::Search => "[SEARCH] ",
+        Marker::Edit => "[EDIT] ",
+        Marker::Allowed => "[ALLOWED] ",
+        Marker::Blocked => "[BLOCKED] ",
+        Marker::Replay => "[REPLAY] ",
+        Marker::Finish => "[END] ",
+        Marker::Bullet => "- ",
+    }
+}
+
+/// Horizontal rule of the given width in the current theme
+pub fn rule(width: usize) -> String {
+    match current() {
+        Theme::Emoji => "─".repeat(width),
+        Theme::Ascii | Theme::Plain => "-".repeat(width),
+    }
+}
+
+/// Theme used by `icon`, set once the configuration is known
+static CURRENT: AtomicU8 = AtomicU8::new(Theme::Emoji as u8);
+
+/// Use this theme for all following output
+pub fn set_theme(theme: Theme) {
+    CURRENT.store(theme as u8, Ordering::Relaxed);
+}
+
+/// The theme chat output is currently drawn with
+pub fn current() -> Theme {
+    match CURRENT.load(Ordering::Relaxed) {
+        x if x == Theme::Ascii as u8 => Theme::Ascii,
+        x if x == Theme::Plain as u8 => Theme::Plain,
+        _ => Theme::Emoji,
+    }
+}
+
+/// Prefix for a marker in the current theme
+pub fn icon(marker: Marker) -> &'static str {
+    current().icon(marker)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ascii_and_plain_themes_are_pure_ascii() {
+        for theme in [Theme::Ascii, Theme::Plain] {
+            for marker in Marker::ALL {
+                assert!(theme.icon(marker).is_ascii(), "{theme:?} {marker:?}");
+            }
+            assert!(theme.spinner_frames().iter().all(|frame| frame.is_ascii()));
+        }
+
+        assert_eq!(Theme::Ascii.icon(Marker::Success), "[OK] ");
+        assert_eq!(Theme::Ascii.icon(Marker::Error), "[ERR] ");
+        assert_eq!(Theme::Plain.icon(Marker::Agent), "");
+        assert_eq!(Theme::Emoji.icon(Marker::Tool), "🔧 ");
+        assert_eq!(
+            serde_json::from_str::<Theme>("\"ascii\"").unwrap(),
+            Theme::Ascii
+        );
+    }
+}
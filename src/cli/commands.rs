@@ -1,6 +1,114 @@
 //! CLI command implementations
 //!
-//! This module can be extended with additional command functionality.
+//! Helpers shared by the one-shot query paths.
 
-// Currently empty - commands are handled in the main CLI module
-// This can be expanded later with command-specific utilities
+use anyhow::{anyhow, Context, Result};
+use std::io::{self, IsTerminal, Read};
+use std::path::Path;
+
+/// Placeholder in a query message that is replaced with piped stdin
+pub const STDIN_PLACEHOLDER: &str = "{stdin}";
+
+/// Read stdin when it is piped or redirected, returning `None` for a terminal
+///
+/// Checking for a terminal first keeps interactive invocations from blocking.
+pub fn read_piped_stdin() -> Result<Option<String>> {
+    let stdin = io::stdin();
+    if stdin.is_terminal() {
+        return Ok(None);
+    }
+
+    let mut input = String::new();
+    stdin
+        .lock()
+        .read_to_string(&mut input)
+        .context("Failed to read piped input")?;
+
+    if input.trim().is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(input))
+    }
+}
+
+/// Combine a query message with piped input
+///
+/// Every `{stdin}` placeholder in the message is replaced with the input; without a
+/// placeholder the input is appended after a separator.
+pub fn merge_piped_input(message: &str, piped: Option<&str>) -> String {
+    let Some(input) = piped else {
+        return message.to_string();
+    };
+    let input = input.trim_end();
+
+    if message.contains(STDIN_PLACEHOLDER) {
+        message.replace(STDIN_PLACEHOLDER, input)
+    } else {
+        format!("{message}\n\n--- stdin ---\n{input}")
+    }
+}
+
+/// Write a query response to `path`, creating parent directories
+///
+/// An existing file is replaced only with `force` or after confirming at a
+/// terminal. Returns `false` when the user declines.
+pub fn write_response_file(path: &Path, contents: &str, force: bool) -> Result<bool> {
+    if path.exists() && !force {
+        if !io::stdin().is_terminal() {
+            return Err(anyhow!(
+                "{} already exists; pass --force to overwrite it",
+                path.display()
+            ));
+        }
+        let overwrite = dialoguer::Confirm::new()
+            .with_prompt(format!("{} exists. Overwrite?", path.display()))
+            .default(false)
+            .interact()?;
+        if !overwrite {
+            return Ok(false);
+        }
+    }
+
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    std::fs::write(path, contents)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_appends_input_after_separator() {
+        let merged = merge_piped_input("explain this error", Some("panic at main.rs:3\n"));
+        assert_eq!(
+            merged,
+            "explain this error\n\n--- stdin ---\npanic at main.rs:3"
+        );
+    }
+
+    #[test]
+    fn merge_substitutes_placeholder() {
+        let merged = merge_piped_input("Translate: {stdin} (keep it short)", Some("hola"));
+        assert_eq!(merged, "Translate: hola (keep it short)");
+        assert_eq!(merge_piped_input("unchanged", None), "unchanged");
+    }
+
+    #[test]
+    fn response_file_is_written_with_parent_directories() {
+        let dir = std::env::temp_dir().join(format!("chatter-output-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("docs").join("README.md");
+
+        assert!(write_response_file(&path, "# Project\n", false).unwrap());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "# Project\n");
+
+        assert!(write_response_file(&path, "# Replaced\n", true).unwrap());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "# Replaced\n");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
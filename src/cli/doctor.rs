@@ -0,0 +1,460 @@
+//! `chatter doctor`
+//!
+//! Checks the configuration, API keys, provider connectivity, and local
+//! directories, and prints a checklist with a suggested fix for each problem.
+
+use crate::api::models::is_listed;
+use crate::api::LlmClient;
+use crate::config::{get_config_file_path, Config, ModelProvider};
+use crate::error::ChatterError;
+use anyhow::{anyhow, Result};
+use colored::*;
+use std::path::Path;
+use std::time::Duration;
+
+/// How long each network check may take
+const NETWORK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long the check before an interactive chat may take
+pub const PREFLIGHT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Outcome of a single check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Pass,
+    /// Not a problem for the configured provider, but worth knowing
+    Warn,
+    Fail,
+}
+
+/// One line of the doctor checklist
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+    /// What to do about a warning or failure
+    pub fix: Option<String>,
+}
+
+impl Check {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Pass,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn problem(
+        name: &'static str,
+        status: CheckStatus,
+        detail: impl Into<String>,
+        fix: impl Into<String>,
+    ) -> Self {
+        Self {
+            name,
+            status,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+/// Run every check, print the checklist, and fail if any check failed
+pub async fn run(config: &Config) -> Result<()> {
+    let checks = run_checks(config).await;
+    for check in &checks {
+        let icon = match check.status {
+            CheckStatus::Pass => "✅",
+            CheckStatus::Warn => "⚠️ ",
+            CheckStatus::Fail => "❌",
+        };
+        println!("{} {}: {}", icon, check.name.bold(), check.detail);
+        if let Some(fix) = &check.fix {
+            println!("   {} {}", "→".bright_black(), fix.bright_black());
+        }
+    }
+
+    let failed = checks
+        .iter()
+        .filter(|check| check.status == CheckStatus::Fail)
+        .count();
+    if failed > 0 {
+        return Err(anyhow!("{failed} check(s) failed"));
+    }
+    println!(
+        "\n🩺 Everything needed for {} looks good",
+        config.provider.display_name()
+    );
+    Ok(())
+}
+
+async fn run_checks(config: &Config) -> Vec<Check> {
+    // Problems with a provider only fail the run when it is the one in use
+    let severity = |provider: ModelProvider| {
+        if config.provider == provider {
+            CheckStatus::Fail
+        } else {
+            CheckStatus::Warn
+        }
+    };
+
+    let gemini_key = std::env::var("GEMINI_API_KEY")
+        .ok()
+        .filter(|key| !key.trim().is_empty())
+        .unwrap_or_else(|| config.api_key.clone());
+    // The config key wins for Anthropic, as in `LlmClient::from_config`
+    let anthropic_key = Some(config.anthropic.api_key.clone())
+        .filter(|key| !key.trim().is_empty())
+        .or_else(|| std::env::var("ANTHROPIC_API_KEY").ok())
+        .unwrap_or_default();
+
+    let mut checks = vec![
+        check_config_file(&get_config_file_path()),
+        check_api_key(
+            "Gemini API key",
+            &gemini_key,
+            severity(ModelProvider::Gemini),
+            "Set GEMINI_API_KEY or run 'chatter config set-api-key'",
+        ),
+    ];
+    if !gemini_key.trim().is_empty() {
+        checks.push(
+            check_provider_api(
+                "Gemini API",
+                config,
+                ModelProvider::Gemini,
+                severity(ModelProvider::Gemini),
+            )
+            .await,
+        );
+    }
+    checks.push(check_api_key(
+        "Anthropic API key",
+        &anthropic_key,
+        severity(ModelProvider::Anthropic),
+        "Set ANTHROPIC_API_KEY or add anthropic.api_key to the config file",
+    ));
+    if !anthropic_key.trim().is_empty() {
+        checks.push(
+            check_provider_api(
+                "Anthropic API",
+                config,
+                ModelProvider::Anthropic,
+                severity(ModelProvider::Anthropic),
+            )
+            .await,
+        );
+    }
+    checks.push(check_ollama(config, severity(ModelProvider::Ollama)).await);
+    checks.push(check_dir_writable(
+        "Sessions directory",
+        &config.sessions_dir,
+    ));
+    checks.push(check_dir_writable(
+        "Templates directory",
+        &crate::templates::storage::get_templates_dir(),
+    ));
+    checks.push(check_editor(
+        std::env::var("VISUAL").ok(),
+        std::env::var("EDITOR").ok(),
+    ));
+    checks
+}
+
+/// The config file is optional, but if it exists it must parse
+fn check_config_file(path: &Path) -> Check {
+    const NAME: &str = "Config file";
+    if !path.exists() {
+        return Check::problem(
+            NAME,
+            CheckStatus::Warn,
+            format!("{} does not exist; using defaults", path.display()),
+            "Run 'chatter config set-api-key' to create it",
+        );
+    }
+
+    let parsed = std::fs::read_to_string(path)
+        .map_err(anyhow::Error::from)
+        .and_then(|content| Ok(serde_json::from_str::<Config>(&content)?));
+    match parsed {
+        Ok(_) => Check::pass(NAME, path.display().to_string()),
+        Err(e) => Check::problem(
+            NAME,
+            CheckStatus::Fail,
+            format!("{} could not be read: {}", path.display(), e),
+            "Fix the JSON by hand or run 'chatter config reset'",
+        ),
+    }
+}
+
+fn check_api_key(name: &'static str, key: &str, severity: CheckStatus, fix: &str) -> Check {
+    if key.trim().is_empty() {
+        Check::problem(name, severity, "not set", fix)
+    } else {
+        Check::pass(name, "set")
+    }
+}
+
+/// Confirm a hosted provider accepts the key by listing its models
+async fn check_provider_api(
+    name: &'static str,
+    config: &Config,
+    provider: ModelProvider,
+    severity: CheckStatus,
+) -> Check {
+    let client = match LlmClient::from_config(config, &provider) {
+        Ok(client) => client,
+        Err(e) => return Check::problem(name, severity, e.to_string(), "Check the API key"),
+    };
+
+    match tokio::time::timeout(NETWORK_TIMEOUT, client.list_models()).await {
+        Ok(Ok(models)) => Check::pass(name, format!("reachable ({} models)", models.len())),
+        Ok(Err(e)) => Check::problem(
+            name,
+            severity,
+            e.to_string(),
+            "Check the API key and your network connection",
+        ),
+        Err(_) => Check::problem(
+            name,
+            severity,
+            format!("no response within {}s", NETWORK_TIMEOUT.as_secs()),
+            "Check your network connection or proxy settings",
+        ),
+    }
+}
+
+async fn check_ollama(config: &Config, severity: CheckStatus) -> Check {
+    const NAME: &str = "Ollama";
+    let endpoint = &config.ollama.endpoint;
+    let client = match LlmClient::from_config(config, &ModelProvider::Ollama) {
+        Ok(client) => client,
+        Err(e) => return Check::problem(NAME, severity, e.to_string(), "Set ollama.endpoint"),
+    };
+
+    let model = config.default_model_for(&ModelProvider::Ollama);
+    match tokio::time::timeout(NETWORK_TIMEOUT, client.list_models()).await {
+        Ok(Ok(models)) if is_listed(model, &models) => {
+            Check::pass(NAME, format!("{endpoint} has {model}"))
+        }
+        Ok(Ok(_)) => Check::problem(
+            NAME,
+            severity,
+            format!("{endpoint} is reachable but {model} is not installed"),
+            format!("Run 'ollama pull {model}'"),
+        ),
+        Ok(Err(e)) => Check::problem(
+            NAME,
+            severity,
+            e.to_string(),
+            "Start the server with 'ollama serve' or fix ollama.endpoint",
+        ),
+        Err(_) => Check::problem(
+            NAME,
+            severity,
+            format!(
+                "{endpoint} did not respond within {}s",
+                NETWORK_TIMEOUT.as_secs()
+            ),
+            "Check that ollama.endpoint points at a running server",
+        ),
+    }
+}
+
+/// Make sure the provider answers before an interactive chat starts
+///
+/// Lists the provider's models, a cheap request every provider supports, so an
+/// unreachable server is reported before anything has been typed.
+pub async fn preflight(
+    client: &LlmClient,
+    provider: &ModelProvider,
+    endpoint: Option<&str>,
+    timeout: Duration,
+) -> Result<()> {
+    let target = match endpoint {
+        Some(url) => format!("{} at {url}", provider.display_name()),
+        None => provider.display_name().to_string(),
+    };
+    let fix = match provider {
+        ModelProvider::Ollama => "Start the server with 'ollama serve' or pass --endpoint",
+        _ => "Check your network connection, proxy settings, and API key",
+    };
+    let skip = "set preflight_check to false to skip this check";
+    let unavailable = |message: String| -> anyhow::Error {
+        ChatterError::ProviderUnavailable {
+            provider: provider.display_name(),
+            message,
+        }
+        .into()
+    };
+
+    match tokio::time::timeout(timeout, client.list_models()).await {
+        Ok(Ok(_)) => Ok(()),
+        // A rejected key is reported as it is, not as an unreachable server
+        Ok(Err(e)) if e.is_quota_or_auth() => Err(e.into()),
+        Ok(Err(e)) => Err(unavailable(format!(
+            "{target} is not reachable: {e}\n{fix}, or {skip}"
+        ))),
+        Err(_) => Err(unavailable(format!(
+            "{target} did not respond within {}s\n{fix}, or {skip}",
+            timeout.as_secs_f64()
+        ))),
+    }
+}
+
+/// Create the directory if needed and confirm a file can be written in it
+fn check_dir_writable(name: &'static str, dir: &Path) -> Check {
+    let probe = dir.join(format!(".chatter-doctor-{}", uuid::Uuid::new_v4()));
+    let result = std::fs::create_dir_all(dir)
+        .and_then(|_| std::fs::write(&probe, b"ok"))
+        .and_then(|_| std::fs::remove_file(&probe));
+
+    match result {
+        Ok(()) => Check::pass(name, format!("{} is writable", dir.display())),
+        Err(e) => Check::problem(
+            name,
+            CheckStatus::Fail,
+            format!("{} is not writable: {}", dir.display(), e),
+            "Fix the directory permissions or choose another location in the config",
+        ),
+    }
+}
+
+/// `/edit` opens `$VISUAL` or `$EDITOR`
+fn check_editor(visual: Option<String>, editor: Option<String>) -> Check {
+    const NAME: &str = "Editor";
+    match visual
+        .into_iter()
+        .chain(editor)
+        .find(|value| !value.trim().is_empty())
+    {
+        Some(editor) => Check::pass(NAME, editor),
+        None => Check::problem(
+            NAME,
+            CheckStatus::Warn,
+            "$EDITOR is not set; /edit falls back to the system default",
+            "Export EDITOR (e.g. EDITOR=vim) in your shell profile",
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_file_check_reports_missing_and_invalid_files() {
+        let dir = std::env::temp_dir().join(format!("chatter-doctor-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.json");
+
+        assert_eq!(check_config_file(&path).status, CheckStatus::Warn);
+
+        std::fs::write(&path, "{ not json").unwrap();
+        let invalid = check_config_file(&path);
+        assert_eq!(invalid.status, CheckStatus::Fail);
+        assert!(invalid.fix.is_some());
+
+        std::fs::write(&path, serde_json::to_string(&Config::default()).unwrap()).unwrap();
+        assert_eq!(check_config_file(&path).status, CheckStatus::Pass);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn preflight_fails_fast_for_unreachable_servers() {
+        use crate::api::http::HttpSettings;
+
+        // Nothing listens on a port that was just released
+        let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", closed.local_addr().unwrap());
+        drop(closed);
+        let client = LlmClient::new_ollama(endpoint.clone(), &HttpSettings::default()).unwrap();
+        let error = preflight(
+            &client,
+            &ModelProvider::Ollama,
+            Some(&endpoint),
+            PREFLIGHT_TIMEOUT,
+        )
+        .await
+        .unwrap_err()
+        .to_string();
+        assert!(error.starts_with(&format!("Ollama at {endpoint} is not reachable")));
+        assert!(error.contains("ollama serve"));
+
+        // A server that accepts but never answers is cut off by the timeout
+        let silent = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", silent.local_addr().unwrap());
+        let client = LlmClient::new_ollama(endpoint.clone(), &HttpSettings::default()).unwrap();
+        let started = std::time::Instant::now();
+        let error = preflight(
+            &client,
+            &ModelProvider::Ollama,
+            Some(&endpoint),
+            Duration::from_millis(200),
+        )
+        .await
+        .unwrap_err()
+        .to_string();
+        assert!(error.contains("did not respond within 0.2s"));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        drop(silent);
+    }
+
+    #[tokio::test]
+    async fn anthropic_is_checked_when_it_is_the_provider() {
+        // An endpoint nobody listens on makes the model listing fail
+        let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut config = Config::default();
+        config.provider = ModelProvider::Anthropic;
+        config.anthropic.api_key = "sk-ant-test".to_string();
+        config.anthropic.endpoint = format!("http://{}", closed.local_addr().unwrap());
+        drop(closed);
+
+        let checks = run_checks(&config).await;
+        let key = checks
+            .iter()
+            .find(|check| check.name == "Anthropic API key")
+            .unwrap();
+        assert_eq!(key.status, CheckStatus::Pass);
+        let api = checks
+            .iter()
+            .find(|check| check.name == "Anthropic API")
+            .unwrap();
+        assert_eq!(api.status, CheckStatus::Fail);
+    }
+
+    #[test]
+    fn local_checks_pass_and_fail_as_expected() {
+        let dir = std::env::temp_dir().join(format!("chatter-doctor-{}", uuid::Uuid::new_v4()));
+        let writable = check_dir_writable("Sessions directory", &dir.join("sessions"));
+        assert_eq!(writable.status, CheckStatus::Pass);
+        assert_eq!(std::fs::read_dir(dir.join("sessions")).unwrap().count(), 0);
+
+        // A regular file cannot be used as a directory
+        let file = dir.join("file");
+        std::fs::write(&file, "x").unwrap();
+        assert_eq!(
+            check_dir_writable("Sessions directory", &file).status,
+            CheckStatus::Fail
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+
+        let missing = check_api_key("Anthropic API key", " ", CheckStatus::Fail, "Set it");
+        assert_eq!(missing.status, CheckStatus::Fail);
+        assert_eq!(missing.fix.as_deref(), Some("Set it"));
+        assert_eq!(
+            check_api_key("Gemini API key", "abc", CheckStatus::Fail, "Set it").status,
+            CheckStatus::Pass
+        );
+
+        assert_eq!(check_editor(None, None).status, CheckStatus::Warn);
+        assert_eq!(
+            check_editor(Some(String::new()), Some("nano".to_string())).detail,
+            "nano"
+        );
+    }
+}
@@ -1,9 +1,10 @@
 //! CLI module for command-line argument parsing and command definitions
 
-use clap::{Parser, Subcommand, ValueEnum};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
 
 pub mod commands;
+pub mod doctor;
 
 #[derive(Parser)]
 #[command(name = "chatter")]
@@ -19,31 +20,104 @@ pub struct Cli {
     #[arg(long, value_enum)]
     pub provider: Option<ProviderArg>,
 
-    /// System instruction to guide the AI's behavior
-    #[arg(short, long)]
-    pub system: Option<String>,
-
-    /// Template to use for system instruction
-    #[arg(short, long)]
-    pub template: Option<String>,
+    /// Where the system instruction comes from
+    #[command(flatten)]
+    pub system_source: SystemArgs,
 
     /// Load a previous chat session
     #[arg(short, long)]
     pub load_session: Option<PathBuf>,
 
+    /// Resume the most recently modified session in the sessions directory
+    #[arg(long = "continue", conflicts_with = "load_session")]
+    pub continue_session: bool,
+
     /// Auto-save the chat session
     #[arg(short, long)]
     pub auto_save: bool,
 
+    /// Keep only the most recent N exchanges in the session
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    pub max_history: Option<u64>,
+
+    /// Wait for complete responses instead of streaming them
+    #[arg(long)]
+    pub no_stream: bool,
+
+    /// Enable agent mode (file tools) from the start
+    #[arg(long)]
+    pub agent: bool,
+
+    /// Show model reasoning (e.g. qwen3 <think> blocks) instead of hiding it
+    #[arg(long)]
+    pub show_thinking: bool,
+
+    /// Start agent mode in dry-run: tool calls are previewed, never performed
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+
+    /// Hide the banner, spinner, and decorative output
+    #[arg(short, long)]
+    pub quiet: bool,
+
+    /// Print streamed replies as fast as they arrive, ignoring ui.stream_delay_ms
+    #[arg(long)]
+    pub fast: bool,
+
+    /// Server URL for the provider in use this run, overriding the configured endpoint
+    #[arg(long, value_name = "URL", global = true)]
+    pub endpoint: Option<String>,
+
+    /// Seconds to wait for a provider response this run, overriding request_timeout
+    #[arg(long, value_name = "SECONDS", global = true)]
+    pub timeout: Option<u64>,
+
+    /// Log diagnostics to stderr (repeat for more detail: -v info, -vv debug, -vvv trace)
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    pub verbose: u8,
+
     /// Message to send once and exit
     #[arg(value_name = "MESSAGE")]
     pub prompt: Option<String>,
 
+    /// Write the response to MESSAGE to this file instead of stdout
+    #[arg(short, long, value_name = "FILE", requires = "prompt")]
+    pub output: Option<PathBuf>,
+
+    /// Overwrite the --output file without asking
+    #[arg(long, requires = "output")]
+    pub force: bool,
+
+    /// Print (or write) the response to MESSAGE as a JSON object
+    #[arg(long, requires = "prompt")]
+    pub json: bool,
+
+    /// Also append replies to this file as they stream in (e.g. to follow with tail -f)
+    #[arg(long, value_name = "FILE")]
+    pub tee: Option<PathBuf>,
+
     /// Subcommands
     #[command(subcommand)]
     pub command: Option<Commands>,
 }
 
+/// Mutually exclusive sources for the system instruction
+#[derive(Args, Debug, Clone, Default)]
+#[group(multiple = false)]
+pub struct SystemArgs {
+    /// System instruction to guide the AI's behavior
+    #[arg(short, long)]
+    pub system: Option<String>,
+
+    /// Read the system instruction from a file
+    #[arg(long, value_name = "PATH")]
+    pub system_file: Option<PathBuf>,
+
+    /// Template to use for system instruction
+    #[arg(short, long)]
+    pub template: Option<String>,
+}
+
 #[derive(Subcommand)]
 pub enum Commands {
     /// Configuration management
@@ -61,18 +135,97 @@ pub enum Commands {
         /// Model provider to use for this query
         #[arg(long, value_enum)]
         provider: Option<ProviderArg>,
-        /// System instruction for this query
-        #[arg(short, long)]
-        system: Option<String>,
-        /// Template to use for this query
-        #[arg(short, long)]
-        template: Option<String>,
+        /// Where the system instruction for this query comes from
+        #[command(flatten)]
+        system_source: SystemArgs,
+        /// Wait for the complete response instead of streaming it
+        #[arg(long)]
+        no_stream: bool,
+        /// Let the model use agent tools to complete the task
+        #[arg(long)]
+        agent: bool,
+        /// Send the query to several providers at once and print the first answer
+        #[arg(
+            long,
+            value_enum,
+            value_delimiter = ',',
+            conflicts_with_all = ["provider", "model", "agent"]
+        )]
+        race: Vec<ProviderArg>,
+        /// Write the response to this file instead of stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+        /// Overwrite the --output file without asking
+        #[arg(long, requires = "output")]
+        force: bool,
+        /// Print (or write) a JSON object with the provider, model, and response
+        #[arg(long)]
+        json: bool,
+        /// Also append the response to this file as it streams in
+        #[arg(long, value_name = "FILE", conflicts_with = "race")]
+        tee: Option<PathBuf>,
     },
     /// Template management
     Template {
         #[command(subcommand)]
         action: TemplateAction,
     },
+    /// List the models available from a provider
+    Models {
+        /// Provider to query (defaults to the configured provider)
+        #[arg(long, value_enum)]
+        provider: Option<ProviderArg>,
+    },
+    /// Export a saved session as a readable transcript
+    Export {
+        /// Session file to export
+        session: PathBuf,
+        /// Output format
+        #[arg(short, long, value_enum, default_value_t = ExportFormatArg::Markdown)]
+        format: ExportFormatArg,
+        /// Write the transcript to a file instead of stdout
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+    },
+    /// Saved chat sessions
+    Sessions {
+        #[command(subcommand)]
+        action: SessionsAction,
+    },
+    /// Check the configuration, API keys, providers, and directories
+    Doctor,
+    /// Step through a saved session one exchange at a time
+    Replay {
+        /// Session file to replay
+        session: PathBuf,
+        /// Advance automatically after this many seconds instead of waiting for Enter
+        #[arg(short, long)]
+        delay: Option<f64>,
+        /// Turn to start from (1-based)
+        #[arg(long, default_value_t = 1)]
+        from: usize,
+    },
+    /// Send a list of prompts through one session and capture every response
+    Batch {
+        /// File with one prompt per line, or a JSON array of prompts
+        #[arg(short, long)]
+        file: PathBuf,
+        /// Save the resulting session transcript to this file
+        #[arg(short, long)]
+        session: Option<PathBuf>,
+        /// Start each prompt from an empty history instead of carrying context over
+        #[arg(long)]
+        fresh: bool,
+        /// Model to use for the batch
+        #[arg(short, long)]
+        model: Option<String>,
+        /// Model provider to use for the batch
+        #[arg(long, value_enum)]
+        provider: Option<ProviderArg>,
+        /// Where the system instruction for the batch comes from
+        #[command(flatten)]
+        system_source: SystemArgs,
+    },
 }
 
 #[derive(Subcommand)]
@@ -81,10 +234,18 @@ pub enum ConfigAction {
     SetApiKey,
     /// Show current configuration
     Show,
+    /// Open the config file in $EDITOR, saving it only if it is valid
+    Edit,
     /// Reset configuration to defaults
     Reset,
 }
 
+#[derive(Subcommand)]
+pub enum SessionsAction {
+    /// List saved sessions with their titles, newest first
+    List,
+}
+
 #[derive(Subcommand)]
 pub enum TemplateAction {
     /// List all available templates
@@ -92,10 +253,17 @@ pub enum TemplateAction {
         /// Filter by category
         #[arg(short, long)]
         category: Option<String>,
+        /// Filter by tag
+        #[arg(short, long)]
+        tag: Option<String>,
         /// Search templates by name or description
         #[arg(short, long)]
         search: Option<String>,
     },
+    /// List template categories with how many templates each has
+    Categories,
+    /// List template tags with how many templates carry each
+    Tags,
     /// Show details of a specific template
     Show {
         /// Template name
@@ -125,10 +293,51 @@ pub enum TemplateAction {
         #[arg(short, long)]
         force: bool,
     },
+    /// Rename a user template, keeping its content and timestamps
+    Rename {
+        /// Current template name
+        old: String,
+        /// New template name
+        new: String,
+    },
+    /// Export a template (or all user templates) to share
+    Export {
+        /// Template name
+        #[arg(required_unless_present = "all", conflicts_with = "all")]
+        name: Option<String>,
+        /// Export every user template as a bundle
+        #[arg(long)]
+        all: bool,
+        /// Write to a file instead of stdout
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+    },
+    /// Import templates from an exported file
+    Import {
+        /// File containing one template or a list of templates
+        file: PathBuf,
+        /// Overwrite existing user templates with the same name
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Check templates for problems before sharing them; exits with an error if any are found
+    Validate {
+        /// Template name
+        #[arg(required_unless_present = "all", conflicts_with = "all")]
+        name: Option<String>,
+        /// Check every template
+        #[arg(long)]
+        all: bool,
+    },
+    /// Suggest templates that fit a message
+    Suggest {
+        /// Text describing what you want to do
+        text: String,
+    },
     /// Use a template to start a chat session
     Use {
-        /// Template name
-        name: String,
+        /// Template name (choose from a menu when omitted)
+        name: Option<String>,
         /// Model to use
         #[arg(short, long)]
         model: Option<String>,
@@ -138,11 +347,30 @@ pub enum TemplateAction {
     },
 }
 
+/// CLI session export format argument
+#[derive(Copy, Clone, Debug, ValueEnum)]
+pub enum ExportFormatArg {
+    Markdown,
+    Text,
+    Html,
+}
+
+impl From<ExportFormatArg> for crate::chat::export::ExportFormat {
+    fn from(arg: ExportFormatArg) -> Self {
+        match arg {
+            ExportFormatArg::Markdown => Self::Markdown,
+            ExportFormatArg::Text => Self::Text,
+            ExportFormatArg::Html => Self::Html,
+        }
+    }
+}
+
 /// CLI model provider argument
 #[derive(Copy, Clone, Debug, ValueEnum)]
 pub enum ProviderArg {
     Gemini,
     Ollama,
+    Anthropic,
 }
 
 impl From<ProviderArg> for crate::config::ModelProvider {
@@ -150,6 +378,7 @@ impl From<ProviderArg> for crate::config::ModelProvider {
         match arg {
             ProviderArg::Gemini => Self::Gemini,
             ProviderArg::Ollama => Self::Ollama,
+            ProviderArg::Anthropic => Self::Anthropic,
         }
     }
 }
@@ -159,6 +388,36 @@ impl From<&crate::config::ModelProvider> for ProviderArg {
         match provider {
             crate::config::ModelProvider::Gemini => ProviderArg::Gemini,
             crate::config::ModelProvider::Ollama => ProviderArg::Ollama,
+            crate::config::ModelProvider::Anthropic => ProviderArg::Anthropic,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn system_sources_are_mutually_exclusive() {
+        assert!(
+            Cli::try_parse_from(["chatter", "--system", "Be brief", "--system-file", "p.txt"])
+                .is_err()
+        );
+        assert!(Cli::try_parse_from([
+            "chatter",
+            "query",
+            "hi",
+            "--template",
+            "tutor",
+            "--system-file",
+            "p.txt"
+        ])
+        .is_err());
+
+        let cli = Cli::try_parse_from(["chatter", "--system-file", "prompt.txt"]).unwrap();
+        assert_eq!(
+            cli.system_source.system_file,
+            Some(PathBuf::from("prompt.txt"))
+        );
+    }
+}
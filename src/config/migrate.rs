@@ -0,0 +1,103 @@
+//! Upgrades of older config files
+//!
+//! Each config file records the schema `version` it was written with. Files
+//! from an older version are passed through one step per version, each of
+//! which fills in fields that became required or moves renamed ones, before
+//! the result is parsed.
+
+use super::{Config, CONFIG_VERSION};
+use serde_json::{Map, Value};
+
+/// One upgrade, from the version at its index to the next
+type Step = fn(&mut Map<String, Value>) -> Vec<String>;
+
+/// Upgrades in order; `STEPS[n]` turns version `n` into version `n + 1`
+const STEPS: &[Step] = &[v0_to_v1];
+
+// Bumping the version without adding its step (or the reverse) fails to compile
+const _: () = assert!(STEPS.len() == CONFIG_VERSION as usize);
+
+/// Schema version a config file was written with; files without one are version 0
+pub fn version_of(value: &Value) -> u32 {
+    value
+        .get("version")
+        .and_then(Value::as_u64)
+        .and_then(|version| u32::try_from(version).ok())
+        .unwrap_or(0)
+}
+
+/// Upgrade a parsed config file to `CONFIG_VERSION`, logging each change
+///
+/// Files from a newer version, and values that are not JSON objects, are
+/// returned unchanged.
+pub fn migrate(mut value: Value) -> Value {
+    let from = version_of(&value);
+    let Some(fields) = value.as_object_mut() else {
+        return value;
+    };
+
+    for (version, step) in STEPS.iter().enumerate().skip(from as usize) {
+        for change in step(fields) {
+            tracing::info!("Config version {} -> {}: {}", version, version + 1, change);
+        }
+        fields.insert("version".to_string(), Value::from(version as u32 + 1));
+    }
+    value
+}
+
+/// Version 1 added `version`
+///
+/// Hand-written files from before often left out `api_key`, `default_model`,
+/// `auto_save`, or `sessions_dir`, and then could not be loaded at all. Missing
+/// ones get their default value.
+fn v0_to_v1(fields: &mut Map<String, Value>) -> Vec<String> {
+    const ONCE_REQUIRED: [&str; 4] = ["api_key", "default_model", "auto_save", "sessions_dir"];
+
+    let defaults = serde_json::to_value(Config::default()).unwrap_or_default();
+    let mut changes = Vec::new();
+    for name in ONCE_REQUIRED {
+        if fields.contains_key(name) {
+            continue;
+        }
+        if let Some(default) = defaults.get(name) {
+            fields.insert(name.to_string(), default.clone());
+            changes.push(format!("added {name} with its default value"));
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn versionless_config_is_upgraded_to_the_current_version() {
+        let old = json!({
+            "default_model": "gemini-2.5-pro",
+            "provider": "ollama",
+            "ollama": {"endpoint": "http://gpu-box:11434"}
+        });
+        assert_eq!(version_of(&old), 0);
+
+        let upgraded = migrate(old);
+        assert_eq!(version_of(&upgraded), CONFIG_VERSION);
+        // Existing values are kept and missing ones filled in
+        assert_eq!(upgraded["default_model"], "gemini-2.5-pro");
+        assert_eq!(upgraded["api_key"], "");
+        assert_eq!(upgraded["auto_save"], false);
+
+        let config: Config = serde_json::from_value(upgraded).unwrap();
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.provider, crate::config::ModelProvider::Ollama);
+        assert_eq!(config.ollama.endpoint, "http://gpu-box:11434");
+        assert_eq!(config.sessions_dir, Config::default().sessions_dir);
+
+        // A current file passes through untouched, as does one from the future
+        let current = serde_json::to_value(Config::default()).unwrap();
+        assert_eq!(migrate(current.clone()), current);
+        let future = json!({"version": CONFIG_VERSION + 1, "shiny": true});
+        assert_eq!(migrate(future.clone()), future);
+    }
+}
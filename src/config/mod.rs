@@ -2,33 +2,76 @@
 //!
 //! Handles API key storage, user preferences, and configuration file management.
 
+use crate::agent::{AgentConfig, ExternalToolConfig, SafetyRules};
+use crate::api::anthropic::ANTHROPIC_API_BASE;
+use crate::api::http::{check_request_timeout, HttpSettings};
+use crate::chat::limits::{self, InteractionLimits};
+use crate::chat::theme::Theme;
+use crate::error::ChatterError;
 use anyhow::{anyhow, Result};
-use dialoguer::Password;
+use dialoguer::{Confirm, Editor, Password};
 use dirs::config_dir;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
+use std::time::Duration;
 
+mod migrate;
+mod provenance;
 pub mod settings;
 
+pub use provenance::{Resolved, Source};
+
 /// Supported model providers
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "lowercase")]
 pub enum ModelProvider {
+    #[default]
     Gemini,
     Ollama,
-}
-
-impl Default for ModelProvider {
-    fn default() -> Self {
-        Self::Gemini
-    }
+    Anthropic,
 }
 
 impl ModelProvider {
     /// Whether this provider requires an API key for authentication
     pub fn requires_api_key(&self) -> bool {
-        matches!(self, Self::Gemini)
+        matches!(self, Self::Gemini | Self::Anthropic)
+    }
+
+    /// Whether the chat loop can offer agent tools to this provider
+    pub fn supports_tools(&self) -> bool {
+        matches!(self, Self::Gemini | Self::Ollama | Self::Anthropic)
+    }
+
+    /// Whether responses can be streamed from this provider
+    pub fn supports_streaming(&self) -> bool {
+        matches!(self, Self::Gemini | Self::Anthropic)
+    }
+
+    /// Human-readable provider name
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Gemini => "Gemini",
+            Self::Ollama => "Ollama",
+            Self::Anthropic => "Claude",
+        }
+    }
+}
+
+impl std::str::FromStr for ModelProvider {
+    type Err = anyhow::Error;
+
+    /// Parse a provider name as typed by the user, e.g. in `/provider ollama`
+    fn from_str(name: &str) -> Result<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "gemini" => Ok(Self::Gemini),
+            "ollama" => Ok(Self::Ollama),
+            "anthropic" | "claude" => Ok(Self::Anthropic),
+            other => Err(anyhow!(
+                "Unknown provider '{other}'; expected gemini, ollama, or anthropic"
+            )),
+        }
     }
 }
 
@@ -47,6 +90,136 @@ impl Default for OllamaConfig {
     }
 }
 
+/// Configuration specific to the Anthropic provider
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnthropicConfig {
+    /// Anthropic API key
+    #[serde(default)]
+    pub api_key: String,
+    /// Base URL for the Anthropic API
+    #[serde(default = "default_anthropic_endpoint")]
+    pub endpoint: String,
+}
+
+fn default_anthropic_endpoint() -> String {
+    ANTHROPIC_API_BASE.to_string()
+}
+
+impl Default for AnthropicConfig {
+    fn default() -> Self {
+        Self {
+            api_key: String::new(),
+            endpoint: default_anthropic_endpoint(),
+        }
+    }
+}
+
+/// Terminal output preferences
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UiSettings {
+    /// Hide the welcome banner, spinner, and decorative emoji
+    #[serde(default)]
+    pub quiet: bool,
+    /// Seconds before the thinking spinner hints that a request is taking long
+    #[serde(default = "default_long_request_hint_secs")]
+    pub long_request_hint_secs: u64,
+    /// Keep a separate input history file for each working directory
+    #[serde(default)]
+    pub per_directory_history: bool,
+    /// Line markers in chat output: `emoji`, `ascii` (`[OK]`, `[ERR]`), or `plain`
+    #[serde(default)]
+    pub theme: Theme,
+    /// Milliseconds to pause after each streamed character, for a typewriter effect
+    #[serde(default)]
+    pub stream_delay_ms: u64,
+}
+
+impl Default for UiSettings {
+    fn default() -> Self {
+        Self {
+            quiet: false,
+            long_request_hint_secs: default_long_request_hint_secs(),
+            per_directory_history: false,
+            theme: Theme::default(),
+            stream_delay_ms: 0,
+        }
+    }
+}
+
+/// Agent settings stored in the configuration file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentSettings {
+    /// User-defined tools backed by external commands
+    #[serde(default)]
+    pub external_tools: Vec<ExternalToolConfig>,
+    /// Tools that agent mode should never offer (e.g. write tools for review sessions)
+    #[serde(default)]
+    pub disabled_tools: Vec<String>,
+    /// Forbidden/allowed paths and the sensitive and dangerous pattern lists
+    #[serde(default)]
+    pub safety: SafetyRules,
+    /// Seconds one model interaction may run, tool calls included, before it is stopped
+    #[serde(default = "default_interaction_timeout_secs")]
+    pub interaction_timeout_secs: u64,
+    /// Total bytes of tool output fed back to the model within one interaction
+    #[serde(default = "default_max_tool_output_bytes")]
+    pub max_tool_output_bytes: usize,
+    /// Bytes of a single tool result fed back to the model; longer fields are truncated
+    #[serde(default = "default_max_tool_result_bytes")]
+    pub max_tool_result_bytes: usize,
+    /// Ask for confirmation before agent tools write or update files
+    #[serde(default)]
+    pub confirm_writes: bool,
+}
+
+impl Default for AgentSettings {
+    fn default() -> Self {
+        Self {
+            external_tools: Vec::new(),
+            disabled_tools: Vec::new(),
+            safety: SafetyRules::default(),
+            interaction_timeout_secs: default_interaction_timeout_secs(),
+            max_tool_output_bytes: default_max_tool_output_bytes(),
+            max_tool_result_bytes: default_max_tool_result_bytes(),
+            confirm_writes: false,
+        }
+    }
+}
+
+impl AgentSettings {
+    /// Build the agent configuration used when agent mode is enabled
+    pub fn agent_config(&self) -> AgentConfig {
+        AgentConfig {
+            external_tools: self.external_tools.clone(),
+            disabled_tools: self.disabled_tools.clone(),
+            safety: self.safety.clone(),
+            confirm_writes: self.confirm_writes,
+            ..AgentConfig::default()
+        }
+    }
+
+    /// Limits applied to each model interaction and its tool loop
+    pub fn interaction_limits(&self) -> InteractionLimits {
+        InteractionLimits {
+            time_budget: Duration::from_secs(self.interaction_timeout_secs),
+            max_tool_output_bytes: self.max_tool_output_bytes,
+            max_tool_result_bytes: self.max_tool_result_bytes,
+        }
+    }
+}
+
+fn default_interaction_timeout_secs() -> u64 {
+    limits::DEFAULT_TIME_BUDGET_SECS
+}
+
+fn default_max_tool_output_bytes() -> usize {
+    limits::DEFAULT_MAX_TOOL_OUTPUT_BYTES
+}
+
+fn default_max_tool_result_bytes() -> usize {
+    limits::DEFAULT_MAX_TOOL_RESULT_BYTES
+}
+
 fn default_provider() -> ModelProvider {
     ModelProvider::default()
 }
@@ -55,13 +228,41 @@ fn default_ollama_config() -> OllamaConfig {
     OllamaConfig::default()
 }
 
+fn default_long_request_hint_secs() -> u64 {
+    30
+}
+
+fn default_summary_keep_turns() -> usize {
+    4
+}
+
+fn default_stream() -> bool {
+    true
+}
+
+fn default_preflight_check() -> bool {
+    true
+}
+
+/// Shown in place of a configured API key by `config edit`; left as is, the key is kept
+const KEPT_API_KEY: &str = "<unchanged>";
+
+/// Schema version written to new config files; older files are upgraded on load
+pub const CONFIG_VERSION: u32 = 1;
+
 /// Application configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
+    /// Schema version of the file this was read from (0 for files before versioning)
+    #[serde(default)]
+    pub version: u32,
     /// Gemini API key
     pub api_key: String,
     /// Default model to use
     pub default_model: String,
+    /// Default model per provider, taking precedence over `default_model`
+    #[serde(default)]
+    pub default_models: HashMap<ModelProvider, String>,
     /// Default system instruction
     pub default_system_instruction: Option<String>,
     /// Auto-save sessions
@@ -71,39 +272,114 @@ pub struct Config {
     /// Preferred model provider
     #[serde(default = "default_provider")]
     pub provider: ModelProvider,
+    /// Provider to retry against when the primary one fails with a quota or auth error
+    #[serde(default)]
+    pub fallback_provider: Option<ModelProvider>,
     /// Provider-specific configuration for Ollama
     #[serde(default = "default_ollama_config")]
     pub ollama: OllamaConfig,
+    /// Provider-specific configuration for Anthropic
+    #[serde(default)]
+    pub anthropic: AnthropicConfig,
+    /// Stream responses as they are generated
+    #[serde(default = "default_stream")]
+    pub stream: bool,
+    /// Check that the provider is reachable before an interactive chat starts
+    #[serde(default = "default_preflight_check")]
+    pub preflight_check: bool,
+    /// Ask the model to continue a streamed reply that was cut off instead of starting over
+    #[serde(default)]
+    pub resume_on_stream_drop: bool,
+    /// Keep only this many recent exchanges in interactive sessions (unset or 0 for no limit)
+    #[serde(default)]
+    pub max_history: Option<usize>,
+    /// Ask the model for a short session title after the first exchange
+    #[serde(default)]
+    pub auto_title: bool,
+    /// Number of recent messages kept verbatim by /summarize
+    #[serde(default = "default_summary_keep_turns")]
+    pub summary_keep_turns: usize,
+    /// Maximum model requests per minute from this process (unset or 0 for no limit)
+    #[serde(default)]
+    pub rate_limit_per_minute: Option<u32>,
+    /// Proxy URL for all provider requests; overrides `HTTP_PROXY` / `HTTPS_PROXY`
+    #[serde(default)]
+    pub proxy: Option<String>,
+    /// Extra headers sent with every provider request, e.g. for an API gateway
+    #[serde(default)]
+    pub http_headers: HashMap<String, String>,
+    /// Seconds a provider request may take before it is abandoned (default 300)
+    #[serde(default)]
+    pub request_timeout: Option<u64>,
+    /// Terminal output preferences
+    #[serde(default)]
+    pub ui: UiSettings,
+    /// Agent mode settings
+    #[serde(default)]
+    pub agent: AgentSettings,
+    /// Path values as written in the config file, before `~` / `$VAR` expansion
+    #[serde(skip)]
+    raw_paths: RawPaths,
+    /// Where settings that were not left at their defaults came from
+    #[serde(skip)]
+    sources: HashMap<String, Source>,
+}
+
+/// Unexpanded path settings, written back on save so the file stays portable
+#[derive(Debug, Clone, Default)]
+struct RawPaths {
+    sessions_dir: Option<PathBuf>,
+    ollama_endpoint: Option<String>,
 }
 
 impl Default for Config {
     fn default() -> Self {
         let config_dir = get_config_dir();
         Self {
+            version: CONFIG_VERSION,
             api_key: String::new(),
             default_model: "gemini-2.5-flash".to_string(),
+            default_models: HashMap::new(),
             default_system_instruction: None,
             auto_save: false,
             sessions_dir: config_dir.join("sessions"),
+            rate_limit_per_minute: None,
+            proxy: None,
+            http_headers: HashMap::new(),
+            request_timeout: None,
+            raw_paths: RawPaths::default(),
+            sources: HashMap::new(),
             provider: ModelProvider::default(),
+            fallback_provider: None,
             ollama: OllamaConfig::default(),
+            anthropic: AnthropicConfig::default(),
+            stream: default_stream(),
+            preflight_check: default_preflight_check(),
+            resume_on_stream_drop: false,
+            max_history: None,
+            auto_title: false,
+            summary_keep_turns: default_summary_keep_turns(),
+            ui: UiSettings::default(),
+            agent: AgentSettings::default(),
         }
     }
 }
 
 impl Config {
     /// Load configuration from file or environment
-    pub async fn load() -> Result<Self> {
+    pub async fn load() -> Result<Self, ChatterError> {
         // Defer API key validation to the point where we actually need it so that
         // users can run Ollama-only workflows without configuring Gemini first.
         Self::load_with_api_key_required(false).await
     }
 
     /// Load configuration, optionally requiring an API key
-    pub async fn load_with_api_key_required(require_api_key: bool) -> Result<Self> {
+    pub async fn load_with_api_key_required(require_api_key: bool) -> Result<Self, ChatterError> {
         // First try to load from config file
         if let Ok(config) = Self::load_from_file().await {
-            if !require_api_key || !config.provider.requires_api_key() || !config.api_key.is_empty()
+            if !require_api_key
+                || !config.provider.requires_api_key()
+                || !config.provider_api_key().is_empty()
             {
                 return Ok(config);
             }
@@ -113,13 +389,19 @@ impl Config {
         let mut config = Self::default();
 
         // Try to get API key from environment variable
+        if let Ok(api_key) = std::env::var("ANTHROPIC_API_KEY") {
+            config.anthropic.api_key = api_key;
+            config.set_source("anthropic.api_key", Source::Env);
+        }
         if config.provider.requires_api_key() {
             if let Ok(api_key) = std::env::var("GEMINI_API_KEY") {
                 config.api_key = api_key;
+                config.set_source("api_key", Source::Env);
             } else if require_api_key && config.api_key.is_empty() {
-                return Err(anyhow!(
-                    "No API key found. Please set GEMINI_API_KEY environment variable or run 'chatter config set-api-key'"
-                ));
+                return Err(ChatterError::MissingApiKey {
+                    provider: config.provider.display_name(),
+                    hint: "Please set GEMINI_API_KEY environment variable or run 'chatter config set-api-key'".to_string(),
+                });
             }
         }
 
@@ -134,17 +416,148 @@ impl Config {
         }
 
         let content = fs::read_to_string(&config_path)?;
-        let config: Config = serde_json::from_str(&content)?;
+        let value: serde_json::Value = serde_json::from_str(&content)?;
+        let from = migrate::version_of(&value);
+        let config = Self::from_file_value(value)?;
+
+        if from < CONFIG_VERSION {
+            tracing::warn!(
+                "Upgraded {} from config version {} to {}",
+                config_path.display(),
+                from,
+                CONFIG_VERSION
+            );
+            if let Err(e) = config.save().await {
+                tracing::warn!("Could not save the upgraded config: {}", e);
+            }
+        }
+        Ok(config)
+    }
+
+    /// Build the configuration from the parsed config file, upgrading older versions
+    fn from_file_value(value: serde_json::Value) -> Result<Self> {
+        let keys = provenance::file_keys(&value);
+        let mut config: Config = serde_json::from_value(migrate::migrate(value))?;
+        for key in keys {
+            config.set_source(&key, Source::File);
+        }
+        config.expand_paths();
         Ok(config)
     }
 
+    /// Expand `~` and environment variables in `sessions_dir` and `ollama.endpoint`
+    ///
+    /// Undefined variables are kept verbatim. The original values are remembered so
+    /// that saving writes them back unexpanded.
+    fn expand_paths(&mut self) {
+        let raw_sessions_dir = self.sessions_dir.to_string_lossy().into_owned();
+        let expanded_sessions_dir = settings::expand_env_vars(&raw_sessions_dir);
+        if expanded_sessions_dir != raw_sessions_dir {
+            self.raw_paths.sessions_dir = Some(self.sessions_dir.clone());
+            self.sessions_dir = PathBuf::from(expanded_sessions_dir);
+        }
+
+        let expanded_endpoint = settings::expand_env_vars(&self.ollama.endpoint);
+        if expanded_endpoint != self.ollama.endpoint {
+            self.raw_paths.ollama_endpoint = Some(self.ollama.endpoint.clone());
+            self.ollama.endpoint = expanded_endpoint;
+        }
+    }
+
+    /// Copy of this configuration with unchanged expanded paths restored to their raw form
+    fn for_disk(&self) -> Config {
+        let mut config = self.clone();
+
+        if let Some(raw) = &self.raw_paths.sessions_dir {
+            let expanded = settings::expand_env_vars(&raw.to_string_lossy());
+            if self.sessions_dir.as_os_str() == expanded.as_str() {
+                config.sessions_dir = raw.clone();
+            }
+        }
+
+        if let Some(raw) = &self.raw_paths.ollama_endpoint {
+            if settings::expand_env_vars(raw) == self.ollama.endpoint {
+                config.ollama.endpoint = raw.clone();
+            }
+        }
+
+        config
+    }
+
+    /// Proxy, header, and timeout settings for the provider HTTP clients
+    pub fn http_settings(&self) -> HttpSettings {
+        HttpSettings {
+            proxy: self.proxy.clone(),
+            headers: self.http_headers.clone(),
+            request_timeout: self.request_timeout.map(Duration::from_secs),
+        }
+    }
+
+    /// Override the request timeout for this run, e.g. from `--timeout`
+    pub fn set_request_timeout(&mut self, secs: u64) -> Result<()> {
+        check_request_timeout(secs)?;
+        self.request_timeout = Some(secs);
+        self.set_source("request_timeout", Source::Cli);
+        Ok(())
+    }
+
+    /// Default model for a provider, falling back to `default_model`
+    pub fn default_model_for(&self, provider: &ModelProvider) -> &str {
+        self.default_models
+            .get(provider)
+            .map(String::as_str)
+            .unwrap_or(&self.default_model)
+    }
+
+    /// Point a provider at another server for this run, without saving the change
+    ///
+    /// Only providers with a configurable endpoint (Ollama and Anthropic) accept
+    /// an override, and the URL must be an absolute http(s) URL.
+    pub fn set_endpoint(&mut self, provider: &ModelProvider, url: &str) -> Result<()> {
+        let parsed = reqwest::Url::parse(url.trim())
+            .map_err(|e| anyhow!("Invalid endpoint '{url}': {e}"))?;
+        if !matches!(parsed.scheme(), "http" | "https") || !parsed.has_host() {
+            return Err(anyhow!(
+                "Invalid endpoint '{url}': expected an http:// or https:// URL"
+            ));
+        }
+
+        let endpoint = url.trim().to_string();
+        match provider {
+            ModelProvider::Ollama => {
+                self.ollama.endpoint = endpoint;
+                self.set_source("ollama.endpoint", Source::Cli);
+            }
+            ModelProvider::Anthropic => {
+                self.anthropic.endpoint = endpoint;
+                self.set_source("anthropic.endpoint", Source::Cli);
+            }
+            ModelProvider::Gemini => {
+                return Err(anyhow!(
+                    "{} does not support a custom endpoint",
+                    provider.display_name()
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    /// API key for the configured provider, empty when none is needed or set
+    pub fn provider_api_key(&self) -> &str {
+        match self.provider {
+            ModelProvider::Gemini => &self.api_key,
+            ModelProvider::Ollama => "",
+            ModelProvider::Anthropic => &self.anthropic.api_key,
+        }
+    }
+
     /// Save configuration to file
     pub async fn save(&self) -> Result<()> {
         let config_dir = get_config_dir();
         fs::create_dir_all(&config_dir)?;
 
         let config_path = get_config_file_path();
-        let content = serde_json::to_string_pretty(self)?;
+        let content = serde_json::to_string_pretty(&self.for_disk())?;
         fs::write(&config_path, content)?;
 
         // Also create sessions directory
@@ -173,16 +586,84 @@ impl Config {
         Ok(())
     }
 
+    /// Open the config file in `$EDITOR` and save it once it parses
+    ///
+    /// API keys are masked in the editor and kept unless replaced. When the edited
+    /// text is not a valid configuration, the file is left alone and the text is
+    /// offered for another round of editing. Returns whether the file was saved.
+    pub async fn edit_interactive() -> Result<bool> {
+        let path = get_config_file_path();
+        let current = if path.exists() {
+            Self::load_from_file().await.ok()
+        } else {
+            Some(Self::default())
+        };
+        // A file that no longer parses is opened verbatim so it can be fixed
+        let mut text = match &current {
+            Some(config) => config.masked_json()?,
+            None => fs::read_to_string(&path)?,
+        };
+        let current = current.unwrap_or_default();
+
+        loop {
+            let Some(edited) = Editor::new().extension(".json").edit(&text)? else {
+                println!("Edit cancelled; configuration unchanged");
+                return Ok(false);
+            };
+
+            match Self::from_edited_json(&edited, &current) {
+                Ok(config) => {
+                    config.save().await?;
+                    return Ok(true);
+                }
+                Err(e) => {
+                    eprintln!("❌ {e}");
+                    let again = Confirm::new()
+                        .with_prompt("Edit again? (the current configuration is kept either way)")
+                        .default(true)
+                        .interact()?;
+                    if !again {
+                        println!("Configuration unchanged");
+                        return Ok(false);
+                    }
+                    text = edited;
+                }
+            }
+        }
+    }
+
+    /// Configuration as written to disk, with API keys replaced by a placeholder
+    fn masked_json(&self) -> Result<String> {
+        let mut config = self.for_disk();
+        for key in [&mut config.api_key, &mut config.anthropic.api_key] {
+            if !key.is_empty() {
+                *key = KEPT_API_KEY.to_string();
+            }
+        }
+        Ok(serde_json::to_string_pretty(&config)?)
+    }
+
+    /// Parse text edited by `config edit`, restoring API keys left masked
+    fn from_edited_json(text: &str, current: &Config) -> Result<Config> {
+        let mut config: Config = serde_json::from_str(text)
+            .map_err(|e| anyhow!("The edited configuration is not valid: {}", e))?;
+        if config.api_key == KEPT_API_KEY {
+            config.api_key = current.api_key.clone();
+        }
+        if config.anthropic.api_key == KEPT_API_KEY {
+            config.anthropic.api_key = current.anthropic.api_key.clone();
+        }
+        config.expand_paths();
+        Ok(config)
+    }
+
     /// Display current configuration
     pub fn display(&self) {
         println!("📋 Current Configuration:");
-        println!(
-            "  Provider: {}",
-            match self.provider {
-                ModelProvider::Gemini => "Gemini",
-                ModelProvider::Ollama => "Ollama",
-            }
-        );
+        println!("  Provider: {}", self.provider.display_name());
+        if let Some(fallback) = &self.fallback_provider {
+            println!("  Fallback Provider: {}", fallback.display_name());
+        }
         println!(
             "  API Key: {}",
             if self.api_key.is_empty() {
@@ -192,7 +673,48 @@ impl Config {
             }
         );
         println!("  Default Model: {}", self.default_model);
+        let mut provider_defaults: Vec<_> = self.default_models.iter().collect();
+        provider_defaults.sort_by_key(|(provider, _)| provider.display_name());
+        for (provider, model) in provider_defaults {
+            println!("  Default Model ({}): {}", provider.display_name(), model);
+        }
         println!("  Auto-save: {}", self.auto_save);
+        println!("  Streaming: {}", self.stream);
+        println!("  Resume on stream drop: {}", self.resume_on_stream_drop);
+        println!("  Preflight Check: {}", self.preflight_check);
+        if let Some(max_history) = self.max_history {
+            println!("  Max History: {max_history} exchanges");
+        }
+        println!("  Auto Title: {}", self.auto_title);
+        println!("  Summary Keep Turns: {}", self.summary_keep_turns);
+        match self.rate_limit_per_minute {
+            Some(limit) if limit > 0 => println!("  Rate Limit: {limit} requests/minute"),
+            _ => println!("  Rate Limit: none"),
+        }
+        if let Some(proxy) = &self.proxy {
+            println!("  Proxy: {proxy}");
+        }
+        if !self.http_headers.is_empty() {
+            // Header values often carry credentials
+            let mut names: Vec<&str> = self.http_headers.keys().map(String::as_str).collect();
+            names.sort_unstable();
+            println!("  Extra Headers: {}", names.join(", "));
+        }
+        if let Some(secs) = self.request_timeout {
+            println!("  Request Timeout: {secs}s");
+        }
+        println!("  Quiet: {}", self.ui.quiet);
+        println!("  Theme: {:?}", self.ui.theme);
+        println!(
+            "  Long request hint after: {}s",
+            self.ui.long_request_hint_secs
+        );
+        if self.ui.stream_delay_ms > 0 {
+            println!(
+                "  Stream delay: {}ms per character",
+                self.ui.stream_delay_ms
+            );
+        }
         println!("  Sessions Directory: {}", self.sessions_dir.display());
         if let Some(ref system) = self.default_system_instruction {
             println!("  Default System Instruction: {system}");
@@ -200,6 +722,39 @@ impl Config {
         if matches!(self.provider, ModelProvider::Ollama) {
             println!("  Ollama Endpoint: {}", self.ollama.endpoint);
         }
+        if matches!(self.provider, ModelProvider::Anthropic) {
+            println!("  Anthropic Endpoint: {}", self.anthropic.endpoint);
+            println!(
+                "  Anthropic API Key: {}",
+                if self.anthropic.api_key.is_empty() {
+                    "Not set"
+                } else {
+                    "Set (hidden)"
+                }
+            );
+        }
+        println!(
+            "  Agent Interaction Timeout: {}s",
+            self.agent.interaction_timeout_secs
+        );
+        println!(
+            "  Agent Tool Output Limit: {} bytes",
+            self.agent.max_tool_output_bytes
+        );
+        println!(
+            "  Agent Tool Result Limit: {} bytes",
+            self.agent.max_tool_result_bytes
+        );
+        println!("  Agent Confirm Writes: {}", self.agent.confirm_writes);
+        if !self.agent.external_tools.is_empty() {
+            let names: Vec<&str> = self
+                .agent
+                .external_tools
+                .iter()
+                .map(|tool| tool.name.as_str())
+                .collect();
+            println!("  External Tools: {}", names.join(", "));
+        }
     }
 
     /// Reset configuration to defaults
@@ -224,6 +779,117 @@ fn get_config_dir() -> PathBuf {
 }
 
 /// Get the configuration file path
-fn get_config_file_path() -> PathBuf {
+pub fn get_config_file_path() -> PathBuf {
     get_config_dir().join("config.json")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edited_config_is_validated_and_keeps_masked_keys() {
+        let mut current = Config {
+            api_key: "gemini-secret".to_string(),
+            ..Config::default()
+        };
+        current.anthropic.api_key = "anthropic-secret".to_string();
+
+        let masked = current.masked_json().unwrap();
+        assert!(!masked.contains("gemini-secret"));
+        assert!(!masked.contains("anthropic-secret"));
+
+        let malformed = masked.replacen('{', "{ \"stream\": nope,", 1);
+        let error = Config::from_edited_json(&malformed, &current).unwrap_err();
+        assert!(error.to_string().contains("not valid"));
+        assert!(Config::from_edited_json("{}", &current).is_err());
+
+        let edited = masked.replace("\"auto_save\": false", "\"auto_save\": true");
+        let config = Config::from_edited_json(&edited, &current).unwrap();
+        assert!(config.auto_save);
+        assert_eq!(config.api_key, "gemini-secret");
+        assert_eq!(config.anthropic.api_key, "anthropic-secret");
+
+        // A key typed over the placeholder replaces the old one
+        let replaced = masked.replacen(KEPT_API_KEY, "new-key", 1);
+        assert_eq!(
+            Config::from_edited_json(&replaced, &current)
+                .unwrap()
+                .api_key,
+            "new-key"
+        );
+    }
+
+    #[test]
+    fn expanded_paths_are_saved_in_raw_form() {
+        let mut config = Config {
+            sessions_dir: PathBuf::from("~/chatter-sessions"),
+            ..Config::default()
+        };
+        config.ollama.endpoint = "http://localhost:11434".to_string();
+        config.expand_paths();
+
+        if let Some(home) = dirs::home_dir() {
+            assert_eq!(config.sessions_dir, home.join("chatter-sessions"));
+        }
+        assert_eq!(
+            config.for_disk().sessions_dir,
+            PathBuf::from("~/chatter-sessions")
+        );
+
+        // Paths changed after loading are saved as the new value
+        config.sessions_dir = PathBuf::from("/srv/sessions");
+        assert_eq!(
+            config.for_disk().sessions_dir,
+            PathBuf::from("/srv/sessions")
+        );
+    }
+
+    #[test]
+    fn default_model_follows_the_selected_provider() {
+        let config: Config = serde_json::from_str(
+            r#"{
+                "api_key": "",
+                "default_model": "gemini-2.5-flash",
+                "default_models": {"ollama": "qwen3", "anthropic": "claude-sonnet-4-5"},
+                "auto_save": false,
+                "sessions_dir": "/tmp/sessions"
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.default_model_for(&ModelProvider::Ollama), "qwen3");
+        assert_eq!(
+            config.default_model_for(&ModelProvider::Anthropic),
+            "claude-sonnet-4-5"
+        );
+        // Providers without an entry use the global default
+        assert_eq!(
+            config.default_model_for(&ModelProvider::Gemini),
+            "gemini-2.5-flash"
+        );
+    }
+
+    #[test]
+    fn endpoint_override_replaces_the_configured_endpoint() {
+        let mut config = Config::default();
+        config.ollama.endpoint = "http://localhost:11434".to_string();
+
+        config
+            .set_endpoint(&ModelProvider::Ollama, "http://gpu-box:11434")
+            .unwrap();
+        assert_eq!(config.ollama.endpoint, "http://gpu-box:11434");
+        assert_eq!(config.anthropic.endpoint, ANTHROPIC_API_BASE);
+
+        assert!(config
+            .set_endpoint(&ModelProvider::Ollama, "gpu-box:11434")
+            .is_err());
+        assert!(config
+            .set_endpoint(&ModelProvider::Anthropic, "not a url")
+            .is_err());
+        assert!(config
+            .set_endpoint(&ModelProvider::Gemini, "https://proxy.example.com")
+            .is_err());
+        assert_eq!(config.ollama.endpoint, "http://gpu-box:11434");
+    }
+}
@@ -0,0 +1,215 @@
+//! Where effective settings come from
+//!
+//! A setting can be given on the command line, by an environment variable, in
+//! the config file, or left at its default. The config remembers which for the
+//! settings that can differ, so `/whoami` can show what is actually in effect.
+
+use super::{Config, ModelProvider};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fmt;
+
+/// Origin of a resolved setting
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Cli,
+    Env,
+    File,
+    Default,
+    /// Taken from a loaded session or changed during the chat
+    Session,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Source::Cli => "cli",
+            Source::Env => "env",
+            Source::File => "file",
+            Source::Default => "default",
+            Source::Session => "session",
+        })
+    }
+}
+
+/// A setting's effective value and where it came from
+#[derive(Debug, Clone, PartialEq)]
+pub struct Resolved<T> {
+    pub value: T,
+    pub source: Source,
+}
+
+impl<T> Resolved<T> {
+    pub fn new(value: T, source: Source) -> Self {
+        Self { value, source }
+    }
+}
+
+/// Settings present in a config file, as `field` and `field.subfield`
+pub(super) fn file_keys(value: &Value) -> HashSet<String> {
+    let mut keys = HashSet::new();
+    for (name, field) in value.as_object().into_iter().flatten() {
+        keys.insert(name.clone());
+        for nested in field
+            .as_object()
+            .into_iter()
+            .flat_map(|object| object.keys())
+        {
+            keys.insert(format!("{name}.{nested}"));
+        }
+    }
+    keys
+}
+
+/// Name of a provider as written in the config file
+fn config_name(provider: &ModelProvider) -> String {
+    serde_json::to_value(provider)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+impl Config {
+    /// Where the value of a setting, named like `ollama.endpoint`, came from
+    pub fn source_of(&self, key: &str) -> Source {
+        self.sources.get(key).copied().unwrap_or(Source::Default)
+    }
+
+    /// Record where the value of a setting came from
+    pub(super) fn set_source(&mut self, key: &str, source: Source) {
+        self.sources.insert(key.to_string(), source);
+    }
+
+    /// Provider for this run: `--provider`, else the configured one
+    pub fn resolve_provider(&self, cli: Option<ModelProvider>) -> Resolved<ModelProvider> {
+        match cli {
+            Some(provider) => Resolved::new(provider, Source::Cli),
+            None => Resolved::new(self.provider.clone(), self.source_of("provider")),
+        }
+    }
+
+    /// Model for a provider: `--model`, else its `default_models` entry, else `default_model`
+    pub fn resolve_model(&self, provider: &ModelProvider, cli: Option<&str>) -> Resolved<String> {
+        if let Some(model) = cli {
+            return Resolved::new(model.to_string(), Source::Cli);
+        }
+        match self.default_models.get(provider) {
+            Some(model) => Resolved::new(
+                model.clone(),
+                self.source_of(&format!("default_models.{}", config_name(provider))),
+            ),
+            None => Resolved::new(self.default_model.clone(), self.source_of("default_model")),
+        }
+    }
+
+    /// Server URL used for a provider, if it has a configurable one
+    pub fn resolve_endpoint(&self, provider: &ModelProvider) -> Option<Resolved<String>> {
+        let (key, endpoint) = match provider {
+            ModelProvider::Gemini => return None,
+            ModelProvider::Ollama => ("ollama.endpoint", &self.ollama.endpoint),
+            ModelProvider::Anthropic => ("anthropic.endpoint", &self.anthropic.endpoint),
+        };
+        Some(Resolved::new(endpoint.clone(), self.source_of(key)))
+    }
+
+    /// Where the API key for a provider comes from, or `None` when there is none
+    pub fn api_key_source(&self, provider: &ModelProvider) -> Option<Source> {
+        self.api_key_source_with_env(provider, |name| std::env::var(name).ok())
+    }
+
+    /// `api_key_source` reading the environment through `env`
+    fn api_key_source_with_env(
+        &self,
+        provider: &ModelProvider,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Option<Source> {
+        let set = |key: &str| !key.trim().is_empty();
+        match provider {
+            ModelProvider::Gemini => set(&self.api_key).then(|| self.source_of("api_key")),
+            ModelProvider::Ollama => None,
+            // The client falls back to the environment when the config has no key
+            ModelProvider::Anthropic if set(&self.anthropic.api_key) => {
+                Some(self.source_of("anthropic.api_key"))
+            }
+            ModelProvider::Anthropic => env("ANTHROPIC_API_KEY")
+                .filter(|key| set(key))
+                .map(|_| Source::Env),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolved_settings_name_their_source() {
+        let mut config = Config::from_file_value(serde_json::json!({
+            "provider": "ollama",
+            "default_models": {"ollama": "qwen3"},
+            "anthropic": {"api_key": "from-file"}
+        }))
+        .unwrap();
+        config
+            .set_endpoint(&ModelProvider::Ollama, "http://gpu-box:11434")
+            .unwrap();
+
+        let provider = config.resolve_provider(None);
+        assert_eq!(provider, Resolved::new(ModelProvider::Ollama, Source::File));
+        assert_eq!(
+            config.resolve_provider(Some(ModelProvider::Gemini)).source,
+            Source::Cli
+        );
+
+        assert_eq!(
+            config.resolve_model(&ModelProvider::Ollama, None),
+            Resolved::new("qwen3".to_string(), Source::File)
+        );
+        assert_eq!(
+            config.resolve_model(&ModelProvider::Gemini, None),
+            Resolved::new("gemini-2.5-flash".to_string(), Source::Default)
+        );
+        assert_eq!(
+            config
+                .resolve_model(&ModelProvider::Ollama, Some("llama3.1"))
+                .source,
+            Source::Cli
+        );
+
+        assert_eq!(
+            config.resolve_endpoint(&ModelProvider::Ollama),
+            Some(Resolved::new(
+                "http://gpu-box:11434".to_string(),
+                Source::Cli
+            ))
+        );
+        assert_eq!(
+            config
+                .resolve_endpoint(&ModelProvider::Anthropic)
+                .unwrap()
+                .source,
+            Source::Default
+        );
+        assert_eq!(config.resolve_endpoint(&ModelProvider::Gemini), None);
+
+        let no_env = |_: &str| None;
+        let env = |name: &str| (name == "ANTHROPIC_API_KEY").then(|| "from-env".to_string());
+        assert_eq!(
+            config.api_key_source_with_env(&ModelProvider::Anthropic, env),
+            Some(Source::File)
+        );
+        assert_eq!(
+            config.api_key_source_with_env(&ModelProvider::Gemini, no_env),
+            None
+        );
+        config.anthropic.api_key.clear();
+        assert_eq!(
+            config.api_key_source_with_env(&ModelProvider::Anthropic, env),
+            Some(Source::Env)
+        );
+        assert_eq!(
+            config.api_key_source_with_env(&ModelProvider::Anthropic, no_env),
+            None
+        );
+    }
+}
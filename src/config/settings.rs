@@ -1,6 +1,86 @@
 //! Configuration settings utilities
 //!
-//! This module can be extended with additional settings functionality.
+//! Expansion of `~` and environment variables in configured paths, so that a
+//! config file can be shared across machines.
 
-// Currently empty - settings are handled in the main config module
-// This can be expanded later with settings-specific utilities
+/// Expand a leading `~` and `$VAR` / `${VAR}` references using the process environment
+///
+/// Undefined variables are left in place verbatim rather than treated as errors.
+pub fn expand_env_vars(input: &str) -> String {
+    expand_with(input, dirs::home_dir(), |name| std::env::var(name).ok())
+}
+
+/// Expansion with injectable home directory and variable lookup
+fn expand_with(
+    input: &str,
+    home: Option<std::path::PathBuf>,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+
+    if let Some(home) = home {
+        if rest == "~" || rest.starts_with("~/") {
+            output.push_str(&home.to_string_lossy());
+            rest = &rest[1..];
+        }
+    }
+
+    while let Some(index) = rest.find('$') {
+        output.push_str(&rest[..index]);
+        let after = &rest[index + 1..];
+
+        let (name, consumed) = if let Some(braced) = after.strip_prefix('{') {
+            match braced.find('}') {
+                Some(end) => (&braced[..end], end + 2),
+                None => ("", 0),
+            }
+        } else {
+            let end = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            (&after[..end], end)
+        };
+
+        match (name.is_empty(), lookup(name)) {
+            (false, Some(value)) => output.push_str(&value),
+            _ => output.push_str(&rest[index..index + 1 + consumed]),
+        }
+        rest = &after[consumed..];
+    }
+
+    output.push_str(rest);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn expand(input: &str) -> String {
+        expand_with(input, Some(PathBuf::from("/home/ada")), |name| match name {
+            "HOME" => Some("/home/ada".to_string()),
+            "XDG_DATA_HOME" => Some("/data".to_string()),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn expands_home_and_variables() {
+        assert_eq!(expand("~"), "/home/ada");
+        assert_eq!(expand("~/chats"), "/home/ada/chats");
+        assert_eq!(expand("$HOME/foo"), "/home/ada/foo");
+        assert_eq!(expand("${XDG_DATA_HOME}/chatter"), "/data/chatter");
+        // Only a leading tilde refers to the home directory
+        assert_eq!(expand("/tmp/~backup"), "/tmp/~backup");
+    }
+
+    #[test]
+    fn undefined_variables_pass_through() {
+        assert_eq!(expand("$NOPE/sessions"), "$NOPE/sessions");
+        assert_eq!(expand("${NOPE}/x"), "${NOPE}/x");
+        assert_eq!(expand("cost $5"), "cost $5");
+        assert_eq!(expand("${unterminated"), "${unterminated");
+    }
+}
@@ -0,0 +1,248 @@
+//! Error types
+//!
+//! `ChatterError` is returned from the main entry points (`LlmClient`,
+//! `Agent::execute_tool`, `Config::load`) so callers can branch on what went
+//! wrong. Internal code keeps using `anyhow` and converts at those boundaries.
+//! The same variants pick the process exit code, so scripts can tell failures
+//! apart too.
+
+use thiserror::Error;
+
+/// Failure modes callers may want to handle differently
+#[derive(Debug, Error)]
+pub enum ChatterError {
+    /// The provider needs an API key that is not configured
+    #[error("{provider} provider requires an API key. {hint}")]
+    MissingApiKey {
+        provider: &'static str,
+        /// How to supply the key
+        hint: String,
+    },
+    /// The provider could not be reached at all
+    #[error("{message}")]
+    ProviderUnavailable {
+        provider: &'static str,
+        message: String,
+    },
+    /// The agent's safety checks rejected a tool call
+    #[error("Safety check failed: {0}")]
+    SafetyViolation(String),
+    /// The requested tool is not registered (or was disabled)
+    #[error("Unknown tool: {0}")]
+    ToolNotFound(String),
+    /// The conversation no longer fits in the model's context window
+    #[error("The conversation is too long for the model: {message}")]
+    ContextOverflow { status: u16, message: String },
+    /// The provider rejected the request with an HTTP error status
+    #[error("{body}")]
+    Api { status: u16, body: String },
+    /// Anything else
+    #[error(transparent)]
+    Other(anyhow::Error),
+}
+
+/// Exit code for failures without a more specific one
+pub const EXIT_FAILURE: u8 = 1;
+/// Missing or rejected API key; clap also exits with 2 for invalid usage
+pub const EXIT_CONFIG: u8 = 2;
+/// The provider could not be reached or did not answer in time
+pub const EXIT_NETWORK: u8 = 3;
+/// A safety check rejected a tool call
+pub const EXIT_SAFETY: u8 = 4;
+/// The conversation does not fit in the model's context window
+pub const EXIT_CONTEXT_OVERFLOW: u8 = 5;
+/// The provider rejected the request for another reason, such as a quota
+pub const EXIT_REJECTED: u8 = 6;
+
+/// Phrases providers use when the prompt exceeds the context window
+const CONTEXT_OVERFLOW_MARKERS: &[&str] = &[
+    "context length",
+    "context window",
+    "context_length_exceeded",
+    "prompt is too long",
+    "too many tokens",
+    "exceeds the maximum number of tokens",
+    "input token count",
+];
+
+impl ChatterError {
+    /// Classify an HTTP error response from a provider
+    pub fn api(status: reqwest::StatusCode, body: String) -> Self {
+        let status = status.as_u16();
+        let lower = body.to_lowercase();
+        if matches!(status, 400 | 413)
+            && CONTEXT_OVERFLOW_MARKERS
+                .iter()
+                .any(|marker| lower.contains(marker))
+        {
+            return Self::ContextOverflow {
+                status,
+                message: body,
+            };
+        }
+        Self::Api { status, body }
+    }
+
+    /// Whether the request failed for quota, rate-limit, or credential reasons
+    pub fn is_quota_or_auth(&self) -> bool {
+        matches!(
+            self,
+            Self::MissingApiKey { .. }
+                | Self::Api {
+                    status: 401 | 402 | 403 | 429,
+                    ..
+                }
+        )
+    }
+}
+
+impl ChatterError {
+    /// Process exit code for this kind of failure
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Self::MissingApiKey { .. }
+            | Self::Api {
+                status: 401 | 403, ..
+            } => EXIT_CONFIG,
+            Self::ProviderUnavailable { .. } => EXIT_NETWORK,
+            Self::SafetyViolation(_) => EXIT_SAFETY,
+            Self::ContextOverflow { .. } => EXIT_CONTEXT_OVERFLOW,
+            Self::Api { .. } => EXIT_REJECTED,
+            Self::ToolNotFound(_) => EXIT_FAILURE,
+            Self::Other(error) => exit_code(error),
+        }
+    }
+}
+
+/// Process exit code for an error that ends the program
+///
+/// Looks through the whole chain of causes, so context added on the way up
+/// does not hide the original failure. Connection failures and timeouts that
+/// never became a `ChatterError` still count as network errors.
+pub fn exit_code(error: &anyhow::Error) -> u8 {
+    for cause in error.chain() {
+        if let Some(error) = cause.downcast_ref::<ChatterError>() {
+            return error.exit_code();
+        }
+        if let Some(error) = cause.downcast_ref::<reqwest::Error>() {
+            if error.is_connect() || error.is_timeout() {
+                return EXIT_NETWORK;
+            }
+        }
+    }
+    EXIT_FAILURE
+}
+
+impl From<anyhow::Error> for ChatterError {
+    /// Recover a `ChatterError` that was passed through `anyhow`, keeping its variant
+    fn from(error: anyhow::Error) -> Self {
+        match error.downcast::<ChatterError>() {
+            Ok(error) => error,
+            Err(error) => Self::Other(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::StatusCode;
+
+    #[test]
+    fn api_errors_are_classified_by_status_and_body() {
+        let overflow = ChatterError::api(
+            StatusCode::BAD_REQUEST,
+            "prompt is too long: 210000 tokens > 200000 maximum".to_string(),
+        );
+        assert!(matches!(
+            overflow,
+            ChatterError::ContextOverflow { status: 400, .. }
+        ));
+
+        let quota = ChatterError::api(StatusCode::TOO_MANY_REQUESTS, "slow down".to_string());
+        assert!(matches!(quota, ChatterError::Api { status: 429, .. }));
+        assert!(quota.is_quota_or_auth());
+        assert_eq!(quota.to_string(), "slow down");
+
+        let bad_request = ChatterError::api(StatusCode::BAD_REQUEST, "invalid role".to_string());
+        assert!(!bad_request.is_quota_or_auth());
+    }
+
+    #[test]
+    fn variants_survive_a_round_trip_through_anyhow() {
+        let error: anyhow::Error = ChatterError::ToolNotFound("teleport".to_string()).into();
+        assert!(matches!(
+            ChatterError::from(error),
+            ChatterError::ToolNotFound(name) if name == "teleport"
+        ));
+
+        let wrapped = anyhow::Error::from(ChatterError::api(
+            StatusCode::UNAUTHORIZED,
+            "bad key".to_string(),
+        ))
+        .context("Gemini request failed");
+        assert!(ChatterError::from(wrapped).is_quota_or_auth());
+
+        let other = ChatterError::from(anyhow::anyhow!("disk full"));
+        assert!(matches!(other, ChatterError::Other(_)));
+        assert_eq!(other.to_string(), "disk full");
+    }
+
+    #[test]
+    fn errors_map_to_distinct_exit_codes() {
+        let code =
+            |error: ChatterError| exit_code(&anyhow::Error::from(error).context("Query failed"));
+
+        let missing_key = ChatterError::MissingApiKey {
+            provider: "Gemini",
+            hint: String::new(),
+        };
+        assert_eq!(code(missing_key), EXIT_CONFIG);
+        assert_eq!(
+            code(ChatterError::api(
+                StatusCode::UNAUTHORIZED,
+                "bad key".to_string()
+            )),
+            EXIT_CONFIG
+        );
+        let unavailable = ChatterError::ProviderUnavailable {
+            provider: "Ollama",
+            message: "connection refused".to_string(),
+        };
+        assert_eq!(code(unavailable), EXIT_NETWORK);
+        assert_eq!(
+            code(ChatterError::SafetyViolation("rm -rf /".to_string())),
+            EXIT_SAFETY
+        );
+        assert_eq!(
+            code(ChatterError::api(
+                StatusCode::BAD_REQUEST,
+                "prompt is too long".to_string()
+            )),
+            EXIT_CONTEXT_OVERFLOW
+        );
+        assert_eq!(
+            code(ChatterError::api(
+                StatusCode::TOO_MANY_REQUESTS,
+                "slow down".to_string()
+            )),
+            EXIT_REJECTED
+        );
+        assert_eq!(
+            code(ChatterError::ToolNotFound("x".to_string())),
+            EXIT_FAILURE
+        );
+        assert_eq!(exit_code(&anyhow::anyhow!("disk full")), EXIT_FAILURE);
+
+        let codes = [
+            EXIT_FAILURE,
+            EXIT_CONFIG,
+            EXIT_NETWORK,
+            EXIT_SAFETY,
+            EXIT_CONTEXT_OVERFLOW,
+            EXIT_REJECTED,
+        ];
+        let distinct: std::collections::HashSet<u8> = codes.into_iter().collect();
+        assert_eq!(distinct.len(), codes.len());
+    }
+}
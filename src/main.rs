@@ -5,23 +5,72 @@
 
 use anyhow::{anyhow, Result};
 use clap::Parser;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::prelude::*;
 
 mod agent;
 mod api;
 mod chat;
 mod cli;
 mod config;
+mod error;
 mod templates;
 
-use api::LlmClient;
-use chat::ChatSession;
+use api::race::{race, RaceEntrant};
+use api::{Content, LlmClient};
+use chat::fallback::ProviderFallback;
+use chat::replay::Advance;
+use chat::tee::TeeFile;
+use chat::{ChatOptions, ChatSession, LaunchSettings};
 use cli::{Cli, Commands, TemplateAction};
-use config::{Config, ModelProvider};
+use config::{Config, ModelProvider, Resolved, Source};
 use templates::TemplateManager;
 
+/// Install the stderr log subscriber for the requested verbosity
+///
+/// Only events from this crate are emitted; dependency logs (HTTP internals in
+/// particular) stay silent so that request details cannot leak credentials.
+fn init_logging(verbose: u8) {
+    let level = match verbose {
+        0 => tracing::Level::WARN,
+        1 => tracing::Level::INFO,
+        2 => tracing::Level::DEBUG,
+        _ => tracing::Level::TRACE,
+    };
+
+    let filter = Targets::new().with_target(env!("CARGO_CRATE_NAME"), level);
+    let _ = tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(std::io::stderr)
+                .with_target(false),
+        )
+        .with(filter)
+        .try_init();
+}
+
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
+    match run().await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("Error: {error:?}");
+            ExitCode::from(error::exit_code(&error))
+        }
+    }
+}
+
+async fn run() -> Result<()> {
     let mut cli = Cli::parse();
+    init_logging(cli.verbose);
+    let overrides = RunOverrides {
+        endpoint: cli.endpoint.clone(),
+        timeout: cli.timeout,
+    };
 
     if let Some(command) = cli.command.take() {
         match command {
@@ -32,28 +81,108 @@ async fn main() -> Result<()> {
                 message,
                 model,
                 provider,
-                system,
-                template,
+                system_source,
+                no_stream,
+                agent,
+                race,
+                output,
+                force,
+                json,
+                tee,
             } => {
                 // Load configuration (API key required for queries)
-                let config = Config::load().await?;
-                handle_query_command(message, model, provider, system, template, config).await?;
+                let mut config = Config::load().await?;
+                apply_overrides(&mut config, provider, &overrides)?;
+                let output = ResponseOutput {
+                    path: output,
+                    force,
+                    json,
+                };
+                if race.is_empty() {
+                    let options = QueryOptions {
+                        no_stream,
+                        agent,
+                        dry_run: cli.dry_run,
+                        output,
+                        tee,
+                    };
+                    handle_query_command(message, model, provider, system_source, options, config)
+                        .await?;
+                } else {
+                    handle_race_query(message, race, system_source, output, config).await?;
+                }
             }
             Commands::Template { action } => {
-                handle_template_command(action).await?;
+                handle_template_command(action, &overrides).await?;
+            }
+            Commands::Models { provider } => {
+                let mut config = Config::load().await?;
+                apply_overrides(&mut config, provider, &overrides)?;
+                handle_models_command(provider, config).await?;
+            }
+            Commands::Export {
+                session,
+                format,
+                out,
+            } => {
+                handle_export_command(session, format, out).await?;
+            }
+            Commands::Sessions { action } => {
+                let config = Config::load_with_api_key_required(false).await?;
+                handle_sessions_command(action, &config)?;
+            }
+            Commands::Doctor => {
+                let mut config = Config::load().await?;
+                apply_overrides(&mut config, None, &overrides)?;
+                cli::doctor::run(&config).await?;
+            }
+            Commands::Replay {
+                session,
+                delay,
+                from,
+            } => {
+                handle_replay_command(session, delay, from).await?;
+            }
+            Commands::Batch {
+                file,
+                session,
+                fresh,
+                model,
+                provider,
+                system_source,
+            } => {
+                let mut config = Config::load().await?;
+                apply_overrides(&mut config, provider, &overrides)?;
+                let options = BatchOptions {
+                    file,
+                    session,
+                    fresh,
+                };
+                handle_batch_command(options, model, provider, system_source, config).await?;
             }
         }
         return Ok(());
     }
 
     if let Some(message) = cli.prompt.take() {
-        let config = Config::load().await?;
+        let mut config = Config::load().await?;
+        apply_overrides(&mut config, cli.provider, &overrides)?;
         handle_query_command(
             message,
             cli.model.clone(),
             cli.provider,
-            cli.system.clone(),
-            cli.template.clone(),
+            cli.system_source.clone(),
+            QueryOptions {
+                no_stream: cli.no_stream,
+                agent: cli.agent,
+                dry_run: cli.dry_run,
+                output: ResponseOutput {
+                    path: cli.output.take(),
+                    force: cli.force,
+                    json: cli.json,
+                },
+                tee: cli.tee.take(),
+            },
             config,
         )
         .await?;
@@ -61,7 +190,8 @@ async fn main() -> Result<()> {
     }
 
     // Load configuration (API key required for interactive chat)
-    let config = Config::load().await?;
+    let mut config = Config::load().await?;
+    apply_overrides(&mut config, cli.provider, &overrides)?;
     handle_interactive_chat(cli, config).await?;
     Ok(())
 }
@@ -80,6 +210,14 @@ async fn handle_config_command(action: cli::ConfigAction) -> Result<()> {
             let config = Config::load_with_api_key_required(false).await?;
             config.display();
         }
+        cli::ConfigAction::Edit => {
+            if Config::edit_interactive().await? {
+                println!(
+                    "✅ Configuration saved to {}",
+                    config::get_config_file_path().display()
+                );
+            }
+        }
         cli::ConfigAction::Reset => {
             // For resetting config, we don't require an API key
             let mut config = Config::load_with_api_key_required(false).await?;
@@ -90,92 +228,401 @@ async fn handle_config_command(action: cli::ConfigAction) -> Result<()> {
     Ok(())
 }
 
+/// Flags controlling how a one-shot query is answered
+struct QueryOptions {
+    no_stream: bool,
+    agent: bool,
+    /// Preview agent tool calls instead of performing them
+    dry_run: bool,
+    output: ResponseOutput,
+    /// File the response is also appended to as it arrives
+    tee: Option<PathBuf>,
+}
+
+/// Where and in what form a one-shot response is delivered
+struct ResponseOutput {
+    /// File to write instead of stdout
+    path: Option<PathBuf>,
+    /// Overwrite the file without asking
+    force: bool,
+    /// Wrap the response in a JSON object with the provider and model
+    json: bool,
+}
+
+impl ResponseOutput {
+    /// Whether the response can be streamed straight to stdout
+    fn is_plain_stdout(&self) -> bool {
+        self.path.is_none() && !self.json
+    }
+
+    /// Print the response or write it to the output file
+    fn emit(&self, provider: &ModelProvider, model: &str, response: &str) -> Result<()> {
+        let contents = if self.json {
+            serde_json::to_string_pretty(&serde_json::json!({
+                "provider": provider,
+                "model": model,
+                "response": response,
+            }))?
+        } else {
+            response.to_string()
+        };
+
+        let Some(path) = &self.path else {
+            println!("{contents}");
+            return Ok(());
+        };
+        if cli::commands::write_response_file(path, &contents, self.force)? {
+            eprintln!("✅ Wrote {} bytes to {}", contents.len(), path.display());
+        } else {
+            eprintln!("Not written; {} was left unchanged", path.display());
+        }
+        Ok(())
+    }
+}
+
 /// Handle one-shot query commands
 async fn handle_query_command(
     message: String,
     model: Option<String>,
     provider: Option<cli::ProviderArg>,
-    system: Option<String>,
-    template: Option<String>,
+    system_source: cli::SystemArgs,
+    options: QueryOptions,
     config: Config,
 ) -> Result<()> {
-    let provider = resolve_provider(provider, &config);
-    let client = create_llm_client(&config, &provider)?;
+    let provider = resolve_provider(provider, &config).value;
+    let client = LlmClient::from_config(&config, &provider)?;
 
-    let model_name = model.unwrap_or_else(|| config.default_model.clone());
+    let piped = cli::commands::read_piped_stdin()?;
+    let message = cli::commands::merge_piped_input(&message, piped.as_deref());
+
+    if let Some(model) = &model {
+        if let Some(warning) = client.model_warning(&provider, model).await {
+            eprintln!("⚠️  {warning}");
+        }
+    }
+    let model_name = model.unwrap_or_else(|| config.default_model_for(&provider).to_string());
 
-    // Resolve system instruction from template or direct input
-    let system_instruction = resolve_system_instruction(system, template).await?;
+    // Resolve system instruction from a file, template, or direct input
+    let system_prompt = resolve_system_instruction(system_source).await?;
 
     // Create a temporary chat session for the query
-    let mut session = ChatSession::new(model_name, provider, system_instruction);
+    let fallback = ProviderFallback::from_config(&config, &provider);
+    let mut session = ChatSession::new(model_name, provider, system_prompt.instruction);
+    session.seed_examples(&system_prompt.examples);
+    session.set_fallback(fallback);
+    session.set_interaction_limits(config.agent.interaction_limits());
 
-    // Send the message and display response
-    let response = session.send_with_client(&client, &message).await?;
-    println!("{response}");
+    let tee = options.tee.as_deref().map(TeeFile::open).transpose()?;
+
+    // Send the message and display response; agent tasks need the tool-capable path
+    let output = &options.output;
+    if let Some(mut agent) = launch_agent(options.agent, agent_config(&config, options.dry_run))? {
+        let response = session
+            .send_with_agent(&client, &message, &mut agent)
+            .await?;
+        if let Some(tee) = &tee {
+            tee.write_reply(&response);
+        }
+        output.emit(&session.provider, &session.model, &response)?;
+    } else if config.stream && !options.no_stream && output.is_plain_stdout() {
+        let mut received = false;
+        let streamed = session
+            .send_streaming(&client, &message, |chunk| {
+                print!("{chunk}");
+                let _ = std::io::Write::flush(&mut std::io::stdout());
+                if let Some(tee) = &tee {
+                    tee.write_chunk(chunk);
+                }
+                received |= !chunk.is_empty();
+            })
+            .await;
+        println!();
+        match (streamed, &tee) {
+            (Ok(_), Some(tee)) => tee.end_reply(),
+            (Ok(_), None) => {}
+            (Err(e), tee) => {
+                // Only a reply that was cut short needs marking in the tee file
+                if let Some(tee) = tee.as_ref().filter(|_| received) {
+                    tee.note_interrupted(&e);
+                }
+                return Err(e);
+            }
+        }
+    } else {
+        let response = session.send_with_client(&client, &message).await?;
+        if let Some(tee) = &tee {
+            tee.write_reply(&response);
+        }
+        output.emit(&session.provider, &session.model, &response)?;
+    }
 
     Ok(())
 }
 
+/// Send one query to several providers and print whichever answers first
+async fn handle_race_query(
+    message: String,
+    providers: Vec<cli::ProviderArg>,
+    system_source: cli::SystemArgs,
+    output: ResponseOutput,
+    config: Config,
+) -> Result<()> {
+    let mut entrants = Vec::new();
+    for provider in providers {
+        let provider: ModelProvider = provider.into();
+        if entrants
+            .iter()
+            .any(|entrant: &RaceEntrant| entrant.provider == provider)
+        {
+            continue;
+        }
+        entrants.push(RaceEntrant {
+            client: LlmClient::from_config(&config, &provider)?,
+            model: config.default_model_for(&provider).to_string(),
+            provider,
+        });
+    }
+    if entrants.len() < 2 {
+        return Err(anyhow!(
+            "--race needs at least two different providers, e.g. --race gemini,ollama"
+        ));
+    }
+
+    let piped = cli::commands::read_piped_stdin()?;
+    let message = cli::commands::merge_piped_input(&message, piped.as_deref());
+    let system_prompt = resolve_system_instruction(system_source).await?;
+
+    let mut conversation = templates::example_turns(&system_prompt.examples);
+    conversation.push(Content::user(message));
+    let winner = race(
+        &entrants,
+        &conversation,
+        system_prompt.instruction.as_deref(),
+    )
+    .await?;
+    eprintln!(
+        "🏁 {} ({}) answered first in {:.1}s",
+        winner.provider.display_name(),
+        winner.model,
+        winner.elapsed.as_secs_f64()
+    );
+    output.emit(
+        &winner.provider,
+        &winner.model,
+        &winner.response.message.joined_text(),
+    )
+}
+
 /// Handle interactive chat mode
 async fn handle_interactive_chat(cli: Cli, config: Config) -> Result<()> {
-    let provider = resolve_provider(cli.provider, &config);
-    let client = create_llm_client(&config, &provider)?;
+    let launch_provider = resolve_provider(cli.provider, &config);
+    let provider = launch_provider.value.clone();
+    let client = LlmClient::from_config(&config, &provider)?;
+
+    // Find out now, not after the first message, that the provider is unreachable
+    if config.preflight_check {
+        let endpoint = config.resolve_endpoint(&provider).map(|e| e.value);
+        cli::doctor::preflight(
+            &client,
+            &provider,
+            endpoint.as_deref(),
+            cli::doctor::PREFLIGHT_TIMEOUT,
+        )
+        .await?;
+    }
 
     // Determine model to use
     let model_override = cli.model.clone();
-    let resolved_model = model_override
-        .clone()
-        .unwrap_or_else(|| config.default_model.clone());
+    let mut launch_model = config.resolve_model(&provider, model_override.as_deref());
+    let resolved_model = launch_model.value.clone();
 
-    // Resolve system instruction from template or direct input
-    let system_instruction = resolve_system_instruction(cli.system, cli.template).await?;
+    // Resolve system instruction from a file, template, or direct input
+    let system_prompt = resolve_system_instruction(cli.system_source).await?;
+
+    let session_file = if cli.continue_session {
+        let latest = chat::session::find_latest_session(&config.sessions_dir)?;
+        if latest.is_none() {
+            println!(
+                "ℹ️  No saved sessions found in {}; starting a new session",
+                config.sessions_dir.display()
+            );
+        }
+        latest
+    } else {
+        cli.load_session
+    };
 
     // Create or load chat session
-    let mut session = if let Some(session_file) = cli.load_session {
+    let loaded = session_file.is_some();
+    let mut session = if let Some(session_file) = session_file {
         let mut loaded = ChatSession::load_from_file(&session_file).await?;
         loaded.provider = provider.clone();
         if model_override.is_some() {
             loaded.model = resolved_model.clone();
+        } else {
+            launch_model = Resolved::new(loaded.model.clone(), Source::Session);
         }
         loaded
     } else {
-        ChatSession::new(
-            resolved_model.clone(),
-            provider.clone(),
-            system_instruction.clone(),
-        )
+        ChatSession::new(resolved_model.clone(), provider.clone(), None)
     };
 
-    if let Some(instr) = system_instruction {
-        session.system_instruction = Some(instr);
-    }
+    apply_system_instruction(
+        &mut session,
+        system_prompt.instruction,
+        loaded,
+        config.default_system_instruction.as_deref(),
+    );
+    session.seed_examples(&system_prompt.examples);
+    session.set_fallback(ProviderFallback::from_config(&config, &provider));
+    session.set_interaction_limits(config.agent.interaction_limits());
+
+    let agent_config = agent_config(&config, cli.dry_run);
+
+    let quiet = resolve_quiet(cli.quiet, &config);
+    let options = ChatOptions {
+        auto_save: cli.auto_save,
+        sessions_dir: Some(config.sessions_dir.clone()),
+        stream: config.stream && !cli.no_stream,
+        resume_on_stream_drop: config.resume_on_stream_drop,
+        max_history: cli
+            .max_history
+            .map(|n| n as usize)
+            .or(config.max_history)
+            .filter(|n| *n > 0),
+        auto_title: config.auto_title,
+        agent_config,
+        summary_keep_turns: config.summary_keep_turns,
+        quiet,
+        long_request_hint: Duration::from_secs(config.ui.long_request_hint_secs),
+        per_directory_history: config.ui.per_directory_history,
+        show_thinking: cli.show_thinking,
+        theme: config.ui.theme,
+        provider_config: Some(config.clone()),
+        launch: Some(LaunchSettings {
+            provider: launch_provider,
+            model: launch_model,
+        }),
+        tee: cli
+            .tee
+            .as_deref()
+            .map(TeeFile::open)
+            .transpose()?
+            .map(Arc::new),
+        stream_delay: resolve_stream_delay(cli.fast, quiet, &config),
+    };
 
-    // Start interactive chat
+    // Start interactive chat, with agent mode already on when requested
+    let agent = launch_agent(cli.agent, options.agent_config.clone())?;
     session
-        .start_interactive_chat(&client, cli.auto_save, Some(config.sessions_dir.clone()))
+        .start_interactive_chat_with_agent(&client, options, agent)
         .await?;
 
     Ok(())
 }
 
+/// Agent settings from the config, with `--dry-run` applied
+fn agent_config(config: &Config, dry_run: bool) -> agent::AgentConfig {
+    let mut agent_config = config.agent.agent_config();
+    agent_config.dry_run_mode = dry_run;
+    agent_config
+}
+
+/// Build an enabled agent when `--agent` was passed
+fn launch_agent(enabled: bool, config: agent::AgentConfig) -> Result<Option<agent::Agent>> {
+    if !enabled {
+        return Ok(None);
+    }
+
+    let mut agent = agent::Agent::new(config)?;
+    agent.set_enabled(true);
+    Ok(Some(agent))
+}
+
+/// Number of templates shown by `template suggest`
+const SUGGESTION_LIMIT: usize = 3;
+
 /// Handle template commands
-async fn handle_template_command(action: TemplateAction) -> Result<()> {
+async fn handle_template_command(action: TemplateAction, overrides: &RunOverrides) -> Result<()> {
     use colored::*;
     use dialoguer::{Confirm, Editor, Input};
 
     let mut manager = TemplateManager::new().await?;
 
     match action {
-        TemplateAction::List { category, search } => {
-            let templates = if let Some(search_query) = search {
-                manager.search(&search_query)
-            } else if let Some(cat) = category {
-                manager.list_by_category(&cat)
-            } else {
-                manager.list_all()
+        TemplateAction::Export { name, all: _, out } => {
+            let exported = manager.export(name.as_deref())?;
+            match out {
+                Some(path) => {
+                    std::fs::write(&path, exported)?;
+                    println!("📤 Exported templates to {}", path.display());
+                }
+                None => println!("{exported}"),
+            }
+        }
+        TemplateAction::Import { file, force } => {
+            let content = std::fs::read_to_string(&file)?;
+            let summary = manager.import(&content, force).await?;
+            for name in &summary.created {
+                println!("✅ Imported template '{}'", name.bright_green());
+            }
+            for name in &summary.replaced {
+                println!("♻️  Replaced template '{}'", name.bright_green());
+            }
+        }
+        TemplateAction::Validate { name, all: _ } => {
+            let templates = match name {
+                Some(name) => match manager.get(&name) {
+                    Some(template) => vec![template],
+                    None => return Err(anyhow!(manager.not_found_message(&name))),
+                },
+                None => manager.picker_entries(),
             };
 
+            let mut with_issues = 0;
+            for template in &templates {
+                let issues = template.validate();
+                if issues.is_empty() {
+                    println!("✅ {}", template.name.bright_green());
+                    continue;
+                }
+                with_issues += 1;
+                println!("❌ {}", template.name.bright_red());
+                for issue in issues {
+                    println!("   - {issue}");
+                }
+            }
+            if with_issues > 0 {
+                return Err(anyhow!(
+                    "{} of {} template(s) have issues",
+                    with_issues,
+                    templates.len()
+                ));
+            }
+        }
+        TemplateAction::Suggest { text } => {
+            let suggestions = manager.recommend(&text);
+            if suggestions.is_empty() {
+                println!("📭 No matching templates found");
+                return Ok(());
+            }
+
+            println!("💡 Suggested Templates:");
+            for template in suggestions.into_iter().take(SUGGESTION_LIMIT) {
+                println!(
+                    "  {} - {}",
+                    template.name.bright_green(),
+                    template.description
+                );
+            }
+        }
+        TemplateAction::List {
+            category,
+            tag,
+            search,
+        } => {
+            let templates = manager.filter(category.as_deref(), tag.as_deref(), search.as_deref());
+
             if templates.is_empty() {
                 println!("📭 No templates found");
                 return Ok(());
@@ -213,6 +660,25 @@ async fn handle_template_command(action: TemplateAction) -> Result<()> {
             }
         }
 
+        TemplateAction::Categories => {
+            println!("📂 Template categories:");
+            for (category, count) in manager.category_counts() {
+                println!("  {} ({})", category.bright_cyan(), count);
+            }
+        }
+
+        TemplateAction::Tags => {
+            let tags = manager.tag_counts();
+            if tags.is_empty() {
+                println!("📭 No templates have tags");
+                return Ok(());
+            }
+            println!("🏷️  Template tags:");
+            for (tag, count) in tags {
+                println!("  {} ({})", tag.bright_yellow(), count);
+            }
+        }
+
         TemplateAction::Show { name } => {
             if let Some(template) = manager.get(&name) {
                 println!("📄 Template: {}", template.name.bright_green().bold());
@@ -240,8 +706,13 @@ async fn handle_template_command(action: TemplateAction) -> Result<()> {
                 println!("{}", "─".repeat(60).bright_black());
                 println!("{}", template.content);
                 println!("{}", "─".repeat(60).bright_black());
+                for (index, (user, model)) in template.examples.iter().enumerate() {
+                    println!("Example {}:", index + 1);
+                    println!("  {} {}", "User:".bright_blue(), user);
+                    println!("  {} {}", "Model:".bright_green(), model);
+                }
             } else {
-                println!("❌ Template '{name}' not found");
+                println!("❌ {}", manager.not_found_message(&name));
             }
         }
 
@@ -343,7 +814,7 @@ async fn handle_template_command(action: TemplateAction) -> Result<()> {
                 manager.update(&name, updated).await?;
                 println!("✅ Template '{name}' updated successfully!");
             } else {
-                println!("❌ Template '{name}' not found");
+                println!("❌ {}", manager.not_found_message(&name));
             }
         }
 
@@ -370,87 +841,505 @@ async fn handle_template_command(action: TemplateAction) -> Result<()> {
                     println!("❌ Template deletion cancelled");
                 }
             } else {
-                println!("❌ Template '{name}' not found");
+                println!("❌ {}", manager.not_found_message(&name));
             }
         }
 
+        TemplateAction::Rename { old, new } => {
+            manager.rename(&old, &new).await?;
+            println!("✅ Template '{old}' renamed to '{}'", new.trim());
+        }
+
         TemplateAction::Use {
             name,
             model,
             provider,
         } => {
-            if let Some(template) = manager.get(&name) {
-                // Load configuration (API key required for chat)
-                let config = Config::load().await?;
-                let provider = resolve_provider(provider, &config);
-                let client = create_llm_client(&config, &provider)?;
+            let template = match name {
+                Some(name) => match manager.get(&name) {
+                    Some(template) => template,
+                    None => {
+                        println!("❌ {}", manager.not_found_message(&name));
+                        return Ok(());
+                    }
+                },
+                None => match manager.select_interactive()? {
+                    Some(template) => template,
+                    None => return Ok(()),
+                },
+            };
+
+            // Load configuration (API key required for chat)
+            let mut config = Config::load().await?;
+            apply_overrides(&mut config, provider, overrides)?;
+            let launch_provider = resolve_provider(provider, &config);
+            let provider = launch_provider.value.clone();
+            let client = LlmClient::from_config(&config, &provider)?;
+
+            // Determine model to use
+            let launch_model = config.resolve_model(&provider, model.as_deref());
+            let model_name = launch_model.value.clone();
+
+            // Create chat session with template
+            let fallback = ProviderFallback::from_config(&config, &provider);
+            let mut session = ChatSession::new(model_name, provider, None);
+            session.apply_template(template);
+            session.set_fallback(fallback);
+            session.set_interaction_limits(config.agent.interaction_limits());
+
+            println!(
+                "🚀 Starting chat with template: {}",
+                template.name.bright_green()
+            );
+            println!("Description: {}", template.description);
+            println!();
+
+            let quiet = resolve_quiet(false, &config);
+            let options = ChatOptions {
+                sessions_dir: Some(config.sessions_dir.clone()),
+                stream: config.stream,
+                resume_on_stream_drop: config.resume_on_stream_drop,
+                max_history: config.max_history.filter(|n| *n > 0),
+                auto_title: config.auto_title,
+                agent_config: config.agent.agent_config(),
+                summary_keep_turns: config.summary_keep_turns,
+                quiet,
+                stream_delay: resolve_stream_delay(false, quiet, &config),
+                long_request_hint: Duration::from_secs(config.ui.long_request_hint_secs),
+                per_directory_history: config.ui.per_directory_history,
+                theme: config.ui.theme,
+                provider_config: Some(config.clone()),
+                launch: Some(LaunchSettings {
+                    provider: launch_provider,
+                    model: launch_model,
+                }),
+                ..ChatOptions::default()
+            };
+
+            // Start interactive chat
+            session.start_interactive_chat(&client, options).await?;
+        }
+    }
 
-                // Determine model to use
-                let model_name = model.unwrap_or_else(|| config.default_model.clone());
+    Ok(())
+}
 
-                // Create chat session with template
-                let mut session =
-                    ChatSession::new(model_name, provider, Some(template.content.clone()));
+/// Quiet mode is on when requested or when stdout is not a terminal
+fn resolve_quiet(cli_quiet: bool, config: &Config) -> bool {
+    use std::io::IsTerminal;
+
+    let quiet = cli_quiet || config.ui.quiet || !std::io::stdout().is_terminal();
+    if quiet {
+        colored::control::set_override(false);
+    }
+    quiet
+}
+
+/// Typewriter delay for streamed replies, off with `--fast` and in quiet mode
+fn resolve_stream_delay(fast: bool, quiet: bool, config: &Config) -> Duration {
+    if fast || quiet {
+        Duration::ZERO
+    } else {
+        Duration::from_millis(config.ui.stream_delay_ms)
+    }
+}
+
+/// Handle the models subcommand
+async fn handle_models_command(provider: Option<cli::ProviderArg>, config: Config) -> Result<()> {
+    let provider = resolve_provider(provider, &config).value;
+    let client = LlmClient::from_config(&config, &provider)?;
+    let models = client.list_models().await?;
+
+    if models.is_empty() {
+        println!("No models reported by {}", provider.display_name());
+        return Ok(());
+    }
+
+    for model in models {
+        if model == config.default_model_for(&provider) {
+            println!("{model} (default)");
+        } else {
+            println!("{model}");
+        }
+    }
 
+    Ok(())
+}
+
+/// Handle the export subcommand
+async fn handle_export_command(
+    session_path: std::path::PathBuf,
+    format: cli::ExportFormatArg,
+    out: Option<std::path::PathBuf>,
+) -> Result<()> {
+    let session = ChatSession::load_from_file(&session_path).await?;
+    let exported = session.export(format.into());
+
+    match out {
+        Some(path) => {
+            std::fs::write(&path, exported)?;
+            println!("📤 Exported session to {}", path.display());
+        }
+        None => print!("{exported}"),
+    }
+
+    Ok(())
+}
+
+/// Handle the sessions subcommand
+fn handle_sessions_command(action: cli::SessionsAction, config: &Config) -> Result<()> {
+    use colored::*;
+
+    match action {
+        cli::SessionsAction::List => {
+            let summaries = chat::session::list_sessions(&config.sessions_dir)?;
+            if summaries.is_empty() {
+                println!("📭 No saved sessions in {}", config.sessions_dir.display());
+                return Ok(());
+            }
+
+            println!("📋 Saved sessions in {}:", config.sessions_dir.display());
+            for summary in summaries {
+                let title = match &summary.title {
+                    Some(title) => title.bright_white(),
+                    None => "(untitled)".bright_black(),
+                };
                 println!(
-                    "🚀 Starting chat with template: {}",
-                    template.name.bright_green()
+                    "  {}  {}  {}  {}",
+                    summary.updated_at.format("%Y-%m-%d %H:%M"),
+                    summary.id[..8.min(summary.id.len())].bright_magenta(),
+                    title,
+                    format!("({} messages, {})", summary.messages, summary.model).bright_black()
                 );
-                println!("Description: {}", template.description);
-                println!();
-
-                // Start interactive chat
-                session
-                    .start_interactive_chat(&client, false, Some(config.sessions_dir.clone()))
-                    .await?;
-            } else {
-                println!("❌ Template '{name}' not found");
+                if let Some(name) = summary.path.file_name() {
+                    println!("      {}", name.to_string_lossy().bright_black());
+                }
             }
         }
     }
-
     Ok(())
 }
 
-fn resolve_provider(cli_provider: Option<cli::ProviderArg>, config: &Config) -> ModelProvider {
-    cli_provider
-        .map(|p| p.into())
-        .unwrap_or_else(|| config.provider.clone())
+/// Handle the replay subcommand
+async fn handle_replay_command(
+    session_path: std::path::PathBuf,
+    delay: Option<f64>,
+    from: usize,
+) -> Result<()> {
+    let session = ChatSession::load_from_file(&session_path).await?;
+    let advance = match delay {
+        Some(secs) if secs.is_finite() && secs >= 0.0 => {
+            Advance::Delay(Duration::from_secs_f64(secs))
+        }
+        Some(secs) => return Err(anyhow!("Invalid --delay {secs}; expected seconds >= 0")),
+        None => Advance::Manual,
+    };
+    chat::replay::replay(&session, from, advance)
 }
 
-fn create_llm_client(config: &Config, provider: &ModelProvider) -> Result<LlmClient> {
-    match provider {
-        ModelProvider::Gemini => {
-            if config.api_key.trim().is_empty() {
-                return Err(anyhow!(
-                    "Gemini provider requires an API key. Run 'chatter config set-api-key'."
-                ));
+/// Input and output options for the batch subcommand
+struct BatchOptions {
+    file: std::path::PathBuf,
+    session: Option<std::path::PathBuf>,
+    fresh: bool,
+}
+
+/// Handle the batch subcommand
+async fn handle_batch_command(
+    options: BatchOptions,
+    model: Option<String>,
+    provider: Option<cli::ProviderArg>,
+    system_source: cli::SystemArgs,
+    config: Config,
+) -> Result<()> {
+    let content = std::fs::read_to_string(&options.file).map_err(|e| {
+        anyhow!(
+            "Failed to read prompt file {}: {}",
+            options.file.display(),
+            e
+        )
+    })?;
+    let prompts = chat::batch::parse_prompts(&content)?;
+    if prompts.is_empty() {
+        return Err(anyhow!("No prompts found in {}", options.file.display()));
+    }
+
+    let provider = resolve_provider(provider, &config).value;
+    let client = LlmClient::from_config(&config, &provider)?;
+    let model_name = model.unwrap_or_else(|| config.default_model_for(&provider).to_string());
+    let system_prompt = resolve_system_instruction(system_source).await?;
+    let fallback = ProviderFallback::from_config(&config, &provider);
+    let mut session = ChatSession::new(model_name, provider, system_prompt.instruction);
+    session.seed_examples(&system_prompt.examples);
+    session.set_fallback(fallback);
+    session.set_interaction_limits(config.agent.interaction_limits());
+
+    let total = prompts.len();
+    let summary = chat::batch::run_batch(
+        &mut session,
+        &client,
+        &prompts,
+        options.fresh,
+        |index, prompt, result| {
+            eprintln!("[{}/{}] {}", index + 1, total, prompt);
+            match result {
+                Ok(response) => println!("{response}\n"),
+                Err(e) => eprintln!("❌ Error: {e}"),
             }
-            LlmClient::new_gemini(config.api_key.clone())
+        },
+    )
+    .await;
+
+    if let Some(path) = &options.session {
+        session.save_to_file(path).await?;
+        eprintln!("💾 Transcript saved to {}", path.display());
+    }
+    eprintln!(
+        "Batch complete: {} succeeded, {} failed",
+        summary.succeeded, summary.failed
+    );
+
+    if summary.failed > 0 {
+        return Err(anyhow!("{} of {} prompts failed", summary.failed, total));
+    }
+    Ok(())
+}
+
+fn resolve_provider(
+    cli_provider: Option<cli::ProviderArg>,
+    config: &Config,
+) -> Resolved<ModelProvider> {
+    config.resolve_provider(cli_provider.map(Into::into))
+}
+
+/// Global flags that adjust the loaded configuration for this run
+struct RunOverrides {
+    endpoint: Option<String>,
+    timeout: Option<u64>,
+}
+
+/// Apply `--endpoint` to the provider this run uses, and `--timeout`
+fn apply_overrides(
+    config: &mut Config,
+    cli_provider: Option<cli::ProviderArg>,
+    overrides: &RunOverrides,
+) -> Result<()> {
+    if let Some(url) = &overrides.endpoint {
+        let provider = resolve_provider(cli_provider, config).value;
+        config.set_endpoint(&provider, url)?;
+    }
+    if let Some(secs) = overrides.timeout {
+        config.set_request_timeout(secs)?;
+    }
+    Ok(())
+}
+
+/// Set the system instruction for an interactive session
+///
+/// Precedence: an explicit `--system`, `--system-file`, or `--template` always wins;
+/// otherwise a loaded session keeps the instruction it was saved with, and a new
+/// session starts with `default_system_instruction` from the config.
+fn apply_system_instruction(
+    session: &mut ChatSession,
+    explicit: Option<String>,
+    loaded: bool,
+    config_default: Option<&str>,
+) {
+    if let Some(instruction) = explicit {
+        session.system_instruction = Some(instruction);
+    } else if !loaded {
+        session.system_instruction = config_default.map(str::to_string);
+    }
+}
+
+/// System instruction and few-shot examples chosen on the command line
+#[derive(Debug, Default)]
+struct SystemPrompt {
+    instruction: Option<String>,
+    /// Only templates carry examples
+    examples: Vec<(String, String)>,
+}
+
+impl From<String> for SystemPrompt {
+    fn from(instruction: String) -> Self {
+        Self {
+            instruction: Some(instruction),
+            examples: Vec::new(),
         }
-        ModelProvider::Ollama => LlmClient::new_ollama(config.ollama.endpoint.clone()),
     }
 }
 
-/// Resolve system instruction from template name or direct input
-async fn resolve_system_instruction(
-    system: Option<String>,
-    template: Option<String>,
-) -> Result<Option<String>> {
-    // Direct system instruction takes precedence
+/// Resolve system instruction from a file, template name, or direct input
+///
+/// The CLI only accepts one source; combinations are still rejected here for
+/// callers that build `SystemArgs` by hand.
+async fn resolve_system_instruction(source: cli::SystemArgs) -> Result<SystemPrompt> {
+    let cli::SystemArgs {
+        system,
+        system_file,
+        template,
+    } = source;
+
+    let provided = [system.is_some(), system_file.is_some(), template.is_some()];
+    if provided.iter().filter(|set| **set).count() > 1 {
+        return Err(anyhow!(
+            "Use only one of --system, --system-file, or --template"
+        ));
+    }
+
     if let Some(instruction) = system {
-        return Ok(Some(instruction));
+        return Ok(instruction.into());
+    }
+
+    if let Some(path) = system_file {
+        let content = std::fs::read_to_string(&path).map_err(|e| {
+            anyhow!(
+                "Failed to read system instruction file {}: {e}",
+                path.display()
+            )
+        })?;
+        if content.trim().is_empty() {
+            return Err(anyhow!(
+                "System instruction file {} is empty",
+                path.display()
+            ));
+        }
+        return Ok(content.trim_end().to_string().into());
     }
 
     // Try to resolve template
     if let Some(template_name) = template {
         let manager = TemplateManager::new().await?;
         if let Some(template) = manager.get(&template_name) {
-            return Ok(Some(template.content.clone()));
+            return Ok(SystemPrompt {
+                instruction: Some(template.content.clone()),
+                examples: template.examples.clone(),
+            });
         } else {
-            return Err(anyhow::anyhow!("Template '{}' not found", template_name));
+            return Err(anyhow::anyhow!(manager.not_found_message(&template_name)));
+        }
+    }
+
+    Ok(SystemPrompt::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn dry_run_flag_reaches_one_shot_agent_queries() {
+        let dir = std::env::temp_dir().join(format!("chatter-query-dry-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let target = dir.join("notes.txt");
+
+        for args in [
+            vec!["chatter", "--agent", "--dry-run", "write notes"],
+            vec!["chatter", "query", "--agent", "--dry-run", "write notes"],
+            vec!["chatter", "--dry-run", "query", "--agent", "write notes"],
+        ] {
+            let cli = Cli::try_parse_from(&args).unwrap();
+            assert!(cli.dry_run, "{args:?}");
+            let mut config = agent_config(&Config::default(), cli.dry_run);
+            config.working_directory = dir.clone();
+            let mut agent = launch_agent(true, config).unwrap().unwrap();
+
+            let result = agent
+                .execute_tool(agent::ToolCall {
+                    tool: "write_file".to_string(),
+                    parameters: std::collections::HashMap::from([
+                        (
+                            "path".to_string(),
+                            serde_json::json!(target.display().to_string()),
+                        ),
+                        ("content".to_string(), serde_json::json!("hello")),
+                    ]),
+                    thought: None,
+                    reasoning: None,
+                })
+                .await
+                .unwrap();
+            assert_eq!(result.data["dry_run"], true);
+            assert!(!target.exists());
         }
+
+        let cli = Cli::try_parse_from(["chatter", "query", "--agent", "task"]).unwrap();
+        assert!(!agent_config(&Config::default(), cli.dry_run).dry_run_mode);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn system_instruction_from_file() {
+        let path =
+            std::env::temp_dir().join(format!("chatter-system-{}.txt", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "You are terse.\n").unwrap();
+
+        let resolved = resolve_system_instruction(cli::SystemArgs {
+            system_file: Some(path.clone()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(resolved.instruction.as_deref(), Some("You are terse."));
+        assert!(resolved.examples.is_empty());
+    }
+
+    #[tokio::test]
+    async fn system_instruction_rejects_missing_file_and_conflicts() {
+        let missing = resolve_system_instruction(cli::SystemArgs {
+            system_file: Some("/nonexistent/chatter-prompt.txt".into()),
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+        assert!(missing
+            .to_string()
+            .contains("/nonexistent/chatter-prompt.txt"));
+
+        let conflict = resolve_system_instruction(cli::SystemArgs {
+            system: Some("inline".to_string()),
+            system_file: Some("prompt.txt".into()),
+            template: Some("tutor".to_string()),
+        })
+        .await
+        .unwrap_err();
+        assert!(conflict.to_string().contains("only one"));
     }
 
-    Ok(None)
+    #[test]
+    fn loaded_sessions_keep_their_instruction_unless_overridden() {
+        let mut saved = ChatSession::new(
+            "gemini-2.5-flash".to_string(),
+            ModelProvider::Gemini,
+            Some("Be terse".to_string()),
+        );
+
+        // Without an explicit instruction the saved one survives, even with a config default
+        apply_system_instruction(&mut saved, None, true, Some("Config default"));
+        assert_eq!(saved.system_instruction.as_deref(), Some("Be terse"));
+
+        // An explicit --system / --template replaces it
+        apply_system_instruction(&mut saved, Some("Be verbose".to_string()), true, None);
+        assert_eq!(saved.system_instruction.as_deref(), Some("Be verbose"));
+
+        // New sessions fall back to the config default
+        let mut fresh = ChatSession::new("m".to_string(), ModelProvider::Gemini, None);
+        apply_system_instruction(&mut fresh, None, false, Some("Config default"));
+        assert_eq!(fresh.system_instruction.as_deref(), Some("Config default"));
+    }
+
+    #[test]
+    fn agent_flag_launches_an_enabled_agent() {
+        let agent = launch_agent(true, agent::AgentConfig::default())
+            .unwrap()
+            .expect("agent should be created");
+        assert!(agent.is_enabled());
+        assert!(!agent.available_tools().is_empty());
+
+        assert!(launch_agent(false, agent::AgentConfig::default())
+            .unwrap()
+            .is_none());
+    }
 }
@@ -2,16 +2,19 @@
 //!
 //! Provides functionality for creating, storing, and managing reusable system instruction templates.
 
+use crate::api::Content;
 use anyhow::{anyhow, Result};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 
 pub mod builtin;
+pub mod recommend;
 pub mod storage;
 
 pub use builtin::get_builtin_templates;
-pub use storage::TemplateStorage;
+pub use storage::{parse_template_bundle, serialize_template_bundle, TemplateStorage};
 
 /// A system instruction template
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,6 +35,69 @@ pub struct Template {
     pub tags: Vec<String>,
     /// Whether this is a built-in template
     pub builtin: bool,
+    /// Few-shot demonstrations as (user, model) pairs, placed at the start of the history
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub examples: Vec<(String, String)>,
+}
+
+/// Problem found by `Template::validate`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationIssue {
+    /// The system instruction is empty or only whitespace
+    EmptyContent,
+    /// A field that should have text is empty or only whitespace
+    BlankField(String),
+    /// The category is not a single word usable with `--category`
+    InvalidCategory(String),
+    /// `{{` and `}}` do not pair up
+    UnbalancedPlaceholders,
+    /// A tag appears more than once, ignoring case
+    DuplicateTag(String),
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EmptyContent => write!(f, "content is empty"),
+            Self::BlankField(field) => write!(f, "{field} is blank"),
+            Self::InvalidCategory(category) => write!(
+                f,
+                "category '{category}' should be one word of letters, digits, '-' or '_'"
+            ),
+            Self::UnbalancedPlaceholders => write!(f, "content has unbalanced {{{{ }}}}"),
+            Self::DuplicateTag(tag) => write!(f, "tag '{tag}' is listed more than once"),
+        }
+    }
+}
+
+/// Whether every `{{` in `text` is closed by a later `}}`
+fn placeholders_balanced(text: &str) -> bool {
+    let mut open = 0usize;
+    let mut rest = text;
+    while let Some(index) = rest.find(['{', '}']) {
+        rest = &rest[index..];
+        if rest.starts_with("{{") {
+            open += 1;
+        } else if rest.starts_with("}}") {
+            match open.checked_sub(1) {
+                Some(remaining) => open = remaining,
+                None => return false,
+            }
+        } else {
+            rest = &rest[1..];
+            continue;
+        }
+        rest = &rest[2..];
+    }
+    open == 0
+}
+
+/// Conversation turns demonstrating the given (user, model) examples
+pub fn example_turns(examples: &[(String, String)]) -> Vec<Content> {
+    examples
+        .iter()
+        .flat_map(|(user, model)| [Content::user(user.clone()), Content::model(model.clone())])
+        .collect()
 }
 
 impl Template {
@@ -53,6 +119,7 @@ impl Template {
             updated_at: now,
             tags,
             builtin: false,
+            examples: Vec::new(),
         }
     }
 
@@ -74,6 +141,7 @@ impl Template {
             updated_at: now,
             tags,
             builtin: true,
+            examples: Vec::new(),
         }
     }
 
@@ -109,6 +177,54 @@ impl Template {
         }
     }
 
+    /// Check if the template carries a tag, ignoring case
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+    }
+
+    /// Check the template for problems worth fixing before it is shared
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+        if self.content.trim().is_empty() {
+            issues.push(ValidationIssue::EmptyContent);
+        } else if !placeholders_balanced(&self.content) {
+            issues.push(ValidationIssue::UnbalancedPlaceholders);
+        }
+
+        for (field, value) in [("name", &self.name), ("description", &self.description)] {
+            if value.trim().is_empty() {
+                issues.push(ValidationIssue::BlankField(field.to_string()));
+            }
+        }
+        if self.category.trim().is_empty() {
+            issues.push(ValidationIssue::BlankField("category".to_string()));
+        } else if !self
+            .category
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+        {
+            issues.push(ValidationIssue::InvalidCategory(self.category.clone()));
+        }
+        for (index, (user, model)) in self.examples.iter().enumerate() {
+            for (side, text) in [("user", user), ("model", model)] {
+                if text.trim().is_empty() {
+                    let field = format!("example {} {side} message", index + 1);
+                    issues.push(ValidationIssue::BlankField(field));
+                }
+            }
+        }
+
+        let mut seen = HashSet::new();
+        for tag in &self.tags {
+            if tag.trim().is_empty() {
+                issues.push(ValidationIssue::BlankField("tag".to_string()));
+            } else if !seen.insert(tag.to_lowercase()) {
+                issues.push(ValidationIssue::DuplicateTag(tag.clone()));
+            }
+        }
+        issues
+    }
+
     /// Check if template matches search query
     pub fn matches_search(&self, query: &str) -> bool {
         let query = query.to_lowercase();
@@ -122,6 +238,15 @@ impl Template {
     }
 }
 
+/// Result of importing templates
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    /// Templates that did not exist before
+    pub created: Vec<String>,
+    /// Existing user templates that were overwritten
+    pub replaced: Vec<String>,
+}
+
 /// Template manager for handling all template operations
 pub struct TemplateManager {
     storage: TemplateStorage,
@@ -143,6 +268,17 @@ impl TemplateManager {
         Ok(manager)
     }
 
+    /// Create a template manager backed by the given storage
+    #[allow(dead_code)]
+    pub async fn with_storage(storage: TemplateStorage) -> Result<Self> {
+        let mut manager = Self {
+            storage,
+            templates: HashMap::new(),
+        };
+        manager.reload().await?;
+        Ok(manager)
+    }
+
     /// Reload all templates from storage
     pub async fn reload(&mut self) -> Result<()> {
         self.templates.clear();
@@ -166,6 +302,36 @@ impl TemplateManager {
         self.templates.values().collect()
     }
 
+    /// Templates in menu order: grouped by category, then sorted by name
+    pub fn picker_entries(&self) -> Vec<&Template> {
+        let mut templates = self.list_all();
+        templates.sort_by(|a, b| (&a.category, &a.name).cmp(&(&b.category, &b.name)));
+        templates
+    }
+
+    /// Let the user pick a template from a menu
+    ///
+    /// Returns `None` when no templates exist or the menu is cancelled with Esc or q.
+    pub fn select_interactive(&self) -> Result<Option<&Template>> {
+        let templates = self.picker_entries();
+        if templates.is_empty() {
+            println!("📭 No templates available. Create one with 'chatter template create'");
+            return Ok(None);
+        }
+
+        let labels: Vec<String> = templates
+            .iter()
+            .map(|t| format!("[{}] {} - {}", t.category, t.name, t.description))
+            .collect();
+        let selection = dialoguer::Select::new()
+            .with_prompt("Choose a template (Esc to cancel)")
+            .items(&labels)
+            .default(0)
+            .interact_opt()?;
+
+        Ok(selection.map(|index| templates[index]))
+    }
+
     /// Get templates by category
     pub fn list_by_category(&self, category: &str) -> Vec<&Template> {
         self.templates
@@ -182,11 +348,73 @@ impl TemplateManager {
             .collect()
     }
 
+    /// Get templates carrying a tag
+    pub fn list_by_tag(&self, tag: &str) -> Vec<&Template> {
+        self.templates.values().filter(|t| t.has_tag(tag)).collect()
+    }
+
+    /// Templates matching every filter that is set
+    pub fn filter(
+        &self,
+        category: Option<&str>,
+        tag: Option<&str>,
+        search: Option<&str>,
+    ) -> Vec<&Template> {
+        let mut templates = match search {
+            Some(query) => self.search(query),
+            None => self.list_all(),
+        };
+        if let Some(category) = category {
+            templates.retain(|t| t.category == category);
+        }
+        if let Some(tag) = tag {
+            templates.retain(|t| t.has_tag(tag));
+        }
+        templates
+    }
+
+    /// Recommend templates for a message, best match first
+    pub fn recommend(&self, text: &str) -> Vec<&Template> {
+        recommend::rank_templates(self.templates.values(), text)
+    }
+
     /// Get a template by name
     pub fn get(&self, name: &str) -> Option<&Template> {
         self.templates.get(name)
     }
 
+    /// Template names within a few edits of `query`, closest first
+    ///
+    /// Used to catch typos when a lookup by exact name fails.
+    pub fn suggest_names(&self, query: &str) -> Vec<&str> {
+        let query = query.to_lowercase();
+        let max_distance = (query.chars().count() / 4).max(1);
+
+        let mut close: Vec<(usize, &str)> = self
+            .templates
+            .keys()
+            .map(|name| {
+                (
+                    strsim::levenshtein(&query, &name.to_lowercase()),
+                    name.as_str(),
+                )
+            })
+            .filter(|(distance, _)| *distance <= max_distance)
+            .collect();
+        close.sort();
+        close.into_iter().take(3).map(|(_, name)| name).collect()
+    }
+
+    /// Error text for a missing template, suggesting a close name if there is one
+    pub fn not_found_message(&self, name: &str) -> String {
+        match self.suggest_names(name).first() {
+            Some(suggestion) => {
+                format!("Template '{name}' not found. Did you mean '{suggestion}'?")
+            }
+            None => format!("Template '{name}' not found"),
+        }
+    }
+
     /// Create a new template
     pub async fn create(&mut self, template: Template) -> Result<()> {
         if self.templates.contains_key(&template.name) {
@@ -197,8 +425,8 @@ impl TemplateManager {
             return Err(anyhow!("Cannot create built-in templates"));
         }
 
-        // Save to storage
-        self.storage.save(&template).await?;
+        // Storage re-checks under its lock in case another process created it meanwhile
+        self.storage.create(&template).await?;
 
         // Add to memory
         self.templates.insert(template.name.clone(), template);
@@ -211,7 +439,7 @@ impl TemplateManager {
         let existing = self
             .templates
             .get(name)
-            .ok_or_else(|| anyhow!("Template '{}' not found", name))?;
+            .ok_or_else(|| anyhow!(self.not_found_message(name)))?;
 
         if existing.builtin {
             return Err(anyhow!("Cannot modify built-in template '{}'", name));
@@ -235,7 +463,7 @@ impl TemplateManager {
         let template = self
             .templates
             .get(name)
-            .ok_or_else(|| anyhow!("Template '{}' not found", name))?;
+            .ok_or_else(|| anyhow!(self.not_found_message(name)))?;
 
         if template.builtin {
             return Err(anyhow!("Cannot delete built-in template '{}'", name));
@@ -250,6 +478,97 @@ impl TemplateManager {
         Ok(())
     }
 
+    /// Rename a user template, keeping its content and timestamps
+    pub async fn rename(&mut self, old_name: &str, new_name: &str) -> Result<()> {
+        let existing = self
+            .templates
+            .get(old_name)
+            .ok_or_else(|| anyhow!(self.not_found_message(old_name)))?;
+
+        if existing.builtin {
+            return Err(anyhow!("Cannot rename built-in template '{}'", old_name));
+        }
+
+        let new_name = new_name.trim();
+        if new_name.is_empty() {
+            return Err(anyhow!("Template name cannot be empty"));
+        }
+        // Storage re-checks the file under its lock, for templates created elsewhere
+        if self.templates.contains_key(new_name) {
+            return Err(anyhow!("Template '{}' already exists", new_name));
+        }
+
+        let mut template = existing.clone();
+        template.name = new_name.to_string();
+        self.storage.rename(old_name, &template).await?;
+
+        self.templates.remove(old_name);
+        self.templates.insert(template.name.clone(), template);
+
+        Ok(())
+    }
+
+    /// Export one template by name, or every user template when `name` is `None`
+    pub fn export(&self, name: Option<&str>) -> Result<String> {
+        let templates: Vec<&Template> = match name {
+            Some(name) => vec![self
+                .get(name)
+                .ok_or_else(|| anyhow!(self.not_found_message(name)))?],
+            None => {
+                let mut user: Vec<&Template> =
+                    self.templates.values().filter(|t| !t.builtin).collect();
+                user.sort_by(|a, b| a.name.cmp(&b.name));
+                if user.is_empty() {
+                    return Err(anyhow!("No user templates to export"));
+                }
+                user
+            }
+        };
+
+        serialize_template_bundle(&templates)
+    }
+
+    /// Import templates from an exported file's contents
+    ///
+    /// Name clashes with built-in templates are always rejected; existing user
+    /// templates are only replaced when `force` is set. Nothing is saved unless
+    /// every template in the file can be imported.
+    pub async fn import(&mut self, content: &str, force: bool) -> Result<ImportSummary> {
+        let templates = parse_template_bundle(content)?;
+
+        for template in &templates {
+            match self.templates.get(&template.name) {
+                Some(existing) if existing.builtin => {
+                    return Err(anyhow!(
+                        "Template '{}' conflicts with a built-in template",
+                        template.name
+                    ));
+                }
+                Some(_) if !force => {
+                    return Err(anyhow!(
+                        "Template '{}' already exists (use --force to overwrite)",
+                        template.name
+                    ));
+                }
+                _ => {}
+            }
+        }
+
+        let mut summary = ImportSummary::default();
+        for template in templates {
+            let name = template.name.clone();
+            if self.templates.contains_key(&name) {
+                self.update(&name, template).await?;
+                summary.replaced.push(name);
+            } else {
+                self.create(template).await?;
+                summary.created.push(name);
+            }
+        }
+
+        Ok(summary)
+    }
+
     /// Get all unique categories
     pub fn get_categories(&self) -> Vec<String> {
         let mut categories: Vec<String> = self
@@ -264,7 +583,6 @@ impl TemplateManager {
     }
 
     /// Get all unique tags
-    #[allow(dead_code)]
     pub fn get_tags(&self) -> Vec<String> {
         let mut tags: Vec<String> = self
             .templates
@@ -276,4 +594,321 @@ impl TemplateManager {
         tags.sort();
         tags
     }
+
+    /// Categories with the number of templates in each, sorted by name
+    pub fn category_counts(&self) -> Vec<(String, usize)> {
+        self.get_categories()
+            .into_iter()
+            .map(|category| {
+                let count = self.list_by_category(&category).len();
+                (category, count)
+            })
+            .collect()
+    }
+
+    /// Tags with the number of templates carrying each, sorted by name
+    pub fn tag_counts(&self) -> Vec<(String, usize)> {
+        self.get_tags()
+            .into_iter()
+            .map(|tag| {
+                let count = self.list_by_tag(&tag).len();
+                (tag, count)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn temp_manager() -> (TemplateManager, std::path::PathBuf) {
+        let dir = std::env::temp_dir().join(format!("chatter-templates-{}", uuid::Uuid::new_v4()));
+        let storage = TemplateStorage::with_dir(dir.clone()).unwrap();
+        (TemplateManager::with_storage(storage).await.unwrap(), dir)
+    }
+
+    #[tokio::test]
+    async fn picker_groups_templates_by_category() {
+        let (mut manager, dir) = temp_manager().await;
+        for (name, category) in [("zeta", "coding"), ("alpha", "writing"), ("beta", "coding")] {
+            manager
+                .create(Template::new(
+                    name.to_string(),
+                    String::new(),
+                    String::new(),
+                    category.to_string(),
+                    Vec::new(),
+                ))
+                .await
+                .unwrap();
+        }
+
+        let entries: Vec<(&str, &str)> = manager
+            .picker_entries()
+            .into_iter()
+            .filter(|t| !t.builtin)
+            .map(|t| (t.category.as_str(), t.name.as_str()))
+            .collect();
+        assert_eq!(
+            entries,
+            vec![("coding", "beta"), ("coding", "zeta"), ("writing", "alpha")]
+        );
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn list_filters_by_tag_and_counts_tags() {
+        let (mut manager, dir) = temp_manager().await;
+        for (name, category, tags) in [
+            ("sql_review", "coding", vec!["zz-sql", "zz-review"]),
+            ("prose_review", "writing", vec!["zz-review"]),
+            ("sql_tutor", "teaching", vec!["ZZ-SQL"]),
+        ] {
+            manager
+                .create(Template::new(
+                    name.to_string(),
+                    String::new(),
+                    String::new(),
+                    category.to_string(),
+                    tags.into_iter().map(str::to_string).collect(),
+                ))
+                .await
+                .unwrap();
+        }
+
+        let names = |templates: Vec<&Template>| {
+            let mut names: Vec<String> = templates.iter().map(|t| t.name.clone()).collect();
+            names.sort();
+            names
+        };
+        assert_eq!(
+            names(manager.filter(None, Some("zz-sql"), None)),
+            vec!["sql_review", "sql_tutor"]
+        );
+        assert_eq!(
+            names(manager.filter(Some("coding"), Some("zz-review"), None)),
+            vec!["sql_review"]
+        );
+        assert_eq!(
+            names(manager.filter(None, Some("zz-review"), Some("prose"))),
+            vec!["prose_review"]
+        );
+
+        let counts = manager.tag_counts();
+        assert!(counts.contains(&("zz-review".to_string(), 2)));
+        assert!(manager
+            .category_counts()
+            .contains(&("teaching".to_string(), 1)));
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn import_export_round_trip() {
+        let (mut source, source_dir) = temp_manager().await;
+        let mut template = Template::new(
+            "sql_helper".to_string(),
+            "Writes SQL".to_string(),
+            "You write SQL queries.".to_string(),
+            "coding".to_string(),
+            vec!["sql".to_string()],
+        );
+        template.examples = vec![(
+            "Count the users".to_string(),
+            "SELECT COUNT(*) FROM users;".to_string(),
+        )];
+        source.create(template).await.unwrap();
+        let exported = source.export(None).unwrap();
+
+        let (mut target, target_dir) = temp_manager().await;
+        let summary = target.import(&exported, false).await.unwrap();
+        assert_eq!(summary.created, vec!["sql_helper".to_string()]);
+        assert_eq!(
+            target.get("sql_helper").unwrap().content,
+            "You write SQL queries."
+        );
+        // Few-shot examples survive the round trip
+        assert_eq!(
+            target.get("sql_helper").unwrap().examples,
+            vec![(
+                "Count the users".to_string(),
+                "SELECT COUNT(*) FROM users;".to_string()
+            )]
+        );
+
+        // Re-importing needs --force, and then replaces the template
+        assert!(target.import(&exported, false).await.is_err());
+        let summary = target.import(&exported, true).await.unwrap();
+        assert_eq!(summary.replaced, vec!["sql_helper".to_string()]);
+
+        std::fs::remove_dir_all(source_dir).unwrap();
+        std::fs::remove_dir_all(target_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn import_rejects_builtin_names_even_with_force() {
+        let (mut manager, dir) = temp_manager().await;
+        let exported = manager.export(Some("coding_assistant")).unwrap();
+
+        let error = manager.import(&exported, true).await.unwrap_err();
+        assert!(error.to_string().contains("built-in"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn rename_moves_the_template_and_keeps_its_timestamps() {
+        let (mut manager, dir) = temp_manager().await;
+        manager
+            .create(Template::new(
+                "draft".to_string(),
+                "Drafts emails".to_string(),
+                "You draft emails.".to_string(),
+                "writing".to_string(),
+                Vec::new(),
+            ))
+            .await
+            .unwrap();
+        let created_at = manager.get("draft").unwrap().created_at;
+
+        manager.rename("draft", "email_writer").await.unwrap();
+        assert!(manager.get("draft").is_none());
+        let renamed = manager.get("email_writer").unwrap();
+        assert_eq!(renamed.content, "You draft emails.");
+        assert_eq!(renamed.created_at, created_at);
+        assert!(dir.join("email_writer.json").exists());
+        assert!(!dir.join("draft.json").exists());
+
+        // The new file is what gets loaded next time
+        manager.reload().await.unwrap();
+        assert_eq!(manager.get("email_writer").unwrap().created_at, created_at);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn rename_rejects_collisions_and_builtins() {
+        let (mut manager, dir) = temp_manager().await;
+        for name in ["first", "second"] {
+            manager
+                .create(Template::new(
+                    name.to_string(),
+                    String::new(),
+                    "content".to_string(),
+                    "general".to_string(),
+                    Vec::new(),
+                ))
+                .await
+                .unwrap();
+        }
+
+        let collision = manager.rename("first", "second").await.unwrap_err();
+        assert!(collision.to_string().contains("already exists"));
+        assert!(manager
+            .rename("first", "coding_assistant")
+            .await
+            .unwrap_err()
+            .to_string()
+            .contains("already exists"));
+
+        // A template saved by another process since loading is not overwritten
+        std::fs::write(dir.join("third.json"), "{}").unwrap();
+        assert!(manager
+            .rename("first", "third")
+            .await
+            .unwrap_err()
+            .to_string()
+            .contains("already exists"));
+        assert_eq!(
+            std::fs::read_to_string(dir.join("third.json")).unwrap(),
+            "{}"
+        );
+        assert!(dir.join("first.json").exists());
+
+        let builtin = manager
+            .rename("coding_assistant", "my_assistant")
+            .await
+            .unwrap_err();
+        assert!(builtin.to_string().contains("built-in"));
+        assert!(manager.get("coding_assistant").is_some());
+        assert!(manager.get("first").is_some());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn typos_suggest_the_closest_template_name() {
+        let (manager, dir) = temp_manager().await;
+
+        assert_eq!(
+            manager.suggest_names("codng_assistant").first(),
+            Some(&"coding_assistant")
+        );
+        assert_eq!(
+            manager.not_found_message("codng_assistant"),
+            "Template 'codng_assistant' not found. Did you mean 'coding_assistant'?"
+        );
+
+        assert!(manager.suggest_names("weather_forecaster").is_empty());
+        assert_eq!(
+            manager.not_found_message("weather_forecaster"),
+            "Template 'weather_forecaster' not found"
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn validate_reports_each_kind_of_issue() {
+        let valid = Template::new(
+            "reviewer".to_string(),
+            "Reviews diffs".to_string(),
+            "Review {{language}} code carefully. Use {braces} freely.".to_string(),
+            "development".to_string(),
+            vec!["review".to_string(), "code".to_string()],
+        );
+        assert_eq!(valid.validate(), Vec::new());
+        for builtin in get_builtin_templates() {
+            assert_eq!(builtin.validate(), Vec::new(), "{}", builtin.name);
+        }
+
+        let mut broken = valid.clone();
+        broken.content = "  \n".to_string();
+        broken.description = "   ".to_string();
+        broken.category = "code review".to_string();
+        broken.tags = vec!["Review".to_string(), "review".to_string(), " ".to_string()];
+        broken.examples = vec![("Hi".to_string(), "".to_string())];
+        assert_eq!(
+            broken.validate(),
+            vec![
+                ValidationIssue::EmptyContent,
+                ValidationIssue::BlankField("description".to_string()),
+                ValidationIssue::InvalidCategory("code review".to_string()),
+                ValidationIssue::BlankField("example 1 model message".to_string()),
+                ValidationIssue::DuplicateTag("review".to_string()),
+                ValidationIssue::BlankField("tag".to_string()),
+            ]
+        );
+
+        broken.category = " ".to_string();
+        assert!(broken
+            .validate()
+            .contains(&ValidationIssue::BlankField("category".to_string())));
+
+        for unbalanced in ["Hello {{name}", "}} then {{", "{{a}} {{b"] {
+            let mut template = valid.clone();
+            template.content = unbalanced.to_string();
+            assert_eq!(
+                template.validate(),
+                vec![ValidationIssue::UnbalancedPlaceholders],
+                "{unbalanced}"
+            );
+        }
+        assert_eq!(
+            ValidationIssue::UnbalancedPlaceholders.to_string(),
+            "content has unbalanced {{ }}"
+        );
+    }
 }
@@ -0,0 +1,107 @@
+//! Template recommendation
+//!
+//! Scores templates against free text by token overlap. Tags and categories weigh
+//! more than descriptions, which weigh more than the instruction body, and repeated
+//! words in the body add a dampened (logarithmic) bonus.
+
+use super::Template;
+use std::collections::{HashMap, HashSet};
+
+const TAG_WEIGHT: f64 = 3.0;
+const CATEGORY_WEIGHT: f64 = 2.0;
+const DESCRIPTION_WEIGHT: f64 = 2.0;
+const CONTENT_WEIGHT: f64 = 1.0;
+
+/// Words too common to say anything about a template
+const STOPWORDS: &[&str] = &[
+    "and", "are", "but", "can", "for", "from", "have", "help", "how", "into", "me", "my", "not",
+    "that", "the", "this", "to", "was", "what", "when", "with", "you", "your",
+];
+
+/// Split text into lowercase word tokens, dropping short and common words
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.len() >= 3)
+        .map(str::to_lowercase)
+        .filter(|word| !STOPWORDS.contains(&word.as_str()))
+        .collect()
+}
+
+/// Score a template against the query tokens; zero means no overlap
+pub fn score_template(template: &Template, query: &HashSet<String>) -> f64 {
+    let mut content_counts: HashMap<String, usize> = HashMap::new();
+    for token in tokenize(&template.content) {
+        *content_counts.entry(token).or_default() += 1;
+    }
+
+    let tags: HashSet<String> = template.tags.iter().flat_map(|tag| tokenize(tag)).collect();
+    let category: HashSet<String> = tokenize(&template.category).into_iter().collect();
+    let description: HashSet<String> =
+        tokenize(&format!("{} {}", template.name, template.description))
+            .into_iter()
+            .collect();
+
+    query
+        .iter()
+        .map(|token| {
+            let mut score = 0.0;
+            if tags.contains(token) {
+                score += TAG_WEIGHT;
+            }
+            if category.contains(token) {
+                score += CATEGORY_WEIGHT;
+            }
+            if description.contains(token) {
+                score += DESCRIPTION_WEIGHT;
+            }
+            if let Some(&count) = content_counts.get(token) {
+                score += CONTENT_WEIGHT * (1.0 + (count as f64).ln());
+            }
+            score
+        })
+        .sum()
+}
+
+/// Rank templates by score for `text`, best first, omitting templates with no overlap
+///
+/// Ties are broken by name so the order is deterministic.
+pub fn rank_templates<'a>(
+    templates: impl IntoIterator<Item = &'a Template>,
+    text: &str,
+) -> Vec<&'a Template> {
+    let query: HashSet<String> = tokenize(text).into_iter().collect();
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    let mut scored: Vec<(f64, &Template)> = templates
+        .into_iter()
+        .map(|template| (score_template(template, &query), template))
+        .filter(|(score, _)| *score > 0.0)
+        .collect();
+
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.name.cmp(&b.1.name)));
+    scored.into_iter().map(|(_, template)| template).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::templates::get_builtin_templates;
+
+    #[test]
+    fn debugging_message_prefers_coding_assistant() {
+        let templates = get_builtin_templates();
+        let ranked = rank_templates(&templates, "I keep hitting a panic while debugging my code");
+
+        let position = |name: &str| ranked.iter().position(|t| t.name == name);
+        let coding = position("coding_assistant").expect("coding_assistant should match");
+        assert!(position("creative_writer").is_none_or(|creative| coding < creative));
+    }
+
+    #[test]
+    fn unrelated_text_recommends_nothing() {
+        let templates = get_builtin_templates();
+        assert!(rank_templates(&templates, "zzz qqq").is_empty());
+    }
+}
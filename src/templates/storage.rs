@@ -1,12 +1,18 @@
 //! Template storage functionality
 //!
-//! Handles file I/O operations for template persistence.
+//! Handles file I/O operations for template persistence. Changes take an
+//! exclusive lock on the templates directory, so several `chatter` processes
+//! can create or delete templates at the same time without losing writes.
 
 use super::Template;
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
 use dirs::config_dir;
-use std::fs;
-use std::path::PathBuf;
+use serde::Deserialize;
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
+
+/// Lock file that serializes changes to the templates directory
+const LOCK_FILE_NAME: &str = ".lock";
 
 /// Template storage manager
 pub struct TemplateStorage {
@@ -24,6 +30,13 @@ impl TemplateStorage {
         Ok(Self { templates_dir })
     }
 
+    /// Create a storage manager rooted at a specific directory
+    #[allow(dead_code)]
+    pub fn with_dir(templates_dir: PathBuf) -> Result<Self> {
+        fs::create_dir_all(&templates_dir)?;
+        Ok(Self { templates_dir })
+    }
+
     /// Load all user templates from storage
     pub async fn load_all(&self) -> Result<Vec<Template>> {
         let mut templates = Vec::new();
@@ -64,23 +77,51 @@ impl TemplateStorage {
 
     /// Save a template to storage
     pub async fn save(&self, template: &Template) -> Result<()> {
+        let _lock = self.lock()?;
+        self.write_template(template)
+    }
+
+    /// Save a new template, failing if one with the same file name already exists
+    ///
+    /// The check and the write happen under the same lock, so two processes
+    /// creating the same name cannot both succeed.
+    pub async fn create(&self, template: &Template) -> Result<()> {
+        let _lock = self.lock()?;
+        if self.exists(&template.name) {
+            return Err(anyhow!("Template '{}' already exists", template.name));
+        }
+        self.write_template(template)
+    }
+
+    /// Write the template file; callers hold the directory lock
+    fn write_template(&self, template: &Template) -> Result<()> {
         if template.builtin {
             return Err(anyhow!("Cannot save built-in templates to storage"));
         }
 
-        let filename = format!("{}.json", sanitize_filename(&template.name));
-        let path = self.templates_dir.join(filename);
-
+        let path = self.get_template_path(&template.name);
         let content = serde_json::to_string_pretty(template)?;
-        fs::write(&path, content)?;
+
+        // Write a temporary file and rename it so readers never see half a template
+        let temp_path = path.with_extension(format!("json.{}.tmp", uuid::Uuid::new_v4()));
+        fs::write(&temp_path, content)?;
+        if let Err(e) = fs::rename(&temp_path, &path) {
+            let _ = fs::remove_file(&temp_path);
+            return Err(e.into());
+        }
 
         Ok(())
     }
 
+    /// Hold an exclusive lock on the templates directory until the file is dropped
+    fn lock(&self) -> Result<File> {
+        lock_dir(&self.templates_dir)
+    }
+
     /// Delete a template from storage
     pub async fn delete(&self, name: &str) -> Result<()> {
-        let filename = format!("{}.json", sanitize_filename(name));
-        let path = self.templates_dir.join(filename);
+        let _lock = self.lock()?;
+        let path = self.get_template_path(name);
 
         if !path.exists() {
             return Err(anyhow!("Template file not found: {}", path.display()));
@@ -90,16 +131,31 @@ impl TemplateStorage {
         Ok(())
     }
 
+    /// Move a template saved under `old_name` to the file for its current name
+    ///
+    /// Fails if another template already uses the new file. The check, the write,
+    /// and removing the old file happen under one lock, so a concurrent create or
+    /// rename cannot be overwritten.
+    pub async fn rename(&self, old_name: &str, template: &Template) -> Result<()> {
+        let _lock = self.lock()?;
+        // Names that sanitize to the same file are overwritten in place
+        let moved = sanitize_filename(old_name) != sanitize_filename(&template.name);
+        if moved && self.exists(&template.name) {
+            return Err(anyhow!("Template '{}' already exists", template.name));
+        }
+        self.write_template(template)?;
+        if moved {
+            fs::remove_file(self.get_template_path(old_name))?;
+        }
+        Ok(())
+    }
+
     /// Check if a template exists in storage
-    #[allow(dead_code)]
     pub fn exists(&self, name: &str) -> bool {
-        let filename = format!("{}.json", sanitize_filename(name));
-        let path = self.templates_dir.join(filename);
-        path.exists()
+        self.get_template_path(name).exists()
     }
 
     /// Get the path to a template file
-    #[allow(dead_code)]
     pub fn get_template_path(&self, name: &str) -> PathBuf {
         let filename = format!("{}.json", sanitize_filename(name));
         self.templates_dir.join(filename)
@@ -112,14 +168,95 @@ impl TemplateStorage {
     }
 }
 
+/// Template fields accepted on import; timestamps and the built-in flag are not trusted
+#[derive(Deserialize)]
+struct ImportedTemplate {
+    name: String,
+    description: String,
+    content: String,
+    #[serde(default = "default_import_category")]
+    category: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    examples: Vec<(String, String)>,
+}
+
+fn default_import_category() -> String {
+    "general".to_string()
+}
+
+/// Import payloads hold either a single template or a list of templates
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TemplateBundle {
+    Many(Vec<ImportedTemplate>),
+    One(ImportedTemplate),
+}
+
+/// Serialize templates for sharing: a single object for one template, a list otherwise
+pub fn serialize_template_bundle(templates: &[&Template]) -> Result<String> {
+    match templates {
+        [template] => Ok(serde_json::to_string_pretty(template)?),
+        _ => Ok(serde_json::to_string_pretty(templates)?),
+    }
+}
+
+/// Parse and validate shared templates
+///
+/// Imported templates are user templates with fresh timestamps.
+pub fn parse_template_bundle(content: &str) -> Result<Vec<Template>> {
+    let bundle: TemplateBundle =
+        serde_json::from_str(content).context("Invalid template file: expected a template object or a list of templates with name, description and content")?;
+
+    let imported = match bundle {
+        TemplateBundle::Many(templates) => templates,
+        TemplateBundle::One(template) => vec![template],
+    };
+
+    imported
+        .into_iter()
+        .map(|t| {
+            if t.name.trim().is_empty() {
+                return Err(anyhow!("Template name cannot be empty"));
+            }
+            if t.content.trim().is_empty() {
+                return Err(anyhow!("Template '{}' has no content", t.name));
+            }
+            let mut template = Template::new(
+                t.name.trim().to_string(),
+                t.description,
+                t.content,
+                t.category,
+                t.tags,
+            );
+            template.examples = t.examples;
+            Ok(template)
+        })
+        .collect()
+}
+
 /// Get the templates directory path
-fn get_templates_dir() -> PathBuf {
+pub fn get_templates_dir() -> PathBuf {
     config_dir()
         .unwrap_or_else(|| PathBuf::from("."))
         .join("chatter")
         .join("templates")
 }
 
+/// Take the advisory lock for a templates directory, waiting for other holders
+fn lock_dir(dir: &Path) -> Result<File> {
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(dir.join(LOCK_FILE_NAME))
+        .with_context(|| format!("Failed to open the lock file in {}", dir.display()))?;
+    file.lock()
+        .with_context(|| format!("Failed to lock {}", dir.display()))?;
+    Ok(file)
+}
+
 /// Sanitize a filename by replacing invalid characters
 fn sanitize_filename(name: &str) -> String {
     name.chars()
@@ -135,6 +272,106 @@ fn sanitize_filename(name: &str) -> String {
 mod tests {
     use super::*;
 
+    #[test]
+    fn bundle_round_trip_resets_metadata() {
+        let mut original = Template::new(
+            "reviewer".to_string(),
+            "Reviews pull requests".to_string(),
+            "You review code.".to_string(),
+            "coding".to_string(),
+            vec!["review".to_string()],
+        );
+        original.created_at = "2020-01-01T00:00:00Z".parse().unwrap();
+
+        let single = serialize_template_bundle(&[&original]).unwrap();
+        let imported = parse_template_bundle(&single).unwrap();
+        assert_eq!(imported.len(), 1);
+        assert_eq!(imported[0].name, "reviewer");
+        assert_eq!(imported[0].tags, vec!["review".to_string()]);
+        assert!(imported[0].created_at > original.created_at);
+        assert!(!imported[0].builtin);
+
+        let other = Template::new(
+            "poet".to_string(),
+            "Writes poems".to_string(),
+            "You write poems.".to_string(),
+            "creative".to_string(),
+            Vec::new(),
+        );
+        let bundle = serialize_template_bundle(&[&original, &other]).unwrap();
+        assert_eq!(parse_template_bundle(&bundle).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn bundle_rejects_missing_fields() {
+        assert!(parse_template_bundle(r#"{"name": "x", "description": "y"}"#).is_err());
+        assert!(
+            parse_template_bundle(r#"{"name": "x", "description": "y", "content": " "}"#).is_err()
+        );
+    }
+
+    fn template(name: &str) -> Template {
+        Template::new(
+            name.to_string(),
+            String::new(),
+            format!("You are {name}."),
+            "general".to_string(),
+            Vec::new(),
+        )
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_saves_from_separate_storages_all_persist() {
+        let dir = std::env::temp_dir().join(format!("chatter-lock-{}", uuid::Uuid::new_v4()));
+
+        // Each task opens its own storage, like a separate chatter process
+        let tasks: Vec<_> = (0..8)
+            .map(|i| {
+                let dir = dir.clone();
+                tokio::spawn(async move {
+                    let storage = TemplateStorage::with_dir(dir).unwrap();
+                    storage.create(&template(&format!("writer_{i}"))).await
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+
+        // Racing creates of one name: exactly one wins
+        let racers: Vec<_> = (0..4)
+            .map(|_| {
+                let dir = dir.clone();
+                tokio::spawn(async move {
+                    let storage = TemplateStorage::with_dir(dir).unwrap();
+                    storage.create(&template("shared")).await
+                })
+            })
+            .collect();
+        let mut created = 0;
+        for racer in racers {
+            if racer.await.unwrap().is_ok() {
+                created += 1;
+            }
+        }
+        assert_eq!(created, 1);
+
+        let storage = TemplateStorage::with_dir(dir.clone()).unwrap();
+        let mut names: Vec<String> = storage
+            .load_all()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|t| t.name)
+            .collect();
+        names.sort();
+        let mut expected: Vec<String> = (0..8).map(|i| format!("writer_{i}")).collect();
+        expected.insert(0, "shared".to_string());
+        assert_eq!(names, expected);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
     #[test]
     fn test_sanitize_filename() {
         assert_eq!(sanitize_filename("normal_name"), "normal_name");